@@ -0,0 +1,51 @@
+// Picks the program id `declare_id!` is expanded with:
+// - `LN_USDT_ESCROW_PROGRAM_ID` (base58), if set, wins;
+// - otherwise the `mainnet` entry of `program-ids.json`.
+// The override is the only way to build for another cluster (devnet, a local validator): the program is deployed on
+// mainnet only, so there are no other ids to pick from.
+//
+// `program-ids.json` is shared with `src/solana/lnUsdtEscrowClient.js`, so both sides resolve the same ids.
+
+use std::{env, fs, path::Path};
+
+const IDS_FILE: &str = "program-ids.json";
+const ENV_OVERRIDE: &str = "LN_USDT_ESCROW_PROGRAM_ID";
+
+// The file is a flat `{ "cluster": "base58", ... }` object; not worth a JSON dependency.
+fn lookup(json: &str, cluster: &str) -> Option<String> {
+    let key = format!("\"{cluster}\"");
+    let rest = &json[json.find(&key)? + key.len()..];
+    let rest = rest
+        .trim_start()
+        .strip_prefix(':')?
+        .trim_start()
+        .strip_prefix('"')?;
+    Some(rest[..rest.find('"')?].to_string())
+}
+
+fn is_base58_pubkey(s: &str) -> bool {
+    (32..=44).contains(&s.len())
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() && !matches!(c, '0' | 'O' | 'I' | 'l'))
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed={IDS_FILE}");
+    println!("cargo:rerun-if-env-changed={ENV_OVERRIDE}");
+
+    let program_id = match env::var(ENV_OVERRIDE) {
+        Ok(id) => id.trim().to_string(),
+        Err(_) => {
+            let json =
+                fs::read_to_string(IDS_FILE).unwrap_or_else(|e| panic!("reading {IDS_FILE}: {e}"));
+            lookup(&json, "mainnet").unwrap_or_else(|| panic!("{IDS_FILE} has no `mainnet` entry"))
+        }
+    };
+    if !is_base58_pubkey(&program_id) {
+        panic!("program id {program_id:?} is not a base58 pubkey");
+    }
+
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("program_id.rs");
+    let code = format!("solana_program::declare_id!({program_id:?});\n");
+    fs::write(out, code).unwrap();
+}
@@ -0,0 +1,92 @@
+//! CPI interface for programs that open and settle escrows on behalf of their own PDAs.
+//!
+//! Depend on this crate with `features = ["no-entrypoint"]` and call [`init`], [`claim`] or [`refund`]. Each one
+//! invokes the escrow program and returns the [`EscrowOutcome`] it left in the return data.
+//!
+//! Accounts are the ones documented on the matching handler, in the v2 layout (no sysvar accounts), and are
+//! forwarded with the writability they have in the calling instruction. Signers:
+//! - `init` (InitCpi): account 0 authorizes the token transfer (owner or approved delegate of the payer token
+//!   account) and is recorded as the escrow's payer. The last account is the rent payer; it funds the escrow,
+//!   the vault and fee vault ATAs, the stats PDAs and any lamport fees, so it must be a system account without
+//!   data. Both may be PDAs of the calling program.
+//! - `claim`: account 0 is the escrow's recipient.
+//! - `refund`: account 0 is the escrow's refund authority; lamport fees the escrow paid are returned to it.
+//!
+//! Pass the seeds of every one of those signers that is a PDA of the caller in `signer_seeds`. The escrow
+//! program's own PDAs never need seeds from the caller.
+
+use solana_program::{
+    account_info::AccountInfo,
+    instruction::{AccountMeta, Instruction},
+    program::{get_return_data, invoke_signed},
+    program_error::ProgramError,
+};
+
+use crate::{
+    id,
+    instruction::{EscrowInstruction, InitArgs},
+    return_data::EscrowOutcome,
+};
+
+pub fn init<'a>(
+    program: &AccountInfo<'a>,
+    accounts: &[AccountInfo<'a>],
+    args: InitArgs,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<EscrowOutcome, ProgramError> {
+    let rent_payer = accounts.len().checked_sub(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    invoke_escrow(program, accounts, &[0, rent_payer], EscrowInstruction::InitCpi(args), signer_seeds)
+}
+
+pub fn claim<'a>(
+    program: &AccountInfo<'a>,
+    accounts: &[AccountInfo<'a>],
+    preimage: [u8; 32],
+    signer_seeds: &[&[&[u8]]],
+) -> Result<EscrowOutcome, ProgramError> {
+    invoke_escrow(program, accounts, &[0], EscrowInstruction::Claim { preimage }, signer_seeds)
+}
+
+pub fn refund<'a>(
+    program: &AccountInfo<'a>,
+    accounts: &[AccountInfo<'a>],
+    signer_seeds: &[&[&[u8]]],
+) -> Result<EscrowOutcome, ProgramError> {
+    invoke_escrow(program, accounts, &[0], EscrowInstruction::Refund, signer_seeds)
+}
+
+// `signers` are the indices the escrow program requires to sign; every other account keeps the caller's flags.
+fn invoke_escrow<'a>(
+    program: &AccountInfo<'a>,
+    accounts: &[AccountInfo<'a>],
+    signers: &[usize],
+    ix: EscrowInstruction,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<EscrowOutcome, ProgramError> {
+    if *program.key != id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let metas = accounts
+        .iter()
+        .enumerate()
+        .map(|(i, ai)| AccountMeta {
+            pubkey: *ai.key,
+            is_signer: ai.is_signer || signers.contains(&i),
+            is_writable: ai.is_writable,
+        })
+        .collect();
+    let instruction = Instruction {
+        program_id: id(),
+        accounts: metas,
+        data: ix.pack(),
+    };
+    let mut infos = accounts.to_vec();
+    infos.push(program.clone());
+    invoke_signed(&instruction, &infos, signer_seeds)?;
+    match get_return_data() {
+        Some((program_id, data)) if program_id == id() => {
+            EscrowOutcome::from_return_data(&data).ok_or(ProgramError::InvalidAccountData)
+        }
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
@@ -0,0 +1,181 @@
+//! Program errors.
+//!
+//! Every variant is returned as `ProgramError::Custom(code)`. Codes are stable: new failure paths get new
+//! variants appended at the end, and existing codes are never renumbered or reused.
+
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use solana_program::{
+    decode_error::DecodeError,
+    msg,
+    program_error::{PrintProgramError, ProgramError},
+};
+use thiserror::Error;
+
+#[repr(u32)]
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error, FromPrimitive)]
+pub enum EscrowError {
+    #[error("invalid instruction argument")]
+    InvalidInstruction = 1,
+    #[error("escrow PDA does not match the payment hash")]
+    InvalidEscrowPda = 2,
+    #[error("vault is not the escrow's associated token account")]
+    InvalidVaultAta = 3,
+    #[error("invalid token account")]
+    InvalidTokenAccount = 4,
+    #[error("missing required signature")]
+    InvalidSigner = 5,
+    #[error("preimage does not hash to the payment hash")]
+    InvalidPreimage = 6,
+    #[error("escrow is not active")]
+    NotActive = 7,
+    #[error("refund timelock has not expired")]
+    TooEarly = 8,
+    #[error("platform config PDA mismatch")]
+    InvalidConfigPda = 9,
+    #[error("platform config missing or unreadable")]
+    InvalidConfigState = 10,
+    #[error("fee bps above the allowed maximum")]
+    FeeTooHigh = 11,
+    #[error("account already initialized")]
+    AlreadyInitialized = 12,
+    #[error("platform fee vault is not the expected associated token account")]
+    InvalidFeeVaultAta = 13,
+    #[error("trade config PDA mismatch")]
+    InvalidTradeConfigPda = 14,
+    #[error("trade config missing, unreadable or inconsistent")]
+    InvalidTradeConfigState = 15,
+    #[error("trade fee vault is not the expected associated token account")]
+    InvalidTradeFeeVaultAta = 16,
+    #[error("fee bps differ from what the client expected")]
+    FeeMismatch = 17,
+    #[error("not allowed in treasury mode")]
+    TreasuryLocked = 18,
+    #[error("treasury mode is not configured")]
+    TreasuryNotConfigured = 19,
+    #[error("fee withdrawal PDA mismatch")]
+    InvalidFeeWithdrawalPda = 20,
+    #[error("fee withdrawal request missing, unreadable or in the wrong state")]
+    InvalidFeeWithdrawalState = 21,
+    #[error("not allowed while a revenue split is active")]
+    RevenueSplitActive = 22,
+    #[error("invalid revenue split")]
+    InvalidRevenueSplit = 23,
+    #[error("referrer token account is not the referrer's associated token account")]
+    InvalidReferrerAta = 24,
+    #[error("not allowed while direct payout is enabled")]
+    DirectPayoutActive = 25,
+    #[error("lamports are reserved for open escrows")]
+    LamportFeesReserved = 26,
+    #[error("payer stats PDA mismatch or unreadable")]
+    InvalidPayerStatsPda = 27,
+    #[error("invalid volume tiers")]
+    InvalidVolumeTiers = 28,
+    #[error("mint stats PDA mismatch or unreadable")]
+    InvalidMintStatsPda = 29,
+    #[error("audit log PDA mismatch or unreadable")]
+    InvalidAuditLogPda = 30,
+    #[error("arithmetic overflow")]
+    ArithmeticOverflow = 31,
+    #[error("payer token balance too low for the escrow amount plus fees")]
+    InsufficientFunds = 32,
+    #[error("withdraw amount exceeds the fee vault balance")]
+    WithdrawExceedsBalance = 33,
+    #[error("account is not an SPL token account")]
+    InvalidTokenAccountData = 34,
+    #[error("token account owner mismatch")]
+    TokenOwnerMismatch = 35,
+    #[error("token account mint mismatch")]
+    TokenMintMismatch = 36,
+    #[error("vault is not owned by the escrow PDA")]
+    VaultAuthorityMismatch = 37,
+    #[error("instruction data too short")]
+    InstructionTooShort = 38,
+    #[error("unknown instruction tag")]
+    UnknownInstruction = 39,
+    #[error("referrer and referral fee must be set together")]
+    ReferrerWithoutFee = 40,
+    #[error("treasury mode needs a guardian and a non-zero delay")]
+    InvalidTreasuryParams = 41,
+    #[error("signer is not the config authority")]
+    AuthorityMismatch = 42,
+    #[error("fee collector does not match the config authority")]
+    FeeCollectorMismatch = 43,
+    #[error("account must be writable")]
+    AccountNotWritable = 44,
+    #[error("referral fee above the config's cap")]
+    ReferralFeeTooHigh = 45,
+    #[error("combined fee bps above the allowed maximum")]
+    TotalFeeTooHigh = 46,
+    #[error("lamport fee differs from what the client expected")]
+    LamportFeeMismatch = 47,
+    #[error("beneficiary token account is not the beneficiary's associated token account")]
+    InvalidBeneficiaryAta = 48,
+    #[error("tenant must be the config authority")]
+    InvalidTenant = 49,
+    #[error("rent payer does not match the fee withdrawal request")]
+    RentPayerMismatch = 50,
+    #[error("trailing bytes after the instruction arguments")]
+    InstructionTooLong = 51,
+    #[error("unsupported instruction ABI version")]
+    UnsupportedAbiVersion = 52,
+    #[error("token program account is not the SPL Token program")]
+    InvalidTokenProgram = 53,
+    #[error("system program account is not the System program")]
+    InvalidSystemProgram = 54,
+    #[error("associated token program account is not the Associated Token Account program")]
+    InvalidAtaProgram = 55,
+    #[error("sysvar account has the wrong address")]
+    InvalidSysvar = 56,
+    #[error("state account is not owned by this program")]
+    InvalidAccountOwner = 57,
+    #[error("the default platform config can not be closed")]
+    DefaultConfigNotClosable = 58,
+    #[error("signer is not the treasury guardian")]
+    GuardianMismatch = 59,
+    #[error("signer is not the escrow's recipient")]
+    RecipientMismatch = 60,
+    #[error("signer is not the escrow's refund address")]
+    RefundMismatch = 61,
+    #[error("escrow account unreadable or of an unsupported version")]
+    InvalidEscrowState = 62,
+    #[error("lamport debit would leave the account below rent exemption")]
+    BelowRentExemption = 63,
+    #[error("lamport withdrawal exceeds the unreserved balance")]
+    WithdrawExceedsLamportFees = 64,
+    #[error("trade config not initialized")]
+    TradeConfigNotInitialized = 65,
+    #[error("trade config is not seeded by its fee collector")]
+    TradeConfigCollectorMismatch = 66,
+    #[error("a fee withdrawal request is still pending")]
+    FeeWithdrawalPending = 67,
+}
+
+impl EscrowError {
+    // Maps a `ProgramError::Custom` code back to the error; `None` for codes this build does not know.
+    pub fn from_code(code: u32) -> Option<Self> {
+        Self::from_u32(code)
+    }
+}
+
+impl From<EscrowError> for ProgramError {
+    fn from(e: EscrowError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+impl<T> DecodeError<T> for EscrowError {
+    fn type_of() -> &'static str {
+        "EscrowError"
+    }
+}
+
+impl PrintProgramError for EscrowError {
+    fn print<E>(&self)
+    where
+        E: 'static + std::error::Error + DecodeError<E> + PrintProgramError + FromPrimitive,
+    {
+        msg!("Error: {}", self);
+    }
+}
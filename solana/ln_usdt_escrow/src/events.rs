@@ -0,0 +1,102 @@
+//! Structured events for indexers.
+//!
+//! Every event is logged with `sol_log_data` as a single data field (a `Program data: <base64>` log line):
+//! one version byte (`EVENT_VERSION`) followed by the Borsh-encoded [`EscrowEvent`]. The enum discriminant
+//! identifies the event type, and new event types are only ever appended, so older decoders can skip what they
+//! do not know.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{log::sol_log_data, pubkey::Pubkey};
+
+pub const EVENT_VERSION: u8 = 1;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub enum EscrowEvent {
+    EscrowInitialized(EscrowInitialized),
+    EscrowClaimed(EscrowClaimed),
+    EscrowRefunded(EscrowRefunded),
+    ConfigUpdated(ConfigUpdated),
+    TradeConfigUpdated(ConfigUpdated),
+    FeesWithdrawn(FeesWithdrawn),
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EscrowInitialized {
+    pub escrow: Pubkey,
+    pub payment_hash: [u8; 32],
+    pub mint: Pubkey,
+    pub payer: Pubkey,
+    pub recipient: Pubkey,
+    pub refund: Pubkey,
+    pub refund_after: i64,
+    pub net_amount: u64,
+    pub platform_fee_amount: u64,
+    pub trade_fee_amount: u64,
+    pub referral_fee_amount: u64,
+    pub platform_config: Pubkey,
+    pub trade_config: Pubkey,
+}
+
+// Amounts are what the claim actually paid out (fees released to the recipient count towards `net_amount`).
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EscrowClaimed {
+    pub escrow: Pubkey,
+    pub payment_hash: [u8; 32],
+    pub preimage: [u8; 32],
+    pub recipient: Pubkey,
+    pub net_amount: u64,
+    pub platform_fee_amount: u64,
+    pub trade_fee_amount: u64,
+    pub referral_fee_amount: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EscrowRefunded {
+    pub escrow: Pubkey,
+    pub payment_hash: [u8; 32],
+    pub refund: Pubkey,
+    pub amount: u64,
+}
+
+// Emitted by SetConfig (as `ConfigUpdated`) and SetTradeConfig (as `TradeConfigUpdated`).
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConfigUpdated {
+    pub config: Pubkey,
+    pub authority: Pubkey,
+    pub old_fee_bps: u16,
+    pub new_fee_bps: u16,
+    pub old_fee_collector: Pubkey,
+    pub new_fee_collector: Pubkey,
+}
+
+// Any movement out of a platform or trade fee vault: withdrawals, sweeps, treasury payouts and distributions.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FeesWithdrawn {
+    pub config: Pubkey,
+    pub fee_vault: Pubkey,
+    pub mint: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+}
+
+impl EscrowEvent {
+    pub fn to_log_data(&self) -> Vec<u8> {
+        let mut data = vec![EVENT_VERSION];
+        // Serializing into a Vec cannot fail.
+        self.serialize(&mut data).expect("event serialization");
+        data
+    }
+
+    // Decodes one `Program data:` field (already base64-decoded). `None` for other programs' data, unknown
+    // versions and event types added after this build.
+    pub fn from_log_data(data: &[u8]) -> Option<Self> {
+        match data.split_first() {
+            Some((&EVENT_VERSION, mut rest)) => Self::deserialize(&mut rest).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn emit(&self) {
+        sol_log_data(&[&self.to_log_data()]);
+    }
+}
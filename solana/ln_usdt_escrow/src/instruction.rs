@@ -0,0 +1,831 @@
+//! Instruction encoding.
+//!
+//! Two wire formats are accepted:
+//! - v1: a tag byte (the variant index below) followed by hand-packed little-endian fields. Instructions that
+//!   create or time-check accounts also carry the rent/clock sysvar accounts.
+//! - v2: `ABI_V2_PREFIX`, `ABI_VERSION`, then the Borsh-encoded [`EscrowInstruction`]. Sysvars are read through
+//!   syscalls, so v2 drops those accounts; every other account keeps its order.
+//!
+//! Both formats reject trailing bytes. `idl/ln_usdt_escrow.json` describes the v2 format; it is generated from
+//! this module and the handlers' account comments by `tests/idl.rs`.
+//!
+//! The builders at the bottom of this module (`init`, `claim`, `refund`, ...) emit v2 instructions for the
+//! program id this crate was built for (see build.rs), with the account lists documented on the matching
+//! `process_*` handler, optional accounts included exactly when the program expects them.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program,
+};
+use spl_associated_token_account::get_associated_token_address;
+
+use crate::{
+    audit_log_pda, config_pda, fee_withdrawal_pda, id, mint_stats_pda, payer_stats_pda, pda_for_hash,
+    trade_config_pda, ConfigState, EscrowError, EscrowState, RevenueBeneficiary, TradeConfigState, VolumeTier,
+    MAX_REVENUE_BENEFICIARIES, MAX_VOLUME_TIERS,
+};
+
+// Never a v1 tag, so a v2 instruction can not be mistaken for one.
+pub const ABI_V2_PREFIX: u8 = 0xff;
+pub const ABI_VERSION: u8 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Abi {
+    V1,
+    V2,
+}
+
+// Arguments shared by Init, ValidateInit and InitCpi.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InitArgs {
+    pub payment_hash: [u8; 32],
+    pub recipient: Pubkey,
+    pub refund: Pubkey,
+    pub refund_after: i64,
+    pub amount: u64,
+    pub expected_platform_fee_bps: u16,
+    pub expected_trade_fee_bps: u16, // upper bound (the lock surcharge depends on the cluster clock)
+    pub trade_fee_collector: Pubkey,
+    pub referrer: Pubkey, // default pubkey = no referrer
+    pub referral_fee_bps: u16,
+    pub expected_platform_lamport_fee: u64,
+    pub expected_trade_lamport_fee: u64,
+}
+
+// Variants are declared in v1 tag order, so the Borsh discriminant of each variant equals its v1 tag.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub enum EscrowInstruction {
+    Init(InitArgs),
+    Claim { preimage: [u8; 32] },
+    Refund,
+    InitConfig {
+        fee_collector: Pubkey,
+        fee_bps: u16,
+        tenant: Pubkey, // default pubkey = the default (un-namespaced) config
+    },
+    SetConfig { fee_collector: Pubkey, fee_bps: u16 },
+    WithdrawFees { amount: u64 },
+    InitTradeConfig { fee_collector: Pubkey, fee_bps: u16 },
+    SetTradeConfig { fee_collector: Pubkey, fee_bps: u16 },
+    WithdrawTradeFees { amount: u64 },
+    CloseConfig,
+    CloseTradeConfig,
+    WithdrawFeesBatch,
+    WithdrawTradeFeesBatch,
+    SetTreasury {
+        treasury_owner: Pubkey,
+        guardian: Pubkey,
+        withdraw_delay_secs: u32,
+    },
+    RequestFeeWithdrawal { amount: u64 },
+    ExecuteFeeWithdrawal,
+    VetoFeeWithdrawal,
+    SetRevenueSplit { beneficiaries: Vec<RevenueBeneficiary> },
+    DistributeFees,
+    SetReferralCap { max_referral_bps: u16 },
+    SetMakerRebate { maker_rebate_bps: u16 },
+    SetDirectPayout { enabled: bool },
+    SetLamportFee { per_escrow: u64 },
+    WithdrawLamportFees { amount: u64 },
+    SetLockSurcharge { bps_per_hour: u16 },
+    SetVolumeTiers { tiers: Vec<VolumeTier> },
+    ValidateInit(InitArgs),
+    InitCpi(InitArgs),
+    MigrateAccount,
+}
+
+fn read_bytes<const N: usize>(data: &mut &[u8]) -> Result<[u8; N], ProgramError> {
+    if data.len() < N {
+        return Err(EscrowError::InstructionTooShort.into());
+    }
+    let (head, tail) = data.split_at(N);
+    *data = tail;
+    let mut out = [0u8; N];
+    out.copy_from_slice(head);
+    Ok(out)
+}
+
+fn read_u64_le(data: &mut &[u8]) -> Result<u64, ProgramError> {
+    Ok(u64::from_le_bytes(read_bytes::<8>(data)?))
+}
+
+fn read_i64_le(data: &mut &[u8]) -> Result<i64, ProgramError> {
+    Ok(i64::from_le_bytes(read_bytes::<8>(data)?))
+}
+
+fn read_u32_le(data: &mut &[u8]) -> Result<u32, ProgramError> {
+    Ok(u32::from_le_bytes(read_bytes::<4>(data)?))
+}
+
+fn read_u16_le(data: &mut &[u8]) -> Result<u16, ProgramError> {
+    Ok(u16::from_le_bytes(read_bytes::<2>(data)?))
+}
+
+// Init, ValidateInit and InitCpi share one argument layout.
+fn read_init_args(data: &mut &[u8]) -> Result<InitArgs, ProgramError> {
+    let payment_hash = read_bytes::<32>(data)?;
+    let recipient = Pubkey::new_from_array(read_bytes::<32>(data)?);
+    let refund = Pubkey::new_from_array(read_bytes::<32>(data)?);
+    let refund_after = read_i64_le(data)?;
+    let amount = read_u64_le(data)?;
+    let expected_platform_fee_bps = read_u16_le(data)?;
+    let expected_trade_fee_bps = read_u16_le(data)?;
+    let trade_fee_collector = Pubkey::new_from_array(read_bytes::<32>(data)?);
+    // Optional trailing args, each group appended after the previous one; older clients simply omit them.
+    let (referrer, referral_fee_bps) = if data.is_empty() {
+        (Pubkey::default(), 0)
+    } else {
+        let referrer = Pubkey::new_from_array(read_bytes::<32>(data)?);
+        (referrer, read_u16_le(data)?)
+    };
+    let (expected_platform_lamport_fee, expected_trade_lamport_fee) = if data.is_empty() {
+        (0, 0)
+    } else {
+        (read_u64_le(data)?, read_u64_le(data)?)
+    };
+    Ok(InitArgs {
+        payment_hash,
+        recipient,
+        refund,
+        refund_after,
+        amount,
+        expected_platform_fee_bps,
+        expected_trade_fee_bps,
+        trade_fee_collector,
+        referrer,
+        referral_fee_bps,
+        expected_platform_lamport_fee,
+        expected_trade_lamport_fee,
+    })
+}
+
+// v1: tag byte plus hand-packed little-endian fields; some instructions take optional trailing groups.
+fn unpack_v1(input: &[u8]) -> Result<EscrowInstruction, ProgramError> {
+    let (&tag, mut data) = input.split_first().ok_or(EscrowError::InstructionTooShort)?;
+    let ix = read_v1_args(tag, &mut data)?;
+    if !data.is_empty() {
+        return Err(EscrowError::InstructionTooLong.into());
+    }
+    Ok(ix)
+}
+
+fn read_v1_args(tag: u8, data: &mut &[u8]) -> Result<EscrowInstruction, ProgramError> {
+    match tag {
+        0 => Ok(EscrowInstruction::Init(read_init_args(data)?)),
+        1 => {
+            let preimage = read_bytes::<32>(data)?;
+            Ok(EscrowInstruction::Claim { preimage })
+        }
+        2 => Ok(EscrowInstruction::Refund),
+        3 => {
+            let fee_collector = Pubkey::new_from_array(read_bytes::<32>(data)?);
+            let fee_bps = read_u16_le(data)?;
+            // Optional trailing tenant; older clients omit it and get the default config.
+            let tenant = if data.is_empty() {
+                Pubkey::default()
+            } else {
+                Pubkey::new_from_array(read_bytes::<32>(data)?)
+            };
+            Ok(EscrowInstruction::InitConfig {
+                fee_collector,
+                fee_bps,
+                tenant,
+            })
+        }
+        4 => {
+            let fee_collector = Pubkey::new_from_array(read_bytes::<32>(data)?);
+            let fee_bps = read_u16_le(data)?;
+            Ok(EscrowInstruction::SetConfig { fee_collector, fee_bps })
+        }
+        5 => {
+            let amount = read_u64_le(data)?;
+            Ok(EscrowInstruction::WithdrawFees { amount })
+        }
+        6 => {
+            let fee_collector = Pubkey::new_from_array(read_bytes::<32>(data)?);
+            let fee_bps = read_u16_le(data)?;
+            Ok(EscrowInstruction::InitTradeConfig { fee_collector, fee_bps })
+        }
+        7 => {
+            let fee_collector = Pubkey::new_from_array(read_bytes::<32>(data)?);
+            let fee_bps = read_u16_le(data)?;
+            Ok(EscrowInstruction::SetTradeConfig { fee_collector, fee_bps })
+        }
+        8 => {
+            let amount = read_u64_le(data)?;
+            Ok(EscrowInstruction::WithdrawTradeFees { amount })
+        }
+        9 => Ok(EscrowInstruction::CloseConfig),
+        10 => Ok(EscrowInstruction::CloseTradeConfig),
+        11 => Ok(EscrowInstruction::WithdrawFeesBatch),
+        12 => Ok(EscrowInstruction::WithdrawTradeFeesBatch),
+        13 => {
+            let treasury_owner = Pubkey::new_from_array(read_bytes::<32>(data)?);
+            let guardian = Pubkey::new_from_array(read_bytes::<32>(data)?);
+            let withdraw_delay_secs = read_u32_le(data)?;
+            Ok(EscrowInstruction::SetTreasury {
+                treasury_owner,
+                guardian,
+                withdraw_delay_secs,
+            })
+        }
+        14 => {
+            let amount = read_u64_le(data)?;
+            Ok(EscrowInstruction::RequestFeeWithdrawal { amount })
+        }
+        15 => Ok(EscrowInstruction::ExecuteFeeWithdrawal),
+        16 => Ok(EscrowInstruction::VetoFeeWithdrawal),
+        17 => {
+            let count = read_bytes::<1>(data)?[0] as usize;
+            if count > MAX_REVENUE_BENEFICIARIES {
+                return Err(EscrowError::InvalidRevenueSplit.into());
+            }
+            let mut beneficiaries = Vec::with_capacity(count);
+            for _ in 0..count {
+                let owner = read_bytes::<32>(data)?;
+                let weight_bps = read_u16_le(data)?;
+                beneficiaries.push(RevenueBeneficiary { owner, weight_bps });
+            }
+            Ok(EscrowInstruction::SetRevenueSplit { beneficiaries })
+        }
+        18 => Ok(EscrowInstruction::DistributeFees),
+        19 => {
+            let max_referral_bps = read_u16_le(data)?;
+            Ok(EscrowInstruction::SetReferralCap { max_referral_bps })
+        }
+        20 => {
+            let maker_rebate_bps = read_u16_le(data)?;
+            Ok(EscrowInstruction::SetMakerRebate { maker_rebate_bps })
+        }
+        21 => {
+            let enabled = match read_bytes::<1>(data)?[0] {
+                0 => false,
+                1 => true,
+                _ => return Err(EscrowError::InvalidInstruction.into()),
+            };
+            Ok(EscrowInstruction::SetDirectPayout { enabled })
+        }
+        22 => {
+            let per_escrow = read_u64_le(data)?;
+            Ok(EscrowInstruction::SetLamportFee { per_escrow })
+        }
+        23 => {
+            let amount = read_u64_le(data)?;
+            Ok(EscrowInstruction::WithdrawLamportFees { amount })
+        }
+        24 => {
+            let bps_per_hour = read_u16_le(data)?;
+            Ok(EscrowInstruction::SetLockSurcharge { bps_per_hour })
+        }
+        25 => {
+            let count = read_bytes::<1>(data)?[0] as usize;
+            if count > MAX_VOLUME_TIERS {
+                return Err(EscrowError::InvalidVolumeTiers.into());
+            }
+            let mut tiers = Vec::with_capacity(count);
+            for _ in 0..count {
+                let min_volume = read_u64_le(data)?;
+                let discount_bps = read_u16_le(data)?;
+                tiers.push(VolumeTier { min_volume, discount_bps });
+            }
+            Ok(EscrowInstruction::SetVolumeTiers { tiers })
+        }
+        26 => Ok(EscrowInstruction::ValidateInit(read_init_args(data)?)),
+        27 => Ok(EscrowInstruction::InitCpi(read_init_args(data)?)),
+        28 => Ok(EscrowInstruction::MigrateAccount),
+        _ => Err(EscrowError::UnknownInstruction.into()),
+    }
+}
+
+impl EscrowInstruction {
+    // Encodes in the v2 format.
+    pub fn pack(&self) -> Vec<u8> {
+        let mut data = vec![ABI_V2_PREFIX, ABI_VERSION];
+        self.serialize(&mut data).expect("instruction serialization");
+        data
+    }
+
+    pub fn unpack(input: &[u8]) -> Result<(Self, Abi), ProgramError> {
+        match input {
+            [ABI_V2_PREFIX, ABI_VERSION, body @ ..] => Ok((Self::unpack_v2(body)?, Abi::V2)),
+            [ABI_V2_PREFIX, ..] => Err(EscrowError::UnsupportedAbiVersion.into()),
+            _ => Ok((unpack_v1(input)?, Abi::V1)),
+        }
+    }
+
+    fn unpack_v2(mut body: &[u8]) -> Result<Self, ProgramError> {
+        let ix = Self::deserialize(&mut body).map_err(|e| match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => EscrowError::InstructionTooShort,
+            _ => EscrowError::InvalidInstruction,
+        })?;
+        if !body.is_empty() {
+            return Err(EscrowError::InstructionTooLong.into());
+        }
+        // Same caps as the v1 count bytes.
+        match &ix {
+            Self::SetRevenueSplit { beneficiaries } if beneficiaries.len() > MAX_REVENUE_BENEFICIARIES => {
+                Err(EscrowError::InvalidRevenueSplit.into())
+            }
+            Self::SetVolumeTiers { tiers } if tiers.len() > MAX_VOLUME_TIERS => {
+                Err(EscrowError::InvalidVolumeTiers.into())
+            }
+            _ => Ok(ix),
+        }
+    }
+}
+
+// Accounts shared by Init and ValidateInit, derived from the two configs the escrow will be opened against.
+fn init_accounts(
+    payer: &Pubkey,
+    payer_token: &Pubkey,
+    mint: &Pubkey,
+    config: &ConfigState,
+    trade_config: &TradeConfigState,
+    args: &InitArgs,
+) -> Vec<AccountMeta> {
+    let escrow = pda_for_hash(&id(), &args.payment_hash).0;
+    let config_pk = config_pda(&id(), &config.tenant()).0;
+    let trade_config_pk = trade_config_pda(&id(), &args.trade_fee_collector).0;
+    let platform_fee_vault = if config.direct_payout {
+        get_associated_token_address(&Pubkey::new_from_array(config.fee_collector), mint)
+    } else {
+        get_associated_token_address(&config_pk, mint)
+    };
+    let trade_fee_vault = if trade_config.direct_payout {
+        get_associated_token_address(&args.trade_fee_collector, mint)
+    } else {
+        get_associated_token_address(&trade_config_pk, mint)
+    };
+    let mut accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(*payer_token, false),
+        AccountMeta::new(escrow, false),
+        AccountMeta::new(get_associated_token_address(&escrow, mint), false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+    ];
+    if config.lamport_fees.per_escrow > 0 {
+        accounts.push(AccountMeta::new(config_pk, false));
+    } else {
+        accounts.push(AccountMeta::new_readonly(config_pk, false));
+    }
+    accounts.push(AccountMeta::new(platform_fee_vault, false));
+    if trade_config.lamport_fees.per_escrow > 0 {
+        accounts.push(AccountMeta::new(trade_config_pk, false));
+    } else {
+        accounts.push(AccountMeta::new_readonly(trade_config_pk, false));
+    }
+    accounts.push(AccountMeta::new(trade_fee_vault, false));
+    if args.referrer != Pubkey::default() {
+        accounts.push(AccountMeta::new_readonly(get_associated_token_address(&args.referrer, mint), false));
+    }
+    if config.volume_tiers.active() {
+        accounts.push(AccountMeta::new(payer_stats_pda(&id(), payer, mint).0, false));
+    }
+    accounts.push(AccountMeta::new(mint_stats_pda(&id(), &config_pk, mint).0, false));
+    accounts.push(AccountMeta::new(mint_stats_pda(&id(), &trade_config_pk, mint).0, false));
+    accounts
+}
+
+pub fn init(
+    payer: &Pubkey,
+    payer_token: &Pubkey,
+    mint: &Pubkey,
+    config: &ConfigState,
+    trade_config: &TradeConfigState,
+    args: InitArgs,
+) -> Instruction {
+    let accounts = init_accounts(payer, payer_token, mint, config, trade_config, &args);
+    Instruction::new_with_bytes(id(), &EscrowInstruction::Init(args).pack(), accounts)
+}
+
+// Same accounts as `init`; meant for `simulateTransaction` (the verdict is in the return data).
+pub fn validate_init(
+    payer: &Pubkey,
+    payer_token: &Pubkey,
+    mint: &Pubkey,
+    config: &ConfigState,
+    trade_config: &TradeConfigState,
+    args: InitArgs,
+) -> Instruction {
+    let accounts = init_accounts(payer, payer_token, mint, config, trade_config, &args);
+    Instruction::new_with_bytes(id(), &EscrowInstruction::ValidateInit(args).pack(), accounts)
+}
+
+// Init with a separate `rent_payer` funding the accounts Init creates (see `process_init_cpi`). Off-chain callers
+// get the same accounts a program passes to `cpi::init`.
+pub fn init_cpi(
+    payer: &Pubkey,
+    payer_token: &Pubkey,
+    rent_payer: &Pubkey,
+    mint: &Pubkey,
+    config: &ConfigState,
+    trade_config: &TradeConfigState,
+    args: InitArgs,
+) -> Instruction {
+    let mut accounts = init_accounts(payer, payer_token, mint, config, trade_config, &args);
+    accounts[0].is_writable = false;
+    accounts.push(AccountMeta::new(*rent_payer, true));
+    Instruction::new_with_bytes(id(), &EscrowInstruction::InitCpi(args).pack(), accounts)
+}
+
+// Config PDAs an escrow's lamport fees and mint stats belong to.
+fn escrow_configs(escrow: &EscrowState) -> (Pubkey, Pubkey) {
+    (
+        config_pda(&id(), &Pubkey::new_from_array(escrow.tenant)).0,
+        trade_config_pda(&id(), &Pubkey::new_from_array(escrow.trade_fee_collector)).0,
+    )
+}
+
+// Builds the claim from the decoded escrow account, which decides the fee vaults and optional accounts.
+pub fn claim(escrow: &EscrowState, recipient_token: &Pubkey, preimage: [u8; 32]) -> Instruction {
+    let mint = Pubkey::new_from_array(escrow.mint);
+    let (config_pk, trade_config_pk) = escrow_configs(escrow);
+    let platform_fee_vault = if escrow.fee_flags & EscrowState::FLAG_PLATFORM_DIRECT_PAYOUT != 0 {
+        get_associated_token_address(&Pubkey::new_from_array(escrow.platform_fee_collector), &mint)
+    } else {
+        get_associated_token_address(&config_pk, &mint)
+    };
+    let trade_fee_vault = if escrow.fee_flags & EscrowState::FLAG_TRADE_DIRECT_PAYOUT != 0 {
+        get_associated_token_address(&Pubkey::new_from_array(escrow.trade_fee_collector), &mint)
+    } else {
+        get_associated_token_address(&trade_config_pk, &mint)
+    };
+    let mut accounts = vec![
+        AccountMeta::new_readonly(Pubkey::new_from_array(escrow.recipient), true),
+        AccountMeta::new(pda_for_hash(&id(), &escrow.payment_hash).0, false),
+        AccountMeta::new(Pubkey::new_from_array(escrow.vault), false),
+        AccountMeta::new(*recipient_token, false),
+        AccountMeta::new(platform_fee_vault, false),
+        AccountMeta::new(trade_fee_vault, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    if escrow.referrer != [0u8; 32] {
+        let referrer = Pubkey::new_from_array(escrow.referrer);
+        accounts.push(AccountMeta::new(get_associated_token_address(&referrer, &mint), false));
+    }
+    if escrow.platform_lamport_fee > 0 {
+        accounts.push(AccountMeta::new(config_pk, false));
+    }
+    if escrow.trade_lamport_fee > 0 {
+        accounts.push(AccountMeta::new(trade_config_pk, false));
+    }
+    if escrow.fee_flags & EscrowState::FLAG_TRACK_PAYER_VOLUME != 0 {
+        let payer = Pubkey::new_from_array(escrow.payer);
+        accounts.push(AccountMeta::new(payer_stats_pda(&id(), &payer, &mint).0, false));
+    }
+    accounts.push(AccountMeta::new(mint_stats_pda(&id(), &config_pk, &mint).0, false));
+    accounts.push(AccountMeta::new(mint_stats_pda(&id(), &trade_config_pk, &mint).0, false));
+    Instruction::new_with_bytes(id(), &EscrowInstruction::Claim { preimage }.pack(), accounts)
+}
+
+pub fn refund(escrow: &EscrowState, refund_token: &Pubkey) -> Instruction {
+    let mint = Pubkey::new_from_array(escrow.mint);
+    let (config_pk, trade_config_pk) = escrow_configs(escrow);
+    let refund = Pubkey::new_from_array(escrow.refund);
+    let has_lamport_fees = escrow.platform_lamport_fee > 0 || escrow.trade_lamport_fee > 0;
+    let mut accounts = vec![
+        if has_lamport_fees {
+            AccountMeta::new(refund, true)
+        } else {
+            AccountMeta::new_readonly(refund, true)
+        },
+        AccountMeta::new(pda_for_hash(&id(), &escrow.payment_hash).0, false),
+        AccountMeta::new(Pubkey::new_from_array(escrow.vault), false),
+        AccountMeta::new(*refund_token, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    if escrow.platform_lamport_fee > 0 {
+        accounts.push(AccountMeta::new(config_pk, false));
+    }
+    if escrow.trade_lamport_fee > 0 {
+        accounts.push(AccountMeta::new(trade_config_pk, false));
+    }
+    if escrow.fee_flags & EscrowState::FLAG_TRACK_MINT_STATS != 0 {
+        accounts.push(AccountMeta::new(mint_stats_pda(&id(), &config_pk, &mint).0, false));
+        accounts.push(AccountMeta::new(mint_stats_pda(&id(), &trade_config_pk, &mint).0, false));
+    }
+    Instruction::new_with_bytes(id(), &EscrowInstruction::Refund.pack(), accounts)
+}
+
+// `payer` becomes the config authority; a non-default `tenant` must be the payer itself.
+pub fn init_config(
+    payer: &Pubkey,
+    fee_collector: Pubkey,
+    fee_bps: u16,
+    tenant: Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(config_pda(&id(), &tenant).0, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    let ix = EscrowInstruction::InitConfig {
+        fee_collector,
+        fee_bps,
+        tenant,
+    };
+    Instruction::new_with_bytes(id(), &ix.pack(), accounts)
+}
+
+pub fn set_config(
+    authority: &Pubkey,
+    config: &Pubkey,
+    fee_collector: Pubkey,
+    fee_bps: u16,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*authority, true),
+        AccountMeta::new(*config, false),
+        AccountMeta::new(audit_log_pda(&id(), config).0, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    let ix = EscrowInstruction::SetConfig { fee_collector, fee_bps };
+    Instruction::new_with_bytes(id(), &ix.pack(), accounts)
+}
+
+pub fn withdraw_fees(
+    fee_collector: &Pubkey,
+    config: &Pubkey,
+    mint: &Pubkey,
+    destination: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*fee_collector, true),
+        AccountMeta::new_readonly(*config, false),
+        AccountMeta::new(get_associated_token_address(config, mint), false),
+        AccountMeta::new(*destination, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(mint_stats_pda(&id(), config, mint).0, false),
+    ];
+    Instruction::new_with_bytes(id(), &EscrowInstruction::WithdrawFees { amount }.pack(), accounts)
+}
+
+// The trade config PDA is seeded by `fee_collector` and owned by `payer`.
+pub fn init_trade_config(payer: &Pubkey, fee_collector: Pubkey, fee_bps: u16) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(trade_config_pda(&id(), &fee_collector).0, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    let ix = EscrowInstruction::InitTradeConfig { fee_collector, fee_bps };
+    Instruction::new_with_bytes(id(), &ix.pack(), accounts)
+}
+
+pub fn set_trade_config(
+    authority: &Pubkey,
+    trade_config: &Pubkey,
+    fee_collector: Pubkey,
+    fee_bps: u16,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*authority, true),
+        AccountMeta::new(*trade_config, false),
+        AccountMeta::new(audit_log_pda(&id(), trade_config).0, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    let ix = EscrowInstruction::SetTradeConfig { fee_collector, fee_bps };
+    Instruction::new_with_bytes(id(), &ix.pack(), accounts)
+}
+
+pub fn withdraw_trade_fees(
+    fee_collector: &Pubkey,
+    trade_config: &Pubkey,
+    mint: &Pubkey,
+    destination: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*fee_collector, true),
+        AccountMeta::new_readonly(*trade_config, false),
+        AccountMeta::new(get_associated_token_address(trade_config, mint), false),
+        AccountMeta::new(*destination, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(mint_stats_pda(&id(), trade_config, mint).0, false),
+    ];
+    Instruction::new_with_bytes(id(), &EscrowInstruction::WithdrawTradeFees { amount }.pack(), accounts)
+}
+
+// (fee vault, destination, mint stats PDA) triples for the batch sweeps, one per (mint, destination) pair;
+// `config` is the platform or trade config PDA owning the vaults.
+fn fee_vault_triples(config: &Pubkey, vaults: &[(Pubkey, Pubkey)]) -> Vec<AccountMeta> {
+    vaults
+        .iter()
+        .flat_map(|(mint, destination)| {
+            [
+                AccountMeta::new(get_associated_token_address(config, mint), false),
+                AccountMeta::new(*destination, false),
+                AccountMeta::new(mint_stats_pda(&id(), config, mint).0, false),
+            ]
+        })
+        .collect()
+}
+
+fn close(fee_collector: &Pubkey, config: &Pubkey, vaults: &[(Pubkey, Pubkey)], ix: EscrowInstruction) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*fee_collector, true),
+        AccountMeta::new(*config, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(audit_log_pda(&id(), config).0, false),
+    ];
+    for (mint, destination) in vaults {
+        let fee_vault = get_associated_token_address(config, mint);
+        accounts.extend([
+            AccountMeta::new(fee_vault, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new(mint_stats_pda(&id(), config, mint).0, false),
+            AccountMeta::new_readonly(fee_withdrawal_pda(&id(), config, &fee_vault).0, false),
+        ]);
+    }
+    Instruction::new_with_bytes(id(), &ix.pack(), accounts)
+}
+
+// `vaults` lists (mint, destination token account) for every mint the config still holds fees in.
+pub fn close_config(fee_collector: &Pubkey, config: &Pubkey, vaults: &[(Pubkey, Pubkey)]) -> Instruction {
+    close(fee_collector, config, vaults, EscrowInstruction::CloseConfig)
+}
+
+pub fn close_trade_config(fee_collector: &Pubkey, trade_config: &Pubkey, vaults: &[(Pubkey, Pubkey)]) -> Instruction {
+    close(fee_collector, trade_config, vaults, EscrowInstruction::CloseTradeConfig)
+}
+
+fn withdraw_batch(
+    fee_collector: &Pubkey,
+    config: &Pubkey,
+    vaults: &[(Pubkey, Pubkey)],
+    ix: EscrowInstruction,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*fee_collector, true),
+        AccountMeta::new_readonly(*config, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    accounts.extend(fee_vault_triples(config, vaults));
+    Instruction::new_with_bytes(id(), &ix.pack(), accounts)
+}
+
+// `vaults` lists (mint, destination token account) for every fee vault to sweep.
+pub fn withdraw_fees_batch(fee_collector: &Pubkey, config: &Pubkey, vaults: &[(Pubkey, Pubkey)]) -> Instruction {
+    withdraw_batch(fee_collector, config, vaults, EscrowInstruction::WithdrawFeesBatch)
+}
+
+pub fn withdraw_trade_fees_batch(
+    fee_collector: &Pubkey,
+    trade_config: &Pubkey,
+    vaults: &[(Pubkey, Pubkey)],
+) -> Instruction {
+    withdraw_batch(fee_collector, trade_config, vaults, EscrowInstruction::WithdrawTradeFeesBatch)
+}
+
+// Authority-only setters sharing the (authority, config PDA, system program) account list.
+fn config_setter(authority: &Pubkey, config: &Pubkey, ix: EscrowInstruction) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*authority, true),
+        AccountMeta::new(*config, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    Instruction::new_with_bytes(id(), &ix.pack(), accounts)
+}
+
+// `config` is a platform or trade config PDA. `current_guardian` must sign when the config is already in
+// treasury mode.
+pub fn set_treasury(
+    authority: &Pubkey,
+    config: &Pubkey,
+    treasury_owner: Pubkey,
+    guardian: Pubkey,
+    withdraw_delay_secs: u32,
+    current_guardian: Option<&Pubkey>,
+) -> Instruction {
+    let ix = EscrowInstruction::SetTreasury {
+        treasury_owner,
+        guardian,
+        withdraw_delay_secs,
+    };
+    let mut ix = config_setter(authority, config, ix);
+    if let Some(current_guardian) = current_guardian {
+        ix.accounts.push(AccountMeta::new_readonly(*current_guardian, true));
+    }
+    ix
+}
+
+pub fn request_fee_withdrawal(authority: &Pubkey, config: &Pubkey, mint: &Pubkey, amount: u64) -> Instruction {
+    let fee_vault = get_associated_token_address(config, mint);
+    let accounts = vec![
+        AccountMeta::new(*authority, true),
+        AccountMeta::new_readonly(*config, false),
+        AccountMeta::new_readonly(fee_vault, false),
+        AccountMeta::new(fee_withdrawal_pda(&id(), config, &fee_vault).0, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    Instruction::new_with_bytes(id(), &EscrowInstruction::RequestFeeWithdrawal { amount }.pack(), accounts)
+}
+
+// `rent_payer` is the authority that requested the withdrawal (see `FeeWithdrawalState::rent_payer`).
+pub fn execute_fee_withdrawal(
+    config: &Pubkey,
+    mint: &Pubkey,
+    treasury_token: &Pubkey,
+    rent_payer: &Pubkey,
+) -> Instruction {
+    let fee_vault = get_associated_token_address(config, mint);
+    let accounts = vec![
+        AccountMeta::new_readonly(*config, false),
+        AccountMeta::new(fee_vault, false),
+        AccountMeta::new(*treasury_token, false),
+        AccountMeta::new(fee_withdrawal_pda(&id(), config, &fee_vault).0, false),
+        AccountMeta::new(*rent_payer, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(mint_stats_pda(&id(), config, mint).0, false),
+    ];
+    Instruction::new_with_bytes(id(), &EscrowInstruction::ExecuteFeeWithdrawal.pack(), accounts)
+}
+
+// `signer` is the guardian, or the config authority once treasury mode is off.
+pub fn veto_fee_withdrawal(signer: &Pubkey, config: &Pubkey, mint: &Pubkey, rent_payer: &Pubkey) -> Instruction {
+    let fee_vault = get_associated_token_address(config, mint);
+    let accounts = vec![
+        AccountMeta::new_readonly(*signer, true),
+        AccountMeta::new_readonly(*config, false),
+        AccountMeta::new(fee_withdrawal_pda(&id(), config, &fee_vault).0, false),
+        AccountMeta::new(*rent_payer, false),
+    ];
+    Instruction::new_with_bytes(id(), &EscrowInstruction::VetoFeeWithdrawal.pack(), accounts)
+}
+
+// `guardian` must sign when the config is in treasury mode.
+pub fn set_revenue_split(
+    authority: &Pubkey,
+    config: &Pubkey,
+    beneficiaries: Vec<RevenueBeneficiary>,
+    guardian: Option<&Pubkey>,
+) -> Instruction {
+    let mut ix = config_setter(authority, config, EscrowInstruction::SetRevenueSplit { beneficiaries });
+    if let Some(guardian) = guardian {
+        ix.accounts.push(AccountMeta::new_readonly(*guardian, true));
+    }
+    ix
+}
+
+// Pays every beneficiary of the config's revenue split into their ATA for `mint`.
+pub fn distribute_fees(config: &ConfigState, mint: &Pubkey) -> Instruction {
+    let config_pk = config_pda(&id(), &config.tenant()).0;
+    let mut accounts = vec![
+        AccountMeta::new_readonly(config_pk, false),
+        AccountMeta::new(get_associated_token_address(&config_pk, mint), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(mint_stats_pda(&id(), &config_pk, mint).0, false),
+    ];
+    let split = &config.revenue_split;
+    for beneficiary in &split.beneficiaries[..split.count as usize] {
+        let owner = Pubkey::new_from_array(beneficiary.owner);
+        accounts.push(AccountMeta::new(get_associated_token_address(&owner, mint), false));
+    }
+    Instruction::new_with_bytes(id(), &EscrowInstruction::DistributeFees.pack(), accounts)
+}
+
+pub fn set_referral_cap(authority: &Pubkey, config: &Pubkey, max_referral_bps: u16) -> Instruction {
+    config_setter(authority, config, EscrowInstruction::SetReferralCap { max_referral_bps })
+}
+
+pub fn set_maker_rebate(authority: &Pubkey, config: &Pubkey, maker_rebate_bps: u16) -> Instruction {
+    config_setter(authority, config, EscrowInstruction::SetMakerRebate { maker_rebate_bps })
+}
+
+// `config` is a platform or trade config PDA (as for the lamport fee setters below).
+pub fn set_direct_payout(authority: &Pubkey, config: &Pubkey, enabled: bool) -> Instruction {
+    config_setter(authority, config, EscrowInstruction::SetDirectPayout { enabled })
+}
+
+pub fn set_lamport_fee(authority: &Pubkey, config: &Pubkey, per_escrow: u64) -> Instruction {
+    config_setter(authority, config, EscrowInstruction::SetLamportFee { per_escrow })
+}
+
+pub fn withdraw_lamport_fees(fee_collector: &Pubkey, config: &Pubkey, amount: u64) -> Instruction {
+    let accounts = vec![AccountMeta::new(*fee_collector, true), AccountMeta::new(*config, false)];
+    Instruction::new_with_bytes(id(), &EscrowInstruction::WithdrawLamportFees { amount }.pack(), accounts)
+}
+
+pub fn set_lock_surcharge(authority: &Pubkey, trade_config: &Pubkey, bps_per_hour: u16) -> Instruction {
+    config_setter(authority, trade_config, EscrowInstruction::SetLockSurcharge { bps_per_hour })
+}
+
+pub fn set_volume_tiers(authority: &Pubkey, config: &Pubkey, tiers: Vec<VolumeTier>) -> Instruction {
+    config_setter(authority, config, EscrowInstruction::SetVolumeTiers { tiers })
+}
+
+// `account` is an escrow, platform config or trade config PDA; `payer` covers the rent of its growth.
+pub fn migrate_account(payer: &Pubkey, account: &Pubkey) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(*account, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    Instruction::new_with_bytes(id(), &EscrowInstruction::MigrateAccount.pack(), accounts)
+}
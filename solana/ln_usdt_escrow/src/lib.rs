@@ -1,217 +1,846 @@
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
-    entrypoint,
     entrypoint::ProgramResult,
     hash::hash,
     msg,
     program::{invoke, invoke_signed},
-    program_error::ProgramError,
+    program_error::{PrintProgramError, ProgramError},
+    program_option::COption,
     program_pack::Pack,
     pubkey::Pubkey,
     system_instruction,
     sysvar::{clock::Clock, rent::Rent, Sysvar},
 };
 
-// Program id for this fork's production deployment.
-// Keep this in sync with `src/solana/lnUsdtEscrowClient.js` (`LN_USDT_ESCROW_PROGRAM_ID`).
-solana_program::declare_id!("4RS6xpspM1V2K7FKSqeSH6VVaZbtzHzhJqacwrz8gJrF");
+pub mod cpi;
+pub mod error;
+pub mod events;
+pub mod instruction;
+pub mod return_data;
 
-const ESCROW_SEED: &[u8] = b"escrow";
-const CONFIG_SEED: &[u8] = b"config";
-const TRADE_CONFIG_SEED: &[u8] = b"trade_config";
+pub use error::EscrowError;
+use events::EscrowEvent;
+use instruction::{Abi, EscrowInstruction, InitArgs};
+use return_data::{EscrowOutcome, InitFailure, InitValidation};
+
+// Program id (`declare_id!`), generated by build.rs from the mainnet entry of `program-ids.json` (shared with
+// `src/solana/lnUsdtEscrowClient.js`) or, for any other cluster, the `LN_USDT_ESCROW_PROGRAM_ID` override.
+include!(concat!(env!("OUT_DIR"), "/program_id.rs"));
+
+pub const ESCROW_SEED: &[u8] = b"escrow";
+pub const CONFIG_SEED: &[u8] = b"config";
+pub const TRADE_CONFIG_SEED: &[u8] = b"trade_config";
+pub const FEE_WITHDRAWAL_SEED: &[u8] = b"fee_withdrawal";
+pub const PAYER_STATS_SEED: &[u8] = b"payer_stats";
+pub const MINT_STATS_SEED: &[u8] = b"stats";
+pub const AUDIT_LOG_SEED: &[u8] = b"audit_log";
 // Fee caps are enforced on-chain (and re-validated during escrow init).
 // Basis points: 10_000 = 100%.
 const MAX_PLATFORM_FEE_BPS: u16 = 500; // 5%
 const MAX_TRADE_FEE_BPS: u16 = 1000; // 10%
-const MAX_TOTAL_FEE_BPS: u16 = 1500; // 15% (platform + trade)
-
-#[repr(u32)]
-enum EscrowError {
-    InvalidInstruction = 1,
-    InvalidEscrowPda = 2,
-    InvalidVaultAta = 3,
-    InvalidTokenAccount = 4,
-    InvalidSigner = 5,
-    InvalidPreimage = 6,
-    NotActive = 7,
-    TooEarly = 8,
-    InvalidConfigPda = 9,
-    InvalidConfigState = 10,
-    FeeTooHigh = 11,
-    AlreadyInitialized = 12,
-    InvalidFeeVaultAta = 13,
-    InvalidTradeConfigPda = 14,
-    InvalidTradeConfigState = 15,
-    InvalidTradeFeeVaultAta = 16,
-    FeeMismatch = 17,
-}
-
-impl From<EscrowError> for ProgramError {
-    fn from(e: EscrowError) -> Self {
-        ProgramError::Custom(e as u32)
+const MAX_REFERRAL_FEE_BPS: u16 = 500; // 5% (hard ceiling for the per-config referral cap)
+const MAX_TOTAL_FEE_BPS: u16 = 1500; // 15% (platform + trade + referral)
+const MAX_LAMPORT_FEE: u64 = 100_000_000; // 0.1 SOL per escrow, per config
+
+// Zeroed tail of the escrow, config and trade config layouts (escrow v10+, config v10+, trade config v6+). Fields
+// added later are carved out of it under a new version, so accounts at these layouts never need to grow again.
+pub const RESERVED_LEN: usize = 64;
+
+// Like the configs, the escrow layout only grows by adding fields gated on `v`, so escrows opened under an
+// older layout stay claimable/refundable after an upgrade (MigrateAccount moves them to the latest layout).
+// v1 had a single amount and no fees; v2 added the fee now stored as the platform fee.
+#[derive(Debug, Clone)]
+pub struct EscrowState {
+    pub v: u8,
+    pub status: u8, // 0=active, 1=claimed, 2=refunded
+    pub payment_hash: [u8; 32],
+    pub recipient: [u8; 32],
+    pub refund: [u8; 32],
+    pub refund_after: i64,
+    pub mint: [u8; 32],
+    pub net_amount: u64,
+    pub platform_fee_amount: u64,         // v2+
+    pub platform_fee_bps: u16,            // v2+
+    pub platform_fee_collector: [u8; 32], // v2+
+    pub trade_fee_amount: u64,            // v3+
+    pub trade_fee_bps: u16,               // v3+
+    pub trade_fee_collector: [u8; 32],    // v3+
+    pub vault: [u8; 32],
+    pub bump: u8,
+    pub referrer: [u8; 32],        // v4+ (all-zero = no referrer)
+    pub referral_fee_bps: u16,     // v4+
+    pub referral_fee_amount: u64,  // v4+
+    pub maker_rebate_amount: u64,  // v5+ (carved out of platform_fee_amount, paid to the recipient on claim)
+    pub fee_flags: u8,             // v6+ (FLAG_* bits, snapshotted from the configs at init)
+    pub platform_lamport_fee: u64, // v7+ (held by the config PDA until claim/refund)
+    pub trade_lamport_fee: u64,    // v7+ (held by the trade config PDA until claim/refund)
+    pub payer: [u8; 32],           // v8+ (initial depositor; its volume stats are credited on claim)
+    pub tenant: [u8; 32],          // v9+ (platform config namespace; all-zero = the default config)
+    pub reserved: [u8; RESERVED_LEN], // v10+
+}
+
+impl EscrowState {
+    pub const V1: u8 = 1;
+    pub const V2: u8 = 2;
+    pub const V3: u8 = 3;
+    pub const V4: u8 = 4;
+    pub const V5: u8 = 5;
+    pub const V6: u8 = 6;
+    pub const V7: u8 = 7;
+    pub const V8: u8 = 8;
+    pub const V9: u8 = 9;
+    pub const V10: u8 = 10;
+    pub const LATEST: u8 = Self::V10;
+    pub const STATUS_ACTIVE: u8 = return_data::STATUS_ACTIVE;
+    pub const STATUS_CLAIMED: u8 = return_data::STATUS_CLAIMED;
+    pub const STATUS_REFUNDED: u8 = return_data::STATUS_REFUNDED;
+    // Fee paid straight to the collector's ATA instead of the config PDA's fee vault.
+    pub const FLAG_PLATFORM_DIRECT_PAYOUT: u8 = 1 << 0;
+    pub const FLAG_TRADE_DIRECT_PAYOUT: u8 = 1 << 1;
+    // Claim credits the payer's volume stats (set when the platform config had volume tiers at init).
+    pub const FLAG_TRACK_PAYER_VOLUME: u8 = 1 << 2;
+    // The escrow counts towards its configs' mint stats (set on every escrow opened since stats exist). Claim credits
+    // the fees to existing stats PDAs either way.
+    pub const FLAG_TRACK_MINT_STATS: u8 = 1 << 3;
+    // Byte offsets for `getProgramAccounts` memcmp filters. Every field has a fixed size, so an offset holds for
+    // every version that has the field; add a `dataSize` filter of `space(v)` to match a single version.
+    pub const STATUS_OFFSET: usize = 1;
+    pub const PAYMENT_HASH_OFFSET: usize = 2;
+    pub const RECIPIENT_OFFSET: usize = 34;
+    pub const REFUND_OFFSET: usize = 66;
+    pub const MINT_OFFSET: usize = 106;
+    pub const PLATFORM_FEE_COLLECTOR_OFFSET: usize = 156; // v2+
+    pub const TRADE_FEE_COLLECTOR_OFFSET: usize = 198; // v3+
+    pub const REFERRER_OFFSET: usize = 263; // v4+
+    pub const PAYER_OFFSET: usize = 330; // v8+
+    pub const TENANT_OFFSET: usize = 362; // v9+
+
+    // Decodes an escrow account of any supported version.
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        let state = Self::try_from_slice(data).map_err(|_| EscrowError::InvalidEscrowState)?;
+        if !state.is_supported_version() {
+            return Err(EscrowError::InvalidEscrowState.into());
+        }
+        Ok(state)
+    }
+
+    pub fn space(v: u8) -> usize {
+        let mut space = 1usize + 1 + 32 + 32 + 32 + 8 + 32 + 8;
+        if v >= Self::V2 {
+            space += 8 + 2 + 32;
+        }
+        if v >= Self::V3 {
+            space += 8 + 2 + 32;
+        }
+        space += 32 + 1;
+        if v >= Self::V4 {
+            space += 32 + 2 + 8;
+        }
+        if v >= Self::V5 {
+            space += 8;
+        }
+        if v >= Self::V6 {
+            space += 1;
+        }
+        if v >= Self::V7 {
+            space += 8 + 8;
+        }
+        if v >= Self::V8 {
+            space += 32;
+        }
+        if v >= Self::V9 {
+            space += 32;
+        }
+        if v >= Self::V10 {
+            space += RESERVED_LEN;
+        }
+        space
+    }
+
+    pub fn is_supported_version(&self) -> bool {
+        (Self::V1..=Self::LATEST).contains(&self.v)
+    }
+}
+
+impl BorshSerialize for EscrowState {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.v.serialize(writer)?;
+        self.status.serialize(writer)?;
+        self.payment_hash.serialize(writer)?;
+        self.recipient.serialize(writer)?;
+        self.refund.serialize(writer)?;
+        self.refund_after.serialize(writer)?;
+        self.mint.serialize(writer)?;
+        self.net_amount.serialize(writer)?;
+        if self.v >= Self::V2 {
+            self.platform_fee_amount.serialize(writer)?;
+            self.platform_fee_bps.serialize(writer)?;
+            self.platform_fee_collector.serialize(writer)?;
+        }
+        if self.v >= Self::V3 {
+            self.trade_fee_amount.serialize(writer)?;
+            self.trade_fee_bps.serialize(writer)?;
+            self.trade_fee_collector.serialize(writer)?;
+        }
+        self.vault.serialize(writer)?;
+        self.bump.serialize(writer)?;
+        if self.v >= Self::V4 {
+            self.referrer.serialize(writer)?;
+            self.referral_fee_bps.serialize(writer)?;
+            self.referral_fee_amount.serialize(writer)?;
+        }
+        if self.v >= Self::V5 {
+            self.maker_rebate_amount.serialize(writer)?;
+        }
+        if self.v >= Self::V6 {
+            self.fee_flags.serialize(writer)?;
+        }
+        if self.v >= Self::V7 {
+            self.platform_lamport_fee.serialize(writer)?;
+            self.trade_lamport_fee.serialize(writer)?;
+        }
+        if self.v >= Self::V8 {
+            self.payer.serialize(writer)?;
+        }
+        if self.v >= Self::V9 {
+            self.tenant.serialize(writer)?;
+        }
+        if self.v >= Self::V10 {
+            self.reserved.serialize(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl BorshDeserialize for EscrowState {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let mut state = EscrowState {
+            v: u8::deserialize_reader(reader)?,
+            status: u8::deserialize_reader(reader)?,
+            payment_hash: <[u8; 32]>::deserialize_reader(reader)?,
+            recipient: <[u8; 32]>::deserialize_reader(reader)?,
+            refund: <[u8; 32]>::deserialize_reader(reader)?,
+            refund_after: i64::deserialize_reader(reader)?,
+            mint: <[u8; 32]>::deserialize_reader(reader)?,
+            net_amount: u64::deserialize_reader(reader)?,
+            platform_fee_amount: 0,
+            platform_fee_bps: 0,
+            platform_fee_collector: [0u8; 32],
+            trade_fee_amount: 0,
+            trade_fee_bps: 0,
+            trade_fee_collector: [0u8; 32],
+            vault: [0u8; 32],
+            bump: 0,
+            referrer: [0u8; 32],
+            referral_fee_bps: 0,
+            referral_fee_amount: 0,
+            maker_rebate_amount: 0,
+            fee_flags: 0,
+            platform_lamport_fee: 0,
+            trade_lamport_fee: 0,
+            payer: [0u8; 32],
+            tenant: [0u8; 32],
+            reserved: [0u8; RESERVED_LEN],
+        };
+        if state.v >= Self::V2 {
+            state.platform_fee_amount = u64::deserialize_reader(reader)?;
+            state.platform_fee_bps = u16::deserialize_reader(reader)?;
+            state.platform_fee_collector = <[u8; 32]>::deserialize_reader(reader)?;
+        }
+        if state.v >= Self::V3 {
+            state.trade_fee_amount = u64::deserialize_reader(reader)?;
+            state.trade_fee_bps = u16::deserialize_reader(reader)?;
+            state.trade_fee_collector = <[u8; 32]>::deserialize_reader(reader)?;
+        }
+        state.vault = <[u8; 32]>::deserialize_reader(reader)?;
+        state.bump = u8::deserialize_reader(reader)?;
+        if state.v >= Self::V4 {
+            state.referrer = <[u8; 32]>::deserialize_reader(reader)?;
+            state.referral_fee_bps = u16::deserialize_reader(reader)?;
+            state.referral_fee_amount = u64::deserialize_reader(reader)?;
+        }
+        if state.v >= Self::V5 {
+            state.maker_rebate_amount = u64::deserialize_reader(reader)?;
+        }
+        if state.v >= Self::V6 {
+            state.fee_flags = u8::deserialize_reader(reader)?;
+        }
+        if state.v >= Self::V7 {
+            state.platform_lamport_fee = u64::deserialize_reader(reader)?;
+            state.trade_lamport_fee = u64::deserialize_reader(reader)?;
+        }
+        if state.v >= Self::V8 {
+            state.payer = <[u8; 32]>::deserialize_reader(reader)?;
+        }
+        if state.v >= Self::V9 {
+            state.tenant = <[u8; 32]>::deserialize_reader(reader)?;
+        }
+        if state.v >= Self::V10 {
+            state.reserved = <[u8; RESERVED_LEN]>::deserialize_reader(reader)?;
+        }
+        Ok(state)
+    }
+}
+
+// Treasury mode (config v2+). When `owner` is set, fees can only leave the fee vaults through a delayed
+// RequestFeeWithdrawal/ExecuteFeeWithdrawal pair into token accounts owned by `owner`, and `guardian` can veto
+// pending requests. All-zero `owner` means treasury mode is off.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default)]
+pub struct TreasuryState {
+    pub owner: [u8; 32],
+    pub guardian: [u8; 32],
+    pub withdraw_delay_secs: u32,
+}
+
+impl TreasuryState {
+    pub const LEN: usize = 32 + 32 + 4;
+
+    pub fn enabled(&self) -> bool {
+        self.owner != [0u8; 32]
+    }
+}
+
+// Lamport fee mode (config v7+ / trade config v4+). While `per_escrow > 0` the config's fee is charged as that many
+// lamports, moved from the payer to the config PDA at Init, instead of a bps cut of the escrowed token. Lamports of
+// still-open escrows are counted in `reserved` so a refund can always hand them back; only the excess over rent and
+// `reserved` can be withdrawn.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default)]
+pub struct LamportFeeState {
+    pub per_escrow: u64,
+    pub reserved: u64,
+}
+
+impl LamportFeeState {
+    pub const LEN: usize = 8 + 8;
+}
+
+pub const MAX_REVENUE_BENEFICIARIES: usize = 8;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RevenueBeneficiary {
+    pub owner: [u8; 32],
+    pub weight_bps: u16,
+}
+
+// Platform revenue split (config v3+). While `count > 0`, platform fees can only leave the fee vaults through
+// DistributeFees, pro rata to `weight_bps` (the active entries always sum to 10_000).
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default)]
+pub struct RevenueSplit {
+    pub count: u8,
+    pub beneficiaries: [RevenueBeneficiary; MAX_REVENUE_BENEFICIARIES],
+}
+
+impl RevenueSplit {
+    pub const LEN: usize = 1 + MAX_REVENUE_BENEFICIARIES * (32 + 2);
+
+    pub fn active(&self) -> bool {
+        self.count > 0
+    }
+
+    pub fn entries(&self) -> &[RevenueBeneficiary] {
+        &self.beneficiaries[..self.count as usize]
+    }
+}
+
+pub const MAX_VOLUME_TIERS: usize = 4;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VolumeTier {
+    pub min_volume: u64,   // rolling 30-day claimed volume, in the mint's base units
+    pub discount_bps: u16, // share of the platform fee waived (10_000 = all of it)
+}
+
+// Platform fee discounts by payer volume (config v8+). Active tiers have strictly increasing `min_volume`; the
+// highest tier the payer's rolling volume reaches applies.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default)]
+pub struct VolumeTiers {
+    pub count: u8,
+    pub tiers: [VolumeTier; MAX_VOLUME_TIERS],
+}
+
+impl VolumeTiers {
+    pub const LEN: usize = 1 + MAX_VOLUME_TIERS * (8 + 2);
+
+    pub fn active(&self) -> bool {
+        self.count > 0
     }
+
+    fn discounted_fee_bps(&self, fee_bps: u16, rolling_volume: u64) -> u16 {
+        let discount_bps = self.tiers[..self.count as usize]
+            .iter()
+            .rev()
+            .find(|t| rolling_volume >= t.min_volume)
+            .map_or(0, |t| t.discount_bps);
+        (fee_bps as u32 * (10_000 - discount_bps as u32) / 10_000) as u16
+    }
+}
+
+pub const PAYER_STATS_DAYS: usize = 30;
+const SECS_PER_DAY: i64 = 86_400;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default)]
+pub struct DayVolume {
+    pub day: u32, // unix day (unix_timestamp / 86_400)
+    pub volume: u64,
 }
 
+// Claimed volume of one payer in one mint (PDA seeded by payer + mint), bucketed per day in a ring indexed by
+// `day % 30`. Buckets older than the window are ignored by `rolling_volume` and reset on the next write.
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
-struct EscrowState {
-    v: u8,
-    status: u8, // 0=active, 1=claimed, 2=refunded
-    payment_hash: [u8; 32],
-    recipient: [u8; 32],
-    refund: [u8; 32],
-    refund_after: i64,
-    mint: [u8; 32],
-    net_amount: u64,
-    platform_fee_amount: u64,
-    platform_fee_bps: u16,
-    platform_fee_collector: [u8; 32],
-    trade_fee_amount: u64,
-    trade_fee_bps: u16,
-    trade_fee_collector: [u8; 32],
-    vault: [u8; 32],
-    bump: u8,
+pub struct PayerStatsState {
+    pub v: u8,
+    pub payer: [u8; 32],
+    pub mint: [u8; 32],
+    pub bump: u8,
+    pub days: [DayVolume; PAYER_STATS_DAYS],
 }
 
-impl EscrowState {
-    const V3: u8 = 3;
-    const STATUS_ACTIVE: u8 = 0;
-    const STATUS_CLAIMED: u8 = 1;
-    const STATUS_REFUNDED: u8 = 2;
+impl PayerStatsState {
+    pub const V1: u8 = 1;
+    pub const LEN: usize = 1 + 32 + 32 + 1 + PAYER_STATS_DAYS * (4 + 8);
+
+    // Decodes a payer stats account. Does not check the PDA; see `payer_stats_pda`.
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        let state = Self::try_from_slice(data).map_err(|_| EscrowError::InvalidPayerStatsPda)?;
+        if state.v != Self::V1 {
+            return Err(EscrowError::InvalidPayerStatsPda.into());
+        }
+        Ok(state)
+    }
+
+    pub fn unix_day(unix_timestamp: i64) -> u32 {
+        (unix_timestamp.max(0) / SECS_PER_DAY) as u32
+    }
+
+    // Volume over the 30 days ending with (and including) `today`.
+    pub fn rolling_volume(&self, today: u32) -> u64 {
+        self.days
+            .iter()
+            .filter(|d| d.day <= today && d.day + PAYER_STATS_DAYS as u32 > today)
+            .fold(0u64, |acc, d| acc.saturating_add(d.volume))
+    }
+
+    fn record(&mut self, today: u32, volume: u64) {
+        let bucket = &mut self.days[today as usize % PAYER_STATS_DAYS];
+        if bucket.day != today {
+            *bucket = DayVolume { day: today, volume: 0 };
+        }
+        bucket.volume = bucket.volume.saturating_add(volume);
+    }
+}
+
+// Running totals for one config (platform or trade) in one mint; PDA seeded by config + mint and created by the
+// first Init that routes fees to that config in that mint. Every instruction that moves tokens into or out of
+// the config's fee vault updates it, so the vault balance always equals `fees_accrued - fees_withdrawn`.
+// Direct payouts never touch the vault and count as accrued and withdrawn at once.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default)]
+pub struct MintStatsState {
+    pub v: u8,
+    pub config: [u8; 32],
+    pub mint: [u8; 32],
+    pub bump: u8,
+    pub total_volume: u64, // escrowed amount including fees, summed over every Init
+    pub active_escrows: u64,
+    pub claimed_escrows: u64,
+    pub refunded_escrows: u64,
+    pub fees_accrued: u64,   // token fees credited to the config on claim
+    pub fees_withdrawn: u64, // token fees moved out of the fee vault (withdrawals, sweeps, distributions)
+}
+
+impl MintStatsState {
+    pub const V1: u8 = 1;
+    pub const LEN: usize = 1 + 32 + 32 + 1 + 6 * 8;
+
+    // Decodes a mint stats account. Does not check the PDA; see `mint_stats_pda`.
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        let state = Self::try_from_slice(data).map_err(|_| EscrowError::InvalidMintStatsPda)?;
+        if state.v != Self::V1 {
+            return Err(EscrowError::InvalidMintStatsPda.into());
+        }
+        Ok(state)
+    }
+}
+
+pub const AUDIT_LOG_ENTRIES: usize = 16;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default)]
+pub struct ConfigChange {
+    pub slot: u64,
+    pub unix_timestamp: i64,
+    pub signer: [u8; 32],
+    pub old_fee_bps: u16,
+    pub new_fee_bps: u16,
+    pub old_fee_collector: [u8; 32],
+    pub new_fee_collector: [u8; 32],
 }
 
+// Last AUDIT_LOG_ENTRIES SetConfig/SetTradeConfig changes of one config (PDA seeded by the config PDA), kept
+// in a ring: change `n` (0-based, counted by `total_changes`) lives at `entries[n % AUDIT_LOG_ENTRIES]`.
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
-struct ConfigState {
-    v: u8,
-    authority: [u8; 32],
-    fee_collector: [u8; 32],
-    fee_bps: u16,
-    bump: u8,
+pub struct AuditLogState {
+    pub v: u8,
+    pub config: [u8; 32],
+    pub bump: u8,
+    pub total_changes: u64,
+    pub entries: [ConfigChange; AUDIT_LOG_ENTRIES],
+}
+
+impl AuditLogState {
+    pub const V1: u8 = 1;
+    pub const LEN: usize = 1 + 32 + 1 + 8 + AUDIT_LOG_ENTRIES * (8 + 8 + 32 + 2 + 2 + 32 + 32);
+
+    // Decodes an audit log account. Does not check the PDA; see `audit_log_pda`.
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        let state = Self::try_from_slice(data).map_err(|_| EscrowError::InvalidAuditLogPda)?;
+        if state.v != Self::V1 {
+            return Err(EscrowError::InvalidAuditLogPda.into());
+        }
+        Ok(state)
+    }
+
+    // The logged changes, oldest first (at most the last AUDIT_LOG_ENTRIES).
+    pub fn changes(&self) -> Vec<ConfigChange> {
+        let kept = self.total_changes.min(AUDIT_LOG_ENTRIES as u64);
+        (self.total_changes - kept..self.total_changes)
+            .map(|n| self.entries[(n % AUDIT_LOG_ENTRIES as u64) as usize])
+            .collect()
+    }
+
+    fn push(&mut self, change: ConfigChange) {
+        self.entries[(self.total_changes % AUDIT_LOG_ENTRIES as u64) as usize] = change;
+        self.total_changes = self.total_changes.saturating_add(1);
+    }
+}
+
+// Config layouts only ever grow by appending fields, gated on `v`, so older accounts keep decoding and are
+// upgraded in place (realloc) the first time a newer field is written.
+#[derive(Debug, Clone)]
+pub struct ConfigState {
+    pub v: u8,
+    pub authority: [u8; 32],
+    pub fee_collector: [u8; 32],
+    pub fee_bps: u16,
+    pub bump: u8,
+    pub treasury: TreasuryState,       // v2+
+    pub revenue_split: RevenueSplit,   // v3+
+    pub max_referral_bps: u16,         // v4+ (0 = referrals disabled)
+    pub maker_rebate_bps: u16,         // v5+ (share of the platform fee rebated to the maker; 10_000 = all of it)
+    pub direct_payout: bool,           // v6+ (fees go straight to ATA(fee_collector, mint) at claim)
+    pub lamport_fees: LamportFeeState, // v7+
+    pub volume_tiers: VolumeTiers,     // v8+
+    pub tenant: [u8; 32],              // v9+ (namespace the PDA is seeded by; all-zero = the default config)
+    pub reserved: [u8; RESERVED_LEN],  // v10+
 }
 
 impl ConfigState {
-    const V1: u8 = 1;
+    pub const V1: u8 = 1;
+    pub const V2: u8 = 2;
+    pub const V3: u8 = 3;
+    pub const V4: u8 = 4;
+    pub const V5: u8 = 5;
+    pub const V6: u8 = 6;
+    pub const V7: u8 = 7;
+    pub const V8: u8 = 8;
+    pub const V9: u8 = 9;
+    pub const V10: u8 = 10;
+    pub const LATEST: u8 = Self::V10;
+    // Byte offsets for memcmp filters (see EscrowState). The tenant is only stored from v9 on.
+    pub const AUTHORITY_OFFSET: usize = 1;
+    pub const FEE_COLLECTOR_OFFSET: usize = 33;
+    pub const TENANT_OFFSET: usize = 471;
+
+    // Decodes a platform config of any supported version. Does not check the PDA; see `config_pda`.
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        let state = Self::try_from_slice(data).map_err(|_| EscrowError::InvalidConfigState)?;
+        if !state.is_supported_version() {
+            return Err(EscrowError::InvalidConfigState.into());
+        }
+        Ok(state)
+    }
+
+    pub fn space(v: u8) -> usize {
+        let mut space = 1usize + 32 + 32 + 2 + 1;
+        if v >= Self::V2 {
+            space += TreasuryState::LEN;
+        }
+        if v >= Self::V3 {
+            space += RevenueSplit::LEN;
+        }
+        if v >= Self::V4 {
+            space += 2;
+        }
+        if v >= Self::V5 {
+            space += 2;
+        }
+        if v >= Self::V6 {
+            space += 1;
+        }
+        if v >= Self::V7 {
+            space += LamportFeeState::LEN;
+        }
+        if v >= Self::V8 {
+            space += VolumeTiers::LEN;
+        }
+        if v >= Self::V9 {
+            space += 32;
+        }
+        if v >= Self::V10 {
+            space += RESERVED_LEN;
+        }
+        space
+    }
+
+    pub fn is_supported_version(&self) -> bool {
+        (Self::V1..=Self::LATEST).contains(&self.v)
+    }
+
+    pub fn tenant(&self) -> Pubkey {
+        Pubkey::new_from_array(self.tenant)
+    }
+
+    fn signer_seeds(&self) -> Vec<&[u8]> {
+        let bump = std::slice::from_ref(&self.bump);
+        if self.tenant() == Pubkey::default() {
+            vec![CONFIG_SEED, bump]
+        } else {
+            vec![CONFIG_SEED, &self.tenant, bump]
+        }
+    }
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
-struct TradeConfigState {
-    v: u8,
-    authority: [u8; 32],
-    fee_collector: [u8; 32],
-    fee_bps: u16,
-    bump: u8,
+impl BorshSerialize for ConfigState {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.v.serialize(writer)?;
+        self.authority.serialize(writer)?;
+        self.fee_collector.serialize(writer)?;
+        self.fee_bps.serialize(writer)?;
+        self.bump.serialize(writer)?;
+        if self.v >= Self::V2 {
+            self.treasury.serialize(writer)?;
+        }
+        if self.v >= Self::V3 {
+            self.revenue_split.serialize(writer)?;
+        }
+        if self.v >= Self::V4 {
+            self.max_referral_bps.serialize(writer)?;
+        }
+        if self.v >= Self::V5 {
+            self.maker_rebate_bps.serialize(writer)?;
+        }
+        if self.v >= Self::V6 {
+            self.direct_payout.serialize(writer)?;
+        }
+        if self.v >= Self::V7 {
+            self.lamport_fees.serialize(writer)?;
+        }
+        if self.v >= Self::V8 {
+            self.volume_tiers.serialize(writer)?;
+        }
+        if self.v >= Self::V9 {
+            self.tenant.serialize(writer)?;
+        }
+        if self.v >= Self::V10 {
+            self.reserved.serialize(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl BorshDeserialize for ConfigState {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let mut state = ConfigState {
+            v: u8::deserialize_reader(reader)?,
+            authority: <[u8; 32]>::deserialize_reader(reader)?,
+            fee_collector: <[u8; 32]>::deserialize_reader(reader)?,
+            fee_bps: u16::deserialize_reader(reader)?,
+            bump: u8::deserialize_reader(reader)?,
+            treasury: TreasuryState::default(),
+            revenue_split: RevenueSplit::default(),
+            max_referral_bps: 0,
+            maker_rebate_bps: 0,
+            direct_payout: false,
+            lamport_fees: LamportFeeState::default(),
+            volume_tiers: VolumeTiers::default(),
+            tenant: [0u8; 32],
+            reserved: [0u8; RESERVED_LEN],
+        };
+        if state.v >= Self::V2 {
+            state.treasury = TreasuryState::deserialize_reader(reader)?;
+        }
+        if state.v >= Self::V3 {
+            state.revenue_split = RevenueSplit::deserialize_reader(reader)?;
+        }
+        if state.v >= Self::V4 {
+            state.max_referral_bps = u16::deserialize_reader(reader)?;
+        }
+        if state.v >= Self::V5 {
+            state.maker_rebate_bps = u16::deserialize_reader(reader)?;
+        }
+        if state.v >= Self::V6 {
+            state.direct_payout = bool::deserialize_reader(reader)?;
+        }
+        if state.v >= Self::V7 {
+            state.lamport_fees = LamportFeeState::deserialize_reader(reader)?;
+        }
+        if state.v >= Self::V8 {
+            state.volume_tiers = VolumeTiers::deserialize_reader(reader)?;
+        }
+        if state.v >= Self::V9 {
+            state.tenant = <[u8; 32]>::deserialize_reader(reader)?;
+        }
+        if state.v >= Self::V10 {
+            state.reserved = <[u8; RESERVED_LEN]>::deserialize_reader(reader)?;
+        }
+        Ok(state)
+    }
+}
+
+// Same layout and versioning as ConfigState up to v2 (the revenue split, referral cap and maker rebate are
+// platform-only).
+#[derive(Debug, Clone)]
+pub struct TradeConfigState {
+    pub v: u8,
+    pub authority: [u8; 32],
+    pub fee_collector: [u8; 32],
+    pub fee_bps: u16,
+    pub bump: u8,
+    pub treasury: TreasuryState,          // v2+
+    pub direct_payout: bool,              // v3+ (fees go straight to ATA(fee_collector, mint) at claim)
+    pub lamport_fees: LamportFeeState,    // v4+
+    pub lock_surcharge_bps_per_hour: u16, // v5+ (added per started hour between Init and refund_after; 0 = off)
+    pub reserved: [u8; RESERVED_LEN],     // v6+
 }
 
 impl TradeConfigState {
-    const V1: u8 = 1;
-}
-
-enum EscrowIx {
-    Init {
-        payment_hash: [u8; 32],
-        recipient: Pubkey,
-        refund: Pubkey,
-        refund_after: i64,
-        amount: u64,
-        expected_platform_fee_bps: u16,
-        expected_trade_fee_bps: u16,
-        trade_fee_collector: Pubkey,
-    },
-    Claim { preimage: [u8; 32] },
-    Refund,
-    InitConfig { fee_collector: Pubkey, fee_bps: u16 },
-    SetConfig { fee_collector: Pubkey, fee_bps: u16 },
-    WithdrawFees { amount: u64 },
-    InitTradeConfig { fee_collector: Pubkey, fee_bps: u16 },
-    SetTradeConfig { fee_collector: Pubkey, fee_bps: u16 },
-    WithdrawTradeFees { amount: u64 },
-}
-
-fn read_bytes<const N: usize>(data: &mut &[u8]) -> Result<[u8; N], ProgramError> {
-    if data.len() < N {
-        return Err(EscrowError::InvalidInstruction.into());
-    }
-    let (head, tail) = data.split_at(N);
-    *data = tail;
-    let mut out = [0u8; N];
-    out.copy_from_slice(head);
-    Ok(out)
-}
-
-fn read_u64_le(data: &mut &[u8]) -> Result<u64, ProgramError> {
-    Ok(u64::from_le_bytes(read_bytes::<8>(data)?))
-}
-
-fn read_i64_le(data: &mut &[u8]) -> Result<i64, ProgramError> {
-    Ok(i64::from_le_bytes(read_bytes::<8>(data)?))
-}
-
-fn read_u16_le(data: &mut &[u8]) -> Result<u16, ProgramError> {
-    Ok(u16::from_le_bytes(read_bytes::<2>(data)?))
-}
-
-fn parse_ix(input: &[u8]) -> Result<EscrowIx, ProgramError> {
-    let mut data = input;
-    if data.is_empty() {
-        return Err(EscrowError::InvalidInstruction.into());
-    }
-    let tag = data[0];
-    data = &data[1..];
-    match tag {
-        0 => {
-            let payment_hash = read_bytes::<32>(&mut data)?;
-            let recipient = Pubkey::new_from_array(read_bytes::<32>(&mut data)?);
-            let refund = Pubkey::new_from_array(read_bytes::<32>(&mut data)?);
-            let refund_after = read_i64_le(&mut data)?;
-            let amount = read_u64_le(&mut data)?;
-            let expected_platform_fee_bps = read_u16_le(&mut data)?;
-            let expected_trade_fee_bps = read_u16_le(&mut data)?;
-            let trade_fee_collector = Pubkey::new_from_array(read_bytes::<32>(&mut data)?);
-            Ok(EscrowIx::Init {
-                payment_hash,
-                recipient,
-                refund,
-                refund_after,
-                amount,
-                expected_platform_fee_bps,
-                expected_trade_fee_bps,
-                trade_fee_collector,
-            })
-        }
-        1 => {
-            let preimage = read_bytes::<32>(&mut data)?;
-            Ok(EscrowIx::Claim { preimage })
-        }
-        2 => Ok(EscrowIx::Refund),
-        3 => {
-            let fee_collector = Pubkey::new_from_array(read_bytes::<32>(&mut data)?);
-            let fee_bps = read_u16_le(&mut data)?;
-            Ok(EscrowIx::InitConfig { fee_collector, fee_bps })
-        }
-        4 => {
-            let fee_collector = Pubkey::new_from_array(read_bytes::<32>(&mut data)?);
-            let fee_bps = read_u16_le(&mut data)?;
-            Ok(EscrowIx::SetConfig { fee_collector, fee_bps })
-        }
-        5 => {
-            let amount = read_u64_le(&mut data)?;
-            Ok(EscrowIx::WithdrawFees { amount })
-        }
-        6 => {
-            let fee_collector = Pubkey::new_from_array(read_bytes::<32>(&mut data)?);
-            let fee_bps = read_u16_le(&mut data)?;
-            Ok(EscrowIx::InitTradeConfig { fee_collector, fee_bps })
-        }
-        7 => {
-            let fee_collector = Pubkey::new_from_array(read_bytes::<32>(&mut data)?);
-            let fee_bps = read_u16_le(&mut data)?;
-            Ok(EscrowIx::SetTradeConfig { fee_collector, fee_bps })
-        }
-        8 => {
-            let amount = read_u64_le(&mut data)?;
-            Ok(EscrowIx::WithdrawTradeFees { amount })
-        }
-        _ => Err(EscrowError::InvalidInstruction.into()),
+    pub const V1: u8 = 1;
+    pub const V2: u8 = 2;
+    pub const V3: u8 = 3;
+    pub const V4: u8 = 4;
+    pub const V5: u8 = 5;
+    pub const V6: u8 = 6;
+    pub const LATEST: u8 = Self::V6;
+    pub const AUTHORITY_OFFSET: usize = 1;
+    pub const FEE_COLLECTOR_OFFSET: usize = 33;
+
+    // Decodes a trade config of any supported version. Does not check the PDA; see `trade_config_pda`.
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        let state = Self::try_from_slice(data).map_err(|_| EscrowError::InvalidTradeConfigState)?;
+        if !state.is_supported_version() {
+            return Err(EscrowError::InvalidTradeConfigState.into());
+        }
+        Ok(state)
+    }
+
+    pub fn space(v: u8) -> usize {
+        let mut space = 1usize + 32 + 32 + 2 + 1;
+        if v >= Self::V2 {
+            space += TreasuryState::LEN;
+        }
+        if v >= Self::V3 {
+            space += 1;
+        }
+        if v >= Self::V4 {
+            space += LamportFeeState::LEN;
+        }
+        if v >= Self::V5 {
+            space += 2;
+        }
+        if v >= Self::V6 {
+            space += RESERVED_LEN;
+        }
+        space
+    }
+
+    pub fn is_supported_version(&self) -> bool {
+        (Self::V1..=Self::LATEST).contains(&self.v)
+    }
+}
+
+impl BorshSerialize for TradeConfigState {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.v.serialize(writer)?;
+        self.authority.serialize(writer)?;
+        self.fee_collector.serialize(writer)?;
+        self.fee_bps.serialize(writer)?;
+        self.bump.serialize(writer)?;
+        if self.v >= Self::V2 {
+            self.treasury.serialize(writer)?;
+        }
+        if self.v >= Self::V3 {
+            self.direct_payout.serialize(writer)?;
+        }
+        if self.v >= Self::V4 {
+            self.lamport_fees.serialize(writer)?;
+        }
+        if self.v >= Self::V5 {
+            self.lock_surcharge_bps_per_hour.serialize(writer)?;
+        }
+        if self.v >= Self::V6 {
+            self.reserved.serialize(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl BorshDeserialize for TradeConfigState {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let mut state = TradeConfigState {
+            v: u8::deserialize_reader(reader)?,
+            authority: <[u8; 32]>::deserialize_reader(reader)?,
+            fee_collector: <[u8; 32]>::deserialize_reader(reader)?,
+            fee_bps: u16::deserialize_reader(reader)?,
+            bump: u8::deserialize_reader(reader)?,
+            treasury: TreasuryState::default(),
+            direct_payout: false,
+            lamport_fees: LamportFeeState::default(),
+            lock_surcharge_bps_per_hour: 0,
+            reserved: [0u8; RESERVED_LEN],
+        };
+        if state.v >= Self::V2 {
+            state.treasury = TreasuryState::deserialize_reader(reader)?;
+        }
+        if state.v >= Self::V3 {
+            state.direct_payout = bool::deserialize_reader(reader)?;
+        }
+        if state.v >= Self::V4 {
+            state.lamport_fees = LamportFeeState::deserialize_reader(reader)?;
+        }
+        if state.v >= Self::V5 {
+            state.lock_surcharge_bps_per_hour = u16::deserialize_reader(reader)?;
+        }
+        if state.v >= Self::V6 {
+            state.reserved = <[u8; RESERVED_LEN]>::deserialize_reader(reader)?;
+        }
+        Ok(state)
+    }
+}
+
+// A pending treasury-mode withdrawal out of one fee vault (PDA seeded by config + fee vault, so at most one
+// request per vault is in flight).
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct FeeWithdrawalState {
+    pub v: u8,
+    pub config: [u8; 32],
+    pub fee_vault: [u8; 32],
+    pub amount: u64, // 0 = whatever the vault holds at execution time
+    pub requested_at: i64,
+    pub executable_at: i64,
+    pub rent_payer: [u8; 32],
+    pub bump: u8,
+}
+
+impl FeeWithdrawalState {
+    pub const V1: u8 = 1;
+    pub const LEN: usize = 1 + 32 + 32 + 8 + 8 + 8 + 32 + 1;
+
+    // Decodes a fee withdrawal request. Does not check the PDA; see `fee_withdrawal_pda`.
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        let state = Self::try_from_slice(data).map_err(|_| EscrowError::InvalidFeeWithdrawalState)?;
+        if state.v != Self::V1 {
+            return Err(EscrowError::InvalidFeeWithdrawalState.into());
+        }
+        Ok(state)
     }
 }
 
@@ -224,535 +853,2358 @@ fn assert_signer(ai: &AccountInfo) -> Result<(), ProgramError> {
 
 fn assert_writable(ai: &AccountInfo) -> Result<(), ProgramError> {
     if !ai.is_writable {
-        return Err(ProgramError::InvalidAccountData);
+        return Err(EscrowError::AccountNotWritable.into());
+    }
+    Ok(())
+}
+
+// Program accounts are only handed to CPIs, but a look-alike program must not get the chance to run.
+fn assert_program_id(ai: &AccountInfo, expected: &Pubkey, err: EscrowError) -> Result<(), ProgramError> {
+    if ai.key != expected {
+        msg!("unexpected program {}", ai.key);
+        return Err(err.into());
     }
     Ok(())
 }
 
-fn pda_for_hash(program_id: &Pubkey, payment_hash: &[u8; 32]) -> (Pubkey, u8) {
+// State accounts are only trusted once we know this program wrote them.
+fn assert_program_owned(ai: &AccountInfo, program_id: &Pubkey) -> Result<(), ProgramError> {
+    if ai.owner != program_id {
+        msg!("account {} not owned by program", ai.key);
+        return Err(EscrowError::InvalidAccountOwner.into());
+    }
+    Ok(())
+}
+
+// v1 instructions pass the rent/clock sysvars as accounts; v2 leaves them out and reads them via syscall.
+fn next_sysvar_account<'a, 'b, S: Sysvar>(
+    abi: Abi,
+    acc_iter: &mut std::slice::Iter<'a, AccountInfo<'b>>,
+) -> Result<Option<&'a AccountInfo<'b>>, ProgramError> {
+    match abi {
+        Abi::V1 => {
+            let account = next_account_info(acc_iter)?;
+            if !S::check_id(account.key) {
+                msg!("unexpected sysvar {}", account.key);
+                return Err(EscrowError::InvalidSysvar.into());
+            }
+            Ok(Some(account))
+        }
+        Abi::V2 => Ok(None),
+    }
+}
+
+fn load_sysvar<S: Sysvar>(account: Option<&AccountInfo>) -> Result<S, ProgramError> {
+    match account {
+        Some(account) => S::from_account_info(account),
+        None => S::get(),
+    }
+}
+
+pub fn pda_for_hash(program_id: &Pubkey, payment_hash: &[u8; 32]) -> (Pubkey, u8) {
     Pubkey::find_program_address(&[ESCROW_SEED, payment_hash], program_id)
 }
 
-fn config_pda(program_id: &Pubkey) -> (Pubkey, u8) {
-    Pubkey::find_program_address(&[CONFIG_SEED], program_id)
+// Platform configs are namespaced by tenant so several operators can share one deployment. The default
+// (all-zero) tenant keeps the original un-namespaced PDA.
+pub fn config_pda(program_id: &Pubkey, tenant: &Pubkey) -> (Pubkey, u8) {
+    if *tenant == Pubkey::default() {
+        Pubkey::find_program_address(&[CONFIG_SEED], program_id)
+    } else {
+        Pubkey::find_program_address(&[CONFIG_SEED, tenant.as_ref()], program_id)
+    }
+}
+
+// Loads a platform config of any tenant. The PDA is re-derived from the tenant stored in the account, so only
+// the canonical config of that tenant passes.
+fn load_config(program_id: &Pubkey, config: &AccountInfo) -> Result<ConfigState, ProgramError> {
+    if config.data_is_empty() {
+        msg!("config not initialized");
+        return Err(EscrowError::InvalidConfigState.into());
+    }
+    assert_program_owned(config, program_id)?;
+    let state =
+        ConfigState::try_from_slice(&config.try_borrow_data()?).map_err(|_| EscrowError::InvalidConfigState)?;
+    let (expected_config, bump) = config_pda(program_id, &state.tenant());
+    if expected_config != *config.key {
+        msg!("config PDA mismatch");
+        return Err(EscrowError::InvalidConfigPda.into());
+    }
+    if !state.is_supported_version() || state.bump != bump {
+        msg!("config state version/bump mismatch");
+        return Err(EscrowError::InvalidConfigState.into());
+    }
+    Ok(state)
 }
 
-fn trade_config_pda(program_id: &Pubkey, fee_collector: &Pubkey) -> (Pubkey, u8) {
+pub fn trade_config_pda(program_id: &Pubkey, fee_collector: &Pubkey) -> (Pubkey, u8) {
     Pubkey::find_program_address(&[TRADE_CONFIG_SEED, fee_collector.as_ref()], program_id)
 }
 
-fn require_active(state: &EscrowState) -> Result<(), ProgramError> {
-    if state.status != EscrowState::STATUS_ACTIVE {
-        return Err(EscrowError::NotActive.into());
+pub fn fee_withdrawal_pda(program_id: &Pubkey, config: &Pubkey, fee_vault: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FEE_WITHDRAWAL_SEED, config.as_ref(), fee_vault.as_ref()], program_id)
+}
+
+// Trade fee bps for an escrow locked from `now` until `refund_after`: the base fee plus the lock surcharge for
+// every started hour, capped at MAX_TRADE_FEE_BPS.
+fn lock_adjusted_trade_fee_bps(trade_cfg: &TradeConfigState, now: i64, refund_after: i64) -> u16 {
+    let lock_secs = refund_after.saturating_sub(now).max(0) as u64;
+    let hours = lock_secs.div_ceil(3600);
+    let surcharge = hours.saturating_mul(trade_cfg.lock_surcharge_bps_per_hour as u64);
+    (trade_cfg.fee_bps as u64)
+        .saturating_add(surcharge)
+        .min(MAX_TRADE_FEE_BPS as u64) as u16
+}
+
+pub fn payer_stats_pda(program_id: &Pubkey, payer: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PAYER_STATS_SEED, payer.as_ref(), mint.as_ref()], program_id)
+}
+
+pub fn mint_stats_pda(program_id: &Pubkey, config: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MINT_STATS_SEED, config.as_ref(), mint.as_ref()], program_id)
+}
+
+// Returns `None` when the stats PDA was never created (no escrow has routed fees to the config in that mint
+// since stats were introduced); there is nothing to keep in step with then.
+fn load_mint_stats(
+    program_id: &Pubkey,
+    stats: &AccountInfo,
+    config: &Pubkey,
+    mint: &Pubkey,
+) -> Result<Option<MintStatsState>, ProgramError> {
+    assert_writable(stats)?;
+    let (expected_stats, bump) = mint_stats_pda(program_id, config, mint);
+    if expected_stats != *stats.key {
+        msg!("mint stats PDA mismatch");
+        return Err(EscrowError::InvalidMintStatsPda.into());
+    }
+    if stats.data_is_empty() {
+        return Ok(None);
+    }
+    assert_program_owned(stats, program_id)?;
+    let state = MintStatsState::unpack(&stats.try_borrow_data()?)?;
+    if state.bump != bump {
+        msg!("mint stats state mismatch");
+        return Err(EscrowError::InvalidMintStatsPda.into());
+    }
+    Ok(Some(state))
+}
+
+fn update_mint_stats(
+    program_id: &Pubkey,
+    stats: &AccountInfo,
+    config: &Pubkey,
+    mint: &Pubkey,
+    update: impl FnOnce(&mut MintStatsState),
+) -> ProgramResult {
+    if let Some(mut state) = load_mint_stats(program_id, stats, config, mint)? {
+        update(&mut state);
+        state
+            .serialize(&mut &mut stats.try_borrow_mut_data()?[..])
+            .map_err(|_| ProgramError::InvalidAccountData)?;
     }
     Ok(())
 }
 
-entrypoint!(process_instruction);
+// Two trailing accounts added after v1 clients were deployed (such as the platform and trade mint stats PDAs): an
+// old client leaves both out, a new one passes both.
+fn next_optional_pair<'a, 'b>(
+    acc_iter: &mut std::slice::Iter<'a, AccountInfo<'b>>,
+) -> Result<Option<(&'a AccountInfo<'b>, &'a AccountInfo<'b>)>, ProgramError> {
+    match acc_iter.next() {
+        Some(first) => Ok(Some((first, next_account_info(acc_iter)?))),
+        None => Ok(None),
+    }
+}
 
-fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
-    let ix = parse_ix(instruction_data)?;
-    match ix {
-        EscrowIx::Init {
-            payment_hash,
-            recipient,
-            refund,
-            refund_after,
-            amount,
-            expected_platform_fee_bps,
-            expected_trade_fee_bps,
-            trade_fee_collector,
-        } => process_init(
+// Creates the (config, mint) stats PDA if needed. Fees already sitting in `fee_vault` (accrued before stats
+// existed) are counted as accrued at creation so the vault invariant holds from then on.
+fn init_mint_stats<'a>(
+    program_id: &Pubkey,
+    payer: &AccountInfo<'a>,
+    stats: &AccountInfo<'a>,
+    config: &Pubkey,
+    mint: &Pubkey,
+    system_program: &AccountInfo<'a>,
+    fee_vault: Option<&AccountInfo<'a>>,
+) -> Result<MintStatsState, ProgramError> {
+    if let Some(state) = load_mint_stats(program_id, stats, config, mint)? {
+        return Ok(state);
+    }
+    let (_, bump) = mint_stats_pda(program_id, config, mint);
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            stats.key,
+            Rent::get()?.minimum_balance(MintStatsState::LEN),
+            MintStatsState::LEN as u64,
             program_id,
-            accounts,
-            payment_hash,
-            recipient,
-            refund,
-            refund_after,
-            amount,
-            expected_platform_fee_bps,
-            expected_trade_fee_bps,
-            trade_fee_collector,
         ),
-        EscrowIx::Claim { preimage } => process_claim(program_id, accounts, preimage),
-        EscrowIx::Refund => process_refund(program_id, accounts),
-        EscrowIx::InitConfig {
-            fee_collector,
-            fee_bps,
-        } => process_init_config(program_id, accounts, fee_collector, fee_bps),
-        EscrowIx::SetConfig {
+        &[payer.clone(), stats.clone(), system_program.clone()],
+        &[&[MINT_STATS_SEED, config.as_ref(), mint.as_ref(), &[bump]]],
+    )?;
+    let fees_accrued = match fee_vault.filter(|v| !v.data_is_empty()) {
+        Some(v) => spl_token::state::Account::unpack(&v.try_borrow_data()?)
+            .map_err(|_| EscrowError::InvalidTokenAccountData)?
+            .amount,
+        None => 0,
+    };
+    Ok(MintStatsState {
+        v: MintStatsState::V1,
+        config: config.to_bytes(),
+        mint: mint.to_bytes(),
+        bump,
+        fees_accrued,
+        ..MintStatsState::default()
+    })
+}
+
+pub fn audit_log_pda(program_id: &Pubkey, config: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[AUDIT_LOG_SEED, config.as_ref()], program_id)
+}
+
+// Appends `change` to the config's audit log, creating the log (paid by `authority`) on the first change.
+fn record_config_change<'a>(
+    program_id: &Pubkey,
+    authority: &AccountInfo<'a>,
+    audit_log: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    config: &Pubkey,
+    change: ConfigChange,
+) -> ProgramResult {
+    assert_writable(audit_log)?;
+    let (expected_log, bump) = audit_log_pda(program_id, config);
+    if expected_log != *audit_log.key {
+        msg!("audit log PDA mismatch");
+        return Err(EscrowError::InvalidAuditLogPda.into());
+    }
+    let mut state = if audit_log.data_is_empty() {
+        assert_writable(authority)?;
+        invoke_signed(
+            &system_instruction::create_account(
+                authority.key,
+                audit_log.key,
+                Rent::get()?.minimum_balance(AuditLogState::LEN),
+                AuditLogState::LEN as u64,
+                program_id,
+            ),
+            &[authority.clone(), audit_log.clone(), system_program.clone()],
+            &[&[AUDIT_LOG_SEED, config.as_ref(), &[bump]]],
+        )?;
+        AuditLogState {
+            v: AuditLogState::V1,
+            config: config.to_bytes(),
+            bump,
+            total_changes: 0,
+            entries: [ConfigChange::default(); AUDIT_LOG_ENTRIES],
+        }
+    } else {
+        assert_program_owned(audit_log, program_id)?;
+        let state = AuditLogState::unpack(&audit_log.try_borrow_data()?)?;
+        if state.bump != bump {
+            msg!("audit log state mismatch");
+            return Err(EscrowError::InvalidAuditLogPda.into());
+        }
+        state
+    };
+    state.push(change);
+    state
+        .serialize(&mut &mut audit_log.try_borrow_mut_data()?[..])
+        .map_err(|_| ProgramError::InvalidAccountData)
+}
+
+// Closes the config's audit log, if it was ever created, when the config itself is closed.
+fn close_audit_log(
+    program_id: &Pubkey,
+    audit_log: &AccountInfo,
+    config: &Pubkey,
+    rent_dest: &AccountInfo,
+) -> ProgramResult {
+    assert_writable(audit_log)?;
+    if audit_log_pda(program_id, config).0 != *audit_log.key {
+        msg!("audit log PDA mismatch");
+        return Err(EscrowError::InvalidAuditLogPda.into());
+    }
+    if audit_log.data_is_empty() {
+        return Ok(());
+    }
+    assert_program_owned(audit_log, program_id)?;
+    close_program_account(audit_log, rent_dest)
+}
+
+fn load_payer_stats(program_id: &Pubkey, payer_stats: &AccountInfo) -> Result<PayerStatsState, ProgramError> {
+    assert_program_owned(payer_stats, program_id)?;
+    let stats = PayerStatsState::unpack(&payer_stats.try_borrow_data()?)?;
+    let (expected_stats, bump) = payer_stats_pda(
+        program_id,
+        &Pubkey::new_from_array(stats.payer),
+        &Pubkey::new_from_array(stats.mint),
+    );
+    if expected_stats != *payer_stats.key || bump != stats.bump {
+        msg!("payer stats state mismatch");
+        return Err(EscrowError::InvalidPayerStatsPda.into());
+    }
+    Ok(stats)
+}
+
+fn require_active(state: &EscrowState) -> Result<(), ProgramError> {
+    if state.status != EscrowState::STATUS_ACTIVE {
+        return Err(EscrowError::NotActive.into());
+    }
+    Ok(())
+}
+
+// In direct payout mode the fee goes to the collector's own canonical ATA, which must already exist (creating it
+// would need the collector wallet as an extra account).
+fn assert_direct_payout_ata(
+    ata: &AccountInfo,
+    collector: &Pubkey,
+    mint: &Pubkey,
+    err: EscrowError,
+) -> Result<(), ProgramError> {
+    if spl_associated_token_account::get_associated_token_address(collector, mint) != *ata.key {
+        msg!("fee collector ATA mismatch");
+        return Err(err.into());
+    }
+    let ata_state =
+        spl_token::state::Account::unpack(&ata.try_borrow_data()?).map_err(|_| EscrowError::InvalidTokenAccountData)?;
+    if ata_state.owner != *collector {
+        msg!("fee collector ATA owner mismatch");
+        return Err(EscrowError::TokenOwnerMismatch.into());
+    }
+    if ata_state.mint != *mint {
+        msg!("fee collector ATA mint mismatch");
+        return Err(EscrowError::TokenMintMismatch.into());
+    }
+    Ok(())
+}
+
+// Moves `amount` (0 = the whole balance) out of one fee vault and returns what was moved. The vault must be
+// the canonical ATA(owner=config PDA, mint) and the destination must hold the same mint and be owned by
+// `dest_owner` (the fee collector, or the treasury owner in treasury mode). The withdrawal is recorded in the
+// (config, mint) stats PDA when one is passed.
+#[allow(clippy::too_many_arguments)]
+fn withdraw_from_fee_vault<'a>(
+    program_id: &Pubkey,
+    config: &AccountInfo<'a>,
+    fee_vault: &AccountInfo<'a>,
+    dest_token: &AccountInfo<'a>,
+    mint_stats: Option<&AccountInfo<'a>>,
+    token_program: &AccountInfo<'a>,
+    dest_owner: &Pubkey,
+    signer_seeds: &[&[u8]],
+    vault_err: EscrowError,
+    amount: u64,
+) -> Result<u64, ProgramError> {
+    assert_writable(fee_vault)?;
+    assert_writable(dest_token)?;
+
+    // Validate fee vault ATA matches ATA(owner=config PDA, mint=fee vault mint).
+    let fee_vault_state = spl_token::state::Account::unpack(&fee_vault.try_borrow_data()?)
+        .map_err(|_| EscrowError::InvalidTokenAccountData)?;
+    if fee_vault_state.owner != *config.key {
+        msg!("fee vault owner mismatch");
+        return Err(EscrowError::TokenOwnerMismatch.into());
+    }
+    let mint_pk = fee_vault_state.mint;
+    let expected_fee_vault = spl_associated_token_account::get_associated_token_address(config.key, &mint_pk);
+    if expected_fee_vault != *fee_vault.key {
+        msg!("fee vault ATA mismatch");
+        return Err(vault_err.into());
+    }
+
+    // Validate destination token account: same mint, owned by dest_owner.
+    let dest_state = spl_token::state::Account::unpack(&dest_token.try_borrow_data()?)
+        .map_err(|_| EscrowError::InvalidTokenAccountData)?;
+    if dest_state.mint != mint_pk {
+        msg!("dest mint mismatch");
+        return Err(EscrowError::TokenMintMismatch.into());
+    }
+    if dest_state.owner != *dest_owner {
+        msg!("dest owner mismatch");
+        return Err(EscrowError::TokenOwnerMismatch.into());
+    }
+
+    let balance = fee_vault_state.amount;
+    let withdraw_amount = if amount == 0 { balance } else { amount };
+    if withdraw_amount > balance {
+        msg!("withdraw amount exceeds balance");
+        return Err(EscrowError::WithdrawExceedsBalance.into());
+    }
+    if let Some(mint_stats) = mint_stats {
+        update_mint_stats(program_id, mint_stats, config.key, &mint_pk, |s| {
+            s.fees_withdrawn = s.fees_withdrawn.saturating_add(withdraw_amount)
+        })?;
+    }
+    if withdraw_amount == 0 {
+        return Ok(0);
+    }
+
+    let transfer_ix = spl_token::instruction::transfer(
+        token_program.key,
+        fee_vault.key,
+        dest_token.key,
+        config.key,
+        &[],
+        withdraw_amount,
+    )?;
+    invoke_signed(
+        &transfer_ix,
+        &[fee_vault.clone(), dest_token.clone(), config.clone(), token_program.clone()],
+        &[signer_seeds],
+    )?;
+    EscrowEvent::FeesWithdrawn(events::FeesWithdrawn {
+        config: *config.key,
+        fee_vault: *fee_vault.key,
+        mint: mint_pk,
+        destination: *dest_token.key,
+        amount: withdraw_amount,
+    })
+    .emit();
+    Ok(withdraw_amount)
+}
+
+// Splits trailing accounts into (fee vault, destination token account, mint stats PDA) triples.
+fn fee_vault_triples<'b, 'a>(
+    accounts: &'b [AccountInfo<'a>],
+) -> Result<std::slice::ChunksExact<'b, AccountInfo<'a>>, ProgramError> {
+    let triples = accounts.chunks_exact(3);
+    if !triples.remainder().is_empty() {
+        msg!("fee vault accounts must come in (vault, destination, mint stats) triples");
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    Ok(triples)
+}
+
+// Splits CloseConfig/CloseTradeConfig's trailing accounts into (fee vault, destination token account, mint stats
+// PDA, fee withdrawal PDA) groups.
+fn closed_fee_vault_groups<'b, 'a>(
+    accounts: &'b [AccountInfo<'a>],
+) -> Result<std::slice::ChunksExact<'b, AccountInfo<'a>>, ProgramError> {
+    let groups = accounts.chunks_exact(4);
+    if !groups.remainder().is_empty() {
+        msg!("fee vault accounts must come in (vault, destination, mint stats, fee withdrawal) groups");
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    Ok(groups)
+}
+
+// Empties one fee vault into the payout owner's token account and closes it together with its (config, mint)
+// stats PDA, with rent going to `rent_dest`. Refused while a fee withdrawal request for the vault is pending: it
+// must be executed or vetoed first, which also returns the request's rent to whoever paid for it.
+#[allow(clippy::too_many_arguments)]
+fn sweep_and_close_fee_vault<'a>(
+    program_id: &Pubkey,
+    config: &AccountInfo<'a>,
+    fee_vault: &AccountInfo<'a>,
+    dest_token: &AccountInfo<'a>,
+    mint_stats: &AccountInfo<'a>,
+    withdrawal: &AccountInfo<'a>,
+    rent_dest: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    dest_owner: &Pubkey,
+    signer_seeds: &[&[u8]],
+    vault_err: EscrowError,
+) -> ProgramResult {
+    if fee_withdrawal_pda(program_id, config.key, fee_vault.key).0 != *withdrawal.key {
+        msg!("fee withdrawal PDA mismatch");
+        return Err(EscrowError::InvalidFeeWithdrawalPda.into());
+    }
+    if !withdrawal.data_is_empty() {
+        msg!("fee withdrawal pending: execute or veto it before closing");
+        return Err(EscrowError::FeeWithdrawalPending.into());
+    }
+
+    let swept = withdraw_from_fee_vault(
+        program_id,
+        config,
+        fee_vault,
+        dest_token,
+        Some(mint_stats),
+        token_program,
+        dest_owner,
+        signer_seeds,
+        vault_err,
+        0,
+    )?;
+    msg!("swept {} from fee vault {}", swept, fee_vault.key);
+
+    let close_ix =
+        spl_token::instruction::close_account(token_program.key, fee_vault.key, rent_dest.key, config.key, &[])?;
+    invoke_signed(
+        &close_ix,
+        &[fee_vault.clone(), rent_dest.clone(), config.clone(), token_program.clone()],
+        &[signer_seeds],
+    )?;
+    // withdraw_from_fee_vault has checked this is the vault's stats PDA.
+    if !mint_stats.data_is_empty() {
+        close_program_account(mint_stats, rent_dest)?;
+    }
+    Ok(())
+}
+
+// Serializes `state` into `account`, first growing the account (topped up to rent exemption by `payer`)
+// when the state's version needs more space than the account has. `reserved` lamports (lamport fees held for
+// open escrows) are not the account's to spend on rent, so they do not count towards the exemption.
+fn write_state_with_realloc<'a, T: BorshSerialize>(
+    account: &AccountInfo<'a>,
+    payer: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    state: &T,
+    space: usize,
+    reserved: u64,
+) -> ProgramResult {
+    if account.data_len() < space {
+        let rent_balance = account.lamports().saturating_sub(reserved);
+        let needed = Rent::get()?.minimum_balance(space).saturating_sub(rent_balance);
+        if needed > 0 {
+            assert_writable(payer)?;
+            invoke(
+                &system_instruction::transfer(payer.key, account.key, needed),
+                &[payer.clone(), account.clone(), system_program.clone()],
+            )?;
+        }
+        account.realloc(space, false)?;
+    }
+    state
+        .serialize(&mut &mut account.try_borrow_mut_data()?[..])
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    Ok(())
+}
+
+// Drains a program-owned account into `rent_dest` and hands it back to the system program, so it can never
+// be read as a valid state account again (not even later in the same transaction).
+fn close_program_account(account: &AccountInfo, rent_dest: &AccountInfo) -> ProgramResult {
+    let lamports = account.lamports();
+    **rent_dest.try_borrow_mut_lamports()? = rent_dest
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(EscrowError::ArithmeticOverflow)?;
+    **account.try_borrow_mut_lamports()? = 0;
+    account.realloc(0, false)?;
+    account.assign(&solana_program::system_program::id());
+    Ok(())
+}
+
+// Moves `amount` lamports out of a program-owned account (which must stay rent exempt).
+fn debit_program_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> ProgramResult {
+    let remaining = from.lamports().checked_sub(amount).ok_or(EscrowError::ArithmeticOverflow)?;
+    if remaining < Rent::get()?.minimum_balance(from.data_len()) {
+        msg!("lamport debit would leave the account below rent exemption");
+        return Err(EscrowError::BelowRentExemption.into());
+    }
+    **to.try_borrow_mut_lamports()? = to.lamports().checked_add(amount).ok_or(EscrowError::ArithmeticOverflow)?;
+    **from.try_borrow_mut_lamports()? = remaining;
+    Ok(())
+}
+
+fn charge_lamport_fee<'a>(
+    payer: &AccountInfo<'a>,
+    config: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    lamport_fees: &mut LamportFeeState,
+) -> ProgramResult {
+    assert_writable(config)?;
+    invoke(
+        &system_instruction::transfer(payer.key, config.key, lamport_fees.per_escrow),
+        &[payer.clone(), config.clone(), system_program.clone()],
+    )?;
+    lamport_fees.reserved = lamport_fees
+        .reserved
+        .checked_add(lamport_fees.per_escrow)
+        .ok_or(EscrowError::ArithmeticOverflow)?;
+    Ok(())
+}
+
+// Releases the reservation an escrow holds on its config's lamport fee (on claim the lamports become
+// withdrawable; on refund the caller hands them back). `expected` is the config PDA the escrow was opened against.
+fn release_lamport_fee(
+    program_id: &Pubkey,
+    config: &AccountInfo,
+    expected: &Pubkey,
+    kind: FeeConfigKind,
+    amount: u64,
+) -> ProgramResult {
+    assert_writable(config)?;
+    match kind {
+        FeeConfigKind::Platform => {
+            if *config.key != *expected {
+                msg!("config PDA mismatch");
+                return Err(EscrowError::InvalidConfigPda.into());
+            }
+            assert_program_owned(config, program_id)?;
+            let mut state = ConfigState::try_from_slice(&config.try_borrow_data()?)
+                .map_err(|_| EscrowError::InvalidConfigState)?;
+            state.lamport_fees.reserved =
+                state.lamport_fees.reserved.checked_sub(amount).ok_or(EscrowError::InvalidConfigState)?;
+            state
+                .serialize(&mut &mut config.try_borrow_mut_data()?[..])
+                .map_err(|_| ProgramError::InvalidAccountData)?;
+        }
+        FeeConfigKind::Trade => {
+            if *config.key != *expected {
+                msg!("trade config PDA mismatch");
+                return Err(EscrowError::InvalidTradeConfigPda.into());
+            }
+            assert_program_owned(config, program_id)?;
+            let mut state = TradeConfigState::try_from_slice(&config.try_borrow_data()?)
+                .map_err(|_| EscrowError::InvalidTradeConfigState)?;
+            state.lamport_fees.reserved =
+                state.lamport_fees.reserved.checked_sub(amount).ok_or(EscrowError::InvalidTradeConfigState)?;
+            state
+                .serialize(&mut &mut config.try_borrow_mut_data()?[..])
+                .map_err(|_| ProgramError::InvalidAccountData)?;
+        }
+    }
+    Ok(())
+}
+
+// Off for crates that link this one as a library (`no-entrypoint`), so its `entrypoint` symbol stays out of theirs.
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
+
+pub fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    if let Err(error) = dispatch(program_id, accounts, instruction_data) {
+        // Spell out our own errors in the logs; `Custom(n)` alone says little.
+        error.print::<EscrowError>();
+        return Err(error);
+    }
+    Ok(())
+}
+
+fn dispatch(program_id: &Pubkey, accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let (ix, abi) = EscrowInstruction::unpack(instruction_data)?;
+    match ix {
+        EscrowInstruction::Init(args) => process_init(program_id, accounts, &args, abi),
+        EscrowInstruction::Claim { preimage } => process_claim(program_id, accounts, preimage),
+        EscrowInstruction::Refund => process_refund(program_id, accounts, abi),
+        EscrowInstruction::InitConfig {
+            fee_collector,
+            fee_bps,
+            tenant,
+        } => process_init_config(program_id, accounts, fee_collector, fee_bps, tenant, abi),
+        EscrowInstruction::SetConfig {
             fee_collector,
             fee_bps,
         } => process_set_config(program_id, accounts, fee_collector, fee_bps),
-        EscrowIx::WithdrawFees { amount } => process_withdraw_fees(program_id, accounts, amount),
-        EscrowIx::InitTradeConfig {
+        EscrowInstruction::WithdrawFees { amount } => process_withdraw_fees(program_id, accounts, amount),
+        EscrowInstruction::InitTradeConfig {
             fee_collector,
             fee_bps,
-        } => process_init_trade_config(program_id, accounts, fee_collector, fee_bps),
-        EscrowIx::SetTradeConfig {
+        } => process_init_trade_config(program_id, accounts, fee_collector, fee_bps, abi),
+        EscrowInstruction::SetTradeConfig {
+            fee_collector,
+            fee_bps,
+        } => process_set_trade_config(program_id, accounts, fee_collector, fee_bps),
+        EscrowInstruction::WithdrawTradeFees { amount } => process_withdraw_trade_fees(program_id, accounts, amount),
+        EscrowInstruction::CloseConfig => process_close_config(program_id, accounts),
+        EscrowInstruction::CloseTradeConfig => process_close_trade_config(program_id, accounts),
+        EscrowInstruction::WithdrawFeesBatch => process_withdraw_fees_batch(program_id, accounts),
+        EscrowInstruction::WithdrawTradeFeesBatch => process_withdraw_trade_fees_batch(program_id, accounts),
+        EscrowInstruction::SetTreasury {
+            treasury_owner,
+            guardian,
+            withdraw_delay_secs,
+        } => process_set_treasury(program_id, accounts, treasury_owner, guardian, withdraw_delay_secs),
+        EscrowInstruction::RequestFeeWithdrawal { amount } => {
+            process_request_fee_withdrawal(program_id, accounts, amount)
+        }
+        EscrowInstruction::ExecuteFeeWithdrawal => process_execute_fee_withdrawal(program_id, accounts),
+        EscrowInstruction::VetoFeeWithdrawal => process_veto_fee_withdrawal(program_id, accounts),
+        EscrowInstruction::SetRevenueSplit { beneficiaries } => {
+            process_set_revenue_split(program_id, accounts, beneficiaries)
+        }
+        EscrowInstruction::DistributeFees => process_distribute_fees(program_id, accounts),
+        EscrowInstruction::SetReferralCap { max_referral_bps } => {
+            process_set_referral_cap(program_id, accounts, max_referral_bps)
+        }
+        EscrowInstruction::SetMakerRebate { maker_rebate_bps } => {
+            process_set_maker_rebate(program_id, accounts, maker_rebate_bps)
+        }
+        EscrowInstruction::SetDirectPayout { enabled } => process_set_direct_payout(program_id, accounts, enabled),
+        EscrowInstruction::SetLamportFee { per_escrow } => process_set_lamport_fee(program_id, accounts, per_escrow),
+        EscrowInstruction::WithdrawLamportFees { amount } => {
+            process_withdraw_lamport_fees(program_id, accounts, amount)
+        }
+        EscrowInstruction::SetLockSurcharge { bps_per_hour } => {
+            process_set_lock_surcharge(program_id, accounts, bps_per_hour)
+        }
+        EscrowInstruction::SetVolumeTiers { tiers } => process_set_volume_tiers(program_id, accounts, tiers),
+        EscrowInstruction::ValidateInit(args) => process_validate_init(program_id, accounts, &args, abi),
+        EscrowInstruction::InitCpi(args) => process_init_cpi(program_id, accounts, &args, abi),
+        EscrowInstruction::MigrateAccount => process_migrate_account(program_id, accounts),
+    }
+}
+
+fn process_init_trade_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fee_collector: Pubkey,
+    fee_bps: u16,
+    abi: Abi,
+) -> ProgramResult {
+    // Accounts:
+    // 0 [signer,writable] payer (also trade config authority)
+    // 1 [writable] trade config PDA (seeded by fee_collector)
+    // 2 [] system program
+    // 3 [] rent sysvar (v1 only)
+    let acc_iter = &mut accounts.iter();
+    let payer = next_account_info(acc_iter)?;
+    let trade_config = next_account_info(acc_iter)?;
+    let system_program = next_account_info(acc_iter)?;
+    let rent_sysvar = next_sysvar_account::<Rent>(abi, acc_iter)?;
+
+    assert_signer(payer)?;
+    assert_writable(payer)?;
+    assert_writable(trade_config)?;
+    assert_program_id(system_program, &solana_program::system_program::id(), EscrowError::InvalidSystemProgram)?;
+
+    if fee_bps > MAX_TRADE_FEE_BPS {
+        msg!("fee_bps too high");
+        return Err(EscrowError::FeeTooHigh.into());
+    }
+    if *payer.key != fee_collector {
+        msg!("fee_collector must be the trade config authority");
+        return Err(EscrowError::FeeCollectorMismatch.into());
+    }
+
+    let (expected_trade_cfg, bump) = trade_config_pda(program_id, &fee_collector);
+    if expected_trade_cfg != *trade_config.key {
+        msg!("trade config PDA mismatch");
+        return Err(EscrowError::InvalidTradeConfigPda.into());
+    }
+
+    if !trade_config.data_is_empty() {
+        msg!("trade config already initialized");
+        return Err(EscrowError::AlreadyInitialized.into());
+    }
+
+    let rent: Rent = load_sysvar(rent_sysvar)?;
+    let space = TradeConfigState::space(TradeConfigState::LATEST);
+    let lamports = rent.minimum_balance(space);
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            trade_config.key,
+            lamports,
+            space as u64,
+            program_id,
+        ),
+        &[payer.clone(), trade_config.clone(), system_program.clone()],
+        &[&[TRADE_CONFIG_SEED, fee_collector.as_ref(), &[bump]]],
+    )?;
+
+    let state = TradeConfigState {
+        v: TradeConfigState::LATEST,
+        authority: payer.key.to_bytes(),
+        fee_collector: fee_collector.to_bytes(),
+        fee_bps,
+        bump,
+        treasury: TreasuryState::default(),
+        direct_payout: false,
+        lamport_fees: LamportFeeState::default(),
+        lock_surcharge_bps_per_hour: 0,
+        reserved: [0u8; RESERVED_LEN],
+    };
+    state
+        .serialize(&mut &mut trade_config.try_borrow_mut_data()?[..])
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    Ok(())
+}
+
+fn process_set_trade_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fee_collector: Pubkey,
+    fee_bps: u16,
+) -> ProgramResult {
+    // Accounts:
+    // 0 [signer,writable] authority (pays for the audit log on the first change)
+    // 1 [writable] trade config PDA
+    // 2 [writable] audit log PDA (seeded by the trade config PDA)
+    // 3 [] system program
+    //
+    // 2-3 follow as a pair that v1 clients leave out; the change is then not logged.
+    let acc_iter = &mut accounts.iter();
+    let authority = next_account_info(acc_iter)?;
+    let trade_config = next_account_info(acc_iter)?;
+    let audit_log = next_optional_pair(acc_iter)?;
+
+    assert_signer(authority)?;
+    assert_writable(trade_config)?;
+    if let Some((_, system_program)) = audit_log {
+        assert_program_id(system_program, &solana_program::system_program::id(), EscrowError::InvalidSystemProgram)?;
+    }
+
+    if fee_bps > MAX_TRADE_FEE_BPS {
+        msg!("fee_bps too high");
+        return Err(EscrowError::FeeTooHigh.into());
+    }
+    if *authority.key != fee_collector {
+        msg!("fee_collector must be the trade config authority");
+        return Err(EscrowError::FeeCollectorMismatch.into());
+    }
+
+    let (expected_trade_cfg, bump) = trade_config_pda(program_id, &fee_collector);
+    if expected_trade_cfg != *trade_config.key {
+        msg!("trade config PDA mismatch");
+        return Err(EscrowError::InvalidTradeConfigPda.into());
+    }
+
+    assert_program_owned(trade_config, program_id)?;
+    let mut state = TradeConfigState::try_from_slice(&trade_config.try_borrow_data()?)
+        .map_err(|_| EscrowError::InvalidTradeConfigState)?;
+    if !state.is_supported_version() || state.bump != bump {
+        msg!("trade config state version/bump mismatch");
+        return Err(EscrowError::InvalidTradeConfigState.into());
+    }
+    if Pubkey::new_from_array(state.authority) != *authority.key {
+        msg!("trade config authority mismatch");
+        return Err(EscrowError::AuthorityMismatch.into());
+    }
+
+    let clock = Clock::get()?;
+    let change = ConfigChange {
+        slot: clock.slot,
+        unix_timestamp: clock.unix_timestamp,
+        signer: authority.key.to_bytes(),
+        old_fee_bps: state.fee_bps,
+        new_fee_bps: fee_bps,
+        old_fee_collector: state.fee_collector,
+        new_fee_collector: fee_collector.to_bytes(),
+    };
+    if let Some((audit_log, system_program)) = audit_log {
+        record_config_change(program_id, authority, audit_log, system_program, trade_config.key, change)?;
+    }
+    EscrowEvent::TradeConfigUpdated(events::ConfigUpdated {
+        config: *trade_config.key,
+        authority: *authority.key,
+        old_fee_bps: change.old_fee_bps,
+        new_fee_bps: fee_bps,
+        old_fee_collector: Pubkey::new_from_array(change.old_fee_collector),
+        new_fee_collector: fee_collector,
+    })
+    .emit();
+
+    state.fee_collector = fee_collector.to_bytes();
+    state.fee_bps = fee_bps;
+    state
+        .serialize(&mut &mut trade_config.try_borrow_mut_data()?[..])
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    Ok(())
+}
+
+fn process_set_lock_surcharge(program_id: &Pubkey, accounts: &[AccountInfo], bps_per_hour: u16) -> ProgramResult {
+    // Accounts:
+    // 0 [signer,writable] authority (pays for the realloc when upgrading an older trade config)
+    // 1 [writable] trade config PDA
+    // 2 [] system program
+    let acc_iter = &mut accounts.iter();
+    let authority = next_account_info(acc_iter)?;
+    let trade_config = next_account_info(acc_iter)?;
+    let system_program = next_account_info(acc_iter)?;
+
+    assert_signer(authority)?;
+    assert_writable(trade_config)?;
+    assert_program_id(system_program, &solana_program::system_program::id(), EscrowError::InvalidSystemProgram)?;
+
+    if bps_per_hour > MAX_TRADE_FEE_BPS {
+        msg!("bps_per_hour too high");
+        return Err(EscrowError::FeeTooHigh.into());
+    }
+
+    assert_program_owned(trade_config, program_id)?;
+    let mut state = TradeConfigState::try_from_slice(&trade_config.try_borrow_data()?)
+        .map_err(|_| EscrowError::InvalidTradeConfigState)?;
+    let (expected_trade_cfg, bump) = trade_config_pda(program_id, &Pubkey::new_from_array(state.fee_collector));
+    if expected_trade_cfg != *trade_config.key {
+        msg!("trade config PDA mismatch");
+        return Err(EscrowError::InvalidTradeConfigPda.into());
+    }
+    if !state.is_supported_version() || state.bump != bump {
+        msg!("trade config state version/bump mismatch");
+        return Err(EscrowError::InvalidTradeConfigState.into());
+    }
+    if Pubkey::new_from_array(state.authority) != *authority.key {
+        msg!("trade config authority mismatch");
+        return Err(EscrowError::AuthorityMismatch.into());
+    }
+
+    state.v = TradeConfigState::LATEST;
+    state.lock_surcharge_bps_per_hour = bps_per_hour;
+    let space = TradeConfigState::space(state.v);
+    write_state_with_realloc(trade_config, authority, system_program, &state, space, state.lamport_fees.reserved)?;
+    Ok(())
+}
+
+fn process_withdraw_trade_fees(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+    // Accounts:
+    // 0 [signer] fee collector (trade config authority)
+    // 1 [] trade config PDA
+    // 2 [writable] trade fee vault ATA (ATA(owner=trade config PDA, mint=configured mint))
+    // 3 [writable] fee collector token account (destination)
+    // 4 [] token program
+    // 5 [writable] mint stats PDA (seeded by trade config + mint; optional, v1 clients leave it out)
+    let acc_iter = &mut accounts.iter();
+    let fee_collector = next_account_info(acc_iter)?;
+    let trade_config = next_account_info(acc_iter)?;
+    let fee_vault = next_account_info(acc_iter)?;
+    let dest_token = next_account_info(acc_iter)?;
+    let token_program = next_account_info(acc_iter)?;
+    let mint_stats = acc_iter.next();
+
+    assert_signer(fee_collector)?;
+    assert_writable(fee_vault)?;
+    assert_writable(dest_token)?;
+    assert_program_id(token_program, &spl_token::id(), EscrowError::InvalidTokenProgram)?;
+
+    // Validate trade config PDA from signer.
+    let (expected_trade_cfg, bump) = trade_config_pda(program_id, fee_collector.key);
+    if expected_trade_cfg != *trade_config.key {
+        msg!("trade config PDA mismatch");
+        return Err(EscrowError::InvalidTradeConfigPda.into());
+    }
+
+    assert_program_owned(trade_config, program_id)?;
+    let state = TradeConfigState::try_from_slice(&trade_config.try_borrow_data()?)
+        .map_err(|_| EscrowError::InvalidTradeConfigState)?;
+    if !state.is_supported_version() || state.bump != bump {
+        msg!("trade config state version/bump mismatch");
+        return Err(EscrowError::InvalidTradeConfigState.into());
+    }
+
+    let auth_pk = Pubkey::new_from_array(state.authority);
+    if auth_pk != *fee_collector.key {
+        msg!("withdraw signer mismatch");
+        return Err(EscrowError::AuthorityMismatch.into());
+    }
+    let collector_pk = Pubkey::new_from_array(state.fee_collector);
+    if collector_pk != *fee_collector.key {
+        msg!("fee_collector mismatch");
+        return Err(EscrowError::FeeCollectorMismatch.into());
+    }
+    if state.treasury.enabled() {
+        msg!("treasury mode: fees must go through RequestFeeWithdrawal");
+        return Err(EscrowError::TreasuryLocked.into());
+    }
+
+    withdraw_from_fee_vault(
+        program_id,
+        trade_config,
+        fee_vault,
+        dest_token,
+        mint_stats,
+        token_program,
+        &collector_pk,
+        &[TRADE_CONFIG_SEED, fee_collector.key.as_ref(), &[bump]],
+        EscrowError::InvalidTradeFeeVaultAta,
+        amount,
+    )?;
+
+    Ok(())
+}
+
+fn process_withdraw_trade_fees_batch(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    // Accounts:
+    // 0 [signer] fee collector (trade config authority)
+    // 1 [] trade config PDA
+    // 2 [] token program
+    // 3.. [writable] (trade fee vault ATA, fee collector token account, mint stats PDA) triples, one per mint
+    //
+    // Sweeps the full balance of every trade fee vault passed in.
+    let acc_iter = &mut accounts.iter();
+    let fee_collector = next_account_info(acc_iter)?;
+    let trade_config = next_account_info(acc_iter)?;
+    let token_program = next_account_info(acc_iter)?;
+    let remaining = acc_iter.as_slice();
+
+    assert_signer(fee_collector)?;
+    assert_program_id(token_program, &spl_token::id(), EscrowError::InvalidTokenProgram)?;
+
+    let (expected_trade_cfg, bump) = trade_config_pda(program_id, fee_collector.key);
+    if expected_trade_cfg != *trade_config.key {
+        msg!("trade config PDA mismatch");
+        return Err(EscrowError::InvalidTradeConfigPda.into());
+    }
+
+    assert_program_owned(trade_config, program_id)?;
+    let state = TradeConfigState::try_from_slice(&trade_config.try_borrow_data()?)
+        .map_err(|_| EscrowError::InvalidTradeConfigState)?;
+    if !state.is_supported_version() || state.bump != bump {
+        msg!("trade config state version/bump mismatch");
+        return Err(EscrowError::InvalidTradeConfigState.into());
+    }
+    if Pubkey::new_from_array(state.authority) != *fee_collector.key {
+        msg!("withdraw signer mismatch");
+        return Err(EscrowError::AuthorityMismatch.into());
+    }
+    let collector_pk = Pubkey::new_from_array(state.fee_collector);
+    if collector_pk != *fee_collector.key {
+        msg!("fee_collector mismatch");
+        return Err(EscrowError::FeeCollectorMismatch.into());
+    }
+    if state.treasury.enabled() {
+        msg!("treasury mode: fees must go through RequestFeeWithdrawal");
+        return Err(EscrowError::TreasuryLocked.into());
+    }
+
+    for triple in fee_vault_triples(remaining)? {
+        let withdrawn = withdraw_from_fee_vault(
+            program_id,
+            trade_config,
+            &triple[0],
+            &triple[1],
+            Some(&triple[2]),
+            token_program,
+            &collector_pk,
+            &[TRADE_CONFIG_SEED, fee_collector.key.as_ref(), &[bump]],
+            EscrowError::InvalidTradeFeeVaultAta,
+            0,
+        )?;
+        msg!("withdrew {} from trade fee vault {}", withdrawn, triple[0].key);
+    }
+    Ok(())
+}
+
+fn process_close_trade_config(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    // Accounts:
+    // 0 [signer,writable] fee collector (trade config authority); receives reclaimed rent
+    // 1 [writable] trade config PDA
+    // 2 [] token program
+    // 3 [writable] audit log PDA (seeded by the trade config PDA; closed too if it exists)
+    // 4.. [writable] (trade fee vault ATA, fee collector token account, mint stats PDA, fee withdrawal PDA (may be
+    //   read-only)) groups, one per mint still held
+    //
+    // Sweeps and closes every fee vault passed in (and its mint stats PDA), then closes the trade config itself.
+    // Init rejects a closed trade config; escrows opened against it before the close can still be claimed (see
+    // process_claim). Refused while any of those vaults has a fee withdrawal request pending.
+    let acc_iter = &mut accounts.iter();
+    let fee_collector = next_account_info(acc_iter)?;
+    let trade_config = next_account_info(acc_iter)?;
+    let token_program = next_account_info(acc_iter)?;
+    let audit_log = next_account_info(acc_iter)?;
+    let remaining = acc_iter.as_slice();
+
+    assert_signer(fee_collector)?;
+    assert_writable(fee_collector)?;
+    assert_writable(trade_config)?;
+    assert_program_id(token_program, &spl_token::id(), EscrowError::InvalidTokenProgram)?;
+
+    let (expected_trade_cfg, bump) = trade_config_pda(program_id, fee_collector.key);
+    if expected_trade_cfg != *trade_config.key {
+        msg!("trade config PDA mismatch");
+        return Err(EscrowError::InvalidTradeConfigPda.into());
+    }
+
+    assert_program_owned(trade_config, program_id)?;
+    let state = TradeConfigState::try_from_slice(&trade_config.try_borrow_data()?)
+        .map_err(|_| EscrowError::InvalidTradeConfigState)?;
+    if !state.is_supported_version() || state.bump != bump {
+        msg!("trade config state version/bump mismatch");
+        return Err(EscrowError::InvalidTradeConfigState.into());
+    }
+    if Pubkey::new_from_array(state.authority) != *fee_collector.key {
+        msg!("close signer mismatch");
+        return Err(EscrowError::AuthorityMismatch.into());
+    }
+    let collector_pk = Pubkey::new_from_array(state.fee_collector);
+    if collector_pk != *fee_collector.key {
+        msg!("fee_collector mismatch");
+        return Err(EscrowError::FeeCollectorMismatch.into());
+    }
+    if state.lamport_fees.reserved > 0 {
+        msg!("lamport fees still reserved for open escrows");
+        return Err(EscrowError::LamportFeesReserved.into());
+    }
+    // In treasury mode the remaining fees may only be swept to the treasury owner.
+    let payout_pk = if state.treasury.enabled() {
+        Pubkey::new_from_array(state.treasury.owner)
+    } else {
+        collector_pk
+    };
+
+    for group in closed_fee_vault_groups(remaining)? {
+        sweep_and_close_fee_vault(
+            program_id,
+            trade_config,
+            &group[0],
+            &group[1],
+            &group[2],
+            &group[3],
             fee_collector,
-            fee_bps,
-        } => process_set_trade_config(program_id, accounts, fee_collector, fee_bps),
-        EscrowIx::WithdrawTradeFees { amount } => process_withdraw_trade_fees(program_id, accounts, amount),
+            token_program,
+            &payout_pk,
+            &[TRADE_CONFIG_SEED, fee_collector.key.as_ref(), &[bump]],
+            EscrowError::InvalidTradeFeeVaultAta,
+        )?;
     }
+
+    close_audit_log(program_id, audit_log, trade_config.key, fee_collector)?;
+    close_program_account(trade_config, fee_collector)?;
+    msg!("trade config closed");
+    Ok(())
 }
 
-fn process_init_trade_config(
+fn process_init_config(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     fee_collector: Pubkey,
     fee_bps: u16,
+    tenant: Pubkey,
+    abi: Abi,
 ) -> ProgramResult {
     // Accounts:
-    // 0 [signer,writable] payer (also trade config authority)
-    // 1 [writable] trade config PDA (seeded by fee_collector)
+    // 0 [signer,writable] payer (also config authority)
+    // 1 [writable] config PDA (seeded by the tenant unless it is the default one)
     // 2 [] system program
-    // 3 [] rent sysvar
+    // 3 [] rent sysvar (v1 only)
+    //
+    // A tenant namespace can only be claimed by its own key, so nobody can squat another operator's config.
     let acc_iter = &mut accounts.iter();
     let payer = next_account_info(acc_iter)?;
-    let trade_config = next_account_info(acc_iter)?;
+    let config = next_account_info(acc_iter)?;
     let system_program = next_account_info(acc_iter)?;
-    let rent_sysvar = next_account_info(acc_iter)?;
+    let rent_sysvar = next_sysvar_account::<Rent>(abi, acc_iter)?;
 
     assert_signer(payer)?;
     assert_writable(payer)?;
-    assert_writable(trade_config)?;
+    assert_writable(config)?;
+    assert_program_id(system_program, &solana_program::system_program::id(), EscrowError::InvalidSystemProgram)?;
 
-    if fee_bps > MAX_TRADE_FEE_BPS {
+    if fee_bps > MAX_PLATFORM_FEE_BPS {
         msg!("fee_bps too high");
         return Err(EscrowError::FeeTooHigh.into());
     }
     if *payer.key != fee_collector {
-        msg!("fee_collector must be the trade config authority");
-        return Err(EscrowError::InvalidSigner.into());
+        msg!("fee_collector must be the config authority");
+        return Err(EscrowError::FeeCollectorMismatch.into());
+    }
+    if tenant != Pubkey::default() && tenant != *payer.key {
+        msg!("tenant must be the config authority");
+        return Err(EscrowError::InvalidTenant.into());
     }
 
-    let (expected_trade_cfg, bump) = trade_config_pda(program_id, &fee_collector);
-    if expected_trade_cfg != *trade_config.key {
-        msg!("trade config PDA mismatch");
-        return Err(EscrowError::InvalidTradeConfigPda.into());
+    let (expected_config, bump) = config_pda(program_id, &tenant);
+    if expected_config != *config.key {
+        msg!("config PDA mismatch");
+        return Err(EscrowError::InvalidConfigPda.into());
     }
 
-    if !trade_config.data_is_empty() {
-        msg!("trade config already initialized");
+    if !config.data_is_empty() {
+        msg!("config already initialized");
         return Err(EscrowError::AlreadyInitialized.into());
     }
 
-    let rent = Rent::from_account_info(rent_sysvar)?;
-    let space = 1usize + 32 + 32 + 2 + 1; // TradeConfigState layout
+    let rent: Rent = load_sysvar(rent_sysvar)?;
+    let space = ConfigState::space(ConfigState::LATEST);
     let lamports = rent.minimum_balance(space);
-    invoke_signed(
-        &system_instruction::create_account(
-            payer.key,
-            trade_config.key,
-            lamports,
-            space as u64,
-            program_id,
-        ),
-        &[payer.clone(), trade_config.clone(), system_program.clone()],
-        &[&[TRADE_CONFIG_SEED, fee_collector.as_ref(), &[bump]]],
-    )?;
-
-    let state = TradeConfigState {
-        v: TradeConfigState::V1,
+    let state = ConfigState {
+        v: ConfigState::LATEST,
         authority: payer.key.to_bytes(),
         fee_collector: fee_collector.to_bytes(),
         fee_bps,
         bump,
+        treasury: TreasuryState::default(),
+        revenue_split: RevenueSplit::default(),
+        max_referral_bps: 0,
+        maker_rebate_bps: 0,
+        direct_payout: false,
+        lamport_fees: LamportFeeState::default(),
+        volume_tiers: VolumeTiers::default(),
+        tenant: tenant.to_bytes(),
+        reserved: [0u8; RESERVED_LEN],
     };
+    invoke_signed(
+        &system_instruction::create_account(payer.key, config.key, lamports, space as u64, program_id),
+        &[payer.clone(), config.clone(), system_program.clone()],
+        &[&state.signer_seeds()],
+    )?;
     state
-        .serialize(&mut &mut trade_config.try_borrow_mut_data()?[..])
+        .serialize(&mut &mut config.try_borrow_mut_data()?[..])
         .map_err(|_| ProgramError::InvalidAccountData)?;
     Ok(())
 }
 
-fn process_set_trade_config(
+fn process_set_config(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     fee_collector: Pubkey,
     fee_bps: u16,
 ) -> ProgramResult {
     // Accounts:
-    // 0 [signer] authority
-    // 1 [writable] trade config PDA
+    // 0 [signer,writable] authority (pays for the audit log on the first change)
+    // 1 [writable] config PDA
+    // 2 [writable] audit log PDA (seeded by the config PDA)
+    // 3 [] system program
+    //
+    // 2-3 follow as a pair that v1 clients leave out; the change is then not logged.
     let acc_iter = &mut accounts.iter();
     let authority = next_account_info(acc_iter)?;
-    let trade_config = next_account_info(acc_iter)?;
+    let config = next_account_info(acc_iter)?;
+    let audit_log = next_optional_pair(acc_iter)?;
 
     assert_signer(authority)?;
-    assert_writable(trade_config)?;
+    assert_writable(config)?;
+    if let Some((_, system_program)) = audit_log {
+        assert_program_id(system_program, &solana_program::system_program::id(), EscrowError::InvalidSystemProgram)?;
+    }
 
-    if fee_bps > MAX_TRADE_FEE_BPS {
+    if fee_bps > MAX_PLATFORM_FEE_BPS {
         msg!("fee_bps too high");
         return Err(EscrowError::FeeTooHigh.into());
     }
     if *authority.key != fee_collector {
-        msg!("fee_collector must be the trade config authority");
-        return Err(EscrowError::InvalidSigner.into());
+        msg!("fee_collector must be the config authority");
+        return Err(EscrowError::FeeCollectorMismatch.into());
     }
 
-    let (expected_trade_cfg, bump) = trade_config_pda(program_id, &fee_collector);
-    if expected_trade_cfg != *trade_config.key {
-        msg!("trade config PDA mismatch");
-        return Err(EscrowError::InvalidTradeConfigPda.into());
+    let mut state = load_config(program_id, config)?;
+    if Pubkey::new_from_array(state.authority) != *authority.key {
+        msg!("config authority mismatch");
+        return Err(EscrowError::AuthorityMismatch.into());
     }
 
-    let mut state = TradeConfigState::try_from_slice(&trade_config.try_borrow_data()?)
-        .map_err(|_| EscrowError::InvalidTradeConfigState)?;
-    if state.v != TradeConfigState::V1 || state.bump != bump {
-        msg!("trade config state version/bump mismatch");
-        return Err(EscrowError::InvalidTradeConfigState.into());
-    }
-    if Pubkey::new_from_array(state.authority) != *authority.key {
-        msg!("trade config authority mismatch");
-        return Err(EscrowError::InvalidSigner.into());
+    let clock = Clock::get()?;
+    let change = ConfigChange {
+        slot: clock.slot,
+        unix_timestamp: clock.unix_timestamp,
+        signer: authority.key.to_bytes(),
+        old_fee_bps: state.fee_bps,
+        new_fee_bps: fee_bps,
+        old_fee_collector: state.fee_collector,
+        new_fee_collector: fee_collector.to_bytes(),
+    };
+    if let Some((audit_log, system_program)) = audit_log {
+        record_config_change(program_id, authority, audit_log, system_program, config.key, change)?;
     }
+    EscrowEvent::ConfigUpdated(events::ConfigUpdated {
+        config: *config.key,
+        authority: *authority.key,
+        old_fee_bps: change.old_fee_bps,
+        new_fee_bps: fee_bps,
+        old_fee_collector: Pubkey::new_from_array(change.old_fee_collector),
+        new_fee_collector: fee_collector,
+    })
+    .emit();
 
     state.fee_collector = fee_collector.to_bytes();
     state.fee_bps = fee_bps;
     state
-        .serialize(&mut &mut trade_config.try_borrow_mut_data()?[..])
+        .serialize(&mut &mut config.try_borrow_mut_data()?[..])
         .map_err(|_| ProgramError::InvalidAccountData)?;
     Ok(())
 }
 
-fn process_withdraw_trade_fees(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+fn process_set_referral_cap(program_id: &Pubkey, accounts: &[AccountInfo], max_referral_bps: u16) -> ProgramResult {
     // Accounts:
-    // 0 [signer] fee collector (trade config authority)
-    // 1 [] trade config PDA
-    // 2 [writable] trade fee vault ATA (ATA(owner=trade config PDA, mint=configured mint))
+    // 0 [signer,writable] authority (pays for the realloc when upgrading an older config)
+    // 1 [writable] config PDA
+    // 2 [] system program
+    //
+    // max_referral_bps = 0 disables referrals for new escrows.
+    let acc_iter = &mut accounts.iter();
+    let authority = next_account_info(acc_iter)?;
+    let config = next_account_info(acc_iter)?;
+    let system_program = next_account_info(acc_iter)?;
+
+    assert_signer(authority)?;
+    assert_writable(config)?;
+    assert_program_id(system_program, &solana_program::system_program::id(), EscrowError::InvalidSystemProgram)?;
+
+    if max_referral_bps > MAX_REFERRAL_FEE_BPS {
+        msg!("max_referral_bps too high");
+        return Err(EscrowError::FeeTooHigh.into());
+    }
+
+    let mut state = load_config(program_id, config)?;
+    if Pubkey::new_from_array(state.authority) != *authority.key {
+        msg!("config authority mismatch");
+        return Err(EscrowError::AuthorityMismatch.into());
+    }
+
+    state.v = ConfigState::LATEST;
+    state.max_referral_bps = max_referral_bps;
+    let space = ConfigState::space(state.v);
+    write_state_with_realloc(config, authority, system_program, &state, space, state.lamport_fees.reserved)?;
+    Ok(())
+}
+
+fn process_set_maker_rebate(program_id: &Pubkey, accounts: &[AccountInfo], maker_rebate_bps: u16) -> ProgramResult {
+    // Accounts:
+    // 0 [signer,writable] authority (pays for the realloc when upgrading an older config)
+    // 1 [writable] config PDA
+    // 2 [] system program
+    //
+    // maker_rebate_bps is a share of the platform fee (not of the escrow amount); 0 disables rebates.
+    let acc_iter = &mut accounts.iter();
+    let authority = next_account_info(acc_iter)?;
+    let config = next_account_info(acc_iter)?;
+    let system_program = next_account_info(acc_iter)?;
+
+    assert_signer(authority)?;
+    assert_writable(config)?;
+    assert_program_id(system_program, &solana_program::system_program::id(), EscrowError::InvalidSystemProgram)?;
+
+    if maker_rebate_bps > 10_000 {
+        msg!("maker_rebate_bps above 10000");
+        return Err(EscrowError::FeeTooHigh.into());
+    }
+
+    let mut state = load_config(program_id, config)?;
+    if Pubkey::new_from_array(state.authority) != *authority.key {
+        msg!("config authority mismatch");
+        return Err(EscrowError::AuthorityMismatch.into());
+    }
+
+    state.v = ConfigState::LATEST;
+    state.maker_rebate_bps = maker_rebate_bps;
+    let space = ConfigState::space(state.v);
+    write_state_with_realloc(config, authority, system_program, &state, space, state.lamport_fees.reserved)?;
+    Ok(())
+}
+
+fn process_set_volume_tiers(program_id: &Pubkey, accounts: &[AccountInfo], tiers: Vec<VolumeTier>) -> ProgramResult {
+    // Accounts:
+    // 0 [signer,writable] authority (pays for the realloc when upgrading an older config)
+    // 1 [writable] config PDA
+    // 2 [] system program
+    //
+    // An empty tier list turns discounts (and payer volume tracking for new escrows) off.
+    let acc_iter = &mut accounts.iter();
+    let authority = next_account_info(acc_iter)?;
+    let config = next_account_info(acc_iter)?;
+    let system_program = next_account_info(acc_iter)?;
+
+    assert_signer(authority)?;
+    assert_writable(config)?;
+    assert_program_id(system_program, &solana_program::system_program::id(), EscrowError::InvalidSystemProgram)?;
+
+    if tiers.len() > MAX_VOLUME_TIERS {
+        msg!("too many volume tiers");
+        return Err(EscrowError::InvalidVolumeTiers.into());
+    }
+    let mut volume_tiers = VolumeTiers::default();
+    for (i, t) in tiers.iter().enumerate() {
+        if t.discount_bps == 0 || t.discount_bps > 10_000 {
+            msg!("tier {} discount_bps must be in 1..=10000", i);
+            return Err(EscrowError::InvalidVolumeTiers.into());
+        }
+        if i > 0 && t.min_volume <= tiers[i - 1].min_volume {
+            msg!("tier {} min_volume must be above the previous tier", i);
+            return Err(EscrowError::InvalidVolumeTiers.into());
+        }
+        volume_tiers.tiers[i] = *t;
+    }
+    volume_tiers.count = tiers.len() as u8;
+
+    let mut state = load_config(program_id, config)?;
+    if Pubkey::new_from_array(state.authority) != *authority.key {
+        msg!("config authority mismatch");
+        return Err(EscrowError::AuthorityMismatch.into());
+    }
+
+    state.v = ConfigState::LATEST;
+    state.volume_tiers = volume_tiers;
+    let space = ConfigState::space(state.v);
+    write_state_with_realloc(config, authority, system_program, &state, space, state.lamport_fees.reserved)?;
+    Ok(())
+}
+
+fn process_withdraw_fees(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+    // Accounts:
+    // 0 [signer] fee collector (config authority)
+    // 1 [] config PDA
+    // 2 [writable] fee vault ATA (ATA(owner=config PDA, mint=configured mint))
     // 3 [writable] fee collector token account (destination)
     // 4 [] token program
+    // 5 [writable] mint stats PDA (seeded by config + mint; optional, v1 clients leave it out)
     let acc_iter = &mut accounts.iter();
     let fee_collector = next_account_info(acc_iter)?;
-    let trade_config = next_account_info(acc_iter)?;
+    let config = next_account_info(acc_iter)?;
     let fee_vault = next_account_info(acc_iter)?;
     let dest_token = next_account_info(acc_iter)?;
     let token_program = next_account_info(acc_iter)?;
+    let mint_stats = acc_iter.next();
 
     assert_signer(fee_collector)?;
     assert_writable(fee_vault)?;
     assert_writable(dest_token)?;
+    assert_program_id(token_program, &spl_token::id(), EscrowError::InvalidTokenProgram)?;
 
-    // Validate trade config PDA from signer.
-    let (expected_trade_cfg, bump) = trade_config_pda(program_id, fee_collector.key);
-    if expected_trade_cfg != *trade_config.key {
-        msg!("trade config PDA mismatch");
-        return Err(EscrowError::InvalidTradeConfigPda.into());
+    let state = load_config(program_id, config)?;
+
+    let auth_pk = Pubkey::new_from_array(state.authority);
+    if auth_pk != *fee_collector.key {
+        msg!("withdraw signer mismatch");
+        return Err(EscrowError::AuthorityMismatch.into());
+    }
+    let collector_pk = Pubkey::new_from_array(state.fee_collector);
+    if collector_pk != *fee_collector.key {
+        msg!("fee_collector mismatch");
+        return Err(EscrowError::FeeCollectorMismatch.into());
+    }
+    if state.treasury.enabled() {
+        msg!("treasury mode: fees must go through RequestFeeWithdrawal");
+        return Err(EscrowError::TreasuryLocked.into());
+    }
+    if state.revenue_split.active() {
+        msg!("revenue split active: fees must go through DistributeFees");
+        return Err(EscrowError::RevenueSplitActive.into());
     }
 
-    let state = TradeConfigState::try_from_slice(&trade_config.try_borrow_data()?)
-        .map_err(|_| EscrowError::InvalidTradeConfigState)?;
-    if state.v != TradeConfigState::V1 || state.bump != bump {
-        msg!("trade config state version/bump mismatch");
-        return Err(EscrowError::InvalidTradeConfigState.into());
+    withdraw_from_fee_vault(
+        program_id,
+        config,
+        fee_vault,
+        dest_token,
+        mint_stats,
+        token_program,
+        &collector_pk,
+        &state.signer_seeds(),
+        EscrowError::InvalidFeeVaultAta,
+        amount,
+    )?;
+
+    Ok(())
+}
+
+fn process_withdraw_fees_batch(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    // Accounts:
+    // 0 [signer] fee collector (config authority)
+    // 1 [] config PDA
+    // 2 [] token program
+    // 3.. [writable] (fee vault ATA, fee collector token account, mint stats PDA) triples, one per mint
+    //
+    // Sweeps the full balance of every fee vault passed in.
+    let acc_iter = &mut accounts.iter();
+    let fee_collector = next_account_info(acc_iter)?;
+    let config = next_account_info(acc_iter)?;
+    let token_program = next_account_info(acc_iter)?;
+    let remaining = acc_iter.as_slice();
+
+    assert_signer(fee_collector)?;
+    assert_program_id(token_program, &spl_token::id(), EscrowError::InvalidTokenProgram)?;
+
+    let state = load_config(program_id, config)?;
+    if Pubkey::new_from_array(state.authority) != *fee_collector.key {
+        msg!("withdraw signer mismatch");
+        return Err(EscrowError::AuthorityMismatch.into());
+    }
+    let collector_pk = Pubkey::new_from_array(state.fee_collector);
+    if collector_pk != *fee_collector.key {
+        msg!("fee_collector mismatch");
+        return Err(EscrowError::FeeCollectorMismatch.into());
+    }
+    if state.treasury.enabled() {
+        msg!("treasury mode: fees must go through RequestFeeWithdrawal");
+        return Err(EscrowError::TreasuryLocked.into());
+    }
+    if state.revenue_split.active() {
+        msg!("revenue split active: fees must go through DistributeFees");
+        return Err(EscrowError::RevenueSplitActive.into());
+    }
+
+    for triple in fee_vault_triples(remaining)? {
+        let withdrawn = withdraw_from_fee_vault(
+            program_id,
+            config,
+            &triple[0],
+            &triple[1],
+            Some(&triple[2]),
+            token_program,
+            &collector_pk,
+            &state.signer_seeds(),
+            EscrowError::InvalidFeeVaultAta,
+            0,
+        )?;
+        msg!("withdrew {} from fee vault {}", withdrawn, triple[0].key);
+    }
+    Ok(())
+}
+
+fn process_close_config(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    // Accounts:
+    // 0 [signer,writable] fee collector (config authority); receives reclaimed rent
+    // 1 [writable] config PDA
+    // 2 [] token program
+    // 3 [writable] audit log PDA (seeded by the config PDA; closed too if it exists)
+    // 4.. [writable] (fee vault ATA, fee collector token account, mint stats PDA, fee withdrawal PDA (may be
+    //   read-only)) groups, one per mint still held
+    //
+    // Sweeps and closes every fee vault passed in (and its mint stats PDA), then closes the config itself. Init
+    // rejects a closed config; escrows opened against it before the close can still be claimed (see process_claim).
+    // Refused while any of those vaults has a fee withdrawal request pending.
+    //
+    // Only tenant configs can be closed: InitConfig lets anyone claim an empty default config, and clients
+    // hard-wired to it would then pay platform fees to whoever re-created it.
+    let acc_iter = &mut accounts.iter();
+    let fee_collector = next_account_info(acc_iter)?;
+    let config = next_account_info(acc_iter)?;
+    let token_program = next_account_info(acc_iter)?;
+    let audit_log = next_account_info(acc_iter)?;
+    let remaining = acc_iter.as_slice();
+
+    assert_signer(fee_collector)?;
+    assert_writable(fee_collector)?;
+    assert_writable(config)?;
+    assert_program_id(token_program, &spl_token::id(), EscrowError::InvalidTokenProgram)?;
+
+    let state = load_config(program_id, config)?;
+    if Pubkey::new_from_array(state.authority) != *fee_collector.key {
+        msg!("close signer mismatch");
+        return Err(EscrowError::AuthorityMismatch.into());
+    }
+    let collector_pk = Pubkey::new_from_array(state.fee_collector);
+    if collector_pk != *fee_collector.key {
+        msg!("fee_collector mismatch");
+        return Err(EscrowError::FeeCollectorMismatch.into());
+    }
+    if state.tenant() == Pubkey::default() {
+        msg!("the default config can not be closed");
+        return Err(EscrowError::DefaultConfigNotClosable.into());
+    }
+    if state.revenue_split.active() {
+        msg!("revenue split active: distribute and clear it before closing");
+        return Err(EscrowError::RevenueSplitActive.into());
+    }
+    if state.lamport_fees.reserved > 0 {
+        msg!("lamport fees still reserved for open escrows");
+        return Err(EscrowError::LamportFeesReserved.into());
+    }
+    // In treasury mode the remaining fees may only be swept to the treasury owner.
+    let payout_pk = if state.treasury.enabled() {
+        Pubkey::new_from_array(state.treasury.owner)
+    } else {
+        collector_pk
+    };
+
+    for group in closed_fee_vault_groups(remaining)? {
+        sweep_and_close_fee_vault(
+            program_id,
+            config,
+            &group[0],
+            &group[1],
+            &group[2],
+            &group[3],
+            fee_collector,
+            token_program,
+            &payout_pk,
+            &state.signer_seeds(),
+            EscrowError::InvalidFeeVaultAta,
+        )?;
+    }
+
+    close_audit_log(program_id, audit_log, config.key, fee_collector)?;
+    close_program_account(config, fee_collector)?;
+    msg!("config closed");
+    Ok(())
+}
+
+fn process_set_revenue_split(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    beneficiaries: Vec<RevenueBeneficiary>,
+) -> ProgramResult {
+    // Accounts:
+    // 0 [signer,writable] authority (pays for the realloc when upgrading an older config)
+    // 1 [writable] config PDA
+    // 2 [] system program
+    // 3 [signer] guardian (required in treasury mode)
+    //
+    // An empty beneficiary list clears the split.
+    let acc_iter = &mut accounts.iter();
+    let authority = next_account_info(acc_iter)?;
+    let config = next_account_info(acc_iter)?;
+    let system_program = next_account_info(acc_iter)?;
+
+    assert_signer(authority)?;
+    assert_writable(config)?;
+    assert_program_id(system_program, &solana_program::system_program::id(), EscrowError::InvalidSystemProgram)?;
+
+    let mut state = load_config(program_id, config)?;
+    if Pubkey::new_from_array(state.authority) != *authority.key {
+        msg!("config authority mismatch");
+        return Err(EscrowError::AuthorityMismatch.into());
+    }
+    // Redirecting revenue is as sensitive as a withdrawal, so treasury mode needs the guardian's consent.
+    if state.treasury.enabled() {
+        let guardian = next_account_info(acc_iter)?;
+        assert_signer(guardian)?;
+        if Pubkey::new_from_array(state.treasury.guardian) != *guardian.key {
+            msg!("guardian mismatch");
+            return Err(EscrowError::GuardianMismatch.into());
+        }
+    }
+
+    if !beneficiaries.is_empty() && state.direct_payout {
+        msg!("disable direct payout before setting a revenue split");
+        return Err(EscrowError::DirectPayoutActive.into());
+    }
+    if beneficiaries.len() > MAX_REVENUE_BENEFICIARIES {
+        msg!("too many beneficiaries");
+        return Err(EscrowError::InvalidRevenueSplit.into());
+    }
+    let mut split = RevenueSplit::default();
+    let mut total_bps: u32 = 0;
+    for (i, b) in beneficiaries.iter().enumerate() {
+        if b.owner == [0u8; 32] || b.weight_bps == 0 {
+            msg!("beneficiary {} must have an owner and a non-zero weight", i);
+            return Err(EscrowError::InvalidRevenueSplit.into());
+        }
+        if beneficiaries[..i].iter().any(|prev| prev.owner == b.owner) {
+            msg!("duplicate beneficiary {}", i);
+            return Err(EscrowError::InvalidRevenueSplit.into());
+        }
+        total_bps += b.weight_bps as u32;
+        split.beneficiaries[i] = *b;
     }
+    if !beneficiaries.is_empty() && total_bps != 10_000 {
+        msg!("beneficiary weights must sum to 10000 bps");
+        return Err(EscrowError::InvalidRevenueSplit.into());
+    }
+    split.count = beneficiaries.len() as u8;
 
-    let auth_pk = Pubkey::new_from_array(state.authority);
-    if auth_pk != *fee_collector.key {
-        msg!("withdraw signer mismatch");
-        return Err(EscrowError::InvalidSigner.into());
+    state.v = ConfigState::LATEST;
+    state.revenue_split = split;
+    let space = ConfigState::space(state.v);
+    write_state_with_realloc(config, authority, system_program, &state, space, state.lamport_fees.reserved)?;
+    Ok(())
+}
+
+fn process_distribute_fees(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    // Accounts:
+    // 0 [] config PDA
+    // 1 [writable] fee vault ATA (ATA(owner=config PDA, mint))
+    // 2 [] token program
+    // 3 [writable] mint stats PDA (seeded by config + fee vault mint)
+    // 4.. [writable] one token account per beneficiary, in split order; each must be ATA(beneficiary, mint)
+    //
+    // Permissionless: destinations are pinned by the split, so anyone can crank it. Each share is rounded
+    // down and the rounding dust goes to the first beneficiary.
+    let acc_iter = &mut accounts.iter();
+    let config = next_account_info(acc_iter)?;
+    let fee_vault = next_account_info(acc_iter)?;
+    let token_program = next_account_info(acc_iter)?;
+    let mint_stats = next_account_info(acc_iter)?;
+    let dest_tokens = acc_iter.as_slice();
+
+    assert_writable(fee_vault)?;
+    assert_program_id(token_program, &spl_token::id(), EscrowError::InvalidTokenProgram)?;
+
+    let state = load_config(program_id, config)?;
+    if !state.revenue_split.active() {
+        msg!("no revenue split configured");
+        return Err(EscrowError::InvalidRevenueSplit.into());
     }
-    let collector_pk = Pubkey::new_from_array(state.fee_collector);
-    if collector_pk != *fee_collector.key {
-        msg!("fee_collector mismatch");
-        return Err(EscrowError::InvalidSigner.into());
+    let entries = state.revenue_split.entries();
+    if dest_tokens.len() != entries.len() {
+        msg!("expected one token account per beneficiary");
+        return Err(ProgramError::NotEnoughAccountKeys);
     }
 
-    // Validate fee vault ATA matches ATA(owner=trade config PDA, mint=fee vault mint).
     let fee_vault_state = spl_token::state::Account::unpack(&fee_vault.try_borrow_data()?)
-        .map_err(|_| EscrowError::InvalidTokenAccount)?;
-    if fee_vault_state.owner != *trade_config.key {
+        .map_err(|_| EscrowError::InvalidTokenAccountData)?;
+    if fee_vault_state.owner != *config.key {
         msg!("fee vault owner mismatch");
-        return Err(EscrowError::InvalidTokenAccount.into());
+        return Err(EscrowError::TokenOwnerMismatch.into());
     }
     let mint_pk = fee_vault_state.mint;
-    let expected_fee_vault =
-        spl_associated_token_account::get_associated_token_address(trade_config.key, &mint_pk);
-    if expected_fee_vault != *fee_vault.key {
+    if spl_associated_token_account::get_associated_token_address(config.key, &mint_pk) != *fee_vault.key {
         msg!("fee vault ATA mismatch");
-        return Err(EscrowError::InvalidTradeFeeVaultAta.into());
+        return Err(EscrowError::InvalidFeeVaultAta.into());
     }
 
-    // Validate destination token account: same mint, owned by collector.
-    let dest_state = spl_token::state::Account::unpack(&dest_token.try_borrow_data()?)
-        .map_err(|_| EscrowError::InvalidTokenAccount)?;
-    if dest_state.mint != mint_pk {
-        msg!("dest mint mismatch");
-        return Err(EscrowError::InvalidTokenAccount.into());
+    let balance = fee_vault_state.amount;
+    update_mint_stats(program_id, mint_stats, config.key, &mint_pk, |s| {
+        s.fees_withdrawn = s.fees_withdrawn.saturating_add(balance)
+    })?;
+    let mut shares: Vec<u64> = Vec::with_capacity(entries.len());
+    for b in entries {
+        let share = (balance as u128 * b.weight_bps as u128 / 10_000u128) as u64;
+        shares.push(share);
     }
-    if dest_state.owner != collector_pk {
-        msg!("dest owner mismatch");
-        return Err(EscrowError::InvalidTokenAccount.into());
+    let distributed: u64 = shares.iter().sum();
+    shares[0] += balance - distributed;
+
+    for ((b, dest_token), share) in entries.iter().zip(dest_tokens).zip(shares) {
+        assert_writable(dest_token)?;
+        let owner = Pubkey::new_from_array(b.owner);
+        if spl_associated_token_account::get_associated_token_address(&owner, &mint_pk) != *dest_token.key {
+            msg!("beneficiary ATA mismatch for {}", owner);
+            return Err(EscrowError::InvalidBeneficiaryAta.into());
+        }
+        if share == 0 {
+            continue;
+        }
+        let transfer_ix = spl_token::instruction::transfer(
+            token_program.key,
+            fee_vault.key,
+            dest_token.key,
+            config.key,
+            &[],
+            share,
+        )?;
+        invoke_signed(
+            &transfer_ix,
+            &[fee_vault.clone(), dest_token.clone(), config.clone(), token_program.clone()],
+            &[&state.signer_seeds()],
+        )?;
+        msg!("distributed {} to {}", share, owner);
+        EscrowEvent::FeesWithdrawn(events::FeesWithdrawn {
+            config: *config.key,
+            fee_vault: *fee_vault.key,
+            mint: mint_pk,
+            destination: *dest_token.key,
+            amount: share,
+        })
+        .emit();
     }
+    Ok(())
+}
 
-    let balance = fee_vault_state.amount;
-    let withdraw_amount = if amount == 0 { balance } else { amount };
-    if withdraw_amount > balance {
-        msg!("withdraw amount exceeds balance");
-        return Err(EscrowError::InvalidInstruction.into());
+// Which config a fee-vault instruction operates on. Both kinds share the authority/collector/treasury fields,
+// so the treasury instructions accept either one and tell them apart by PDA derivation.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FeeConfigKind {
+    Platform,
+    Trade,
+}
+
+struct FeeConfig {
+    kind: FeeConfigKind,
+    authority: Pubkey,
+    fee_collector: Pubkey,
+    treasury: TreasuryState,
+    revenue_split_active: bool,
+    direct_payout: bool,
+    lamport_fees: LamportFeeState,
+    tenant: [u8; 32], // platform configs only
+    bump: [u8; 1],
+}
+
+impl FeeConfig {
+    fn signer_seeds(&self) -> Vec<&[u8]> {
+        match self.kind {
+            FeeConfigKind::Platform if self.tenant == [0u8; 32] => vec![CONFIG_SEED, &self.bump],
+            FeeConfigKind::Platform => vec![CONFIG_SEED, &self.tenant, &self.bump],
+            FeeConfigKind::Trade => vec![TRADE_CONFIG_SEED, self.fee_collector.as_ref(), &self.bump],
+        }
     }
-    if withdraw_amount == 0 {
-        return Ok(());
+
+    fn vault_err(&self) -> EscrowError {
+        match self.kind {
+            FeeConfigKind::Platform => EscrowError::InvalidFeeVaultAta,
+            FeeConfigKind::Trade => EscrowError::InvalidTradeFeeVaultAta,
+        }
     }
+}
 
-    let transfer_ix = spl_token::instruction::transfer(
-        token_program.key,
-        fee_vault.key,
-        dest_token.key,
-        trade_config.key,
-        &[],
-        withdraw_amount,
-    )?;
-    invoke_signed(
-        &transfer_ix,
-        &[
-            fee_vault.clone(),
-            dest_token.clone(),
-            trade_config.clone(),
-            token_program.clone(),
-        ],
-        &[&[TRADE_CONFIG_SEED, fee_collector.key.as_ref(), &[bump]]],
-    )?;
+fn load_fee_config(program_id: &Pubkey, config: &AccountInfo) -> Result<FeeConfig, ProgramError> {
+    // A platform config is recognised by its PDA, which depends on the tenant stored in it.
+    assert_program_owned(config, program_id)?;
+    let platform_state = ConfigState::try_from_slice(&config.try_borrow_data()?).ok().and_then(|state| {
+        let (expected_cfg, config_bump) = config_pda(program_id, &state.tenant());
+        (expected_cfg == *config.key).then_some((state, config_bump))
+    });
+    if let Some((state, config_bump)) = platform_state {
+        if !state.is_supported_version() || state.bump != config_bump {
+            msg!("config state version/bump mismatch");
+            return Err(EscrowError::InvalidConfigState.into());
+        }
+        return Ok(FeeConfig {
+            kind: FeeConfigKind::Platform,
+            authority: Pubkey::new_from_array(state.authority),
+            fee_collector: Pubkey::new_from_array(state.fee_collector),
+            revenue_split_active: state.revenue_split.active(),
+            direct_payout: state.direct_payout,
+            lamport_fees: state.lamport_fees,
+            treasury: state.treasury,
+            tenant: state.tenant,
+            bump: [state.bump],
+        });
+    }
 
-    Ok(())
+    let state = TradeConfigState::try_from_slice(&config.try_borrow_data()?)
+        .map_err(|_| EscrowError::InvalidTradeConfigState)?;
+    let fee_collector = Pubkey::new_from_array(state.fee_collector);
+    let (expected_trade_cfg, trade_cfg_bump) = trade_config_pda(program_id, &fee_collector);
+    if expected_trade_cfg != *config.key {
+        msg!("fee config PDA mismatch");
+        return Err(EscrowError::InvalidTradeConfigPda.into());
+    }
+    if !state.is_supported_version() || state.bump != trade_cfg_bump {
+        msg!("trade config state version/bump mismatch");
+        return Err(EscrowError::InvalidTradeConfigState.into());
+    }
+    Ok(FeeConfig {
+        kind: FeeConfigKind::Trade,
+        authority: Pubkey::new_from_array(state.authority),
+        fee_collector,
+        treasury: state.treasury,
+        revenue_split_active: false,
+        direct_payout: state.direct_payout,
+        lamport_fees: state.lamport_fees,
+        tenant: [0u8; 32],
+        bump: [state.bump],
+    })
 }
 
-fn process_init_config(
+fn load_fee_withdrawal(
+    program_id: &Pubkey,
+    config: &AccountInfo,
+    withdrawal: &AccountInfo,
+) -> Result<FeeWithdrawalState, ProgramError> {
+    assert_program_owned(withdrawal, program_id)?;
+    let state = FeeWithdrawalState::unpack(&withdrawal.try_borrow_data()?)?;
+    if Pubkey::new_from_array(state.config) != *config.key {
+        msg!("fee withdrawal state mismatch");
+        return Err(EscrowError::InvalidFeeWithdrawalState.into());
+    }
+    let (expected_withdrawal, bump) =
+        fee_withdrawal_pda(program_id, config.key, &Pubkey::new_from_array(state.fee_vault));
+    if expected_withdrawal != *withdrawal.key || bump != state.bump {
+        msg!("fee withdrawal PDA mismatch");
+        return Err(EscrowError::InvalidFeeWithdrawalPda.into());
+    }
+    Ok(state)
+}
+
+fn process_set_treasury(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
-    fee_collector: Pubkey,
-    fee_bps: u16,
+    treasury_owner: Pubkey,
+    guardian: Pubkey,
+    withdraw_delay_secs: u32,
 ) -> ProgramResult {
     // Accounts:
-    // 0 [signer,writable] payer (also config authority)
-    // 1 [writable] config PDA
+    // 0 [signer,writable] authority (pays for the realloc when upgrading a v1 config)
+    // 1 [writable] config PDA (platform or trade)
     // 2 [] system program
-    // 3 [] rent sysvar
+    // 3 [signer] current guardian (required only to change or disable an active treasury)
+    //
+    // treasury_owner = default pubkey disables treasury mode.
     let acc_iter = &mut accounts.iter();
-    let payer = next_account_info(acc_iter)?;
+    let authority = next_account_info(acc_iter)?;
     let config = next_account_info(acc_iter)?;
     let system_program = next_account_info(acc_iter)?;
-    let rent_sysvar = next_account_info(acc_iter)?;
 
-    assert_signer(payer)?;
-    assert_writable(payer)?;
+    assert_signer(authority)?;
     assert_writable(config)?;
+    assert_program_id(system_program, &solana_program::system_program::id(), EscrowError::InvalidSystemProgram)?;
 
-    if fee_bps > MAX_PLATFORM_FEE_BPS {
-        msg!("fee_bps too high");
-        return Err(EscrowError::FeeTooHigh.into());
+    let fee_cfg = load_fee_config(program_id, config)?;
+    if fee_cfg.authority != *authority.key {
+        msg!("config authority mismatch");
+        return Err(EscrowError::AuthorityMismatch.into());
     }
-    if *payer.key != fee_collector {
-        msg!("fee_collector must be the config authority");
-        return Err(EscrowError::InvalidSigner.into());
+    // Once active, the treasury can only be changed with the guardian's consent; otherwise a compromised
+    // authority key could simply switch it off.
+    if fee_cfg.treasury.enabled() {
+        let current_guardian = next_account_info(acc_iter)?;
+        assert_signer(current_guardian)?;
+        if Pubkey::new_from_array(fee_cfg.treasury.guardian) != *current_guardian.key {
+            msg!("guardian mismatch");
+            return Err(EscrowError::GuardianMismatch.into());
+        }
     }
 
-    let (expected_config, bump) = config_pda(program_id);
-    if expected_config != *config.key {
-        msg!("config PDA mismatch");
-        return Err(EscrowError::InvalidConfigPda.into());
+    if treasury_owner != Pubkey::default() && fee_cfg.direct_payout {
+        msg!("disable direct payout before enabling treasury mode");
+        return Err(EscrowError::DirectPayoutActive.into());
     }
 
-    if !config.data_is_empty() {
-        msg!("config already initialized");
-        return Err(EscrowError::AlreadyInitialized.into());
+    let treasury = if treasury_owner == Pubkey::default() {
+        TreasuryState::default()
+    } else {
+        if guardian == Pubkey::default() || withdraw_delay_secs == 0 {
+            msg!("treasury mode needs a guardian and a non-zero delay");
+            return Err(EscrowError::InvalidTreasuryParams.into());
+        }
+        TreasuryState {
+            owner: treasury_owner.to_bytes(),
+            guardian: guardian.to_bytes(),
+            withdraw_delay_secs,
+        }
+    };
+
+    match fee_cfg.kind {
+        FeeConfigKind::Platform => {
+            assert_program_owned(config, program_id)?;
+            let mut state = ConfigState::try_from_slice(&config.try_borrow_data()?)
+                .map_err(|_| EscrowError::InvalidConfigState)?;
+            state.v = ConfigState::LATEST;
+            state.treasury = treasury;
+            let space = ConfigState::space(state.v);
+            write_state_with_realloc(config, authority, system_program, &state, space, state.lamport_fees.reserved)?;
+        }
+        FeeConfigKind::Trade => {
+            assert_program_owned(config, program_id)?;
+            let mut state = TradeConfigState::try_from_slice(&config.try_borrow_data()?)
+                .map_err(|_| EscrowError::InvalidTradeConfigState)?;
+            state.v = TradeConfigState::LATEST;
+            state.treasury = treasury;
+            let space = TradeConfigState::space(state.v);
+            write_state_with_realloc(config, authority, system_program, &state, space, state.lamport_fees.reserved)?;
+        }
     }
+    Ok(())
+}
 
-    let rent = Rent::from_account_info(rent_sysvar)?;
-    let space = 1usize + 32 + 32 + 2 + 1; // ConfigState layout
-    let lamports = rent.minimum_balance(space);
-    invoke_signed(
-        &system_instruction::create_account(payer.key, config.key, lamports, space as u64, program_id),
-        &[payer.clone(), config.clone(), system_program.clone()],
-        &[&[CONFIG_SEED, &[bump]]],
-    )?;
+fn process_set_direct_payout(program_id: &Pubkey, accounts: &[AccountInfo], enabled: bool) -> ProgramResult {
+    // Accounts:
+    // 0 [signer,writable] authority (pays for the realloc when upgrading an older config)
+    // 1 [writable] config PDA (platform or trade)
+    // 2 [] system program
+    //
+    // Only affects escrows initialized afterwards; open escrows keep the payout mode they were created with.
+    let acc_iter = &mut accounts.iter();
+    let authority = next_account_info(acc_iter)?;
+    let config = next_account_info(acc_iter)?;
+    let system_program = next_account_info(acc_iter)?;
 
-    let state = ConfigState {
-        v: ConfigState::V1,
-        authority: payer.key.to_bytes(),
-        fee_collector: fee_collector.to_bytes(),
-        fee_bps,
-        bump,
-    };
-    state
-        .serialize(&mut &mut config.try_borrow_mut_data()?[..])
-        .map_err(|_| ProgramError::InvalidAccountData)?;
+    assert_signer(authority)?;
+    assert_writable(config)?;
+    assert_program_id(system_program, &solana_program::system_program::id(), EscrowError::InvalidSystemProgram)?;
+
+    let fee_cfg = load_fee_config(program_id, config)?;
+    if fee_cfg.authority != *authority.key {
+        msg!("config authority mismatch");
+        return Err(EscrowError::AuthorityMismatch.into());
+    }
+    // Direct payouts bypass the fee vault, so they cannot coexist with the vault-based treasury or revenue split.
+    if enabled && fee_cfg.treasury.enabled() {
+        msg!("direct payout is not available in treasury mode");
+        return Err(EscrowError::TreasuryLocked.into());
+    }
+    if enabled && fee_cfg.revenue_split_active {
+        msg!("direct payout is not available while a revenue split is active");
+        return Err(EscrowError::RevenueSplitActive.into());
+    }
+
+    match fee_cfg.kind {
+        FeeConfigKind::Platform => {
+            assert_program_owned(config, program_id)?;
+            let mut state = ConfigState::try_from_slice(&config.try_borrow_data()?)
+                .map_err(|_| EscrowError::InvalidConfigState)?;
+            state.v = ConfigState::LATEST;
+            state.direct_payout = enabled;
+            let space = ConfigState::space(state.v);
+            write_state_with_realloc(config, authority, system_program, &state, space, state.lamport_fees.reserved)?;
+        }
+        FeeConfigKind::Trade => {
+            assert_program_owned(config, program_id)?;
+            let mut state = TradeConfigState::try_from_slice(&config.try_borrow_data()?)
+                .map_err(|_| EscrowError::InvalidTradeConfigState)?;
+            state.v = TradeConfigState::LATEST;
+            state.direct_payout = enabled;
+            let space = TradeConfigState::space(state.v);
+            write_state_with_realloc(config, authority, system_program, &state, space, state.lamport_fees.reserved)?;
+        }
+    }
     Ok(())
 }
 
-fn process_set_config(
-    program_id: &Pubkey,
-    accounts: &[AccountInfo],
-    fee_collector: Pubkey,
-    fee_bps: u16,
-) -> ProgramResult {
+fn process_set_lamport_fee(program_id: &Pubkey, accounts: &[AccountInfo], per_escrow: u64) -> ProgramResult {
     // Accounts:
-    // 0 [signer] authority
-    // 1 [writable] config PDA
+    // 0 [signer,writable] authority (pays for the realloc when upgrading an older config)
+    // 1 [writable] config PDA (platform or trade)
+    // 2 [] system program
+    //
+    // per_escrow = 0 switches the config back to its bps token fee.
     let acc_iter = &mut accounts.iter();
     let authority = next_account_info(acc_iter)?;
     let config = next_account_info(acc_iter)?;
+    let system_program = next_account_info(acc_iter)?;
 
     assert_signer(authority)?;
     assert_writable(config)?;
+    assert_program_id(system_program, &solana_program::system_program::id(), EscrowError::InvalidSystemProgram)?;
 
-    if fee_bps > MAX_PLATFORM_FEE_BPS {
-        msg!("fee_bps too high");
+    if per_escrow > MAX_LAMPORT_FEE {
+        msg!("lamport fee too high");
         return Err(EscrowError::FeeTooHigh.into());
     }
-    if *authority.key != fee_collector {
-        msg!("fee_collector must be the config authority");
-        return Err(EscrowError::InvalidSigner.into());
+
+    let fee_cfg = load_fee_config(program_id, config)?;
+    if fee_cfg.authority != *authority.key {
+        msg!("config authority mismatch");
+        return Err(EscrowError::AuthorityMismatch.into());
     }
 
-    let (expected_config, bump) = config_pda(program_id);
-    if expected_config != *config.key {
-        msg!("config PDA mismatch");
-        return Err(EscrowError::InvalidConfigPda.into());
+    match fee_cfg.kind {
+        FeeConfigKind::Platform => {
+            assert_program_owned(config, program_id)?;
+            let mut state = ConfigState::try_from_slice(&config.try_borrow_data()?)
+                .map_err(|_| EscrowError::InvalidConfigState)?;
+            state.v = ConfigState::LATEST;
+            state.lamport_fees.per_escrow = per_escrow;
+            let space = ConfigState::space(state.v);
+            write_state_with_realloc(config, authority, system_program, &state, space, state.lamport_fees.reserved)?;
+        }
+        FeeConfigKind::Trade => {
+            assert_program_owned(config, program_id)?;
+            let mut state = TradeConfigState::try_from_slice(&config.try_borrow_data()?)
+                .map_err(|_| EscrowError::InvalidTradeConfigState)?;
+            state.v = TradeConfigState::LATEST;
+            state.lamport_fees.per_escrow = per_escrow;
+            let space = TradeConfigState::space(state.v);
+            write_state_with_realloc(config, authority, system_program, &state, space, state.lamport_fees.reserved)?;
+        }
     }
+    Ok(())
+}
 
-    let mut state =
-        ConfigState::try_from_slice(&config.try_borrow_data()?).map_err(|_| EscrowError::InvalidConfigState)?;
-    if state.v != ConfigState::V1 || state.bump != bump {
-        msg!("config state version/bump mismatch");
-        return Err(EscrowError::InvalidConfigState.into());
+fn process_withdraw_lamport_fees(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+    // Accounts:
+    // 0 [signer,writable] fee collector (config authority); receives the lamports
+    // 1 [writable] config PDA (platform or trade)
+    //
+    // amount = 0 withdraws everything above rent exemption and the lamports reserved for open escrows.
+    let acc_iter = &mut accounts.iter();
+    let fee_collector = next_account_info(acc_iter)?;
+    let config = next_account_info(acc_iter)?;
+
+    assert_signer(fee_collector)?;
+    assert_writable(fee_collector)?;
+    assert_writable(config)?;
+
+    let fee_cfg = load_fee_config(program_id, config)?;
+    if fee_cfg.authority != *fee_collector.key || fee_cfg.fee_collector != *fee_collector.key {
+        msg!("withdraw signer mismatch");
+        return Err(EscrowError::AuthorityMismatch.into());
     }
-    if Pubkey::new_from_array(state.authority) != *authority.key {
-        msg!("config authority mismatch");
-        return Err(EscrowError::InvalidSigner.into());
+    if fee_cfg.treasury.enabled() {
+        msg!("treasury mode: lamport fees cannot be withdrawn instantly");
+        return Err(EscrowError::TreasuryLocked.into());
+    }
+    if fee_cfg.revenue_split_active {
+        msg!("revenue split active: clear it before withdrawing lamport fees");
+        return Err(EscrowError::RevenueSplitActive.into());
     }
 
-    state.fee_collector = fee_collector.to_bytes();
-    state.fee_bps = fee_bps;
-    state
-        .serialize(&mut &mut config.try_borrow_mut_data()?[..])
-        .map_err(|_| ProgramError::InvalidAccountData)?;
+    let floor = Rent::get()?
+        .minimum_balance(config.data_len())
+        .checked_add(fee_cfg.lamport_fees.reserved)
+        .ok_or(EscrowError::ArithmeticOverflow)?;
+    let available = config.lamports().saturating_sub(floor);
+    let amount = if amount == 0 { available } else { amount };
+    if amount == 0 || amount > available {
+        msg!("insufficient withdrawable lamport fees");
+        return Err(EscrowError::WithdrawExceedsLamportFees.into());
+    }
+    debit_program_lamports(config, fee_collector, amount)?;
     Ok(())
 }
 
-fn process_withdraw_fees(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+fn process_request_fee_withdrawal(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
     // Accounts:
-    // 0 [signer] fee collector (config authority)
-    // 1 [] config PDA
-    // 2 [writable] fee vault ATA (ATA(owner=config PDA, mint=configured mint))
-    // 3 [writable] fee collector token account (destination)
-    // 4 [] token program
+    // 0 [signer,writable] authority (pays rent for the request account)
+    // 1 [] config PDA (platform or trade)
+    // 2 [] fee vault ATA (ATA(owner=config PDA, mint))
+    // 3 [writable] fee withdrawal PDA (seeded by config + fee vault)
+    // 4 [] system program
+    //
+    // amount = 0 withdraws whatever the vault holds at execution time.
     let acc_iter = &mut accounts.iter();
-    let fee_collector = next_account_info(acc_iter)?;
+    let authority = next_account_info(acc_iter)?;
     let config = next_account_info(acc_iter)?;
     let fee_vault = next_account_info(acc_iter)?;
-    let dest_token = next_account_info(acc_iter)?;
-    let token_program = next_account_info(acc_iter)?;
+    let withdrawal = next_account_info(acc_iter)?;
+    let system_program = next_account_info(acc_iter)?;
 
-    assert_signer(fee_collector)?;
-    assert_writable(fee_vault)?;
-    assert_writable(dest_token)?;
+    assert_signer(authority)?;
+    assert_writable(authority)?;
+    assert_writable(withdrawal)?;
+    assert_program_id(system_program, &solana_program::system_program::id(), EscrowError::InvalidSystemProgram)?;
 
-    let (expected_config, bump) = config_pda(program_id);
-    if expected_config != *config.key {
-        msg!("config PDA mismatch");
-        return Err(EscrowError::InvalidConfigPda.into());
+    let fee_cfg = load_fee_config(program_id, config)?;
+    if fee_cfg.authority != *authority.key {
+        msg!("config authority mismatch");
+        return Err(EscrowError::AuthorityMismatch.into());
+    }
+    if !fee_cfg.treasury.enabled() {
+        msg!("treasury mode not enabled");
+        return Err(EscrowError::TreasuryNotConfigured.into());
+    }
+    if fee_cfg.revenue_split_active {
+        msg!("revenue split active: fees must go through DistributeFees");
+        return Err(EscrowError::RevenueSplitActive.into());
     }
 
-    let state =
-        ConfigState::try_from_slice(&config.try_borrow_data()?).map_err(|_| EscrowError::InvalidConfigState)?;
-    if state.v != ConfigState::V1 || state.bump != bump {
-        msg!("config state version/bump mismatch");
-        return Err(EscrowError::InvalidConfigState.into());
+    let fee_vault_state = spl_token::state::Account::unpack(&fee_vault.try_borrow_data()?)
+        .map_err(|_| EscrowError::InvalidTokenAccountData)?;
+    if fee_vault_state.owner != *config.key {
+        msg!("fee vault owner mismatch");
+        return Err(EscrowError::TokenOwnerMismatch.into());
+    }
+    let expected_fee_vault =
+        spl_associated_token_account::get_associated_token_address(config.key, &fee_vault_state.mint);
+    if expected_fee_vault != *fee_vault.key {
+        msg!("fee vault ATA mismatch");
+        return Err(fee_cfg.vault_err().into());
     }
 
-    let auth_pk = Pubkey::new_from_array(state.authority);
-    if auth_pk != *fee_collector.key {
-        msg!("withdraw signer mismatch");
-        return Err(EscrowError::InvalidSigner.into());
+    let (expected_withdrawal, bump) = fee_withdrawal_pda(program_id, config.key, fee_vault.key);
+    if expected_withdrawal != *withdrawal.key {
+        msg!("fee withdrawal PDA mismatch");
+        return Err(EscrowError::InvalidFeeWithdrawalPda.into());
     }
-    let collector_pk = Pubkey::new_from_array(state.fee_collector);
-    if collector_pk != *fee_collector.key {
-        msg!("fee_collector mismatch");
-        return Err(EscrowError::InvalidSigner.into());
+    if !withdrawal.data_is_empty() {
+        msg!("fee withdrawal already pending");
+        return Err(EscrowError::AlreadyInitialized.into());
     }
 
-    // Validate fee vault ATA matches ATA(owner=config PDA, mint=fee vault mint).
-    let fee_vault_state = spl_token::state::Account::unpack(&fee_vault.try_borrow_data()?)
-        .map_err(|_| EscrowError::InvalidTokenAccount)?;
-    if fee_vault_state.owner != *config.key {
-        msg!("fee vault owner mismatch");
-        return Err(EscrowError::InvalidTokenAccount.into());
+    let clock = Clock::get()?;
+    let executable_at = clock
+        .unix_timestamp
+        .checked_add(fee_cfg.treasury.withdraw_delay_secs as i64)
+        .ok_or(EscrowError::ArithmeticOverflow)?;
+
+    let rent = Rent::get()?;
+    let space = FeeWithdrawalState::LEN;
+    invoke_signed(
+        &system_instruction::create_account(
+            authority.key,
+            withdrawal.key,
+            rent.minimum_balance(space),
+            space as u64,
+            program_id,
+        ),
+        &[authority.clone(), withdrawal.clone(), system_program.clone()],
+        &[&[FEE_WITHDRAWAL_SEED, config.key.as_ref(), fee_vault.key.as_ref(), &[bump]]],
+    )?;
+
+    let state = FeeWithdrawalState {
+        v: FeeWithdrawalState::V1,
+        config: config.key.to_bytes(),
+        fee_vault: fee_vault.key.to_bytes(),
+        amount,
+        requested_at: clock.unix_timestamp,
+        executable_at,
+        rent_payer: authority.key.to_bytes(),
+        bump,
+    };
+    state
+        .serialize(&mut &mut withdrawal.try_borrow_mut_data()?[..])
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    msg!("fee withdrawal of {} requested; executable at {}", amount, executable_at);
+    Ok(())
+}
+
+fn process_execute_fee_withdrawal(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    // Accounts:
+    // 0 [] config PDA (platform or trade)
+    // 1 [writable] fee vault ATA
+    // 2 [writable] treasury token account (owned by the configured treasury owner)
+    // 3 [writable] fee withdrawal PDA
+    // 4 [writable] request rent payer (receives the request account's rent)
+    // 5 [] token program
+    // 6 [writable] mint stats PDA (seeded by config + fee vault mint)
+    //
+    // Permissionless: anyone may execute once the delay has passed, since funds can only reach the treasury.
+    let acc_iter = &mut accounts.iter();
+    let config = next_account_info(acc_iter)?;
+    let fee_vault = next_account_info(acc_iter)?;
+    let dest_token = next_account_info(acc_iter)?;
+    let withdrawal = next_account_info(acc_iter)?;
+    let rent_payer = next_account_info(acc_iter)?;
+    let token_program = next_account_info(acc_iter)?;
+    let mint_stats = next_account_info(acc_iter)?;
+
+    assert_writable(withdrawal)?;
+    assert_writable(rent_payer)?;
+    assert_program_id(token_program, &spl_token::id(), EscrowError::InvalidTokenProgram)?;
+
+    let fee_cfg = load_fee_config(program_id, config)?;
+    if !fee_cfg.treasury.enabled() {
+        msg!("treasury mode not enabled");
+        return Err(EscrowError::TreasuryNotConfigured.into());
     }
-    let mint_pk = fee_vault_state.mint;
-    let expected_fee_vault =
-        spl_associated_token_account::get_associated_token_address(config.key, &mint_pk);
-    if expected_fee_vault != *fee_vault.key {
-        msg!("fee vault ATA mismatch");
-        return Err(EscrowError::InvalidFeeVaultAta.into());
+    let state = load_fee_withdrawal(program_id, config, withdrawal)?;
+    if Pubkey::new_from_array(state.fee_vault) != *fee_vault.key {
+        msg!("fee vault mismatch");
+        return Err(fee_cfg.vault_err().into());
     }
-
-    // Validate destination token account: same mint, owned by collector.
-    let dest_state = spl_token::state::Account::unpack(&dest_token.try_borrow_data()?)
-        .map_err(|_| EscrowError::InvalidTokenAccount)?;
-    if dest_state.mint != mint_pk {
-        msg!("dest mint mismatch");
-        return Err(EscrowError::InvalidTokenAccount.into());
+    if Pubkey::new_from_array(state.rent_payer) != *rent_payer.key {
+        msg!("rent payer mismatch");
+        return Err(EscrowError::RentPayerMismatch.into());
     }
-    if dest_state.owner != collector_pk {
-        msg!("dest owner mismatch");
-        return Err(EscrowError::InvalidTokenAccount.into());
+
+    let clock = Clock::get()?;
+    if clock.unix_timestamp < state.executable_at {
+        msg!("withdrawal delay not elapsed");
+        return Err(EscrowError::TooEarly.into());
     }
 
-    let balance = fee_vault_state.amount;
-    let withdraw_amount = if amount == 0 { balance } else { amount };
-    if withdraw_amount > balance {
-        msg!("withdraw amount exceeds balance");
-        return Err(EscrowError::InvalidInstruction.into());
+    let treasury_pk = Pubkey::new_from_array(fee_cfg.treasury.owner);
+    let withdrawn = withdraw_from_fee_vault(
+        program_id,
+        config,
+        fee_vault,
+        dest_token,
+        Some(mint_stats),
+        token_program,
+        &treasury_pk,
+        &fee_cfg.signer_seeds(),
+        fee_cfg.vault_err(),
+        state.amount,
+    )?;
+    msg!("withdrew {} from fee vault {} to treasury", withdrawn, fee_vault.key);
+
+    close_program_account(withdrawal, rent_payer)
+}
+
+fn process_veto_fee_withdrawal(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    // Accounts:
+    // 0 [signer] guardian (or the config authority once treasury mode is off)
+    // 1 [] config PDA (platform or trade)
+    // 2 [writable] fee withdrawal PDA
+    // 3 [writable] request rent payer (receives the request account's rent)
+    let acc_iter = &mut accounts.iter();
+    let signer = next_account_info(acc_iter)?;
+    let config = next_account_info(acc_iter)?;
+    let withdrawal = next_account_info(acc_iter)?;
+    let rent_payer = next_account_info(acc_iter)?;
+
+    assert_signer(signer)?;
+    assert_writable(withdrawal)?;
+    assert_writable(rent_payer)?;
+
+    let fee_cfg = load_fee_config(program_id, config)?;
+    let (allowed, mismatch) = if fee_cfg.treasury.enabled() {
+        (Pubkey::new_from_array(fee_cfg.treasury.guardian), EscrowError::GuardianMismatch)
+    } else {
+        (fee_cfg.authority, EscrowError::AuthorityMismatch)
+    };
+    if allowed != *signer.key {
+        msg!("veto signer mismatch");
+        return Err(mismatch.into());
     }
-    if withdraw_amount == 0 {
-        return Ok(());
+
+    let state = load_fee_withdrawal(program_id, config, withdrawal)?;
+    if Pubkey::new_from_array(state.rent_payer) != *rent_payer.key {
+        msg!("rent payer mismatch");
+        return Err(EscrowError::RentPayerMismatch.into());
     }
 
-    let transfer_ix = spl_token::instruction::transfer(
-        token_program.key,
-        fee_vault.key,
-        dest_token.key,
-        config.key,
-        &[],
-        withdraw_amount,
-    )?;
-    invoke_signed(
-        &transfer_ix,
-        &[fee_vault.clone(), dest_token.clone(), config.clone(), token_program.clone()],
-        &[&[CONFIG_SEED, &[bump]]],
-    )?;
+    msg!("fee withdrawal vetoed");
+    close_program_account(withdrawal, rent_payer)
+}
 
+// Runs every Init check without creating or transferring anything; the verdict goes to the return data.
+fn process_validate_init(program_id: &Pubkey, accounts: &[AccountInfo], args: &InitArgs, abi: Abi) -> ProgramResult {
+    // Accounts: those of init_escrow, flags included; none of them is modified.
+    let mut validation = InitValidation::default();
+    if let Err(reject) = init_escrow(program_id, accounts, args, abi, true, false, &mut validation) {
+        validation.reason = reject.reason as u8;
+        validation.program_error = u64::from(reject.error);
+    }
+    validation.set();
     Ok(())
 }
 
-fn process_init(
+fn process_init(program_id: &Pubkey, accounts: &[AccountInfo], args: &InitArgs, abi: Abi) -> ProgramResult {
+    // Accounts: those of init_escrow.
+    init_escrow(program_id, accounts, args, abi, false, false, &mut InitValidation::default())
+        .map_err(|reject| reject.error)
+}
+
+// Init for calling programs (see the `cpi` module): same accounts plus a trailing rent payer.
+fn process_init_cpi(program_id: &Pubkey, accounts: &[AccountInfo], args: &InitArgs, abi: Abi) -> ProgramResult {
+    // Accounts: those of init_escrow, with
+    // 0 [signer] payer/refund authority (authorizes the token transfer and is recorded as the escrow's payer)
+    // last [signer,writable] rent payer (after every init_escrow account; pays for each account Init creates
+    //   and for the lamport fees, so the system program requires a system account without data)
+    init_escrow(program_id, accounts, args, abi, false, true, &mut InitValidation::default())
+        .map_err(|reject| reject.error)
+}
+
+// A failed Init check: the error Init returns plus the finer-grained reason ValidateInit reports.
+struct InitReject {
+    reason: InitFailure,
+    error: ProgramError,
+}
+
+impl From<ProgramError> for InitReject {
+    fn from(error: ProgramError) -> Self {
+        let reason = match error {
+            ProgramError::NotEnoughAccountKeys => InitFailure::NotEnoughAccounts,
+            ProgramError::Custom(code) => match EscrowError::from_code(code) {
+                Some(
+                    EscrowError::InvalidTokenProgram
+                    | EscrowError::InvalidSystemProgram
+                    | EscrowError::InvalidAtaProgram,
+                ) => InitFailure::InvalidProgramAccount,
+                Some(EscrowError::InvalidSysvar) => InitFailure::InvalidSysvar,
+                _ => InitFailure::Other,
+            },
+            _ => InitFailure::Other,
+        };
+        InitReject { reason, error }
+    }
+}
+
+fn reject(reason: InitFailure, error: impl Into<ProgramError>) -> InitReject {
+    InitReject {
+        reason,
+        error: error.into(),
+    }
+}
+
+// Body of Init. With `dry_run` (ValidateInit) every check still runs, but nothing is created or transferred;
+// `validation` picks up the fees as soon as they are known so a rejected call still reports them. With
+// `separate_rent_payer` (InitCpi) the last account funds everything Init creates, instead of the payer.
+fn init_escrow(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
-    payment_hash: [u8; 32],
-    recipient: Pubkey,
-    refund: Pubkey,
-    refund_after: i64,
-    amount: u64,
-    expected_platform_fee_bps: u16,
-    expected_trade_fee_bps: u16,
-    trade_fee_collector: Pubkey,
-) -> ProgramResult {
+    args: &InitArgs,
+    abi: Abi,
+    dry_run: bool,
+    separate_rent_payer: bool,
+    validation: &mut InitValidation,
+) -> Result<(), InitReject> {
     // Accounts:
     // 0 [signer,writable] payer/refund authority (initial depositor)
     // 1 [writable] payer token account (USDT)
     // 2 [writable] escrow PDA (state account)
-    // 3 [writable] vault ATA for escrow PDA + mint
+    // 3 [writable] vault ATA (ATA(owner=escrow PDA, mint))
     // 4 [] mint
     // 5 [] system program
     // 6 [] token program
     // 7 [] associated token program
-    // 8 [] rent sysvar
-    // 9 [] config PDA
-    // 10 [writable] platform fee vault ATA (ATA(owner=config PDA, mint))
-    // 11 [] trade config PDA (seeded by trade_fee_collector)
-    // 12 [writable] trade fee vault ATA (ATA(owner=trade config PDA, mint))
+    // 8 [] rent sysvar (v1 only; v2 omits it and every later index moves down by one)
+    // 9 [] config PDA (writable when it charges a lamport fee)
+    // 10 [writable] platform fee vault ATA (ATA(owner=config PDA, mint); ATA(owner=fee collector, mint) in direct
+    //    payout mode)
+    // 11 [] trade config PDA (seeded by trade_fee_collector; writable when it charges a lamport fee)
+    // 12 [writable] trade fee vault ATA (ATA(owner=trade config PDA, mint); ATA(owner=trade fee collector, mint) in
+    //    direct payout mode)
+    // 13 [] referrer token account (ATA(owner=referrer, mint); only when a referrer is set)
+    // 14 [writable] payer stats PDA (only when the config has volume tiers; created if missing)
+    // 15 [writable] platform mint stats PDA (seeded by config PDA + mint; created if missing)
+    // 16 [writable] trade mint stats PDA (seeded by trade config PDA + mint; created if missing)
+    //
+    // Optional accounts 13-14 are packed in order, skipping the ones that do not apply. 15-16 follow them as a pair
+    // that v1 clients leave out; the escrow is then not counted in the mint stats.
+    let InitArgs {
+        payment_hash,
+        recipient,
+        refund,
+        refund_after,
+        amount,
+        expected_platform_fee_bps,
+        expected_trade_fee_bps,
+        trade_fee_collector,
+        referrer,
+        referral_fee_bps,
+        expected_platform_lamport_fee,
+        expected_trade_lamport_fee,
+    } = *args;
+    let (accounts, rent_payer) = match (separate_rent_payer, accounts.split_last()) {
+        (true, Some((last, rest))) => (rest, Some(last)),
+        (true, None) => return Err(ProgramError::NotEnoughAccountKeys.into()),
+        (false, _) => (accounts, None),
+    };
     let acc_iter = &mut accounts.iter();
     let payer = next_account_info(acc_iter)?;
     let payer_token = next_account_info(acc_iter)?;
@@ -762,105 +3214,202 @@ fn process_init(
     let system_program = next_account_info(acc_iter)?;
     let token_program = next_account_info(acc_iter)?;
     let ata_program = next_account_info(acc_iter)?;
-    let rent_sysvar = next_account_info(acc_iter)?;
+    let rent_sysvar = next_sysvar_account::<Rent>(abi, acc_iter)?;
     let config = next_account_info(acc_iter)?;
     let platform_fee_vault = next_account_info(acc_iter)?;
     let trade_config = next_account_info(acc_iter)?;
     let trade_fee_vault = next_account_info(acc_iter)?;
+    let funder = rent_payer.unwrap_or(payer);
 
-    assert_signer(payer)?;
-    assert_writable(payer)?;
-    assert_writable(payer_token)?;
-    assert_writable(escrow)?;
-    assert_writable(vault)?;
+    assert_signer(payer).map_err(|e| reject(InitFailure::PayerNotSigner, e))?;
+    assert_signer(funder).map_err(|e| reject(InitFailure::PayerNotSigner, e))?;
+    assert_writable(funder).map_err(|e| reject(InitFailure::AccountNotWritable, e))?;
+    assert_writable(payer_token).map_err(|e| reject(InitFailure::AccountNotWritable, e))?;
+    assert_writable(escrow).map_err(|e| reject(InitFailure::AccountNotWritable, e))?;
+    assert_writable(vault).map_err(|e| reject(InitFailure::AccountNotWritable, e))?;
+    assert_program_id(system_program, &solana_program::system_program::id(), EscrowError::InvalidSystemProgram)?;
+    assert_program_id(token_program, &spl_token::id(), EscrowError::InvalidTokenProgram)?;
+    assert_program_id(ata_program, &spl_associated_token_account::id(), EscrowError::InvalidAtaProgram)?;
 
     let (expected_escrow, bump) = pda_for_hash(program_id, &payment_hash);
     if expected_escrow != *escrow.key {
         msg!("escrow PDA mismatch");
-        return Err(EscrowError::InvalidEscrowPda.into());
+        return Err(reject(InitFailure::EscrowPdaMismatch, EscrowError::InvalidEscrowPda));
     }
 
-    let (expected_config, config_bump) = config_pda(program_id);
-    if expected_config != *config.key {
-        msg!("config PDA mismatch");
-        return Err(EscrowError::InvalidConfigPda.into());
-    }
-    if config.data_is_empty() {
-        msg!("config not initialized");
-        return Err(EscrowError::InvalidConfigState.into());
-    }
-    let config_state =
-        ConfigState::try_from_slice(&config.try_borrow_data()?).map_err(|_| EscrowError::InvalidConfigState)?;
-    if config_state.v != ConfigState::V1 || config_state.bump != config_bump {
-        msg!("config state version/bump mismatch");
-        return Err(EscrowError::InvalidConfigState.into());
-    }
+    // Any tenant's platform config; the escrow remembers which one so claim/refund can find it again.
+    let mut config_state =
+        load_config(program_id, config).map_err(|e| reject(InitFailure::InvalidPlatformConfig, e))?;
     if config_state.fee_bps > MAX_PLATFORM_FEE_BPS {
         msg!("config fee_bps too high");
-        return Err(EscrowError::FeeTooHigh.into());
+        return Err(reject(InitFailure::PlatformFeeTooHigh, EscrowError::FeeTooHigh));
     }
     let fee_collector_pk = Pubkey::new_from_array(config_state.fee_collector);
-    if config_state.fee_bps != expected_platform_fee_bps {
+
+    let has_referrer = referrer != Pubkey::default();
+    if has_referrer != (referral_fee_bps > 0) {
+        msg!("referrer and referral_fee_bps must be set together");
+        return Err(reject(InitFailure::ReferrerWithoutFee, EscrowError::ReferrerWithoutFee));
+    }
+    if referral_fee_bps > config_state.max_referral_bps || referral_fee_bps > MAX_REFERRAL_FEE_BPS {
+        msg!("referral_fee_bps above cap");
+        return Err(reject(InitFailure::ReferralFeeAboveCap, EscrowError::ReferralFeeTooHigh));
+    }
+
+    // The referrer's ATA must already exist (creating it would need the referrer wallet as an extra account).
+    if has_referrer {
+        let referrer_token = next_account_info(acc_iter)?;
+        let expected_referrer_token =
+            spl_associated_token_account::get_associated_token_address(&referrer, mint.key);
+        if expected_referrer_token != *referrer_token.key {
+            msg!("referrer ATA mismatch");
+            return Err(reject(InitFailure::ReferrerAtaMismatch, EscrowError::InvalidReferrerAta));
+        }
+        let referrer_token_state = spl_token::state::Account::unpack(&referrer_token.try_borrow_data()?)
+            .map_err(|_| reject(InitFailure::InvalidReferrerAta, EscrowError::InvalidReferrerAta))?;
+        if referrer_token_state.owner != referrer || referrer_token_state.mint != *mint.key {
+            msg!("referrer ATA owner/mint mismatch");
+            return Err(reject(InitFailure::InvalidReferrerAta, EscrowError::InvalidReferrerAta));
+        }
+    }
+
+    // Volume tiers: the payer's stats PDA (created on first use) decides the platform fee discount and is
+    // credited again when this escrow is claimed.
+    let today = PayerStatsState::unix_day(Clock::get()?.unix_timestamp);
+    let track_payer_volume = config_state.volume_tiers.active();
+    let rolling_volume = if track_payer_volume {
+        let payer_stats = next_account_info(acc_iter)?;
+        assert_writable(payer_stats).map_err(|e| reject(InitFailure::AccountNotWritable, e))?;
+        let (expected_stats, stats_bump) = payer_stats_pda(program_id, payer.key, mint.key);
+        if expected_stats != *payer_stats.key {
+            msg!("payer stats PDA mismatch");
+            return Err(reject(InitFailure::InvalidPayerStats, EscrowError::InvalidPayerStatsPda));
+        }
+        if payer_stats.data_is_empty() && !dry_run {
+            let lamports = Rent::get()?.minimum_balance(PayerStatsState::LEN);
+            invoke_signed(
+                &system_instruction::create_account(
+                    funder.key,
+                    payer_stats.key,
+                    lamports,
+                    PayerStatsState::LEN as u64,
+                    program_id,
+                ),
+                &[funder.clone(), payer_stats.clone(), system_program.clone()],
+                &[&[PAYER_STATS_SEED, payer.key.as_ref(), mint.key.as_ref(), &[stats_bump]]],
+            )?;
+            let stats = PayerStatsState {
+                v: PayerStatsState::V1,
+                payer: payer.key.to_bytes(),
+                mint: mint.key.to_bytes(),
+                bump: stats_bump,
+                days: [DayVolume::default(); PAYER_STATS_DAYS],
+            };
+            stats
+                .serialize(&mut &mut payer_stats.try_borrow_mut_data()?[..])
+                .map_err(|_| ProgramError::InvalidAccountData)?;
+        }
+        if payer_stats.data_is_empty() {
+            0
+        } else {
+            load_payer_stats(program_id, payer_stats)
+                .map_err(|e| reject(InitFailure::InvalidPayerStats, e))?
+                .rolling_volume(today)
+        }
+    } else {
+        0
+    };
+
+    // A config in lamport fee mode takes no token cut.
+    let platform_fee_bps = if config_state.lamport_fees.per_escrow > 0 {
+        0
+    } else {
+        config_state.volume_tiers.discounted_fee_bps(config_state.fee_bps, rolling_volume)
+    };
+    validation.platform_fee_bps = platform_fee_bps;
+    validation.platform_lamport_fee = config_state.lamport_fees.per_escrow;
+    if platform_fee_bps != expected_platform_fee_bps {
         msg!("platform fee_bps mismatch vs expected");
-        return Err(EscrowError::FeeMismatch.into());
+        return Err(reject(InitFailure::PlatformFeeBpsMismatch, EscrowError::FeeMismatch));
+    }
+    if config_state.lamport_fees.per_escrow != expected_platform_lamport_fee {
+        msg!("platform lamport fee mismatch vs expected");
+        return Err(reject(InitFailure::PlatformLamportFeeMismatch, EscrowError::LamportFeeMismatch));
     }
 
     let expected_vault = spl_associated_token_account::get_associated_token_address(escrow.key, mint.key);
     if expected_vault != *vault.key {
         msg!("vault ATA mismatch");
-        return Err(EscrowError::InvalidVaultAta.into());
+        return Err(reject(InitFailure::VaultAtaMismatch, EscrowError::InvalidVaultAta));
     }
 
     // Validate trade config PDA + state.
     let (expected_trade_cfg, trade_cfg_bump) = trade_config_pda(program_id, &trade_fee_collector);
     if expected_trade_cfg != *trade_config.key {
         msg!("trade config PDA mismatch");
-        return Err(EscrowError::InvalidTradeConfigPda.into());
+        return Err(reject(InitFailure::TradeConfigPdaMismatch, EscrowError::InvalidTradeConfigPda));
     }
     if trade_config.data_is_empty() {
         msg!("trade config not initialized");
-        return Err(EscrowError::InvalidTradeConfigState.into());
+        return Err(reject(InitFailure::TradeConfigNotInitialized, EscrowError::TradeConfigNotInitialized));
     }
-    let trade_cfg_state = TradeConfigState::try_from_slice(&trade_config.try_borrow_data()?)
-        .map_err(|_| EscrowError::InvalidTradeConfigState)?;
-    if trade_cfg_state.v != TradeConfigState::V1 || trade_cfg_state.bump != trade_cfg_bump {
+    assert_program_owned(trade_config, program_id).map_err(|e| reject(InitFailure::InvalidTradeConfig, e))?;
+    let mut trade_cfg_state = TradeConfigState::try_from_slice(&trade_config.try_borrow_data()?)
+        .map_err(|_| reject(InitFailure::InvalidTradeConfig, EscrowError::InvalidTradeConfigState))?;
+    if !trade_cfg_state.is_supported_version() || trade_cfg_state.bump != trade_cfg_bump {
         msg!("trade config state version/bump mismatch");
-        return Err(EscrowError::InvalidTradeConfigState.into());
+        return Err(reject(InitFailure::InvalidTradeConfig, EscrowError::InvalidTradeConfigState));
     }
     if trade_cfg_state.fee_bps > MAX_TRADE_FEE_BPS {
         msg!("trade config fee_bps too high");
-        return Err(EscrowError::FeeTooHigh.into());
+        return Err(reject(InitFailure::TradeFeeTooHigh, EscrowError::FeeTooHigh));
     }
     if Pubkey::new_from_array(trade_cfg_state.fee_collector) != trade_fee_collector {
         msg!("trade config fee_collector mismatch");
-        return Err(EscrowError::InvalidTradeConfigState.into());
+        return Err(reject(InitFailure::InvalidTradeConfig, EscrowError::TradeConfigCollectorMismatch));
     }
     if Pubkey::new_from_array(trade_cfg_state.authority) != trade_fee_collector {
         msg!("trade config authority mismatch");
-        return Err(EscrowError::InvalidTradeConfigState.into());
+        return Err(reject(InitFailure::InvalidTradeConfig, EscrowError::TradeConfigCollectorMismatch));
     }
-    if trade_cfg_state.fee_bps != expected_trade_fee_bps {
+    let trade_fee_bps = if trade_cfg_state.lamport_fees.per_escrow > 0 {
+        0
+    } else {
+        lock_adjusted_trade_fee_bps(&trade_cfg_state, Clock::get()?.unix_timestamp, refund_after)
+    };
+    validation.trade_fee_bps = trade_fee_bps;
+    validation.trade_lamport_fee = trade_cfg_state.lamport_fees.per_escrow;
+    // The surcharge counts started hours from the cluster clock, which a client can only quote a little ahead
+    // of; near an hour boundary it lands one hour above us. The expected bps is therefore a cap, and the escrow
+    // is charged what the program computed.
+    if trade_fee_bps > expected_trade_fee_bps {
         msg!("trade fee_bps mismatch vs expected");
-        return Err(EscrowError::FeeMismatch.into());
+        return Err(reject(InitFailure::TradeFeeBpsMismatch, EscrowError::FeeMismatch));
+    }
+    if trade_cfg_state.lamport_fees.per_escrow != expected_trade_lamport_fee {
+        msg!("trade lamport fee mismatch vs expected");
+        return Err(reject(InitFailure::TradeLamportFeeMismatch, EscrowError::LamportFeeMismatch));
     }
 
-    let total_fee_bps: u32 = config_state.fee_bps as u32 + trade_cfg_state.fee_bps as u32;
+    let total_fee_bps: u32 = platform_fee_bps as u32 + trade_fee_bps as u32 + referral_fee_bps as u32;
     if total_fee_bps > MAX_TOTAL_FEE_BPS as u32 {
         msg!("total fee_bps too high");
-        return Err(EscrowError::FeeTooHigh.into());
+        return Err(reject(InitFailure::TotalFeeTooHigh, EscrowError::TotalFeeTooHigh));
     }
 
     // Ensure platform fee vault ATA exists (ATA(owner=config PDA, mint)).
-    assert_writable(platform_fee_vault)?;
-    let expected_fee_vault =
-        spl_associated_token_account::get_associated_token_address(config.key, mint.key);
-    if expected_fee_vault != *platform_fee_vault.key {
+    assert_writable(platform_fee_vault).map_err(|e| reject(InitFailure::AccountNotWritable, e))?;
+    if config_state.direct_payout {
+        assert_direct_payout_ata(platform_fee_vault, &fee_collector_pk, mint.key, EscrowError::InvalidFeeVaultAta)
+            .map_err(|e| reject(InitFailure::PlatformFeeVaultMismatch, e))?;
+    } else if spl_associated_token_account::get_associated_token_address(config.key, mint.key)
+        != *platform_fee_vault.key
+    {
         msg!("platform fee vault ATA mismatch");
-        return Err(EscrowError::InvalidFeeVaultAta.into());
-    }
-    if platform_fee_vault.data_is_empty() {
+        return Err(reject(InitFailure::PlatformFeeVaultMismatch, EscrowError::InvalidFeeVaultAta));
+    } else if platform_fee_vault.data_is_empty() && !dry_run {
         let ix = spl_associated_token_account::instruction::create_associated_token_account(
-            payer.key,
+            funder.key,
             config.key,
             mint.key,
             token_program.key,
@@ -868,29 +3417,35 @@ fn process_init(
         invoke(
             &ix,
             &[
-                payer.clone(),
+                funder.clone(),
                 platform_fee_vault.clone(),
                 config.clone(),
                 mint.clone(),
                 system_program.clone(),
                 token_program.clone(),
                 ata_program.clone(),
-                rent_sysvar.clone(),
             ],
         )?;
     }
 
     // Ensure trade fee vault ATA exists (ATA(owner=trade config PDA, mint)).
-    assert_writable(trade_fee_vault)?;
-    let expected_trade_fee_vault =
-        spl_associated_token_account::get_associated_token_address(trade_config.key, mint.key);
-    if expected_trade_fee_vault != *trade_fee_vault.key {
+    assert_writable(trade_fee_vault).map_err(|e| reject(InitFailure::AccountNotWritable, e))?;
+    if trade_cfg_state.direct_payout {
+        assert_direct_payout_ata(
+            trade_fee_vault,
+            &trade_fee_collector,
+            mint.key,
+            EscrowError::InvalidTradeFeeVaultAta,
+        )
+        .map_err(|e| reject(InitFailure::TradeFeeVaultMismatch, e))?;
+    } else if spl_associated_token_account::get_associated_token_address(trade_config.key, mint.key)
+        != *trade_fee_vault.key
+    {
         msg!("trade fee vault ATA mismatch");
-        return Err(EscrowError::InvalidTradeFeeVaultAta.into());
-    }
-    if trade_fee_vault.data_is_empty() {
+        return Err(reject(InitFailure::TradeFeeVaultMismatch, EscrowError::InvalidTradeFeeVaultAta));
+    } else if trade_fee_vault.data_is_empty() && !dry_run {
         let ix = spl_associated_token_account::instruction::create_associated_token_account(
-            payer.key,
+            funder.key,
             trade_config.key,
             mint.key,
             token_program.key,
@@ -898,84 +3453,129 @@ fn process_init(
         invoke(
             &ix,
             &[
-                payer.clone(),
+                funder.clone(),
                 trade_fee_vault.clone(),
                 trade_config.clone(),
                 mint.clone(),
                 system_program.clone(),
                 token_program.clone(),
                 ata_program.clone(),
-                rent_sysvar.clone(),
             ],
         )?;
     }
 
+    let mint_stats = next_optional_pair(acc_iter)?;
+    if let Some((platform_stats, trade_stats)) = mint_stats.filter(|_| dry_run) {
+        for (stats, stats_config) in [(platform_stats, config.key), (trade_stats, trade_config.key)] {
+            load_mint_stats(program_id, stats, stats_config, mint.key)
+                .map_err(|e| reject(InitFailure::InvalidMintStats, e))?;
+        }
+    }
+
     // Validate payer token account.
     let payer_token_state = spl_token::state::Account::unpack(&payer_token.try_borrow_data()?)
-        .map_err(|_| EscrowError::InvalidTokenAccount)?;
-    if payer_token_state.owner != *payer.key {
+        .map_err(|_| reject(InitFailure::InvalidPayerToken, EscrowError::InvalidTokenAccountData))?;
+    // The payer signs as the owner or as the approved delegate (how programs usually lend out tokens they keep
+    // under their own authority); a delegate can move no more than its allowance.
+    let spendable = if payer_token_state.owner == *payer.key {
+        payer_token_state.amount
+    } else if payer_token_state.delegate == COption::Some(*payer.key) {
+        payer_token_state.delegated_amount.min(payer_token_state.amount)
+    } else {
         msg!("payer token owner mismatch");
-        return Err(EscrowError::InvalidTokenAccount.into());
-    }
+        return Err(reject(InitFailure::PayerTokenOwnerMismatch, EscrowError::TokenOwnerMismatch));
+    };
     if payer_token_state.mint != *mint.key {
         msg!("payer token mint mismatch");
-        return Err(EscrowError::InvalidTokenAccount.into());
+        return Err(reject(InitFailure::PayerTokenMintMismatch, EscrowError::TokenMintMismatch));
     }
 
+    let overflow = || reject(InitFailure::AmountOverflow, EscrowError::ArithmeticOverflow);
     let platform_fee_amount_u128 = (amount as u128)
-        .checked_mul(config_state.fee_bps as u128)
-        .ok_or(EscrowError::InvalidInstruction)?
+        .checked_mul(platform_fee_bps as u128)
+        .ok_or_else(overflow)?
+        / 10_000u128;
+    let platform_fee_amount: u64 = platform_fee_amount_u128.try_into().map_err(|_| overflow())?;
+
+    // The rebate is snapshotted here so later config changes never alter what an open escrow pays out.
+    let maker_rebate_amount_u128 = platform_fee_amount_u128
+        .checked_mul(config_state.maker_rebate_bps as u128)
+        .ok_or_else(overflow)?
         / 10_000u128;
-    let platform_fee_amount: u64 = platform_fee_amount_u128
-        .try_into()
-        .map_err(|_| EscrowError::InvalidInstruction)?;
+    let maker_rebate_amount: u64 = maker_rebate_amount_u128.try_into().map_err(|_| overflow())?;
 
     let trade_fee_amount_u128 = (amount as u128)
-        .checked_mul(trade_cfg_state.fee_bps as u128)
-        .ok_or(EscrowError::InvalidInstruction)?
+        .checked_mul(trade_fee_bps as u128)
+        .ok_or_else(overflow)?
         / 10_000u128;
-    let trade_fee_amount: u64 = trade_fee_amount_u128
-        .try_into()
-        .map_err(|_| EscrowError::InvalidInstruction)?;
+    let trade_fee_amount: u64 = trade_fee_amount_u128.try_into().map_err(|_| overflow())?;
+
+    let referral_fee_amount_u128 = (amount as u128)
+        .checked_mul(referral_fee_bps as u128)
+        .ok_or_else(overflow)?
+        / 10_000u128;
+    let referral_fee_amount: u64 = referral_fee_amount_u128.try_into().map_err(|_| overflow())?;
 
     let total_amount: u64 = amount
         .checked_add(platform_fee_amount)
-        .ok_or(EscrowError::InvalidInstruction)?
+        .ok_or_else(overflow)?
         .checked_add(trade_fee_amount)
-        .ok_or(EscrowError::InvalidInstruction)?;
+        .ok_or_else(overflow)?
+        .checked_add(referral_fee_amount)
+        .ok_or_else(overflow)?;
 
-    if payer_token_state.amount < total_amount {
+    validation.net_amount = amount;
+    validation.platform_fee_amount = platform_fee_amount;
+    validation.trade_fee_amount = trade_fee_amount;
+    validation.referral_fee_amount = referral_fee_amount;
+    validation.maker_rebate_amount = maker_rebate_amount;
+    validation.total_amount = total_amount;
+    if spendable < total_amount {
         msg!("payer token insufficient balance");
-        return Err(EscrowError::InvalidTokenAccount.into());
+        return Err(reject(InitFailure::InsufficientBalance, EscrowError::InsufficientFunds));
     }
 
-    // Create escrow PDA account if uninitialized; disallow re-init to keep payment_hash unique.
+    // Disallow re-init to keep payment_hash unique.
     if !escrow.data_is_empty() {
         msg!("escrow already initialized");
-        return Err(EscrowError::AlreadyInitialized.into());
+        return Err(reject(InitFailure::EscrowAlreadyInitialized, EscrowError::AlreadyInitialized));
+    }
+    if dry_run {
+        return Ok(());
+    }
+
+    // Accounting: count the escrow in both configs' mint stats now; its fees are credited on claim.
+    let mut counted_stats = Vec::with_capacity(2);
+    if let Some((platform_stats, trade_stats)) = mint_stats {
+        let platform_stats_state = init_mint_stats(
+            program_id,
+            funder,
+            platform_stats,
+            config.key,
+            mint.key,
+            system_program,
+            (!config_state.direct_payout).then_some(platform_fee_vault),
+        )?;
+        let trade_stats_state = init_mint_stats(
+            program_id,
+            funder,
+            trade_stats,
+            trade_config.key,
+            mint.key,
+            system_program,
+            (!trade_cfg_state.direct_payout).then_some(trade_fee_vault),
+        )?;
+        counted_stats.extend([(platform_stats, platform_stats_state), (trade_stats, trade_stats_state)]);
     }
+
+    // Create the escrow PDA account.
     {
-        let rent = Rent::from_account_info(rent_sysvar)?;
-        let space = 1usize
-            + 1usize
-            + 32
-            + 32
-            + 32
-            + 8
-            + 32
-            + 8
-            + 8
-            + 2
-            + 32
-            + 8
-            + 2
-            + 32
-            + 32
-            + 1; // EscrowState layout (v3)
+        let rent: Rent = load_sysvar(rent_sysvar)?;
+        let space = EscrowState::space(EscrowState::LATEST);
         let lamports = rent.minimum_balance(space);
         invoke_signed(
-            &system_instruction::create_account(payer.key, escrow.key, lamports, space as u64, program_id),
-            &[payer.clone(), escrow.clone(), system_program.clone()],
+            &system_instruction::create_account(funder.key, escrow.key, lamports, space as u64, program_id),
+            &[funder.clone(), escrow.clone(), system_program.clone()],
             &[&[ESCROW_SEED, &payment_hash, &[bump]]],
         )?;
     }
@@ -983,7 +3583,7 @@ fn process_init(
     // Create vault ATA if needed.
     if vault.data_is_empty() {
         let ix = spl_associated_token_account::instruction::create_associated_token_account(
-            payer.key,
+            funder.key,
             escrow.key,
             mint.key,
             token_program.key,
@@ -991,19 +3591,18 @@ fn process_init(
         invoke(
             &ix,
             &[
-                payer.clone(),
+                funder.clone(),
                 vault.clone(),
                 escrow.clone(),
                 mint.clone(),
                 system_program.clone(),
                 token_program.clone(),
                 ata_program.clone(),
-                rent_sysvar.clone(),
             ],
         )?;
     }
 
-    // Transfer tokens into the vault (net + platform fee + trade fee).
+    // Transfer tokens into the vault (net + platform fee + trade fee + referral fee).
     let transfer_ix = spl_token::instruction::transfer(
         token_program.key,
         payer_token.key,
@@ -1014,9 +3613,44 @@ fn process_init(
     )?;
     invoke(&transfer_ix, &[payer_token.clone(), vault.clone(), payer.clone(), token_program.clone()])?;
 
+    // Charge lamport fees into the config PDAs and reserve them until the escrow settles.
+    if config_state.lamport_fees.per_escrow > 0 {
+        charge_lamport_fee(funder, config, system_program, &mut config_state.lamport_fees)?;
+        config_state
+            .serialize(&mut &mut config.try_borrow_mut_data()?[..])
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+    }
+    if trade_cfg_state.lamport_fees.per_escrow > 0 {
+        charge_lamport_fee(funder, trade_config, system_program, &mut trade_cfg_state.lamport_fees)?;
+        trade_cfg_state
+            .serialize(&mut &mut trade_config.try_borrow_mut_data()?[..])
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+    }
+
+    for (stats, mut stats_state) in counted_stats {
+        stats_state.total_volume = stats_state.total_volume.saturating_add(total_amount);
+        stats_state.active_escrows = stats_state.active_escrows.saturating_add(1);
+        stats_state
+            .serialize(&mut &mut stats.try_borrow_mut_data()?[..])
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+    }
+
     // Persist state.
+    let mut fee_flags = 0;
+    if mint_stats.is_some() {
+        fee_flags |= EscrowState::FLAG_TRACK_MINT_STATS;
+    }
+    if config_state.direct_payout {
+        fee_flags |= EscrowState::FLAG_PLATFORM_DIRECT_PAYOUT;
+    }
+    if trade_cfg_state.direct_payout {
+        fee_flags |= EscrowState::FLAG_TRADE_DIRECT_PAYOUT;
+    }
+    if track_payer_volume {
+        fee_flags |= EscrowState::FLAG_TRACK_PAYER_VOLUME;
+    }
     let state = EscrowState {
-        v: EscrowState::V3,
+        v: EscrowState::LATEST,
         status: EscrowState::STATUS_ACTIVE,
         payment_hash,
         recipient: recipient.to_bytes(),
@@ -1025,17 +3659,54 @@ fn process_init(
         mint: mint.key.to_bytes(),
         net_amount: amount,
         platform_fee_amount,
-        platform_fee_bps: config_state.fee_bps,
+        platform_fee_bps,
         platform_fee_collector: fee_collector_pk.to_bytes(),
         trade_fee_amount,
-        trade_fee_bps: trade_cfg_state.fee_bps,
+        trade_fee_bps,
         trade_fee_collector: trade_fee_collector.to_bytes(),
         vault: vault.key.to_bytes(),
         bump,
+        referrer: referrer.to_bytes(),
+        referral_fee_bps,
+        referral_fee_amount,
+        maker_rebate_amount,
+        fee_flags,
+        platform_lamport_fee: config_state.lamport_fees.per_escrow,
+        trade_lamport_fee: trade_cfg_state.lamport_fees.per_escrow,
+        payer: payer.key.to_bytes(),
+        tenant: config_state.tenant,
+        reserved: [0u8; RESERVED_LEN],
     };
     state
         .serialize(&mut &mut escrow.try_borrow_mut_data()?[..])
         .map_err(|_| ProgramError::InvalidAccountData)?;
+    EscrowEvent::EscrowInitialized(events::EscrowInitialized {
+        escrow: *escrow.key,
+        payment_hash,
+        mint: *mint.key,
+        payer: *payer.key,
+        recipient,
+        refund,
+        refund_after,
+        net_amount: amount,
+        platform_fee_amount,
+        trade_fee_amount,
+        referral_fee_amount,
+        platform_config: *config.key,
+        trade_config: *trade_config.key,
+    })
+    .emit();
+    EscrowOutcome {
+        escrow: *escrow.key,
+        vault: *vault.key,
+        status: EscrowState::STATUS_ACTIVE,
+        total_amount,
+        net_amount: amount,
+        platform_fee_amount,
+        trade_fee_amount,
+        referral_fee_amount,
+    }
+    .set();
     Ok(())
 }
 
@@ -1045,9 +3716,20 @@ fn process_claim(program_id: &Pubkey, accounts: &[AccountInfo], preimage: [u8; 3
     // 1 [writable] escrow PDA (state account)
     // 2 [writable] vault ATA
     // 3 [writable] recipient token account
-    // 4 [writable] platform fee vault ATA (ATA(owner=config PDA, mint))
-    // 5 [writable] trade fee vault ATA (ATA(owner=trade config PDA, mint))
+    // 4 [writable] platform fee vault ATA (ATA(owner=config PDA, mint); ATA(owner=platform fee collector, mint) for
+    //   escrows opened in direct payout mode)
+    // 5 [writable] trade fee vault ATA (ATA(owner=trade config PDA, mint); ATA(owner=trade fee collector, mint) for
+    //   escrows opened in direct payout mode)
     // 6 [] token program
+    // 7 [writable] referrer token account (ATA(owner=referrer, mint); only when the escrow has a referrer)
+    // 8 [writable] config PDA (only when the escrow paid a platform lamport fee)
+    // 9 [writable] trade config PDA (only when the escrow paid a trade lamport fee)
+    // 10 [writable] payer stats PDA (only when the escrow tracks payer volume)
+    // 11 [writable] platform mint stats PDA (seeded by config PDA + mint)
+    // 12 [writable] trade mint stats PDA (seeded by trade config PDA + mint)
+    //
+    // Optional accounts 7-10 are packed in order, skipping the ones that do not apply. 11-12 follow them as a pair
+    // that v1 clients leave out; the stats are then not updated.
     let acc_iter = &mut accounts.iter();
     let recipient = next_account_info(acc_iter)?;
     let escrow = next_account_info(acc_iter)?;
@@ -1063,15 +3745,17 @@ fn process_claim(program_id: &Pubkey, accounts: &[AccountInfo], preimage: [u8; 3
     assert_writable(recipient_token)?;
     assert_writable(platform_fee_vault)?;
     assert_writable(trade_fee_vault)?;
+    assert_program_id(token_program, &spl_token::id(), EscrowError::InvalidTokenProgram)?;
 
-    let mut state = EscrowState::try_from_slice(&escrow.try_borrow_data()?)
-        .map_err(|_| ProgramError::InvalidAccountData)?;
+    assert_program_owned(escrow, program_id)?;
+    let mut state = EscrowState::unpack(&escrow.try_borrow_data()?)?;
     require_active(&state)?;
+    let config_pk = config_pda(program_id, &Pubkey::new_from_array(state.tenant)).0;
 
     let recipient_pk = Pubkey::new_from_array(state.recipient);
     if recipient_pk != *recipient.key {
         msg!("recipient mismatch");
-        return Err(EscrowError::InvalidSigner.into());
+        return Err(EscrowError::RecipientMismatch.into());
     }
     if Pubkey::new_from_array(state.vault) != *vault.key {
         msg!("vault mismatch");
@@ -1086,18 +3770,18 @@ fn process_claim(program_id: &Pubkey, accounts: &[AccountInfo], preimage: [u8; 3
 
     // Validate vault + recipient token accounts.
     let vault_state = spl_token::state::Account::unpack(&vault.try_borrow_data()?)
-        .map_err(|_| EscrowError::InvalidTokenAccount)?;
+        .map_err(|_| EscrowError::InvalidTokenAccountData)?;
     let recipient_token_state = spl_token::state::Account::unpack(&recipient_token.try_borrow_data()?)
-        .map_err(|_| EscrowError::InvalidTokenAccount)?;
+        .map_err(|_| EscrowError::InvalidTokenAccountData)?;
 
     let mint_pk = Pubkey::new_from_array(state.mint);
     if vault_state.mint != mint_pk || recipient_token_state.mint != mint_pk {
         msg!("mint mismatch");
-        return Err(EscrowError::InvalidTokenAccount.into());
+        return Err(EscrowError::TokenMintMismatch.into());
     }
     if recipient_token_state.owner != *recipient.key {
         msg!("recipient token owner mismatch");
-        return Err(EscrowError::InvalidTokenAccount.into());
+        return Err(EscrowError::TokenOwnerMismatch.into());
     }
 
     let (expected_escrow, bump) = pda_for_hash(program_id, &state.payment_hash);
@@ -1107,52 +3791,157 @@ fn process_claim(program_id: &Pubkey, accounts: &[AccountInfo], preimage: [u8; 3
     }
     if vault_state.owner != expected_escrow {
         msg!("vault authority mismatch");
-        return Err(EscrowError::InvalidTokenAccount.into());
+        return Err(EscrowError::VaultAuthorityMismatch.into());
     }
 
-    // Validate platform fee vault ATA (ATA(owner=config PDA, mint)).
-    let (cfg_pda, _cfg_bump) = config_pda(program_id);
+    // Validate platform fee vault ATA (ATA(owner=config PDA, mint), or the collector's ATA for direct payouts).
+    let platform_direct = state.fee_flags & EscrowState::FLAG_PLATFORM_DIRECT_PAYOUT != 0;
+    let platform_fee_owner = if platform_direct {
+        Pubkey::new_from_array(state.platform_fee_collector)
+    } else {
+        config_pk
+    };
     let expected_fee_vault =
-        spl_associated_token_account::get_associated_token_address(&cfg_pda, &mint_pk);
+        spl_associated_token_account::get_associated_token_address(&platform_fee_owner, &mint_pk);
     if expected_fee_vault != *platform_fee_vault.key {
         msg!("platform fee vault ATA mismatch");
         return Err(EscrowError::InvalidFeeVaultAta.into());
     }
-    let platform_fee_vault_state = spl_token::state::Account::unpack(&platform_fee_vault.try_borrow_data()?)
-        .map_err(|_| EscrowError::InvalidTokenAccount)?;
-    if platform_fee_vault_state.mint != mint_pk {
-        msg!("platform fee vault mint mismatch");
+    // A fee vault closed by CloseConfig/CloseTradeConfig can no longer take its fee. That fee is released to the
+    // recipient instead, so decommissioning a config never blocks claims on escrows opened against it. A closed
+    // collector ATA in direct payout mode is not a decommissioned config: the claim fails until it is re-created.
+    let platform_fee_vault_closed = platform_fee_vault.data_is_empty();
+    if platform_fee_vault_closed && platform_direct {
+        msg!("platform fee collector ATA closed");
         return Err(EscrowError::InvalidTokenAccount.into());
     }
-    if platform_fee_vault_state.owner != cfg_pda {
-        msg!("platform fee vault owner mismatch");
-        return Err(EscrowError::InvalidTokenAccount.into());
+    if !platform_fee_vault_closed {
+        let platform_fee_vault_state = spl_token::state::Account::unpack(&platform_fee_vault.try_borrow_data()?)
+            .map_err(|_| EscrowError::InvalidTokenAccountData)?;
+        if platform_fee_vault_state.mint != mint_pk {
+            msg!("platform fee vault mint mismatch");
+            return Err(EscrowError::TokenMintMismatch.into());
+        }
+        if platform_fee_vault_state.owner != platform_fee_owner {
+            msg!("platform fee vault owner mismatch");
+            return Err(EscrowError::TokenOwnerMismatch.into());
+        }
     }
 
-    // Validate trade fee vault ATA (ATA(owner=trade config PDA, mint)).
+    // Validate trade fee vault ATA (ATA(owner=trade config PDA, mint), or the collector's ATA for direct payouts).
     let trade_collector_pk = Pubkey::new_from_array(state.trade_fee_collector);
-    let (trade_cfg_pda, _trade_cfg_bump) = trade_config_pda(program_id, &trade_collector_pk);
+    let trade_direct = state.fee_flags & EscrowState::FLAG_TRADE_DIRECT_PAYOUT != 0;
+    let trade_fee_owner = if trade_direct {
+        trade_collector_pk
+    } else {
+        trade_config_pda(program_id, &trade_collector_pk).0
+    };
     let expected_trade_fee_vault =
-        spl_associated_token_account::get_associated_token_address(&trade_cfg_pda, &mint_pk);
+        spl_associated_token_account::get_associated_token_address(&trade_fee_owner, &mint_pk);
     if expected_trade_fee_vault != *trade_fee_vault.key {
         msg!("trade fee vault ATA mismatch");
         return Err(EscrowError::InvalidTradeFeeVaultAta.into());
     }
-    let trade_fee_vault_state = spl_token::state::Account::unpack(&trade_fee_vault.try_borrow_data()?)
-        .map_err(|_| EscrowError::InvalidTokenAccount)?;
-    if trade_fee_vault_state.mint != mint_pk {
-        msg!("trade fee vault mint mismatch");
+    let trade_fee_vault_closed = trade_fee_vault.data_is_empty();
+    if trade_fee_vault_closed && trade_direct {
+        msg!("trade fee collector ATA closed");
         return Err(EscrowError::InvalidTokenAccount.into());
     }
-    if trade_fee_vault_state.owner != trade_cfg_pda {
-        msg!("trade fee vault owner mismatch");
-        return Err(EscrowError::InvalidTokenAccount.into());
+    if !trade_fee_vault_closed {
+        let trade_fee_vault_state = spl_token::state::Account::unpack(&trade_fee_vault.try_borrow_data()?)
+            .map_err(|_| EscrowError::InvalidTokenAccountData)?;
+        if trade_fee_vault_state.mint != mint_pk {
+            msg!("trade fee vault mint mismatch");
+            return Err(EscrowError::TokenMintMismatch.into());
+        }
+        if trade_fee_vault_state.owner != trade_fee_owner {
+            msg!("trade fee vault owner mismatch");
+            return Err(EscrowError::TokenOwnerMismatch.into());
+        }
+    }
+
+    // Validate the referrer's ATA, which must still exist to take its cut.
+    let referrer_pk = Pubkey::new_from_array(state.referrer);
+    let referrer_token = if referrer_pk != Pubkey::default() {
+        let referrer_token = next_account_info(acc_iter)?;
+        assert_writable(referrer_token)?;
+        let expected_referrer_token =
+            spl_associated_token_account::get_associated_token_address(&referrer_pk, &mint_pk);
+        if expected_referrer_token != *referrer_token.key {
+            msg!("referrer ATA mismatch");
+            return Err(EscrowError::InvalidReferrerAta.into());
+        }
+        if referrer_token.data_is_empty() {
+            msg!("referrer ATA closed");
+            return Err(EscrowError::InvalidTokenAccount.into());
+        }
+        let referrer_token_state = spl_token::state::Account::unpack(&referrer_token.try_borrow_data()?)
+            .map_err(|_| EscrowError::InvalidReferrerAta)?;
+        if referrer_token_state.owner != referrer_pk || referrer_token_state.mint != mint_pk {
+            msg!("referrer ATA owner/mint mismatch");
+            return Err(EscrowError::InvalidReferrerAta.into());
+        }
+        Some(referrer_token)
+    } else {
+        None
+    };
+
+    // Lamport fees already sit in the config PDAs; claiming only lifts their reservation so they become withdrawable.
+    if state.platform_lamport_fee > 0 {
+        let config = next_account_info(acc_iter)?;
+        release_lamport_fee(program_id, config, &config_pk, FeeConfigKind::Platform, state.platform_lamport_fee)?;
+    }
+    if state.trade_lamport_fee > 0 {
+        let trade_config = next_account_info(acc_iter)?;
+        let (expected_trade_cfg, _) = trade_config_pda(program_id, &trade_collector_pk);
+        let fee = state.trade_lamport_fee;
+        release_lamport_fee(program_id, trade_config, &expected_trade_cfg, FeeConfigKind::Trade, fee)?;
+    }
+
+    // Credit the escrowed amount to the payer's rolling volume.
+    if state.fee_flags & EscrowState::FLAG_TRACK_PAYER_VOLUME != 0 {
+        let payer_stats = next_account_info(acc_iter)?;
+        assert_writable(payer_stats)?;
+        let (expected_stats, _) = payer_stats_pda(program_id, &Pubkey::new_from_array(state.payer), &mint_pk);
+        if expected_stats != *payer_stats.key {
+            msg!("payer stats PDA mismatch");
+            return Err(EscrowError::InvalidPayerStatsPda.into());
+        }
+        let mut stats = load_payer_stats(program_id, payer_stats)?;
+        stats.record(PayerStatsState::unix_day(Clock::get()?.unix_timestamp), state.net_amount);
+        stats
+            .serialize(&mut &mut payer_stats.try_borrow_mut_data()?[..])
+            .map_err(|_| ProgramError::InvalidAccountData)?;
     }
+    let mint_stats = next_optional_pair(acc_iter)?;
 
     // Transfer net amount to recipient, then fees to their respective fee vaults.
-    let net_amount = state.net_amount;
-    let platform_fee_amount = state.platform_fee_amount;
-    let trade_fee_amount = state.trade_fee_amount;
+    let mut net_amount = state.net_amount;
+    let mut platform_fee_amount = state.platform_fee_amount;
+    let mut trade_fee_amount = state.trade_fee_amount;
+    if platform_fee_vault_closed {
+        msg!("platform fee vault closed; fee released to recipient");
+        net_amount = net_amount
+            .checked_add(platform_fee_amount)
+            .ok_or(EscrowError::ArithmeticOverflow)?;
+        platform_fee_amount = 0;
+    } else if state.maker_rebate_amount > 0 {
+        // Maker rebate: that share of the platform fee goes to the recipient together with the net amount.
+        net_amount = net_amount
+            .checked_add(state.maker_rebate_amount)
+            .ok_or(EscrowError::ArithmeticOverflow)?;
+        platform_fee_amount = platform_fee_amount
+            .checked_sub(state.maker_rebate_amount)
+            .ok_or(EscrowError::ArithmeticOverflow)?;
+    }
+    if trade_fee_vault_closed {
+        msg!("trade fee vault closed; fee released to recipient");
+        net_amount = net_amount
+            .checked_add(trade_fee_amount)
+            .ok_or(EscrowError::ArithmeticOverflow)?;
+        trade_fee_amount = 0;
+    }
+    let referral_fee_amount = state.referral_fee_amount;
     let bump_seed = [state.bump];
     let seeds: &[&[u8]] = &[ESCROW_SEED, &state.payment_hash, &bump_seed];
 
@@ -1204,71 +3993,153 @@ fn process_claim(program_id: &Pubkey, accounts: &[AccountInfo], preimage: [u8; 3
             &[seeds],
         )?;
     }
+    if let Some(referrer_token) = referrer_token.filter(|_| referral_fee_amount > 0) {
+        let fee_ix = spl_token::instruction::transfer(
+            token_program.key,
+            vault.key,
+            referrer_token.key,
+            escrow.key,
+            &[],
+            referral_fee_amount,
+        )?;
+        invoke_signed(
+            &fee_ix,
+            &[vault.clone(), referrer_token.clone(), escrow.clone(), token_program.clone()],
+            &[seeds],
+        )?;
+    }
+
+    // Escrows opened before the stats PDA existed still pay into the fee vault, so their fees are credited too
+    // (keeping the vault invariant); only escrows Init counted move the escrow counters. Direct payouts bypass the
+    // fee vault, so they are withdrawn as soon as they accrue.
+    let counted = state.fee_flags & EscrowState::FLAG_TRACK_MINT_STATS != 0;
+    let stats_updates = mint_stats.map(|(platform_stats, trade_stats)| {
+        [
+            (platform_stats, config_pk, platform_fee_amount, platform_direct),
+            (trade_stats, trade_config_pda(program_id, &trade_collector_pk).0, trade_fee_amount, trade_direct),
+        ]
+    });
+    for (stats, stats_config_pk, fee, direct) in stats_updates.into_iter().flatten() {
+        update_mint_stats(program_id, stats, &stats_config_pk, &mint_pk, |s| {
+            if counted {
+                s.active_escrows = s.active_escrows.saturating_sub(1);
+                s.claimed_escrows = s.claimed_escrows.saturating_add(1);
+            }
+            s.fees_accrued = s.fees_accrued.saturating_add(fee);
+            if direct {
+                s.fees_withdrawn = s.fees_withdrawn.saturating_add(fee);
+            }
+        })?;
+    }
+
+    EscrowEvent::EscrowClaimed(events::EscrowClaimed {
+        escrow: *escrow.key,
+        payment_hash: state.payment_hash,
+        preimage,
+        recipient: Pubkey::new_from_array(state.recipient),
+        net_amount,
+        platform_fee_amount,
+        trade_fee_amount,
+        referral_fee_amount,
+    })
+    .emit();
 
     state.status = EscrowState::STATUS_CLAIMED;
     state.net_amount = 0;
     state.platform_fee_amount = 0;
     state.trade_fee_amount = 0;
+    state.referral_fee_amount = 0;
+    state.maker_rebate_amount = 0;
+    state.platform_lamport_fee = 0;
+    state.trade_lamport_fee = 0;
     state
         .serialize(&mut &mut escrow.try_borrow_mut_data()?[..])
         .map_err(|_| ProgramError::InvalidAccountData)?;
+    let total_amount = net_amount
+        .checked_add(platform_fee_amount)
+        .ok_or(EscrowError::ArithmeticOverflow)?
+        .checked_add(trade_fee_amount)
+        .ok_or(EscrowError::ArithmeticOverflow)?
+        .checked_add(referral_fee_amount)
+        .ok_or(EscrowError::ArithmeticOverflow)?;
+    EscrowOutcome {
+        escrow: *escrow.key,
+        vault: *vault.key,
+        status: EscrowState::STATUS_CLAIMED,
+        total_amount,
+        net_amount,
+        platform_fee_amount,
+        trade_fee_amount,
+        referral_fee_amount,
+    }
+    .set();
     Ok(())
 }
 
-fn process_refund(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+fn process_refund(program_id: &Pubkey, accounts: &[AccountInfo], abi: Abi) -> ProgramResult {
     // Accounts:
-    // 0 [signer] refund authority
+    // 0 [signer] refund authority (writable when the escrow paid lamport fees; they are returned to it)
     // 1 [writable] escrow PDA (state account)
     // 2 [writable] vault ATA
     // 3 [writable] refund token account
     // 4 [] token program
-    // 5 [] clock sysvar
+    // 5 [] clock sysvar (v1 only; v2 omits it and every later index moves down by one)
+    // 6 [writable] config PDA (only when the escrow paid a platform lamport fee)
+    // 7 [writable] trade config PDA (only when the escrow paid a trade lamport fee; index 6 if there is no platform
+    //   lamport fee)
+    // 8 [writable] platform mint stats PDA (only when the escrow tracks mint stats)
+    // 9 [writable] trade mint stats PDA (only when the escrow tracks mint stats)
+    //
+    // Optional accounts 6-9 are packed in order, skipping the ones that do not apply. v1 clients may leave out 8-9
+    // even when they apply; the stats are then not updated.
     let acc_iter = &mut accounts.iter();
     let refund = next_account_info(acc_iter)?;
     let escrow = next_account_info(acc_iter)?;
     let vault = next_account_info(acc_iter)?;
     let refund_token = next_account_info(acc_iter)?;
     let token_program = next_account_info(acc_iter)?;
-    let clock_sysvar = next_account_info(acc_iter)?;
+    let clock_sysvar = next_sysvar_account::<Clock>(abi, acc_iter)?;
 
     assert_signer(refund)?;
     assert_writable(escrow)?;
     assert_writable(vault)?;
     assert_writable(refund_token)?;
+    assert_program_id(token_program, &spl_token::id(), EscrowError::InvalidTokenProgram)?;
 
-    let mut state = EscrowState::try_from_slice(&escrow.try_borrow_data()?)
-        .map_err(|_| ProgramError::InvalidAccountData)?;
+    assert_program_owned(escrow, program_id)?;
+    let mut state = EscrowState::unpack(&escrow.try_borrow_data()?)?;
     require_active(&state)?;
+    let config_pk = config_pda(program_id, &Pubkey::new_from_array(state.tenant)).0;
 
     let refund_pk = Pubkey::new_from_array(state.refund);
     if refund_pk != *refund.key {
         msg!("refund signer mismatch");
-        return Err(EscrowError::InvalidSigner.into());
+        return Err(EscrowError::RefundMismatch.into());
     }
     if Pubkey::new_from_array(state.vault) != *vault.key {
         msg!("vault mismatch");
         return Err(EscrowError::InvalidVaultAta.into());
     }
 
-    let clock = Clock::from_account_info(clock_sysvar)?;
+    let clock: Clock = load_sysvar(clock_sysvar)?;
     if clock.unix_timestamp < state.refund_after {
         msg!("too early to refund");
         return Err(EscrowError::TooEarly.into());
     }
 
     let vault_state = spl_token::state::Account::unpack(&vault.try_borrow_data()?)
-        .map_err(|_| EscrowError::InvalidTokenAccount)?;
+        .map_err(|_| EscrowError::InvalidTokenAccountData)?;
     let refund_token_state = spl_token::state::Account::unpack(&refund_token.try_borrow_data()?)
-        .map_err(|_| EscrowError::InvalidTokenAccount)?;
+        .map_err(|_| EscrowError::InvalidTokenAccountData)?;
 
     let mint_pk = Pubkey::new_from_array(state.mint);
     if vault_state.mint != mint_pk || refund_token_state.mint != mint_pk {
         msg!("mint mismatch");
-        return Err(EscrowError::InvalidTokenAccount.into());
+        return Err(EscrowError::TokenMintMismatch.into());
     }
     if refund_token_state.owner != *refund.key {
         msg!("refund token owner mismatch");
-        return Err(EscrowError::InvalidTokenAccount.into());
+        return Err(EscrowError::TokenOwnerMismatch.into());
     }
 
     let (expected_escrow, bump) = pda_for_hash(program_id, &state.payment_hash);
@@ -1278,15 +4149,17 @@ fn process_refund(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResul
     }
     if vault_state.owner != expected_escrow {
         msg!("vault authority mismatch");
-        return Err(EscrowError::InvalidTokenAccount.into());
+        return Err(EscrowError::VaultAuthorityMismatch.into());
     }
 
     let total_amount = state
         .net_amount
         .checked_add(state.platform_fee_amount)
-        .ok_or(EscrowError::InvalidInstruction)?
+        .ok_or(EscrowError::ArithmeticOverflow)?
         .checked_add(state.trade_fee_amount)
-        .ok_or(EscrowError::InvalidInstruction)?;
+        .ok_or(EscrowError::ArithmeticOverflow)?
+        .checked_add(state.referral_fee_amount)
+        .ok_or(EscrowError::ArithmeticOverflow)?;
     let transfer_ix = spl_token::instruction::transfer(
         token_program.key,
         vault.key,
@@ -1301,12 +4174,122 @@ fn process_refund(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResul
         &[&[ESCROW_SEED, &state.payment_hash, &[state.bump]]],
     )?;
 
+    if state.platform_lamport_fee > 0 {
+        let config = next_account_info(acc_iter)?;
+        release_lamport_fee(program_id, config, &config_pk, FeeConfigKind::Platform, state.platform_lamport_fee)?;
+        assert_writable(refund)?;
+        debit_program_lamports(config, refund, state.platform_lamport_fee)?;
+    }
+    if state.trade_lamport_fee > 0 {
+        let trade_config = next_account_info(acc_iter)?;
+        let (expected_trade_cfg, _) =
+            trade_config_pda(program_id, &Pubkey::new_from_array(state.trade_fee_collector));
+        let fee = state.trade_lamport_fee;
+        release_lamport_fee(program_id, trade_config, &expected_trade_cfg, FeeConfigKind::Trade, fee)?;
+        assert_writable(refund)?;
+        debit_program_lamports(trade_config, refund, state.trade_lamport_fee)?;
+    }
+    let mint_stats = next_optional_pair(acc_iter)?;
+    if let Some((platform_stats, trade_stats)) =
+        mint_stats.filter(|_| state.fee_flags & EscrowState::FLAG_TRACK_MINT_STATS != 0)
+    {
+        let trade_config_pk = trade_config_pda(program_id, &Pubkey::new_from_array(state.trade_fee_collector)).0;
+        for (stats, stats_config_pk) in [(platform_stats, config_pk), (trade_stats, trade_config_pk)] {
+            update_mint_stats(program_id, stats, &stats_config_pk, &mint_pk, |s| {
+                s.active_escrows = s.active_escrows.saturating_sub(1);
+                s.refunded_escrows = s.refunded_escrows.saturating_add(1);
+            })?;
+        }
+    }
+
+    EscrowEvent::EscrowRefunded(events::EscrowRefunded {
+        escrow: *escrow.key,
+        payment_hash: state.payment_hash,
+        refund: Pubkey::new_from_array(state.refund),
+        amount: total_amount,
+    })
+    .emit();
+
     state.status = EscrowState::STATUS_REFUNDED;
     state.net_amount = 0;
     state.platform_fee_amount = 0;
     state.trade_fee_amount = 0;
+    state.referral_fee_amount = 0;
+    state.maker_rebate_amount = 0;
+    state.platform_lamport_fee = 0;
+    state.trade_lamport_fee = 0;
     state
         .serialize(&mut &mut escrow.try_borrow_mut_data()?[..])
         .map_err(|_| ProgramError::InvalidAccountData)?;
+    EscrowOutcome {
+        escrow: *escrow.key,
+        vault: *vault.key,
+        status: EscrowState::STATUS_REFUNDED,
+        total_amount,
+        net_amount: total_amount,
+        platform_fee_amount: 0,
+        trade_fee_amount: 0,
+        referral_fee_amount: 0,
+    }
+    .set();
+    Ok(())
+}
+
+// Rewrites an escrow, platform config or trade config at the latest layout of its type, growing the account to
+// the new size. Fields the old layout did not have take their defaults, exactly as when the old layout is read.
+// Anyone can migrate any account; one already at the latest layout is left as it is.
+fn process_migrate_account(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    // Accounts:
+    // 0 [signer,writable] payer (tops the account up to rent exemption at its new size)
+    // 1 [writable] state PDA (escrow, platform config or trade config)
+    // 2 [] system program
+    let acc_iter = &mut accounts.iter();
+    let payer = next_account_info(acc_iter)?;
+    let account = next_account_info(acc_iter)?;
+    let system_program = next_account_info(acc_iter)?;
+
+    assert_signer(payer)?;
+    assert_writable(account)?;
+    assert_program_id(system_program, &solana_program::system_program::id(), EscrowError::InvalidSystemProgram)?;
+    assert_program_owned(account, program_id)?;
+
+    // An escrow is recognised by its PDA, like the two configs in `load_fee_config`.
+    let escrow_state = EscrowState::unpack(&account.try_borrow_data()?)
+        .ok()
+        .filter(|state| pda_for_hash(program_id, &state.payment_hash) == (*account.key, state.bump));
+    if let Some(mut state) = escrow_state {
+        let from = state.v;
+        if from != EscrowState::LATEST {
+            state.v = EscrowState::LATEST;
+            write_state_with_realloc(account, payer, system_program, &state, EscrowState::space(state.v), 0)?;
+            msg!("escrow migrated from v{} to v{}", from, state.v);
+        }
+        return Ok(());
+    }
+
+    match load_fee_config(program_id, account)?.kind {
+        FeeConfigKind::Platform => {
+            let mut state = ConfigState::try_from_slice(&account.try_borrow_data()?)
+                .map_err(|_| EscrowError::InvalidConfigState)?;
+            let from = state.v;
+            if from != ConfigState::LATEST {
+                state.v = ConfigState::LATEST;
+                let space = ConfigState::space(state.v);
+                write_state_with_realloc(account, payer, system_program, &state, space, state.lamport_fees.reserved)?;
+                msg!("config migrated from v{} to v{}", from, state.v);
+            }
+        }
+        FeeConfigKind::Trade => {
+            let mut state = TradeConfigState::try_from_slice(&account.try_borrow_data()?)
+                .map_err(|_| EscrowError::InvalidTradeConfigState)?;
+            let from = state.v;
+            if from != TradeConfigState::LATEST {
+                state.v = TradeConfigState::LATEST;
+                let space = TradeConfigState::space(state.v);
+                write_state_with_realloc(account, payer, system_program, &state, space, state.lamport_fees.reserved)?;
+                msg!("trade config migrated from v{} to v{}", from, state.v);
+            }
+        }
+    }
     Ok(())
 }
@@ -0,0 +1,175 @@
+//! Return data for Init, Claim and Refund.
+//!
+//! Each of these instructions finishes with `set_return_data`: one version byte (`RETURN_DATA_VERSION`) followed
+//! by a Borsh-encoded [`EscrowOutcome`]. CPI callers read it with `get_return_data` right after the invoke, and
+//! clients get it from the `returnData` field of a simulation.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{program::set_return_data, pubkey::Pubkey};
+
+pub const RETURN_DATA_VERSION: u8 = 1;
+
+pub const STATUS_ACTIVE: u8 = 0;
+pub const STATUS_CLAIMED: u8 = 1;
+pub const STATUS_REFUNDED: u8 = 2;
+
+// Amounts describe the tokens this instruction moved:
+// - Init: the payer's total debit and how it splits into the net amount and each fee.
+// - Claim: what was paid out; fees released to the recipient (closed vaults, maker rebate) count as net.
+// - Refund: the whole vault goes back to the refund address as `net_amount`; no fees are charged.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EscrowOutcome {
+    pub escrow: Pubkey,
+    pub vault: Pubkey,
+    pub status: u8,
+    pub total_amount: u64,
+    pub net_amount: u64,
+    pub platform_fee_amount: u64,
+    pub trade_fee_amount: u64,
+    pub referral_fee_amount: u64,
+}
+
+impl EscrowOutcome {
+    pub fn to_return_data(&self) -> Vec<u8> {
+        let mut data = vec![RETURN_DATA_VERSION];
+        self.serialize(&mut data).expect("return data serialization");
+        data
+    }
+
+    // Expects the bytes of `get_return_data` (or a simulation's decoded `returnData`), minus the program id.
+    pub fn from_return_data(data: &[u8]) -> Option<Self> {
+        match data.split_first() {
+            Some((&RETURN_DATA_VERSION, mut rest)) => Self::deserialize(&mut rest).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn set(&self) {
+        set_return_data(&self.to_return_data());
+    }
+}
+
+// Why ValidateInit rejected an Init, in the order `process_init` runs its checks. `Other` covers errors outside
+// the checks themselves (e.g. a sysvar that could not be read); `InitValidation::program_error` has the details.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitFailure {
+    NotEnoughAccounts = 1,
+    PayerNotSigner = 2,
+    AccountNotWritable = 3,
+    EscrowPdaMismatch = 4,
+    InvalidPlatformConfig = 5,
+    PlatformFeeTooHigh = 6,
+    ReferrerWithoutFee = 7,
+    ReferralFeeAboveCap = 8,
+    ReferrerAtaMismatch = 9,
+    InvalidReferrerAta = 10,
+    InvalidPayerStats = 11,
+    PlatformFeeBpsMismatch = 12,
+    PlatformLamportFeeMismatch = 13,
+    VaultAtaMismatch = 14,
+    TradeConfigPdaMismatch = 15,
+    TradeConfigNotInitialized = 16,
+    InvalidTradeConfig = 17,
+    TradeFeeTooHigh = 18,
+    TradeFeeBpsMismatch = 19,
+    TradeLamportFeeMismatch = 20,
+    TotalFeeTooHigh = 21,
+    PlatformFeeVaultMismatch = 22,
+    TradeFeeVaultMismatch = 23,
+    InvalidMintStats = 24,
+    InvalidPayerToken = 25,
+    PayerTokenOwnerMismatch = 26,
+    PayerTokenMintMismatch = 27,
+    AmountOverflow = 28,
+    InsufficientBalance = 29,
+    EscrowAlreadyInitialized = 30,
+    InvalidProgramAccount = 31,
+    InvalidSysvar = 32,
+    Other = 255,
+}
+
+impl InitFailure {
+    pub fn from_code(code: u8) -> Option<Self> {
+        use InitFailure::*;
+        const ALL: [InitFailure; 33] = [
+            NotEnoughAccounts,
+            PayerNotSigner,
+            AccountNotWritable,
+            EscrowPdaMismatch,
+            InvalidPlatformConfig,
+            PlatformFeeTooHigh,
+            ReferrerWithoutFee,
+            ReferralFeeAboveCap,
+            ReferrerAtaMismatch,
+            InvalidReferrerAta,
+            InvalidPayerStats,
+            PlatformFeeBpsMismatch,
+            PlatformLamportFeeMismatch,
+            VaultAtaMismatch,
+            TradeConfigPdaMismatch,
+            TradeConfigNotInitialized,
+            InvalidTradeConfig,
+            TradeFeeTooHigh,
+            TradeFeeBpsMismatch,
+            TradeLamportFeeMismatch,
+            TotalFeeTooHigh,
+            PlatformFeeVaultMismatch,
+            TradeFeeVaultMismatch,
+            InvalidMintStats,
+            InvalidPayerToken,
+            PayerTokenOwnerMismatch,
+            PayerTokenMintMismatch,
+            AmountOverflow,
+            InsufficientBalance,
+            EscrowAlreadyInitialized,
+            InvalidProgramAccount,
+            InvalidSysvar,
+            Other,
+        ];
+        ALL.into_iter().find(|f| *f as u8 == code)
+    }
+}
+
+// ValidateInit's return data (same version byte framing as `EscrowOutcome`). `reason` is 0 when Init would
+// succeed, otherwise an `InitFailure` code, with `program_error` holding the error Init would fail with (as
+// `u64::from(ProgramError)`). Fees and amounts are filled in as far as the checks got: the fee bps are the
+// program's own numbers, so on a fee mismatch they are the values the client should have sent.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct InitValidation {
+    pub reason: u8,
+    pub program_error: u64,
+    pub platform_fee_bps: u16,
+    pub trade_fee_bps: u16,
+    pub platform_lamport_fee: u64,
+    pub trade_lamport_fee: u64,
+    pub net_amount: u64,
+    pub platform_fee_amount: u64,
+    pub trade_fee_amount: u64,
+    pub referral_fee_amount: u64,
+    pub maker_rebate_amount: u64,
+    pub total_amount: u64,
+}
+
+impl InitValidation {
+    pub fn failure(&self) -> Option<InitFailure> {
+        InitFailure::from_code(self.reason)
+    }
+
+    pub fn to_return_data(&self) -> Vec<u8> {
+        let mut data = vec![RETURN_DATA_VERSION];
+        self.serialize(&mut data).expect("return data serialization");
+        data
+    }
+
+    pub fn from_return_data(data: &[u8]) -> Option<Self> {
+        match data.split_first() {
+            Some((&RETURN_DATA_VERSION, mut rest)) => Self::deserialize(&mut rest).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn set(&self) {
+        set_return_data(&self.to_return_data());
+    }
+}
@@ -0,0 +1,594 @@
+//! Claim: where the escrowed amount and each fee end up, and every check it can reject on.
+
+mod common;
+
+use common::{signer, Account, Fixture, Opened};
+use ln_usdt_escrow::{
+    instruction::{self, InitArgs},
+    return_data::{EscrowOutcome, STATUS_CLAIMED},
+    EscrowError, EscrowState, VolumeTier,
+};
+use solana_program::{hash::hash, instruction::Instruction, program_error::ProgramError, pubkey::Pubkey};
+use spl_associated_token_account::get_associated_token_address;
+
+// Account indices of a Claim on an escrow without referrer, lamport fees or payer volume.
+const RECIPIENT: usize = 0;
+const ESCROW: usize = 1;
+const VAULT: usize = 2;
+const RECIPIENT_TOKEN: usize = 3;
+const PLATFORM_FEE_VAULT: usize = 4;
+const TRADE_FEE_VAULT: usize = 5;
+const TOKEN_PROGRAM: usize = 6;
+const PLATFORM_STATS: usize = 7;
+const TRADE_STATS: usize = 8;
+
+const PREIMAGE: [u8; 32] = [1u8; 32];
+
+// An active 100_000 escrow at 1% platform and 0.5% trade fee, and its recipient's token account.
+struct Case {
+    fx: Fixture,
+    opened: Opened,
+    recipient: Pubkey,
+    recipient_token: Pubkey,
+}
+
+fn case() -> Case {
+    case_with(|_| (), |_| ())
+}
+
+// Opens the escrow after `configure` has changed the fixture and `edit` the Init arguments.
+fn case_with(configure: impl FnOnce(&mut Fixture), edit: impl FnOnce(&mut InitArgs)) -> Case {
+    let mut fx = Fixture::new(100, 50);
+    configure(&mut fx);
+    let recipient = signer();
+    let mint = fx.mint;
+    let recipient_token = get_associated_token_address(&recipient, &mint);
+    fx.bank.create_token_account(&recipient_token, &mint, &recipient, 0);
+
+    let (payer, payer_token) = fx.funded_payer(1_000_000);
+    let mut args = fx.init_args(&PREIMAGE, &recipient, &payer, 100_000);
+    edit(&mut args);
+    let ix = fx.init_ix(&payer, &payer_token, args);
+    fx.bank.process(&ix, &[&payer]).expect("init");
+    let escrow = ln_usdt_escrow::pda_for_hash(&ln_usdt_escrow::id(), &args.payment_hash).0;
+    let opened = Opened {
+        args,
+        escrow,
+        vault: get_associated_token_address(&escrow, &mint),
+        payer,
+        payer_token,
+    };
+    Case {
+        fx,
+        opened,
+        recipient,
+        recipient_token,
+    }
+}
+
+impl Case {
+    fn state(&self) -> EscrowState {
+        self.fx.escrow(&self.opened.args.payment_hash)
+    }
+
+    fn ix(&self) -> Instruction {
+        instruction::claim(&self.state(), &self.recipient_token, PREIMAGE)
+    }
+
+    fn process(&mut self, ix: &Instruction, signer: &Pubkey) -> Result<EscrowOutcome, ProgramError> {
+        let (_, data) = self.fx.bank.process(ix, &[signer])?.expect("return data");
+        Ok(EscrowOutcome::from_return_data(&data).expect("escrow outcome"))
+    }
+
+    fn claim(&mut self) -> Result<EscrowOutcome, ProgramError> {
+        let (ix, recipient) = (self.ix(), self.recipient);
+        self.process(&ix, &recipient)
+    }
+
+    // Runs Claim with the instruction changed by `edit`; it must fail with the vault untouched.
+    fn claim_with(&mut self, edit: impl FnOnce(&mut Instruction)) -> ProgramError {
+        let mut ix = self.ix();
+        edit(&mut ix);
+        self.reject(&ix)
+    }
+
+    // Builds Claim from the escrow as it stands, then lets `corrupt` break the accounts before it runs.
+    fn claim_after(&mut self, corrupt: impl FnOnce(&mut Fixture)) -> ProgramError {
+        let ix = self.ix();
+        corrupt(&mut self.fx);
+        self.reject(&ix)
+    }
+
+    fn reject(&mut self, ix: &Instruction) -> ProgramError {
+        let (before, recipient) = (self.fx.bank.token_balance(&self.opened.vault), self.recipient);
+        let err = self.process(ix, &recipient).unwrap_err();
+        assert_eq!(self.fx.bank.token_balance(&self.opened.vault), before);
+        err
+    }
+
+    fn refund(&mut self) -> Result<EscrowOutcome, ProgramError> {
+        let refund_after = self.opened.args.refund_after;
+        self.fx.bank.set_clock(refund_after);
+        let ix = instruction::refund(&self.state(), &self.opened.payer_token);
+        let payer = self.opened.payer;
+        self.process(&ix, &payer)
+    }
+
+    // The config that owns the fee vault at account `index` of the claim.
+    fn fee_vault_owner(&self, index: usize) -> Pubkey {
+        if index == PLATFORM_FEE_VAULT {
+            self.fx.config_key()
+        } else {
+            self.fx.trade_config_key()
+        }
+    }
+
+    fn fee_vault(&self, index: usize) -> Pubkey {
+        get_associated_token_address(&self.fee_vault_owner(index), &self.fx.mint)
+    }
+}
+
+#[test]
+fn pays_the_recipient_and_both_fee_vaults() {
+    let mut c = case();
+    let outcome = c.claim().unwrap();
+    assert_eq!(
+        outcome,
+        EscrowOutcome {
+            escrow: c.opened.escrow,
+            vault: c.opened.vault,
+            status: STATUS_CLAIMED,
+            total_amount: 101_500,
+            net_amount: 100_000,
+            platform_fee_amount: 1_000,
+            trade_fee_amount: 500,
+            referral_fee_amount: 0,
+        }
+    );
+    assert_eq!(c.fx.bank.token_balance(&c.recipient_token), 100_000);
+    assert_eq!(c.fx.bank.token_balance(&c.fee_vault(PLATFORM_FEE_VAULT)), 1_000);
+    assert_eq!(c.fx.bank.token_balance(&c.fee_vault(TRADE_FEE_VAULT)), 500);
+    assert_eq!(c.fx.bank.token_balance(&c.opened.vault), 0);
+
+    let state = c.state();
+    assert_eq!(state.status, EscrowState::STATUS_CLAIMED);
+    assert_eq!((state.net_amount, state.platform_fee_amount, state.trade_fee_amount), (0, 0, 0));
+
+    for (config, fee) in [(c.fx.config_key(), 1_000), (c.fx.trade_config_key(), 500)] {
+        let stats = c.fx.mint_stats(&config).unwrap();
+        assert_eq!((stats.active_escrows, stats.claimed_escrows, stats.fees_accrued), (0, 1, fee));
+        assert_eq!(stats.fees_withdrawn, 0);
+    }
+}
+
+#[test]
+fn fees_of_escrows_opened_before_mint_stats_are_credited_too() {
+    let mut c = case();
+    // As if opened before stats existed: Init never counted the escrow, but later escrows created the PDAs.
+    let mut state = c.state();
+    state.fee_flags &= !EscrowState::FLAG_TRACK_MINT_STATS;
+    c.fx.bank.set_data(&c.opened.escrow, borsh::to_vec(&state).unwrap());
+    c.claim().unwrap();
+
+    for index in [PLATFORM_FEE_VAULT, TRADE_FEE_VAULT] {
+        let stats = c.fx.mint_stats(&c.fee_vault_owner(index)).unwrap();
+        assert_eq!((stats.active_escrows, stats.claimed_escrows), (1, 0));
+        assert_eq!(stats.fees_accrued - stats.fees_withdrawn, c.fx.bank.token_balance(&c.fee_vault(index)));
+    }
+}
+
+#[test]
+fn claims_without_mint_stats_leave_them_as_they_were() {
+    let mut c = case();
+    let mut ix = c.ix();
+    ix.accounts.truncate(PLATFORM_STATS);
+    let recipient = c.recipient;
+    assert_eq!(c.process(&ix, &recipient).unwrap().net_amount, 100_000);
+    assert_eq!(c.fx.bank.token_balance(&c.fee_vault(PLATFORM_FEE_VAULT)), 1_000);
+    for index in [PLATFORM_FEE_VAULT, TRADE_FEE_VAULT] {
+        let stats = c.fx.mint_stats(&c.fee_vault_owner(index)).unwrap();
+        assert_eq!((stats.active_escrows, stats.claimed_escrows, stats.fees_accrued), (1, 0, 0));
+    }
+}
+
+#[test]
+fn claims_after_the_refund_time_too() {
+    let mut c = case();
+    let refund_after = c.opened.args.refund_after;
+    c.fx.bank.set_clock(refund_after + 1);
+    assert_eq!(c.claim().unwrap().net_amount, 100_000);
+}
+
+#[test]
+fn an_escrow_settles_once() {
+    let mut c = case();
+    c.claim().unwrap();
+    assert_eq!(c.claim(), Err(EscrowError::NotActive.into()));
+    assert_eq!(c.refund(), Err(EscrowError::NotActive.into()));
+    assert_eq!(c.fx.bank.token_balance(&c.recipient_token), 100_000);
+    assert_eq!(c.fx.bank.token_balance(&c.opened.payer_token), 1_000_000 - 101_500);
+}
+
+#[test]
+fn maker_rebate_comes_out_of_the_platform_fee() {
+    // A third of the 1_000 platform fee is 333.33; the rebate rounds down.
+    let mut c = case_with(
+        |fx| {
+            let mut config = fx.config();
+            config.maker_rebate_bps = 3_333;
+            fx.write_config(&config);
+        },
+        |_| (),
+    );
+    assert_eq!(c.state().maker_rebate_amount, 333);
+    let outcome = c.claim().unwrap();
+    assert_eq!((outcome.net_amount, outcome.platform_fee_amount, outcome.total_amount), (100_333, 667, 101_500));
+    assert_eq!(c.fx.bank.token_balance(&c.recipient_token), 100_333);
+    assert_eq!(c.fx.bank.token_balance(&c.fee_vault(PLATFORM_FEE_VAULT)), 667);
+    assert_eq!(c.fx.mint_stats(&c.fx.config_key()).unwrap().fees_accrued, 667);
+}
+
+#[test]
+fn closed_fee_vaults_release_their_fee_to_the_recipient() {
+    let mut c = case();
+    let (platform_fee_vault, trade_fee_vault) = (c.fee_vault(PLATFORM_FEE_VAULT), c.fee_vault(TRADE_FEE_VAULT));
+    c.fx.bank.close_account(&platform_fee_vault);
+    c.fx.bank.close_account(&trade_fee_vault);
+    let outcome = c.claim().unwrap();
+    assert_eq!((outcome.net_amount, outcome.platform_fee_amount, outcome.trade_fee_amount), (101_500, 0, 0));
+    assert_eq!(c.fx.bank.token_balance(&c.recipient_token), 101_500);
+    assert_eq!(c.fx.mint_stats(&c.fx.config_key()).unwrap().fees_accrued, 0);
+}
+
+// An escrow opened with both configs in direct payout mode, paying into the collectors' own ATAs.
+fn direct_payout_case() -> Case {
+    case_with(
+        |fx| {
+            let mint = fx.mint;
+            for collector in [fx.platform_authority, fx.trade_authority] {
+                fx.bank.create_token_account(&get_associated_token_address(&collector, &mint), &mint, &collector, 0);
+            }
+            let mut config = fx.config();
+            config.direct_payout = true;
+            fx.write_config(&config);
+            let mut trade_config = fx.trade_config();
+            trade_config.direct_payout = true;
+            fx.write_trade_config(&trade_config);
+        },
+        |_| (),
+    )
+}
+
+#[test]
+fn direct_payout_fees_go_to_the_collectors() {
+    let mut c = direct_payout_case();
+    let ix = c.ix();
+    let platform_ata = get_associated_token_address(&c.fx.platform_authority, &c.fx.mint);
+    let trade_ata = get_associated_token_address(&c.fx.trade_authority, &c.fx.mint);
+    assert_eq!(ix.accounts[PLATFORM_FEE_VAULT].pubkey, platform_ata);
+    assert_eq!(ix.accounts[TRADE_FEE_VAULT].pubkey, trade_ata);
+
+    // The config vaults are not accepted in their place.
+    let config_vault = c.fee_vault(PLATFORM_FEE_VAULT);
+    let err = c.claim_with(|ix| ix.accounts[PLATFORM_FEE_VAULT].pubkey = config_vault);
+    assert_eq!(err, EscrowError::InvalidFeeVaultAta.into());
+
+    c.claim().unwrap();
+    assert_eq!(c.fx.bank.token_balance(&platform_ata), 1_000);
+    assert_eq!(c.fx.bank.token_balance(&trade_ata), 500);
+    for config in [c.fx.config_key(), c.fx.trade_config_key()] {
+        let stats = c.fx.mint_stats(&config).unwrap();
+        assert_eq!(stats.fees_withdrawn, stats.fees_accrued);
+    }
+}
+
+#[test]
+fn closed_direct_payout_atas_block_the_claim() {
+    // Unlike a fee vault closed with its config, a collector's closed ATA does not release the fee.
+    for fee_vault in [PLATFORM_FEE_VAULT, TRADE_FEE_VAULT] {
+        let mut c = direct_payout_case();
+        let ata = c.ix().accounts[fee_vault].pubkey;
+        assert_eq!(c.claim_after(|fx| fx.bank.close_account(&ata)), EscrowError::InvalidTokenAccount.into());
+    }
+}
+
+#[test]
+fn needs_every_account() {
+    let mut c = case();
+    assert_eq!(c.claim_with(|ix| ix.accounts.truncate(TOKEN_PROGRAM)), ProgramError::NotEnoughAccountKeys);
+    let err = c.claim_with(|ix| ix.accounts.truncate(TRADE_STATS));
+    assert_eq!(err, ProgramError::NotEnoughAccountKeys);
+}
+
+#[test]
+fn only_the_recipient_can_claim() {
+    let mut c = case();
+    assert_eq!(c.claim_with(|ix| ix.accounts[RECIPIENT].is_signer = false), EscrowError::InvalidSigner.into());
+
+    // Someone else who knows the preimage, claiming into their own token account.
+    let stranger = signer();
+    let stranger_token = Pubkey::new_unique();
+    let mint = c.fx.mint;
+    c.fx.bank.create_token_account(&stranger_token, &mint, &stranger, 0);
+    let mut ix = c.ix();
+    ix.accounts[RECIPIENT].pubkey = stranger;
+    ix.accounts[RECIPIENT_TOKEN].pubkey = stranger_token;
+    assert_eq!(c.process(&ix, &stranger), Err(EscrowError::RecipientMismatch.into()));
+}
+
+#[test]
+fn written_accounts_must_be_writable() {
+    for index in [ESCROW, VAULT, RECIPIENT_TOKEN, PLATFORM_FEE_VAULT, TRADE_FEE_VAULT, PLATFORM_STATS, TRADE_STATS] {
+        let mut c = case();
+        let err = c.claim_with(|ix| ix.accounts[index].is_writable = false);
+        assert_eq!(err, EscrowError::AccountNotWritable.into(), "account {index}");
+    }
+}
+
+#[test]
+fn token_program_must_be_the_real_one() {
+    let mut c = case();
+    let err = c.claim_with(|ix| ix.accounts[TOKEN_PROGRAM].pubkey = Pubkey::new_unique());
+    assert_eq!(err, EscrowError::InvalidTokenProgram.into());
+}
+
+#[test]
+fn escrow_must_be_a_program_escrow() {
+    let mut c = case();
+    let escrow = c.opened.escrow;
+    let err = c.claim_after(|fx| {
+        let account = Account {
+            owner: Pubkey::new_unique(),
+            ..fx.bank.account(&escrow).unwrap().clone()
+        };
+        fx.bank.set_account(escrow, account);
+    });
+    assert_eq!(err, EscrowError::InvalidAccountOwner.into());
+
+    let mut c = case();
+    let escrow = c.opened.escrow;
+    let err = c.claim_after(|fx| fx.bank.set_data(&escrow, vec![0xff; 16]));
+    assert_eq!(err, EscrowError::InvalidEscrowState.into());
+}
+
+#[test]
+fn escrow_must_be_the_payment_hash_pda() {
+    // A copy of the escrow at another address.
+    let mut c = case();
+    let copy = Pubkey::new_unique();
+    let account = c.fx.bank.account(&c.opened.escrow).unwrap().clone();
+    c.fx.bank.set_account(copy, account);
+    assert_eq!(c.claim_with(|ix| ix.accounts[ESCROW].pubkey = copy), EscrowError::InvalidEscrowPda.into());
+
+    // The right address with a bump that does not derive it.
+    let mut c = case();
+    let mut state = c.state();
+    state.bump = state.bump.wrapping_add(1);
+    let escrow = c.opened.escrow;
+    c.fx.bank.set_data(&escrow, borsh::to_vec(&state).unwrap());
+    assert_eq!(c.claim_with(|_| ()), EscrowError::InvalidEscrowPda.into());
+}
+
+#[test]
+fn preimage_must_hash_to_the_payment_hash() {
+    let mut c = case();
+    let wrong = [2u8; 32];
+    assert_ne!(hash(&wrong).to_bytes(), c.opened.args.payment_hash);
+    let ix = instruction::claim(&c.state(), &c.recipient_token, wrong);
+    let recipient = c.recipient;
+    assert_eq!(c.process(&ix, &recipient), Err(EscrowError::InvalidPreimage.into()));
+}
+
+#[test]
+fn vault_must_be_the_escrows_own() {
+    let mut c = case();
+    let other = c.fx.open(&c.fx.config(), &[9u8; 32], &Pubkey::new_unique(), 1_000).vault;
+    assert_eq!(c.claim_with(|ix| ix.accounts[VAULT].pubkey = other), EscrowError::InvalidVaultAta.into());
+
+    // The recorded vault, no longer held by the escrow or no longer in its mint.
+    let mut c = case();
+    let (vault, mint) = (c.opened.vault, c.fx.mint);
+    c.fx.bank.create_token_account(&vault, &mint, &Pubkey::new_unique(), 101_500);
+    assert_eq!(c.claim_with(|_| ()), EscrowError::VaultAuthorityMismatch.into());
+
+    let mut c = case();
+    let (vault, escrow, other_mint) = (c.opened.vault, c.opened.escrow, Pubkey::new_unique());
+    c.fx.bank.create_mint(&other_mint, 6);
+    c.fx.bank.create_token_account(&vault, &other_mint, &escrow, 101_500);
+    assert_eq!(c.claim_with(|_| ()), EscrowError::TokenMintMismatch.into());
+}
+
+#[test]
+fn recipient_token_account_must_be_the_recipients_in_the_mint() {
+    let mut c = case();
+    let wallet = c.recipient;
+    c.fx.bank.airdrop(&wallet, 1_000_000);
+    let err = c.claim_with(|ix| ix.accounts[RECIPIENT_TOKEN].pubkey = wallet);
+    assert_eq!(err, EscrowError::InvalidTokenAccountData.into());
+
+    let mut c = case();
+    let (other, mint) = (Pubkey::new_unique(), c.fx.mint);
+    c.fx.bank.create_token_account(&other, &mint, &Pubkey::new_unique(), 0);
+    let err = c.claim_with(|ix| ix.accounts[RECIPIENT_TOKEN].pubkey = other);
+    assert_eq!(err, EscrowError::TokenOwnerMismatch.into());
+
+    let mut c = case();
+    let (other, other_mint, recipient) = (Pubkey::new_unique(), Pubkey::new_unique(), c.recipient);
+    c.fx.bank.create_mint(&other_mint, 6);
+    c.fx.bank.create_token_account(&other, &other_mint, &recipient, 0);
+    let err = c.claim_with(|ix| ix.accounts[RECIPIENT_TOKEN].pubkey = other);
+    assert_eq!(err, EscrowError::TokenMintMismatch.into());
+}
+
+#[test]
+fn fee_vaults_must_be_the_config_atas() {
+    for (index, wrong_key) in [
+        (PLATFORM_FEE_VAULT, EscrowError::InvalidFeeVaultAta),
+        (TRADE_FEE_VAULT, EscrowError::InvalidTradeFeeVaultAta),
+    ] {
+        let mut c = case();
+        let err = c.claim_with(|ix| ix.accounts[index].pubkey = Pubkey::new_unique());
+        assert_eq!(err, wrong_key.into(), "account {index}");
+
+        // The right address holding something else.
+        let mut c = case();
+        let fee_vault = c.fee_vault(index);
+        c.fx.bank.set_data(&fee_vault, vec![0xff; 16]);
+        assert_eq!(c.claim_with(|_| ()), EscrowError::InvalidTokenAccountData.into(), "account {index}");
+
+        let mut c = case();
+        let mint = c.fx.mint;
+        c.fx.bank.create_token_account(&c.fee_vault(index), &mint, &Pubkey::new_unique(), 0);
+        assert_eq!(c.claim_with(|_| ()), EscrowError::TokenOwnerMismatch.into(), "account {index}");
+
+        let mut c = case();
+        let (other_mint, owner) = (Pubkey::new_unique(), c.fee_vault_owner(index));
+        c.fx.bank.create_mint(&other_mint, 6);
+        c.fx.bank.create_token_account(&c.fee_vault(index), &other_mint, &owner, 0);
+        assert_eq!(c.claim_with(|_| ()), EscrowError::TokenMintMismatch.into(), "account {index}");
+    }
+}
+
+#[test]
+fn referral_fee_goes_to_the_referrers_ata() {
+    const REFERRER_TOKEN: usize = 7;
+    let referrer = Pubkey::new_unique();
+    let referral_case = || {
+        case_with(
+            |fx| {
+                let mut config = fx.config();
+                config.max_referral_bps = 25;
+                fx.write_config(&config);
+                let mint = fx.mint;
+                fx.bank.create_token_account(&get_associated_token_address(&referrer, &mint), &mint, &referrer, 0);
+            },
+            |args| {
+                args.referrer = referrer;
+                args.referral_fee_bps = 25;
+            },
+        )
+    };
+
+    let mut c = referral_case();
+    let ata = get_associated_token_address(&referrer, &c.fx.mint);
+    let outcome = c.claim().unwrap();
+    assert_eq!((outcome.referral_fee_amount, outcome.total_amount), (250, 101_750));
+    assert_eq!(c.fx.bank.token_balance(&ata), 250);
+    assert_eq!(c.fx.bank.token_balance(&c.recipient_token), 100_000);
+
+    let mut c = referral_case();
+    let err = c.claim_with(|ix| ix.accounts[REFERRER_TOKEN].pubkey = Pubkey::new_unique());
+    assert_eq!(err, EscrowError::InvalidReferrerAta.into());
+
+    let mut c = referral_case();
+    let err = c.claim_with(|ix| ix.accounts[REFERRER_TOKEN].is_writable = false);
+    assert_eq!(err, EscrowError::AccountNotWritable.into());
+
+    let mut c = referral_case();
+    let mint = c.fx.mint;
+    let ata = get_associated_token_address(&referrer, &mint);
+    c.fx.bank.create_token_account(&ata, &mint, &Pubkey::new_unique(), 0);
+    assert_eq!(c.claim_with(|_| ()), EscrowError::InvalidReferrerAta.into());
+
+    let mut c = referral_case();
+    let ata = get_associated_token_address(&referrer, &c.fx.mint);
+    c.fx.bank.set_data(&ata, vec![0xff; 16]);
+    assert_eq!(c.claim_with(|_| ()), EscrowError::InvalidReferrerAta.into());
+
+    // The referral fee is the referrer's: a closed referrer ATA blocks the claim rather than releasing it.
+    let mut c = referral_case();
+    let ata = get_associated_token_address(&referrer, &c.fx.mint);
+    assert_eq!(c.claim_after(|fx| fx.bank.close_account(&ata)), EscrowError::InvalidTokenAccount.into());
+}
+
+#[test]
+fn lamport_fees_are_released_to_the_configs() {
+    const CONFIG: usize = 7;
+    const TRADE_CONFIG: usize = 8;
+    let lamport_case = || {
+        case_with(
+            |fx| {
+                let mut config = fx.config();
+                config.lamport_fees.per_escrow = 5_000;
+                fx.write_config(&config);
+                let mut trade_config = fx.trade_config();
+                trade_config.lamport_fees.per_escrow = 3_000;
+                fx.write_trade_config(&trade_config);
+            },
+            |_| (),
+        )
+    };
+
+    let mut c = lamport_case();
+    let (config, trade_config) = (c.fx.config_key(), c.fx.trade_config_key());
+    let lamports = (c.fx.bank.lamports(&config), c.fx.bank.lamports(&trade_config));
+    assert_eq!((c.fx.config().lamport_fees.reserved, c.fx.trade_config().lamport_fees.reserved), (5_000, 3_000));
+    let outcome = c.claim().unwrap();
+    assert_eq!((outcome.net_amount, outcome.platform_fee_amount, outcome.trade_fee_amount), (100_000, 0, 0));
+    assert_eq!((c.fx.config().lamport_fees.reserved, c.fx.trade_config().lamport_fees.reserved), (0, 0));
+    // The lamports stay where they are, now free to withdraw.
+    assert_eq!((c.fx.bank.lamports(&config), c.fx.bank.lamports(&trade_config)), lamports);
+    let state = c.state();
+    assert_eq!((state.platform_lamport_fee, state.trade_lamport_fee), (0, 0));
+
+    for (index, wrong_key) in [
+        (CONFIG, EscrowError::InvalidConfigPda),
+        (TRADE_CONFIG, EscrowError::InvalidTradeConfigPda),
+    ] {
+        let mut c = lamport_case();
+        let err = c.claim_with(|ix| ix.accounts[index].pubkey = Pubkey::new_unique());
+        assert_eq!(err, wrong_key.into(), "account {index}");
+
+        let mut c = lamport_case();
+        let err = c.claim_with(|ix| ix.accounts[index].is_writable = false);
+        assert_eq!(err, EscrowError::AccountNotWritable.into(), "account {index}");
+    }
+}
+
+#[test]
+fn payer_volume_is_credited_on_claim() {
+    const PAYER_STATS: usize = 7;
+    let tiered_case = || {
+        case_with(
+            |fx| {
+                let mut config = fx.config();
+                config.volume_tiers.count = 1;
+                config.volume_tiers.tiers[0] = VolumeTier {
+                    min_volume: 1_000_000,
+                    discount_bps: 5_000,
+                };
+                fx.write_config(&config);
+            },
+            |_| (),
+        )
+    };
+
+    let mut c = tiered_case();
+    let payer_stats = c.ix().accounts[PAYER_STATS].pubkey;
+    let before = c.fx.bank.account(&payer_stats).unwrap().data.clone();
+    c.claim().unwrap();
+    assert_ne!(c.fx.bank.account(&payer_stats).unwrap().data, before);
+
+    let mut c = tiered_case();
+    let err = c.claim_with(|ix| ix.accounts[PAYER_STATS].pubkey = Pubkey::new_unique());
+    assert_eq!(err, EscrowError::InvalidPayerStatsPda.into());
+
+    let mut c = tiered_case();
+    let err = c.claim_with(|ix| ix.accounts[PAYER_STATS].is_writable = false);
+    assert_eq!(err, EscrowError::AccountNotWritable.into());
+}
+
+#[test]
+fn mint_stats_must_be_the_config_pdas() {
+    for index in [PLATFORM_STATS, TRADE_STATS] {
+        let mut c = case();
+        let err = c.claim_with(|ix| ix.accounts[index].pubkey = Pubkey::new_unique());
+        assert_eq!(err, EscrowError::InvalidMintStatsPda.into(), "account {index}");
+
+        // The other config's stats PDA.
+        let mut c = case();
+        let other = c.ix().accounts[PLATFORM_STATS + TRADE_STATS - index].pubkey;
+        let err = c.claim_with(|ix| ix.accounts[index].pubkey = other);
+        assert_eq!(err, EscrowError::InvalidMintStatsPda.into(), "account {index}");
+    }
+}
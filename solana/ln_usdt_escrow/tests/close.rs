@@ -0,0 +1,152 @@
+//! CloseConfig and CloseTradeConfig: fee vaults swept and closed with their stats and audit log, and what a closed
+//! config still allows.
+
+mod common;
+
+use common::{signer, Fixture, Opened};
+use ln_usdt_escrow::{
+    audit_log_pda, config_pda, instruction, mint_stats_pda, pda_for_hash, ConfigState, EscrowError, EscrowState,
+};
+use solana_program::pubkey::Pubkey;
+use spl_associated_token_account::get_associated_token_address;
+
+// Account index of the first vault's fee withdrawal PDA in CloseConfig.
+const FEE_WITHDRAWAL: usize = 7;
+
+// The fixture plus a tenant config at 250 bps whose fee vault holds 2_500 from one claimed escrow, with an audit log
+// from one SetConfig and a second escrow still open against it.
+struct Case {
+    fx: Fixture,
+    tenant: Pubkey,
+    config: Pubkey,
+    tenant_token: Pubkey,
+    open: Opened,
+}
+
+fn case() -> Case {
+    let mut fx = Fixture::new(100, 50);
+    let tenant = signer();
+    fx.bank.airdrop(&tenant, 1_000_000_000);
+    let config = config_pda(&ln_usdt_escrow::id(), &tenant).0;
+    fx.bank.process(&instruction::init_config(&tenant, tenant, 200, tenant), &[&tenant]).expect("init config");
+    fx.bank.process(&instruction::set_config(&tenant, &config, tenant, 250), &[&tenant]).expect("set config");
+
+    let state = ConfigState::unpack(&fx.bank.account(&config).unwrap().data).unwrap();
+    let claimed = fx.open(&state, &[1u8; 32], &signer(), 100_000);
+    fx.claim(&claimed, [1u8; 32]);
+    let open = fx.open(&state, &[2u8; 32], &signer(), 100_000);
+
+    let mint = fx.mint;
+    let tenant_token = get_associated_token_address(&tenant, &mint);
+    fx.bank.create_token_account(&tenant_token, &mint, &tenant, 0);
+    assert_eq!(fx.bank.token_balance(&get_associated_token_address(&config, &mint)), 2_500);
+    Case {
+        fx,
+        tenant,
+        config,
+        tenant_token,
+        open,
+    }
+}
+
+#[test]
+fn close_config_sweeps_the_vault_and_closes_its_accounts() {
+    let mut c = case();
+    let (mint, config, tenant) = (c.fx.mint, c.config, c.tenant);
+    let fee_vault = get_associated_token_address(&config, &mint);
+    let stats = mint_stats_pda(&ln_usdt_escrow::id(), &config, &mint).0;
+    let audit_log = audit_log_pda(&ln_usdt_escrow::id(), &config).0;
+    assert!(c.fx.mint_stats(&config).is_some() && c.fx.audit_log(&config).is_some());
+    let reclaimed: u64 = [fee_vault, stats, audit_log, config].iter().map(|key| c.fx.bank.lamports(key)).sum();
+    let before = c.fx.bank.lamports(&tenant);
+
+    let ix = instruction::close_config(&tenant, &config, &[(mint, c.tenant_token)]);
+    c.fx.bank.process(&ix, &[&tenant]).unwrap();
+    assert_eq!(c.fx.bank.token_balance(&c.tenant_token), 2_500);
+    for key in [fee_vault, stats, audit_log, config] {
+        assert!(c.fx.bank.account(&key).is_none(), "{key} still open");
+    }
+    assert_eq!(c.fx.bank.lamports(&tenant), before + reclaimed);
+
+    // No new escrow against the closed config.
+    let (payer, payer_token) = c.fx.funded_payer(1_000_000);
+    let mut args = c.fx.init_args(&[3u8; 32], &signer(), &payer, 100_000);
+    args.expected_platform_fee_bps = 250;
+    let mut state = c.fx.config();
+    state.tenant = tenant.to_bytes();
+    let ix = instruction::init(&payer, &payer_token, &mint, &state, &c.fx.trade_config(), args);
+    assert_eq!(c.fx.bank.process(&ix, &[&payer]), Err(EscrowError::InvalidConfigState.into()));
+    assert!(c.fx.bank.account(&pda_for_hash(&ln_usdt_escrow::id(), &args.payment_hash).0).is_none());
+}
+
+#[test]
+fn escrows_opened_before_the_close_still_claim() {
+    let mut c = case();
+    let (mint, config, tenant) = (c.fx.mint, c.config, c.tenant);
+    let ix = instruction::close_config(&tenant, &config, &[(mint, c.tenant_token)]);
+    c.fx.bank.process(&ix, &[&tenant]).unwrap();
+
+    // The platform fee has no vault left to go to, so the recipient gets it.
+    assert_eq!(c.fx.claim(&c.open, [2u8; 32]), 100_000 + 2_500);
+    assert_eq!(c.fx.bank.token_balance(&c.tenant_token), 2_500);
+    assert_eq!(c.fx.escrow(&c.open.args.payment_hash).status, EscrowState::STATUS_CLAIMED);
+}
+
+#[test]
+fn a_pending_fee_withdrawal_blocks_the_close() {
+    let mut c = case();
+    let (mint, config, tenant) = (c.fx.mint, c.config, c.tenant);
+    let guardian = signer();
+    let ix = instruction::set_treasury(&tenant, &config, tenant, guardian, 3_600, None);
+    c.fx.bank.process(&ix, &[&tenant]).unwrap();
+    let ix = instruction::request_fee_withdrawal(&tenant, &config, &mint, 0);
+    c.fx.bank.process(&ix, &[&tenant]).unwrap();
+
+    let close = instruction::close_config(&tenant, &config, &[(mint, c.tenant_token)]);
+    assert_eq!(c.fx.bank.process(&close, &[&tenant]), Err(EscrowError::FeeWithdrawalPending.into()));
+    assert_eq!(c.fx.bank.token_balance(&get_associated_token_address(&config, &mint)), 2_500);
+    assert!(c.fx.bank.account(&config).is_some());
+
+    // Only the vault's own request PDA is accepted.
+    let mut ix = close.clone();
+    ix.accounts[FEE_WITHDRAWAL].pubkey = Pubkey::new_unique();
+    assert_eq!(c.fx.bank.process(&ix, &[&tenant]), Err(EscrowError::InvalidFeeWithdrawalPda.into()));
+
+    let ix = instruction::veto_fee_withdrawal(&guardian, &config, &mint, &tenant);
+    c.fx.bank.process(&ix, &[&guardian]).unwrap();
+    c.fx.bank.process(&close, &[&tenant]).unwrap();
+    assert_eq!(c.fx.bank.token_balance(&c.tenant_token), 2_500);
+    assert!(c.fx.bank.account(&config).is_none());
+}
+
+#[test]
+fn the_default_config_is_not_closable() {
+    let mut fx = Fixture::new(100, 50);
+    let (authority, config, mint) = (fx.platform_authority, fx.config_key(), fx.mint);
+    let destination = get_associated_token_address(&authority, &mint);
+    fx.bank.create_token_account(&destination, &mint, &authority, 0);
+    let ix = instruction::close_config(&authority, &config, &[(mint, destination)]);
+    assert_eq!(fx.bank.process(&ix, &[&authority]), Err(EscrowError::DefaultConfigNotClosable.into()));
+    assert!(fx.bank.account(&config).is_some());
+}
+
+#[test]
+fn close_trade_config_sweeps_the_vault_and_closes_its_accounts() {
+    let mut c = case();
+    let (mint, collector, trade_config) = (c.fx.mint, c.fx.trade_authority, c.fx.trade_config_key());
+    let fee_vault = get_associated_token_address(&trade_config, &mint);
+    assert_eq!(c.fx.bank.token_balance(&fee_vault), 500);
+    let set = instruction::set_trade_config(&collector, &trade_config, collector, 50);
+    c.fx.bank.process(&set, &[&collector]).unwrap();
+    let destination = get_associated_token_address(&collector, &mint);
+    c.fx.bank.create_token_account(&destination, &mint, &collector, 0);
+
+    let ix = instruction::close_trade_config(&collector, &trade_config, &[(mint, destination)]);
+    c.fx.bank.process(&ix, &[&collector]).unwrap();
+    assert_eq!(c.fx.bank.token_balance(&destination), 500);
+    let stats = mint_stats_pda(&ln_usdt_escrow::id(), &trade_config, &mint).0;
+    let audit_log = audit_log_pda(&ln_usdt_escrow::id(), &trade_config).0;
+    for key in [fee_vault, stats, audit_log, trade_config] {
+        assert!(c.fx.bank.account(&key).is_none(), "{key} still open");
+    }
+}
@@ -0,0 +1,522 @@
+//! Test bank for integration tests.
+//!
+//! Runs the escrow program natively under `solana-program-test`, next to the SPL Token and Associated Token Account
+//! programs it bundles, so signatures, CPI privileges, rent and the runtime's ownership rules are the real ones. Each
+//! test gets its own bank behind a single-threaded tokio runtime; the helpers below block on it. Signers are
+//! keypairs minted by `signer()`, the context's payer pays the transaction fees, and time is moved by overriding the
+//! Clock sysvar.
+//!
+//! Natively, `sol_log_data` does not reach the transaction logs; see `LogData`.
+
+#![allow(dead_code)]
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    sync::Once,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::ProgramResult,
+    hash::hash,
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    program_option::COption,
+    program_pack::Pack,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_program,
+};
+use solana_program_test::{processor, tokio::runtime::Runtime, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::AccountSharedData,
+    compute_budget::ComputeBudgetInstruction,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+
+use ln_usdt_escrow::{
+    audit_log_pda, config_pda,
+    instruction::{self, InitArgs, ABI_V2_PREFIX},
+    mint_stats_pda, pda_for_hash, trade_config_pda, AuditLogState, ConfigState, EscrowState, MintStatsState,
+    TradeConfigState,
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Account {
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+    pub executable: bool,
+}
+
+thread_local! {
+    static KEYPAIRS: RefCell<HashMap<Pubkey, Keypair>> = RefCell::new(HashMap::new());
+}
+
+// A fresh key the bank can sign for.
+pub fn signer() -> Pubkey {
+    let keypair = Keypair::new();
+    let key = keypair.pubkey();
+    KEYPAIRS.with(|k| k.borrow_mut().insert(key, keypair));
+    key
+}
+
+// The escrow program, loaded natively; tests that need a program of their own add it before `Bank::start`.
+pub fn program_test() -> ProgramTest {
+    let mut program_test = ProgramTest::new(
+        "ln_usdt_escrow",
+        ln_usdt_escrow::id(),
+        processor!(ln_usdt_escrow::process_instruction),
+    );
+    program_test.prefer_bpf(false);
+    program_test
+}
+
+const PROGRAM_DATA: &str = "Program data: ";
+
+// program-test's native syscall stubs print `sol_log_data` fields to stdout. This wraps them to log the fields with
+// `sol_log` instead (as "Program log: Program data: ..."), and `Bank::process` strips the "Program log: " to leave the
+// line the runtime writes. Every other syscall goes to program-test's stubs; the memory ones keep the trait defaults,
+// which program-test does not override either.
+struct LogData(Box<dyn SyscallStubs>);
+
+impl SyscallStubs for LogData {
+    fn sol_log(&self, message: &str) {
+        self.0.sol_log(message)
+    }
+    fn sol_log_compute_units(&self) {
+        self.0.sol_log_compute_units()
+    }
+    fn sol_remaining_compute_units(&self) -> u64 {
+        self.0.sol_remaining_compute_units()
+    }
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        self.0.sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_clock_sysvar(var_addr)
+    }
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_epoch_schedule_sysvar(var_addr)
+    }
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_fees_sysvar(var_addr)
+    }
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_rent_sysvar(var_addr)
+    }
+    fn sol_get_epoch_rewards_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_epoch_rewards_sysvar(var_addr)
+    }
+    fn sol_get_last_restart_slot(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_last_restart_slot(var_addr)
+    }
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        self.0.sol_get_return_data()
+    }
+    fn sol_set_return_data(&self, data: &[u8]) {
+        self.0.sol_set_return_data(data)
+    }
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        let fields: Vec<String> = fields.iter().map(|field| STANDARD.encode(field)).collect();
+        self.0.sol_log(&format!("{PROGRAM_DATA}{}", fields.join(" ")))
+    }
+    fn sol_get_processed_sibling_instruction(&self, index: usize) -> Option<Instruction> {
+        self.0.sol_get_processed_sibling_instruction(index)
+    }
+    fn sol_get_stack_height(&self) -> u64 {
+        self.0.sol_get_stack_height()
+    }
+}
+
+// Holds the place of program-test's stubs while they are moved into `LogData`.
+struct Unset;
+
+impl SyscallStubs for Unset {}
+
+pub struct Bank {
+    runtime: Runtime,
+    context: ProgramTestContext,
+    // Bumps the compute unit price so that otherwise identical transactions get distinct signatures.
+    transactions: u64,
+    logs: Vec<String>,
+}
+
+impl Default for Bank {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bank {
+    pub fn new() -> Self {
+        Self::start(program_test())
+    }
+
+    pub fn start(program_test: ProgramTest) -> Self {
+        let runtime = solana_program_test::tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let context = runtime.block_on(program_test.start_with_context());
+        // program-test installs its stubs when the first bank starts.
+        static LOG_DATA: Once = Once::new();
+        LOG_DATA.call_once(|| {
+            let stubs = set_syscall_stubs(Box::new(Unset));
+            set_syscall_stubs(Box::new(LogData(stubs)));
+        });
+        let mut bank = Bank {
+            runtime,
+            context,
+            transactions: 0,
+            logs: Vec::new(),
+        };
+        bank.set_clock(1_700_000_000);
+        bank
+    }
+
+    fn clock(&self) -> Clock {
+        let mut client = self.context.banks_client.clone();
+        self.runtime.block_on(client.get_sysvar::<Clock>()).unwrap()
+    }
+
+    pub fn set_clock(&mut self, unix_timestamp: i64) {
+        let clock = Clock {
+            unix_timestamp,
+            ..self.clock()
+        };
+        self.context.set_sysvar(&clock);
+    }
+
+    pub fn now(&self) -> i64 {
+        self.clock().unix_timestamp
+    }
+
+    pub fn account(&self, key: &Pubkey) -> Option<Account> {
+        let mut client = self.context.banks_client.clone();
+        let account = self.runtime.block_on(client.get_account(*key)).unwrap()?;
+        Some(Account {
+            lamports: account.lamports,
+            data: account.data,
+            owner: account.owner,
+            executable: account.executable,
+        })
+    }
+
+    pub fn set_account(&mut self, key: Pubkey, account: Account) {
+        let account = solana_sdk::account::Account {
+            lamports: account.lamports,
+            data: account.data,
+            owner: account.owner,
+            executable: account.executable,
+            rent_epoch: 0,
+        };
+        self.context.set_account(&key, &AccountSharedData::from(account));
+    }
+
+    // Replaces the data of an existing account, keeping its lamports and owner.
+    pub fn set_data(&mut self, key: &Pubkey, data: Vec<u8>) {
+        let account = self.account(key).expect("account");
+        self.set_account(*key, Account { data, ..account });
+    }
+
+    // Drops an account, as if it had been closed.
+    pub fn close_account(&mut self, key: &Pubkey) {
+        self.context.set_account(key, &AccountSharedData::default());
+    }
+
+    pub fn lamports(&self, key: &Pubkey) -> u64 {
+        self.account(key).map_or(0, |a| a.lamports)
+    }
+
+    pub fn airdrop(&mut self, key: &Pubkey, lamports: u64) {
+        let mut account = self.account(key).unwrap_or_else(|| Account {
+            owner: system_program::id(),
+            ..Account::default()
+        });
+        account.lamports += lamports;
+        self.set_account(*key, account);
+    }
+
+    pub fn create_mint(&mut self, mint: &Pubkey, decimals: u8) {
+        let state = spl_token::state::Mint {
+            mint_authority: COption::None,
+            supply: u64::MAX / 2,
+            decimals,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        };
+        self.set_token_state(mint, spl_token::state::Mint::LEN, |data| state.pack_into_slice(data));
+    }
+
+    pub fn create_token_account(&mut self, key: &Pubkey, mint: &Pubkey, owner: &Pubkey, amount: u64) {
+        let state = spl_token::state::Account {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            ..spl_token::state::Account::default()
+        };
+        self.set_token_state(key, spl_token::state::Account::LEN, |data| state.pack_into_slice(data));
+    }
+
+    pub fn approve(&mut self, token_account: &Pubkey, delegate: &Pubkey, amount: u64) {
+        let mut state = self.token_account(token_account).expect("token account");
+        state.delegate = COption::Some(*delegate);
+        state.delegated_amount = amount;
+        let mut account = self.account(token_account).unwrap();
+        state.pack_into_slice(&mut account.data);
+        self.set_account(*token_account, account);
+    }
+
+    fn set_token_state(&mut self, key: &Pubkey, len: usize, pack: impl FnOnce(&mut [u8])) {
+        let mut data = vec![0u8; len];
+        pack(&mut data);
+        let account = Account {
+            lamports: Rent::default().minimum_balance(len),
+            data,
+            owner: spl_token::id(),
+            executable: false,
+        };
+        self.set_account(*key, account);
+    }
+
+    pub fn token_account(&self, key: &Pubkey) -> Option<spl_token::state::Account> {
+        let account = self.account(key)?;
+        spl_token::state::Account::unpack(&account.data).ok()
+    }
+
+    pub fn token_balance(&self, key: &Pubkey) -> u64 {
+        self.token_account(key).map_or(0, |a| a.amount)
+    }
+
+    // The log messages of the last transaction processed.
+    pub fn logs(&self) -> &[String] {
+        &self.logs
+    }
+
+    // Runs one instruction in its own transaction, signed by `signers` (keys from `signer()`), and returns its return
+    // data. Changes are only kept if the instruction succeeds.
+    pub fn process(
+        &mut self,
+        ix: &Instruction,
+        signers: &[&Pubkey],
+    ) -> Result<Option<(Pubkey, Vec<u8>)>, ProgramError> {
+        self.transactions += 1;
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
+            ComputeBudgetInstruction::set_compute_unit_price(self.transactions),
+            ix.clone(),
+        ];
+        let payer = &self.context.payer;
+        let tx = KEYPAIRS.with(|k| {
+            let keypairs = k.borrow();
+            let mut signing: Vec<&Keypair> = vec![payer];
+            for key in signers {
+                if ix.accounts.iter().any(|meta| meta.is_signer && meta.pubkey == **key) {
+                    signing.push(keypairs.get(key).unwrap_or_else(|| panic!("no keypair for signer {key}")));
+                }
+            }
+            let blockhash = self.context.last_blockhash;
+            Transaction::new_signed_with_payer(&instructions, Some(&payer.pubkey()), &signing, blockhash)
+        });
+        let mut client = self.context.banks_client.clone();
+        let outcome = self.runtime.block_on(client.process_transaction_with_metadata(tx)).unwrap();
+        let logs = outcome.metadata.as_ref().map_or(&[][..], |m| &m.log_messages[..]);
+        self.logs = logs
+            .iter()
+            .map(|line| match line.strip_prefix("Program log: ") {
+                Some(data) if data.starts_with(PROGRAM_DATA) => data.to_string(),
+                _ => line.clone(),
+            })
+            .collect();
+        match outcome.result {
+            Ok(()) => Ok(outcome
+                .metadata
+                .and_then(|m| m.return_data)
+                .filter(|r| !r.data.is_empty())
+                .map(|r| (r.program_id, r.data))),
+            Err(TransactionError::InstructionError(_, err)) => {
+                Err(ProgramError::try_from(err).unwrap_or_else(|err| panic!("runtime error: {err:?}")))
+            }
+            Err(err) => panic!("transaction failed: {err}"),
+        }
+    }
+}
+
+// The v1 form of a v2 instruction built by `ln_usdt_escrow::instruction`, with `sysvar` inserted at its index.
+// This works for the instructions whose v1 fields are packed in Borsh order (Init, Refund, the config inits).
+pub fn to_v1(ix: &Instruction, sysvar: Option<(usize, Pubkey)>) -> Instruction {
+    assert_eq!(ix.data[0], ABI_V2_PREFIX);
+    let mut accounts = ix.accounts.clone();
+    if let Some((index, id)) = sysvar {
+        accounts.insert(index, AccountMeta::new_readonly(id, false));
+    }
+    Instruction::new_with_bytes(ix.program_id, &ix.data[2..], accounts)
+}
+
+// An escrow opened by `Fixture::open`.
+pub struct Opened {
+    pub args: InitArgs,
+    pub escrow: Pubkey,
+    pub vault: Pubkey,
+    pub payer: Pubkey,
+    pub payer_token: Pubkey,
+}
+
+// A mint plus the default platform config and one trade config, both initialized through the program.
+pub struct Fixture {
+    pub bank: Bank,
+    pub mint: Pubkey,
+    pub platform_authority: Pubkey,
+    pub trade_authority: Pubkey,
+}
+
+impl Fixture {
+    pub fn new(platform_fee_bps: u16, trade_fee_bps: u16) -> Self {
+        Self::start(program_test(), platform_fee_bps, trade_fee_bps)
+    }
+
+    pub fn start(program_test: ProgramTest, platform_fee_bps: u16, trade_fee_bps: u16) -> Self {
+        let mut bank = Bank::start(program_test);
+        let mint = Pubkey::new_unique();
+        bank.create_mint(&mint, 6);
+        let platform_authority = signer();
+        let trade_authority = signer();
+        bank.airdrop(&platform_authority, 10_000_000_000);
+        bank.airdrop(&trade_authority, 10_000_000_000);
+        let ix = instruction::init_config(&platform_authority, platform_authority, platform_fee_bps, Pubkey::default());
+        bank.process(&ix, &[&platform_authority]).expect("init config");
+        let ix = instruction::init_trade_config(&trade_authority, trade_authority, trade_fee_bps);
+        bank.process(&ix, &[&trade_authority]).expect("init trade config");
+        Fixture {
+            bank,
+            mint,
+            platform_authority,
+            trade_authority,
+        }
+    }
+
+    pub fn config_key(&self) -> Pubkey {
+        config_pda(&ln_usdt_escrow::id(), &Pubkey::default()).0
+    }
+
+    pub fn trade_config_key(&self) -> Pubkey {
+        trade_config_pda(&ln_usdt_escrow::id(), &self.trade_authority).0
+    }
+
+    pub fn config(&self) -> ConfigState {
+        ConfigState::unpack(&self.bank.account(&self.config_key()).unwrap().data).unwrap()
+    }
+
+    pub fn trade_config(&self) -> TradeConfigState {
+        TradeConfigState::unpack(&self.bank.account(&self.trade_config_key()).unwrap().data).unwrap()
+    }
+
+    pub fn escrow(&self, payment_hash: &[u8; 32]) -> EscrowState {
+        let key = pda_for_hash(&ln_usdt_escrow::id(), payment_hash).0;
+        EscrowState::unpack(&self.bank.account(&key).expect("escrow account").data).unwrap()
+    }
+
+    // Init arguments matching the fixture's configs, locked for an hour.
+    pub fn init_args(&self, preimage: &[u8; 32], recipient: &Pubkey, refund: &Pubkey, amount: u64) -> InitArgs {
+        let (config, trade_config) = (self.config(), self.trade_config());
+        InitArgs {
+            payment_hash: hash(preimage).to_bytes(),
+            recipient: *recipient,
+            refund: *refund,
+            refund_after: self.bank.now() + 3600,
+            amount,
+            expected_platform_fee_bps: if config.lamport_fees.per_escrow > 0 { 0 } else { config.fee_bps },
+            expected_trade_fee_bps: if trade_config.lamport_fees.per_escrow > 0 { 0 } else { trade_config.fee_bps },
+            trade_fee_collector: self.trade_authority,
+            referrer: Pubkey::default(),
+            referral_fee_bps: 0,
+            expected_platform_lamport_fee: config.lamport_fees.per_escrow,
+            expected_trade_lamport_fee: trade_config.lamport_fees.per_escrow,
+        }
+    }
+
+    // A wallet with 10 SOL and a token account of the fixture's mint holding `balance`.
+    pub fn funded_payer(&mut self, balance: u64) -> (Pubkey, Pubkey) {
+        let (payer, payer_token) = (signer(), Pubkey::new_unique());
+        let mint = self.mint;
+        self.bank.airdrop(&payer, 10_000_000_000);
+        self.bank.create_token_account(&payer_token, &mint, &payer, balance);
+        (payer, payer_token)
+    }
+
+    pub fn init_ix(&self, payer: &Pubkey, payer_token: &Pubkey, args: InitArgs) -> Instruction {
+        instruction::init(payer, payer_token, &self.mint, &self.config(), &self.trade_config(), args)
+    }
+
+    // Opens an escrow for `amount` to `recipient` against `config` and the fixture's trade config, paid and refundable
+    // by a fresh payer.
+    pub fn open(&mut self, config: &ConfigState, preimage: &[u8; 32], recipient: &Pubkey, amount: u64) -> Opened {
+        let (payer, payer_token) = self.funded_payer(10_000_000);
+        let mut args = self.init_args(preimage, recipient, &payer, amount);
+        args.expected_platform_fee_bps = if config.lamport_fees.per_escrow > 0 { 0 } else { config.fee_bps };
+        args.expected_platform_lamport_fee = config.lamport_fees.per_escrow;
+        let ix = instruction::init(&payer, &payer_token, &self.mint, config, &self.trade_config(), args);
+        self.bank.process(&ix, &[&payer]).expect("init");
+        let escrow = pda_for_hash(&ln_usdt_escrow::id(), &args.payment_hash).0;
+        Opened {
+            args,
+            escrow,
+            vault: spl_associated_token_account::get_associated_token_address(&escrow, &self.mint),
+            payer,
+            payer_token,
+        }
+    }
+
+    // Claims `opened` to its recipient's ATA, created empty here, and returns what the recipient received.
+    pub fn claim(&mut self, opened: &Opened, preimage: [u8; 32]) -> u64 {
+        let (recipient, mint) = (opened.args.recipient, self.mint);
+        let recipient_token = spl_associated_token_account::get_associated_token_address(&recipient, &mint);
+        self.bank.create_token_account(&recipient_token, &mint, &recipient, 0);
+        let ix = instruction::claim(&self.escrow(&opened.args.payment_hash), &recipient_token, preimage);
+        self.bank.process(&ix, &[&recipient]).expect("claim");
+        self.bank.token_balance(&recipient_token)
+    }
+
+    // Opens an escrow of `amount` against the default config to a fresh recipient and claims it, leaving its fees in
+    // the fee vaults.
+    pub fn open_and_claim(&mut self, preimage: [u8; 32], amount: u64) -> Opened {
+        let opened = self.open(&self.config(), &preimage, &signer(), amount);
+        self.claim(&opened, preimage);
+        opened
+    }
+
+    // Overwrites the default platform config, the way the setters not exercised by a test would have left it.
+    pub fn write_config(&mut self, state: &ConfigState) {
+        let key = self.config_key();
+        self.bank.set_data(&key, borsh::to_vec(state).unwrap());
+    }
+
+    pub fn write_trade_config(&mut self, state: &TradeConfigState) {
+        let key = self.trade_config_key();
+        self.bank.set_data(&key, borsh::to_vec(state).unwrap());
+    }
+
+    pub fn mint_stats(&self, config: &Pubkey) -> Option<MintStatsState> {
+        let key = mint_stats_pda(&ln_usdt_escrow::id(), config, &self.mint).0;
+        let account = self.bank.account(&key)?;
+        Some(MintStatsState::unpack(&account.data).unwrap())
+    }
+
+    pub fn audit_log(&self, config: &Pubkey) -> Option<AuditLogState> {
+        let account = self.bank.account(&audit_log_pda(&ln_usdt_escrow::id(), config).0)?;
+        Some(AuditLogState::unpack(&account.data).unwrap())
+    }
+}
+
@@ -0,0 +1,535 @@
+//! InitConfig, SetConfig, WithdrawFees and WithdrawFeesBatch on the platform config, and every check they reject on.
+
+mod common;
+
+use common::{signer, to_v1, Account, Fixture};
+use ln_usdt_escrow::{
+    audit_log_pda, config_pda, instruction, mint_stats_pda, ConfigState, EscrowError, MintStatsState,
+    RevenueBeneficiary, TreasuryState,
+};
+use solana_program::{
+    instruction::Instruction, program_error::ProgramError, pubkey::Pubkey, rent::Rent, sysvar,
+};
+use spl_associated_token_account::get_associated_token_address;
+
+// Account indices of InitConfig.
+const PAYER: usize = 0;
+const CONFIG: usize = 1;
+const SYSTEM_PROGRAM: usize = 2;
+
+// Account indices of SetConfig.
+const AUDIT_LOG: usize = 2;
+const SET_SYSTEM_PROGRAM: usize = 3;
+
+// Account indices of WithdrawFees.
+const FEE_VAULT: usize = 2;
+const DESTINATION: usize = 3;
+const TOKEN_PROGRAM: usize = 4;
+const MINT_STATS: usize = 5;
+
+fn process(fx: &mut Fixture, ix: &Instruction, signer: &Pubkey) -> Result<(), ProgramError> {
+    fx.bank.process(ix, &[signer]).map(|_| ())
+}
+
+// A fresh wallet about to create the config of its own tenant namespace.
+fn tenant_case() -> (Fixture, Pubkey, Instruction) {
+    let mut fx = Fixture::new(100, 50);
+    let tenant = signer();
+    fx.bank.airdrop(&tenant, 1_000_000_000);
+    let ix = instruction::init_config(&tenant, tenant, 250, tenant);
+    (fx, tenant, ix)
+}
+
+fn init_config_with(edit: impl FnOnce(&mut Instruction)) -> ProgramError {
+    let (mut fx, tenant, mut ix) = tenant_case();
+    edit(&mut ix);
+    let err = process(&mut fx, &ix, &tenant).unwrap_err();
+    assert!(fx.bank.account(&config_pda(&ln_usdt_escrow::id(), &tenant).0).is_none());
+    err
+}
+
+#[test]
+fn init_config_creates_a_tenant_config() {
+    let (mut fx, tenant, ix) = tenant_case();
+    process(&mut fx, &ix, &tenant).unwrap();
+    let (key, bump) = config_pda(&ln_usdt_escrow::id(), &tenant);
+    let account = fx.bank.account(&key).unwrap();
+    assert_eq!(account.owner, ln_usdt_escrow::id());
+    assert_eq!(account.data.len(), ConfigState::space(ConfigState::LATEST));
+    assert_eq!(account.lamports, Rent::default().minimum_balance(account.data.len()));
+
+    let state = ConfigState::unpack(&account.data).unwrap();
+    assert_eq!((state.v, state.fee_bps, state.bump), (ConfigState::LATEST, 250, bump));
+    assert_eq!([state.authority, state.fee_collector, state.tenant], [tenant.to_bytes(); 3]);
+    assert!(!state.treasury.enabled() && !state.revenue_split.active() && !state.direct_payout);
+    assert_eq!((state.max_referral_bps, state.maker_rebate_bps, state.lamport_fees.per_escrow), (0, 0, 0));
+
+    // Only once.
+    assert_eq!(process(&mut fx, &ix, &tenant), Err(EscrowError::AlreadyInitialized.into()));
+}
+
+#[test]
+fn the_default_config_is_taken_by_the_fixture() {
+    let mut fx = Fixture::new(100, 50);
+    let payer = signer();
+    fx.bank.airdrop(&payer, 1_000_000_000);
+    let ix = instruction::init_config(&payer, payer, 100, Pubkey::default());
+    assert_eq!(process(&mut fx, &ix, &payer), Err(EscrowError::AlreadyInitialized.into()));
+    assert_eq!(fx.config().authority, fx.platform_authority.to_bytes());
+}
+
+#[test]
+fn v1_init_config_takes_the_rent_sysvar() {
+    let err = init_config_with(|ix| *ix = to_v1(ix, Some((3, sysvar::clock::id()))));
+    assert_eq!(err, EscrowError::InvalidSysvar.into());
+
+    let (mut fx, tenant, ix) = tenant_case();
+    process(&mut fx, &to_v1(&ix, Some((3, sysvar::rent::id()))), &tenant).unwrap();
+    assert!(fx.bank.account(&config_pda(&ln_usdt_escrow::id(), &tenant).0).is_some());
+}
+
+#[test]
+fn init_config_rejects_bad_accounts() {
+    assert_eq!(init_config_with(|ix| ix.accounts.truncate(SYSTEM_PROGRAM)), ProgramError::NotEnoughAccountKeys);
+    assert_eq!(init_config_with(|ix| ix.accounts[PAYER].is_signer = false), EscrowError::InvalidSigner.into());
+    for index in [PAYER, CONFIG] {
+        let err = init_config_with(|ix| ix.accounts[index].is_writable = false);
+        assert_eq!(err, EscrowError::AccountNotWritable.into(), "account {index}");
+    }
+    let err = init_config_with(|ix| ix.accounts[SYSTEM_PROGRAM].pubkey = Pubkey::new_unique());
+    assert_eq!(err, EscrowError::InvalidSystemProgram.into());
+    let err = init_config_with(|ix| ix.accounts[CONFIG].pubkey = Pubkey::new_unique());
+    assert_eq!(err, EscrowError::InvalidConfigPda.into());
+}
+
+#[test]
+fn init_config_rejects_bad_arguments() {
+    let rebuild = |fee_collector: Option<Pubkey>, fee_bps: u16, tenant: Option<Pubkey>| {
+        move |ix: &mut Instruction| {
+            let payer = ix.accounts[PAYER].pubkey;
+            let tenant = tenant.unwrap_or(payer);
+            let mut rebuilt = instruction::init_config(&payer, fee_collector.unwrap_or(payer), fee_bps, tenant);
+            // Keep the payer's own tenant PDA so only the argument under test is off.
+            rebuilt.accounts[CONFIG] = ix.accounts[CONFIG].clone();
+            *ix = rebuilt;
+        }
+    };
+    assert_eq!(init_config_with(rebuild(None, 501, None)), EscrowError::FeeTooHigh.into());
+    let err = init_config_with(rebuild(Some(Pubkey::new_unique()), 100, None));
+    assert_eq!(err, EscrowError::FeeCollectorMismatch.into());
+    assert_eq!(init_config_with(rebuild(None, 100, Some(Pubkey::new_unique()))), EscrowError::InvalidTenant.into());
+
+    // The cap itself is allowed.
+    let (mut fx, tenant, _) = tenant_case();
+    process(&mut fx, &instruction::init_config(&tenant, tenant, 500, tenant), &tenant).unwrap();
+}
+
+// The fixture's platform authority about to change its fee from 1% to 2%.
+struct SetCase {
+    fx: Fixture,
+    authority: Pubkey,
+    config: Pubkey,
+}
+
+fn set_case() -> SetCase {
+    let fx = Fixture::new(100, 50);
+    let (authority, config) = (fx.platform_authority, fx.config_key());
+    SetCase { fx, authority, config }
+}
+
+impl SetCase {
+    fn ix(&self, fee_bps: u16) -> Instruction {
+        instruction::set_config(&self.authority, &self.config, self.authority, fee_bps)
+    }
+
+    fn set(&mut self, fee_bps: u16) -> Result<(), ProgramError> {
+        let (ix, authority) = (self.ix(fee_bps), self.authority);
+        process(&mut self.fx, &ix, &authority)
+    }
+
+    // Runs SetConfig(200) changed by `edit`; it must fail with the config untouched.
+    fn set_with(&mut self, edit: impl FnOnce(&mut Instruction)) -> ProgramError {
+        let mut ix = self.ix(200);
+        edit(&mut ix);
+        let authority = ix.accounts[PAYER].pubkey;
+        let err = process(&mut self.fx, &ix, &authority).unwrap_err();
+        assert_eq!(self.fx.config().fee_bps, 100);
+        err
+    }
+}
+
+#[test]
+fn set_config_changes_the_fee_and_logs_it() {
+    let mut c = set_case();
+    c.fx.bank.set_clock(1_700_000_000);
+    c.set(200).unwrap();
+    assert_eq!(c.fx.config().fee_bps, 200);
+    let log = c.fx.audit_log(&c.config).unwrap();
+    assert_eq!((log.config, log.total_changes), (c.config.to_bytes(), 1));
+    let change = log.entries[0];
+    assert_eq!((change.unix_timestamp, change.signer), (1_700_000_000, c.authority.to_bytes()));
+    assert_eq!((change.old_fee_bps, change.new_fee_bps), (100, 200));
+    assert_eq!((change.old_fee_collector, change.new_fee_collector), (c.authority.to_bytes(), c.authority.to_bytes()));
+
+    c.set(0).unwrap();
+    let log = c.fx.audit_log(&c.config).unwrap();
+    assert_eq!(log.total_changes, 2);
+    assert_eq!((log.entries[1].old_fee_bps, log.entries[1].new_fee_bps), (200, 0));
+}
+
+#[test]
+fn clients_that_predate_the_audit_log_change_the_config_unlogged() {
+    let mut c = set_case();
+    let mut ix = c.ix(200);
+    ix.accounts.truncate(AUDIT_LOG);
+    ix.accounts[PAYER].is_writable = false;
+    process(&mut c.fx, &ix, &c.authority).unwrap();
+    assert_eq!(c.fx.config().fee_bps, 200);
+    assert!(c.fx.audit_log(&c.config).is_none());
+}
+
+#[test]
+fn audit_log_keeps_the_last_sixteen_changes() {
+    let mut c = set_case();
+    for fee_bps in 1..=17 {
+        c.set(fee_bps).unwrap();
+    }
+    let log = c.fx.audit_log(&c.config).unwrap();
+    assert_eq!(log.total_changes, 17);
+    // Change 17 (index 16) overwrote the first one.
+    assert_eq!((log.entries[0].old_fee_bps, log.entries[0].new_fee_bps), (16, 17));
+    assert_eq!(log.entries[1].new_fee_bps, 2);
+}
+
+#[test]
+fn open_escrows_keep_the_fee_they_were_opened_with() {
+    let mut c = set_case();
+    let opened = c.fx.open(&c.fx.config(), &[1u8; 32], &Pubkey::new_unique(), 100_000);
+    c.set(500).unwrap();
+    let state = c.fx.escrow(&opened.args.payment_hash);
+    assert_eq!((state.platform_fee_bps, state.platform_fee_amount), (100, 1_000));
+}
+
+#[test]
+fn set_config_rejects_bad_accounts() {
+    let mut c = set_case();
+    assert_eq!(c.set_with(|ix| ix.accounts.truncate(SET_SYSTEM_PROGRAM)), ProgramError::NotEnoughAccountKeys);
+    assert_eq!(c.set_with(|ix| ix.accounts[PAYER].is_signer = false), EscrowError::InvalidSigner.into());
+    assert_eq!(c.set_with(|ix| ix.accounts[CONFIG].is_writable = false), EscrowError::AccountNotWritable.into());
+    let err = c.set_with(|ix| ix.accounts[SET_SYSTEM_PROGRAM].pubkey = Pubkey::new_unique());
+    assert_eq!(err, EscrowError::InvalidSystemProgram.into());
+
+    // The audit log is created on the first change, paid by the authority.
+    let err = c.set_with(|ix| ix.accounts[AUDIT_LOG].pubkey = Pubkey::new_unique());
+    assert_eq!(err, EscrowError::InvalidAuditLogPda.into());
+    assert_eq!(c.set_with(|ix| ix.accounts[AUDIT_LOG].is_writable = false), EscrowError::AccountNotWritable.into());
+    assert_eq!(c.set_with(|ix| ix.accounts[PAYER].is_writable = false), EscrowError::AccountNotWritable.into());
+
+    // Once it exists, it must still be the program's log.
+    let mut c = set_case();
+    c.set(100).unwrap();
+    let audit_log = audit_log_pda(&ln_usdt_escrow::id(), &c.config).0;
+    let mut data = c.fx.bank.account(&audit_log).unwrap().data.clone();
+    data[0] = 2;
+    c.fx.bank.set_data(&audit_log, data);
+    assert_eq!(c.set_with(|_| ()), EscrowError::InvalidAuditLogPda.into());
+}
+
+#[test]
+fn set_config_rejects_bad_arguments() {
+    let mut c = set_case();
+    let (authority, config) = (c.authority, c.config);
+    let err = c.set_with(|ix| *ix = instruction::set_config(&authority, &config, authority, 501));
+    assert_eq!(err, EscrowError::FeeTooHigh.into());
+    let err = c.set_with(|ix| *ix = instruction::set_config(&authority, &config, Pubkey::new_unique(), 200));
+    assert_eq!(err, EscrowError::FeeCollectorMismatch.into());
+    c.set(500).unwrap();
+}
+
+#[test]
+fn only_the_authority_sets_the_config() {
+    let mut c = set_case();
+    let stranger = signer();
+    c.fx.bank.airdrop(&stranger, 1_000_000_000);
+    let config = c.config;
+    let err = c.set_with(|ix| *ix = instruction::set_config(&stranger, &config, stranger, 200));
+    assert_eq!(err, EscrowError::AuthorityMismatch.into());
+}
+
+#[test]
+fn set_config_needs_a_valid_config() {
+    // Not created.
+    let mut c = set_case();
+    c.config = config_pda(&ln_usdt_escrow::id(), &c.authority).0;
+    let ix = c.ix(200);
+    let authority = c.authority;
+    assert_eq!(process(&mut c.fx, &ix, &authority), Err(EscrowError::InvalidConfigState.into()));
+
+    // Written by another program.
+    let mut c = set_case();
+    let account = Account {
+        owner: Pubkey::new_unique(),
+        ..c.fx.bank.account(&c.config).unwrap().clone()
+    };
+    c.fx.bank.set_account(c.config, account);
+    let (ix, authority) = (c.ix(200), c.authority);
+    assert_eq!(process(&mut c.fx, &ix, &authority), Err(EscrowError::InvalidAccountOwner.into()));
+
+    // A copy at another address.
+    let mut c = set_case();
+    let copy = Pubkey::new_unique();
+    let account = c.fx.bank.account(&c.config).unwrap().clone();
+    c.fx.bank.set_account(copy, account);
+    c.config = copy;
+    let (ix, authority) = (c.ix(200), c.authority);
+    assert_eq!(process(&mut c.fx, &ix, &authority), Err(EscrowError::InvalidConfigPda.into()));
+
+    // Wrong bump.
+    let mut c = set_case();
+    let mut state = c.fx.config();
+    state.bump = state.bump.wrapping_add(1);
+    c.fx.write_config(&state);
+    assert_eq!(c.set(200), Err(EscrowError::InvalidConfigState.into()));
+}
+
+// The fixture's platform authority, with 1_000 of fees accrued from one claimed escrow, withdrawing to its own
+// token account.
+struct WithdrawCase {
+    fx: Fixture,
+    collector: Pubkey,
+    destination: Pubkey,
+    fee_vault: Pubkey,
+}
+
+fn withdraw_case() -> WithdrawCase {
+    let mut fx = Fixture::new(100, 50);
+    fx.open_and_claim([1u8; 32], 100_000);
+    let mint = fx.mint;
+
+    let collector = fx.platform_authority;
+    let destination = get_associated_token_address(&collector, &mint);
+    fx.bank.create_token_account(&destination, &mint, &collector, 0);
+    let fee_vault = get_associated_token_address(&fx.config_key(), &mint);
+    assert_eq!(fx.bank.token_balance(&fee_vault), 1_000);
+    WithdrawCase {
+        fx,
+        collector,
+        destination,
+        fee_vault,
+    }
+}
+
+impl WithdrawCase {
+    fn ix(&self, amount: u64) -> Instruction {
+        instruction::withdraw_fees(&self.collector, &self.fx.config_key(), &self.fx.mint, &self.destination, amount)
+    }
+
+    fn withdraw(&mut self, amount: u64) -> Result<(), ProgramError> {
+        let (ix, collector) = (self.ix(amount), self.collector);
+        process(&mut self.fx, &ix, &collector)
+    }
+
+    // Runs a full withdrawal changed by `edit`; it must fail with the fee vault untouched.
+    fn withdraw_with(&mut self, edit: impl FnOnce(&mut Instruction)) -> ProgramError {
+        let mut ix = self.ix(0);
+        edit(&mut ix);
+        let signer = ix.accounts[PAYER].pubkey;
+        let err = process(&mut self.fx, &ix, &signer).unwrap_err();
+        assert_eq!(self.fx.bank.token_balance(&self.fee_vault), 1_000);
+        err
+    }
+}
+
+#[test]
+fn withdraws_part_or_all_of_the_fee_vault() {
+    let mut c = withdraw_case();
+    c.withdraw(400).unwrap();
+    assert_eq!((c.fx.bank.token_balance(&c.fee_vault), c.fx.bank.token_balance(&c.destination)), (600, 400));
+    assert_eq!(c.withdraw(601), Err(EscrowError::WithdrawExceedsBalance.into()));
+
+    // Zero takes whatever is left; with nothing left it is a no-op.
+    c.withdraw(0).unwrap();
+    assert_eq!((c.fx.bank.token_balance(&c.fee_vault), c.fx.bank.token_balance(&c.destination)), (0, 1_000));
+    c.withdraw(0).unwrap();
+    let stats = c.fx.mint_stats(&c.fx.config_key()).unwrap();
+    assert_eq!((stats.fees_accrued, stats.fees_withdrawn), (1_000, 1_000));
+}
+
+#[test]
+fn withdrawals_without_mint_stats_leave_them_as_they_were() {
+    let mut c = withdraw_case();
+    let mut ix = c.ix(0);
+    ix.accounts.pop();
+    let collector = c.collector;
+    process(&mut c.fx, &ix, &collector).unwrap();
+    assert_eq!(c.fx.bank.token_balance(&c.destination), 1_000);
+    let stats = c.fx.mint_stats(&c.fx.config_key()).unwrap();
+    assert_eq!((stats.fees_accrued, stats.fees_withdrawn), (1_000, 0));
+}
+
+#[test]
+fn withdraw_fees_rejects_bad_accounts() {
+    let mut c = withdraw_case();
+    assert_eq!(c.withdraw_with(|ix| ix.accounts.truncate(TOKEN_PROGRAM)), ProgramError::NotEnoughAccountKeys);
+    assert_eq!(c.withdraw_with(|ix| ix.accounts[PAYER].is_signer = false), EscrowError::InvalidSigner.into());
+    for index in [FEE_VAULT, DESTINATION, MINT_STATS] {
+        let err = c.withdraw_with(|ix| ix.accounts[index].is_writable = false);
+        assert_eq!(err, EscrowError::AccountNotWritable.into(), "account {index}");
+    }
+    let err = c.withdraw_with(|ix| ix.accounts[TOKEN_PROGRAM].pubkey = Pubkey::new_unique());
+    assert_eq!(err, EscrowError::InvalidTokenProgram.into());
+    let err = c.withdraw_with(|ix| ix.accounts[MINT_STATS].pubkey = Pubkey::new_unique());
+    assert_eq!(err, EscrowError::InvalidMintStatsPda.into());
+    let other_stats = mint_stats_pda(&ln_usdt_escrow::id(), &c.fx.trade_config_key(), &c.fx.mint).0;
+    let err = c.withdraw_with(|ix| ix.accounts[MINT_STATS].pubkey = other_stats);
+    assert_eq!(err, EscrowError::InvalidMintStatsPda.into());
+}
+
+#[test]
+fn only_the_fee_collector_withdraws() {
+    let mut c = withdraw_case();
+    let stranger = signer();
+    let (mint, config) = (c.fx.mint, c.fx.config_key());
+    let stranger_token = get_associated_token_address(&stranger, &mint);
+    c.fx.bank.create_token_account(&stranger_token, &mint, &stranger, 0);
+    let err = c.withdraw_with(|ix| *ix = instruction::withdraw_fees(&stranger, &config, &mint, &stranger_token, 0));
+    assert_eq!(err, EscrowError::AuthorityMismatch.into());
+
+    // An authority that handed collection to someone else can no longer withdraw itself.
+    let mut state = c.fx.config();
+    state.fee_collector = stranger.to_bytes();
+    c.fx.write_config(&state);
+    assert_eq!(c.withdraw_with(|_| ()), EscrowError::FeeCollectorMismatch.into());
+}
+
+#[test]
+fn treasury_and_revenue_split_lock_direct_withdrawals() {
+    let mut c = withdraw_case();
+    let mut state = c.fx.config();
+    state.treasury = TreasuryState {
+        owner: Pubkey::new_unique().to_bytes(),
+        guardian: Pubkey::new_unique().to_bytes(),
+        withdraw_delay_secs: 3600,
+    };
+    c.fx.write_config(&state);
+    assert_eq!(c.withdraw_with(|_| ()), EscrowError::TreasuryLocked.into());
+
+    let mut c = withdraw_case();
+    let mut state = c.fx.config();
+    state.revenue_split.count = 1;
+    state.revenue_split.beneficiaries[0] = RevenueBeneficiary {
+        owner: Pubkey::new_unique().to_bytes(),
+        weight_bps: 10_000,
+    };
+    c.fx.write_config(&state);
+    assert_eq!(c.withdraw_with(|_| ()), EscrowError::RevenueSplitActive.into());
+}
+
+#[test]
+fn fee_vault_must_be_the_configs_ata() {
+    // A token account the config owns that is not its ATA.
+    let mut c = withdraw_case();
+    let (mint, config, other) = (c.fx.mint, c.fx.config_key(), Pubkey::new_unique());
+    c.fx.bank.create_token_account(&other, &mint, &config, 0);
+    let err = c.withdraw_with(|ix| ix.accounts[FEE_VAULT].pubkey = other);
+    assert_eq!(err, EscrowError::InvalidFeeVaultAta.into());
+
+    // The collector's own account in its place.
+    let destination = c.destination;
+    let err = c.withdraw_with(|ix| ix.accounts[FEE_VAULT].pubkey = destination);
+    assert_eq!(err, EscrowError::TokenOwnerMismatch.into());
+
+    let err = c.withdraw_with(|ix| ix.accounts[FEE_VAULT].pubkey = Pubkey::new_unique());
+    assert_eq!(err, EscrowError::InvalidTokenAccountData.into());
+}
+
+#[test]
+fn destination_must_be_the_collectors_in_the_mint() {
+    let mut c = withdraw_case();
+    let collector = c.collector;
+    let err = c.withdraw_with(|ix| ix.accounts[DESTINATION].pubkey = collector);
+    assert_eq!(err, EscrowError::InvalidTokenAccountData.into());
+
+    let (mint, other) = (c.fx.mint, Pubkey::new_unique());
+    c.fx.bank.create_token_account(&other, &mint, &Pubkey::new_unique(), 0);
+    let err = c.withdraw_with(|ix| ix.accounts[DESTINATION].pubkey = other);
+    assert_eq!(err, EscrowError::TokenOwnerMismatch.into());
+
+    let (other_mint, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+    c.fx.bank.create_mint(&other_mint, 6);
+    c.fx.bank.create_token_account(&other, &other_mint, &collector, 0);
+    let err = c.withdraw_with(|ix| ix.accounts[DESTINATION].pubkey = other);
+    assert_eq!(err, EscrowError::TokenMintMismatch.into());
+}
+
+#[test]
+fn withdraw_fees_needs_a_valid_config() {
+    let mut c = withdraw_case();
+    let config = c.fx.config_key();
+    let mut data = c.fx.bank.account(&config).unwrap().data.clone();
+    c.fx.bank.set_data(&config, vec![0xff; 16]);
+    assert_eq!(c.withdraw_with(|_| ()), EscrowError::InvalidConfigState.into());
+
+    data[0] = 200;
+    c.fx.bank.set_data(&config, data);
+    assert_eq!(c.withdraw_with(|_| ()), EscrowError::InvalidConfigState.into());
+
+    let err = c.withdraw_with(|ix| ix.accounts[CONFIG].pubkey = Pubkey::new_unique());
+    assert_eq!(err, EscrowError::InvalidConfigState.into());
+}
+
+// The withdraw case plus a second mint whose fee vault also holds 1_000, and the collector's (mint, ATA) pairs.
+fn batch_case() -> (WithdrawCase, [(Pubkey, Pubkey); 2]) {
+    let mut c = withdraw_case();
+    let (first, second) = (c.fx.mint, Pubkey::new_unique());
+    c.fx.bank.create_mint(&second, 6);
+    c.fx.mint = second;
+    c.fx.open_and_claim([2u8; 32], 100_000);
+    c.fx.mint = first;
+    let destination = get_associated_token_address(&c.collector, &second);
+    c.fx.bank.create_token_account(&destination, &second, &c.collector, 0);
+    let pairs = [(first, c.destination), (second, destination)];
+    (c, pairs)
+}
+
+#[test]
+fn batch_withdrawal_sweeps_every_fee_vault() {
+    let (mut c, pairs) = batch_case();
+    let (collector, config) = (c.collector, c.fx.config_key());
+    process(&mut c.fx, &instruction::withdraw_fees_batch(&collector, &config, &pairs), &collector).unwrap();
+    for (mint, destination) in pairs {
+        let fee_vault = get_associated_token_address(&config, &mint);
+        assert_eq!((c.fx.bank.token_balance(&fee_vault), c.fx.bank.token_balance(&destination)), (0, 1_000));
+        let stats = c.fx.bank.account(&mint_stats_pda(&ln_usdt_escrow::id(), &config, &mint).0).unwrap();
+        let stats = MintStatsState::unpack(&stats.data).unwrap();
+        assert_eq!((stats.fees_accrued, stats.fees_withdrawn), (1_000, 1_000));
+        let logged = format!("Program log: withdrew 1000 from fee vault {fee_vault}");
+        assert!(c.fx.bank.logs().contains(&logged), "missing {logged:?}");
+    }
+}
+
+#[test]
+fn batch_withdrawal_checks_every_pair() {
+    let (mut c, pairs) = batch_case();
+    let (collector, config, second) = (c.collector, c.fx.config_key(), pairs[1].0);
+    let (stranger, other) = (signer(), Pubkey::new_unique());
+    c.fx.bank.create_token_account(&other, &second, &stranger, 0);
+    let mut odd = instruction::withdraw_fees_batch(&collector, &config, &pairs);
+    odd.accounts.pop();
+    let cases: [(Instruction, Pubkey, ProgramError); 3] = [
+        // A destination of someone else's in the second pair undoes the first pair as well.
+        (
+            instruction::withdraw_fees_batch(&collector, &config, &[pairs[0], (second, other)]),
+            collector,
+            EscrowError::TokenOwnerMismatch.into(),
+        ),
+        (odd, collector, ProgramError::NotEnoughAccountKeys),
+        (instruction::withdraw_fees_batch(&stranger, &config, &pairs), stranger, EscrowError::AuthorityMismatch.into()),
+    ];
+    for (ix, signer, expected) in cases {
+        assert_eq!(process(&mut c.fx, &ix, &signer), Err(expected));
+        for (mint, _) in pairs {
+            assert_eq!(c.fx.bank.token_balance(&get_associated_token_address(&config, &mint)), 1_000);
+        }
+    }
+}
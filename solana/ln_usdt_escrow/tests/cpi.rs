@@ -0,0 +1,213 @@
+//! A mock caller program opens, claims and refunds escrows through `ln_usdt_escrow::cpi`, with PDAs of its own
+//! as payer, recipient, refund authority and rent payer.
+
+mod common;
+
+use borsh::BorshDeserialize;
+use std::panic::{self, AssertUnwindSafe};
+
+use common::{program_test, Account, Fixture};
+use ln_usdt_escrow::{
+    cpi,
+    instruction::{self, InitArgs},
+    return_data::{EscrowOutcome, STATUS_ACTIVE, STATUS_CLAIMED, STATUS_REFUNDED},
+    EscrowError,
+};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    program::set_return_data,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use solana_program_test::processor;
+use spl_associated_token_account::get_associated_token_address;
+
+const MOCK_ID: Pubkey = Pubkey::new_from_array([0xca; 32]);
+const VAULT_SEED: &[u8] = b"vault";
+const FUNDER_SEED: &[u8] = b"funder";
+
+const OP_OPEN: u8 = 0;
+const OP_CLAIM: u8 = 1;
+const OP_REFUND: u8 = 2;
+const OP_OPEN_UNSIGNED: u8 = 3;
+
+// Accounts: 0 the escrow program, then the escrow instruction's own accounts. The caller's "vault" PDA (a data
+// account it owns) acts as payer, recipient and refund authority; its data-less "funder" PDA pays the rent.
+fn mock_process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let (op, rest) = data.split_first().ok_or(ProgramError::InvalidInstructionData)?;
+    let (program, escrow_accounts) = accounts.split_first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let (_, vault_bump) = Pubkey::find_program_address(&[VAULT_SEED], program_id);
+    let (_, funder_bump) = Pubkey::find_program_address(&[FUNDER_SEED], program_id);
+    let vault_seeds: &[&[u8]] = &[VAULT_SEED, &[vault_bump]];
+    let funder_seeds: &[&[u8]] = &[FUNDER_SEED, &[funder_bump]];
+    let outcome = match *op {
+        OP_OPEN => {
+            let args = InitArgs::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?;
+            cpi::init(program, escrow_accounts, args, &[vault_seeds, funder_seeds])?
+        }
+        OP_OPEN_UNSIGNED => {
+            let args = InitArgs::try_from_slice(rest).map_err(|_| ProgramError::InvalidInstructionData)?;
+            // On chain the runtime fails this CPI with a privilege escalation; program-test's native CPI panics on
+            // it instead, so the mock reports it as the missing signature it is.
+            panic::catch_unwind(AssertUnwindSafe(|| cpi::init(program, escrow_accounts, args, &[funder_seeds])))
+                .map_err(|_| ProgramError::MissingRequiredSignature)??
+        }
+        OP_CLAIM => {
+            let preimage = <[u8; 32]>::try_from(rest).map_err(|_| ProgramError::InvalidInstructionData)?;
+            cpi::claim(program, escrow_accounts, preimage, &[vault_seeds])?
+        }
+        OP_REFUND => cpi::refund(program, escrow_accounts, &[vault_seeds])?,
+        _ => return Err(ProgramError::InvalidInstructionData),
+    };
+    // Hand the escrow program's result back to the test.
+    set_return_data(&outcome.to_return_data());
+    Ok(())
+}
+
+// Wraps an escrow instruction into a mock call: signer flags of the caller's PDAs are dropped (the mock signs for
+// them), everything else is passed through.
+fn mock_ix(op: u8, payload: Vec<u8>, escrow_ix: &Instruction, pdas: &[Pubkey]) -> Instruction {
+    let mut accounts = vec![AccountMeta::new_readonly(ln_usdt_escrow::id(), false)];
+    accounts.extend(escrow_ix.accounts.iter().map(|meta| AccountMeta {
+        is_signer: meta.is_signer && !pdas.contains(&meta.pubkey),
+        ..meta.clone()
+    }));
+    let mut data = vec![op];
+    data.extend(payload);
+    Instruction::new_with_bytes(MOCK_ID, &data, accounts)
+}
+
+struct Caller {
+    fx: Fixture,
+    vault: Pubkey,
+    funder: Pubkey,
+    vault_token: Pubkey,
+}
+
+fn setup(balance: u64) -> Caller {
+    let mut program_test = program_test();
+    program_test.add_program("mock_caller", MOCK_ID, processor!(mock_process));
+    let mut fx = Fixture::start(program_test, 100, 50);
+    let vault = Pubkey::find_program_address(&[VAULT_SEED], &MOCK_ID).0;
+    let funder = Pubkey::find_program_address(&[FUNDER_SEED], &MOCK_ID).0;
+    // The vault carries state of its own, so only the funder can pay for system accounts.
+    fx.bank.set_account(
+        vault,
+        Account {
+            lamports: 10_000_000,
+            data: vec![1; 64],
+            owner: MOCK_ID,
+            executable: false,
+        },
+    );
+    fx.bank.airdrop(&funder, 1_000_000_000);
+    let vault_token = get_associated_token_address(&vault, &fx.mint);
+    let mint = fx.mint;
+    fx.bank.create_token_account(&vault_token, &mint, &vault, balance);
+    Caller {
+        fx,
+        vault,
+        funder,
+        vault_token,
+    }
+}
+
+impl Caller {
+    fn open(&mut self, op: u8, payer_token: &Pubkey, args: InitArgs) -> Result<EscrowOutcome, ProgramError> {
+        let (config, trade_config) = (self.fx.config(), self.fx.trade_config());
+        let (vault, funder, mint) = (self.vault, self.funder, self.fx.mint);
+        let ix = instruction::init_cpi(&vault, payer_token, &funder, &mint, &config, &trade_config, args);
+        let ix = mock_ix(op, borsh::to_vec(&args).unwrap(), &ix, &[self.vault, self.funder]);
+        self.call(&ix)
+    }
+
+    fn call(&mut self, ix: &Instruction) -> Result<EscrowOutcome, ProgramError> {
+        let (program_id, data) = self.fx.bank.process(ix, &[])?.expect("mock return data");
+        assert_eq!(program_id, MOCK_ID);
+        Ok(EscrowOutcome::from_return_data(&data).expect("escrow outcome"))
+    }
+}
+
+#[test]
+fn pda_opens_and_claims_through_cpi() {
+    let mut c = setup(1_000_000);
+    let preimage = [7u8; 32];
+    let args = c.fx.init_args(&preimage, &c.vault, &c.vault, 100_000);
+    let vault_token = c.vault_token;
+    let opened = c.open(OP_OPEN, &vault_token, args).unwrap();
+    assert_eq!(opened.status, STATUS_ACTIVE);
+    assert_eq!(opened.net_amount, 100_000);
+    assert_eq!(opened.platform_fee_amount, 1_000);
+    assert_eq!(opened.trade_fee_amount, 500);
+    assert_eq!(opened.total_amount, 101_500);
+    assert_eq!(c.fx.bank.token_balance(&c.vault_token), 1_000_000 - 101_500);
+    assert_eq!(c.fx.bank.token_balance(&opened.vault), 101_500);
+
+    let escrow = c.fx.escrow(&args.payment_hash);
+    assert_eq!(escrow.payer, c.vault.to_bytes());
+    // The rent came from the funder, not from the vault's own lamports.
+    assert_eq!(c.fx.bank.lamports(&c.vault), 10_000_000);
+
+    let recipient_token = Pubkey::new_unique();
+    let (mint, vault) = (c.fx.mint, c.vault);
+    c.fx.bank.create_token_account(&recipient_token, &mint, &vault, 0);
+    let ix = instruction::claim(&escrow, &recipient_token, preimage);
+    let ix = mock_ix(OP_CLAIM, preimage.to_vec(), &ix, &[c.vault]);
+    let claimed = c.call(&ix).unwrap();
+    assert_eq!(claimed.status, STATUS_CLAIMED);
+    assert_eq!(claimed.escrow, opened.escrow);
+    assert_eq!(c.fx.bank.token_balance(&recipient_token), claimed.net_amount);
+    assert_eq!(claimed.net_amount, 100_000);
+}
+
+#[test]
+fn pda_refunds_through_cpi() {
+    let mut c = setup(1_000_000);
+    let preimage = [9u8; 32];
+    let args = c.fx.init_args(&preimage, &Pubkey::new_unique(), &c.vault, 250_000);
+    let vault_token = c.vault_token;
+    let opened = c.open(OP_OPEN, &vault_token, args).unwrap();
+
+    let escrow = c.fx.escrow(&args.payment_hash);
+    let ix = instruction::refund(&escrow, &vault_token);
+    let ix = mock_ix(OP_REFUND, Vec::new(), &ix, &[c.vault]);
+    assert_eq!(c.call(&ix), Err(EscrowError::TooEarly.into()));
+
+    let refund_after = args.refund_after;
+    c.fx.bank.set_clock(refund_after + 1);
+    let refunded = c.call(&ix).unwrap();
+    assert_eq!(refunded.status, STATUS_REFUNDED);
+    assert_eq!(refunded.net_amount, opened.total_amount);
+    assert_eq!(c.fx.bank.token_balance(&c.vault_token), 1_000_000);
+}
+
+#[test]
+fn pda_opens_with_delegated_tokens() {
+    let mut c = setup(0);
+    let (owner, owner_token) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (mint, vault) = (c.fx.mint, c.vault);
+    c.fx.bank.create_token_account(&owner_token, &mint, &owner, 1_000_000);
+    c.fx.bank.approve(&owner_token, &vault, 150_000);
+
+    let args = c.fx.init_args(&[3u8; 32], &Pubkey::new_unique(), &c.vault, 100_000);
+    let opened = c.open(OP_OPEN, &owner_token, args).unwrap();
+    assert_eq!(opened.total_amount, 101_500);
+    assert_eq!(c.fx.bank.token_balance(&owner_token), 1_000_000 - 101_500);
+
+    // What is left of the allowance is short of a second escrow, whatever the owner's balance.
+    let args = c.fx.init_args(&[4u8; 32], &Pubkey::new_unique(), &c.vault, 100_000);
+    assert_eq!(c.open(OP_OPEN, &owner_token, args), Err(EscrowError::InsufficientFunds.into()));
+    assert_eq!(c.fx.bank.token_account(&owner_token).unwrap().delegated_amount, 150_000 - 101_500);
+}
+
+#[test]
+fn cpi_init_needs_the_payer_seeds() {
+    let mut c = setup(1_000_000);
+    let args = c.fx.init_args(&[5u8; 32], &Pubkey::new_unique(), &c.vault, 100_000);
+    let vault_token = c.vault_token;
+    assert_eq!(c.open(OP_OPEN_UNSIGNED, &vault_token, args), Err(ProgramError::MissingRequiredSignature));
+    assert!(c.fx.bank.account(&get_associated_token_address(&c.vault, &c.fx.mint)).is_some());
+    assert_eq!(c.fx.bank.token_balance(&c.vault_token), 1_000_000);
+}
@@ -0,0 +1,168 @@
+//! Events: what each instruction logs as `Program data:` and how `EscrowEvent::from_log_data` decodes it.
+
+mod common;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use common::{signer, Bank, Fixture, Opened};
+use ln_usdt_escrow::{
+    events::{
+        ConfigUpdated, EscrowClaimed, EscrowEvent, EscrowInitialized, EscrowRefunded, FeesWithdrawn, EVENT_VERSION,
+    },
+    instruction,
+};
+use solana_program::pubkey::Pubkey;
+use spl_associated_token_account::get_associated_token_address;
+
+const PREIMAGE: [u8; 32] = [1u8; 32];
+
+// Every data field the last transaction logged, base64-decoded, with what the program's own decoder makes of it.
+// The round trip through `to_log_data` must give back the exact bytes.
+fn events(bank: &Bank) -> Vec<EscrowEvent> {
+    bank.logs()
+        .iter()
+        .filter_map(|line| line.strip_prefix("Program data: "))
+        .flat_map(|fields| fields.split(' '))
+        .map(|field| {
+            let data = STANDARD.decode(field).expect("base64 data field");
+            let event = EscrowEvent::from_log_data(&data).expect("escrow event");
+            assert_eq!(event.to_log_data(), data);
+            event
+        })
+        .collect()
+}
+
+fn open(fx: &mut Fixture) -> Opened {
+    let opened = fx.open(&fx.config(), &PREIMAGE, &signer(), 100_000);
+    let (config, trade_config) = (fx.config_key(), fx.trade_config_key());
+    assert_eq!(
+        events(&fx.bank),
+        [EscrowEvent::EscrowInitialized(EscrowInitialized {
+            escrow: opened.escrow,
+            payment_hash: opened.args.payment_hash,
+            mint: fx.mint,
+            payer: opened.payer,
+            recipient: opened.args.recipient,
+            refund: opened.payer,
+            refund_after: opened.args.refund_after,
+            net_amount: 100_000,
+            platform_fee_amount: 1_000,
+            trade_fee_amount: 500,
+            referral_fee_amount: 0,
+            platform_config: config,
+            trade_config,
+        })]
+    );
+    opened
+}
+
+#[test]
+fn claim_logs_the_preimage_and_what_it_paid() {
+    let mut fx = Fixture::new(100, 50);
+    let opened = open(&mut fx);
+    let (recipient, mint) = (opened.args.recipient, fx.mint);
+    fx.claim(&opened, PREIMAGE);
+    assert_eq!(
+        events(&fx.bank),
+        [EscrowEvent::EscrowClaimed(EscrowClaimed {
+            escrow: opened.escrow,
+            payment_hash: opened.args.payment_hash,
+            preimage: PREIMAGE,
+            recipient,
+            net_amount: 100_000,
+            platform_fee_amount: 1_000,
+            trade_fee_amount: 500,
+            referral_fee_amount: 0,
+        })]
+    );
+
+    // The fees it paid leave their vaults with a FeesWithdrawn each.
+    let (authority, config) = (fx.platform_authority, fx.config_key());
+    let destination = get_associated_token_address(&authority, &mint);
+    fx.bank.create_token_account(&destination, &mint, &authority, 0);
+    let ix = instruction::withdraw_fees(&authority, &config, &mint, &destination, 0);
+    fx.bank.process(&ix, &[&authority]).unwrap();
+    assert_eq!(
+        events(&fx.bank),
+        [EscrowEvent::FeesWithdrawn(FeesWithdrawn {
+            config,
+            fee_vault: get_associated_token_address(&config, &mint),
+            mint,
+            destination,
+            amount: 1_000,
+        })]
+    );
+}
+
+#[test]
+fn refund_logs_what_went_back() {
+    let mut fx = Fixture::new(100, 50);
+    let opened = open(&mut fx);
+    let state = fx.escrow(&opened.args.payment_hash);
+    fx.bank.set_clock(state.refund_after);
+    fx.bank.process(&instruction::refund(&state, &opened.payer_token), &[&opened.payer]).unwrap();
+    assert_eq!(
+        events(&fx.bank),
+        [EscrowEvent::EscrowRefunded(EscrowRefunded {
+            escrow: opened.escrow,
+            payment_hash: opened.args.payment_hash,
+            refund: opened.payer,
+            amount: 101_500,
+        })]
+    );
+}
+
+#[test]
+fn config_setters_log_the_old_and_new_values() {
+    let mut fx = Fixture::new(100, 50);
+    let (authority, config) = (fx.platform_authority, fx.config_key());
+    fx.bank.process(&instruction::set_config(&authority, &config, authority, 150), &[&authority]).unwrap();
+    assert_eq!(
+        events(&fx.bank),
+        [EscrowEvent::ConfigUpdated(ConfigUpdated {
+            config,
+            authority,
+            old_fee_bps: 100,
+            new_fee_bps: 150,
+            old_fee_collector: authority,
+            new_fee_collector: authority,
+        })]
+    );
+
+    let (trade_authority, trade_config) = (fx.trade_authority, fx.trade_config_key());
+    let ix = instruction::set_trade_config(&trade_authority, &trade_config, trade_authority, 75);
+    fx.bank.process(&ix, &[&trade_authority]).unwrap();
+    assert_eq!(
+        events(&fx.bank),
+        [EscrowEvent::TradeConfigUpdated(ConfigUpdated {
+            config: trade_config,
+            authority: trade_authority,
+            old_fee_bps: 50,
+            new_fee_bps: 75,
+            old_fee_collector: trade_authority,
+            new_fee_collector: trade_authority,
+        })]
+    );
+}
+
+#[test]
+fn other_data_does_not_decode_as_an_event() {
+    let event = EscrowEvent::EscrowRefunded(EscrowRefunded {
+        escrow: Pubkey::new_unique(),
+        payment_hash: [7u8; 32],
+        refund: Pubkey::new_unique(),
+        amount: 1,
+    });
+    let data = event.to_log_data();
+    assert_eq!(data[0], EVENT_VERSION);
+    assert_eq!(EscrowEvent::from_log_data(&data), Some(event));
+
+    let mut newer = data.clone();
+    newer[0] = EVENT_VERSION + 1;
+    assert_eq!(EscrowEvent::from_log_data(&newer), None);
+    // An event type appended after this build.
+    let mut unknown = data.clone();
+    unknown[1] = 0xff;
+    assert_eq!(EscrowEvent::from_log_data(&unknown), None);
+    assert_eq!(EscrowEvent::from_log_data(&[]), None);
+    assert_eq!(EscrowEvent::from_log_data(&data[..data.len() - 1]), None);
+}
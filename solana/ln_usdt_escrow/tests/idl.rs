@@ -0,0 +1,626 @@
+//! The program's IDL, `idl/ln_usdt_escrow.json`, in the Anchor IDL format (spec 0.1.0).
+//!
+//! It is generated from the program source, the way Shank reads a native program: instructions and their args
+//! from `EscrowInstruction`, account lists from the `// Accounts:` comment of the handler each instruction is
+//! dispatched to, account layouts from the state structs (every struct that starts with a `v` version byte) and
+//! errors from `EscrowError`. `idl_is_up_to_date` fails whenever the checked-in file no longer matches the
+//! source; `UPDATE_IDL=1 cargo test --test idl` rewrites it. The remaining tests hold the checked-in file
+//! against the compiled program (parser, errors, state sizes and offsets, instruction builders), so neither the
+//! comments nor this generator can drift from what the program actually does.
+
+use std::collections::BTreeMap;
+
+use num_traits::FromPrimitive;
+use serde_json::{json, Value};
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+
+use ln_usdt_escrow::{
+    instruction::{self, Abi, EscrowInstruction, InitArgs, ABI_V2_PREFIX, ABI_VERSION},
+    ConfigState, EscrowError, EscrowState, TradeConfigState,
+};
+
+const INSTRUCTION_RS: &str = include_str!("../src/instruction.rs");
+const LIB_RS: &str = include_str!("../src/lib.rs");
+const ERROR_RS: &str = include_str!("../src/error.rs");
+const PROGRAM_IDS: &str = include_str!("../program-ids.json");
+const IDL_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/idl/ln_usdt_escrow.json");
+
+const IDL_DOCS: &[&str] = &[
+    "Instruction data is the v2 encoding: the discriminator (0xff, ABI version 2, variant index) followed by the \
+     Borsh-encoded args. The sysvar accounts of the v1 encoding are left out.",
+    "Optional accounts are packed in order, leaving out the ones that do not apply; nothing is passed in their \
+     place.",
+    "Account layouts start with a version byte `v`; fields documented as `vN+` are only present from version N on.",
+];
+
+// Text between the first `{` from `header` on and its matching `}`.
+fn body_after<'a>(src: &'a str, header: &str) -> &'a str {
+    let start = src.find(header).unwrap_or_else(|| panic!("`{header}` not found"));
+    let open = start + src[start..].find('{').expect("opening brace");
+    let mut depth = 0;
+    for (i, c) in src[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return &src[open + 1..open + i];
+                }
+            }
+            _ => {}
+        }
+    }
+    panic!("unbalanced braces after `{header}`")
+}
+
+// The `//` comment lines right above the line holding `header`, attributes skipped.
+fn comment_above(src: &str, header: &str) -> Vec<String> {
+    let at = src.find(header).unwrap_or_else(|| panic!("`{header}` not found"));
+    let line_start = src[..at].rfind('\n').map_or(0, |i| i + 1);
+    let mut lines = Vec::new();
+    for line in src[..line_start].lines().rev().map(str::trim) {
+        if line.starts_with("#[") {
+            continue;
+        }
+        match line.strip_prefix("//") {
+            Some(text) if !text.starts_with('!') => lines.push(text.trim().to_string()),
+            _ => break,
+        }
+    }
+    lines.reverse();
+    lines
+}
+
+fn split_comment(line: &str) -> (&str, Option<&str>) {
+    match line.split_once("//") {
+        Some((code, comment)) => (code.trim(), Some(comment.trim())),
+        None => (line.trim(), None),
+    }
+}
+
+struct Field {
+    name: String,
+    ty: String,
+    docs: Option<String>,
+}
+
+// `a: T, b: U` (a trailing comment documents the last field).
+fn parse_fields(code: &str, comment: Option<&str>) -> Vec<Field> {
+    let mut fields: Vec<Field> = code
+        .split(',')
+        .map(|f| f.trim().trim_start_matches("pub "))
+        .filter(|f| !f.is_empty())
+        .map(|f| {
+            let (name, ty) = f.split_once(':').unwrap_or_else(|| panic!("not a field: `{f}`"));
+            Field {
+                name: name.trim().to_string(),
+                ty: ty.trim().to_string(),
+                docs: None,
+            }
+        })
+        .collect();
+    if let Some(last) = fields.last_mut() {
+        last.docs = comment.map(str::to_string);
+    }
+    fields
+}
+
+fn struct_fields(src: &str, name: &str) -> Vec<Field> {
+    body_after(src, &format!("struct {name} {{"))
+        .lines()
+        .map(split_comment)
+        .filter(|(code, _)| !code.is_empty() && !code.starts_with("#["))
+        .flat_map(|(code, comment)| parse_fields(code, comment))
+        .collect()
+}
+
+struct Variant {
+    name: String,
+    fields: Vec<Field>,
+    tuple: Option<String>,
+}
+
+fn instruction_variants() -> Vec<Variant> {
+    let mut variants = Vec::new();
+    let mut open: Option<Variant> = None;
+    for (code, comment) in body_after(INSTRUCTION_RS, "pub enum EscrowInstruction").lines().map(split_comment) {
+        if code.is_empty() {
+            continue;
+        }
+        if let Some(variant) = open.as_mut() {
+            if code.starts_with('}') {
+                variants.extend(open.take());
+            } else {
+                variant.fields.extend(parse_fields(code, comment));
+            }
+            continue;
+        }
+        let code = code.trim_end_matches(',');
+        let mut variant = Variant {
+            name: code.split(|c: char| !c.is_alphanumeric()).next().unwrap().to_string(),
+            fields: Vec::new(),
+            tuple: None,
+        };
+        if let Some((_, rest)) = code.split_once(" {") {
+            match rest.trim().strip_suffix('}') {
+                Some(inner) => {
+                    variant.fields = parse_fields(inner, comment);
+                    variants.push(variant);
+                }
+                None => open = Some(variant),
+            }
+        } else if let Some((_, rest)) = code.split_once('(') {
+            variant.tuple = Some(rest.trim_end_matches(')').to_string());
+            variants.push(variant);
+        } else {
+            variants.push(variant);
+        }
+    }
+    variants
+}
+
+fn array_len(len: &str) -> usize {
+    let len = len.trim();
+    len.parse().unwrap_or_else(|_| {
+        let value = LIB_RS
+            .split_once(&format!("const {len}: usize = "))
+            .unwrap_or_else(|| panic!("const {len} not found"))
+            .1;
+        value[..value.find(';').unwrap()].parse().unwrap()
+    })
+}
+
+// Struct types are collected into `defined` so they end up in the IDL's `types`.
+fn idl_type(ty: &str, defined: &mut Vec<String>) -> Value {
+    match ty.trim() {
+        ty @ ("u8" | "u16" | "u32" | "u64" | "i64" | "bool") => json!(ty),
+        "Pubkey" => json!("pubkey"),
+        ty if ty.starts_with('[') => {
+            let (elem, len) = ty[1..ty.len() - 1].split_once(';').unwrap();
+            json!({ "array": [idl_type(elem, defined), array_len(len)] })
+        }
+        ty if ty.starts_with("Vec<") => json!({ "vec": idl_type(&ty[4..ty.len() - 1], defined) }),
+        ty => {
+            defined.push(ty.to_string());
+            json!({ "defined": { "name": ty } })
+        }
+    }
+}
+
+fn with_docs(mut value: Value, docs: Vec<String>) -> Value {
+    if !docs.is_empty() {
+        value["docs"] = json!(docs);
+    }
+    value
+}
+
+fn idl_fields(fields: &[Field], defined: &mut Vec<String>) -> Value {
+    fields
+        .iter()
+        .map(|f| {
+            let field = json!({ "name": f.name, "type": idl_type(&f.ty, defined) });
+            with_docs(field, f.docs.iter().cloned().collect())
+        })
+        .collect()
+}
+
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            out.push('_');
+        }
+        out.push(c.to_ascii_lowercase());
+    }
+    out
+}
+
+// One line of an `// Accounts:` comment: `<index> [<flags>] <description>`.
+struct AccountLine {
+    index: String,
+    signer: bool,
+    writable: bool,
+    text: String,
+}
+
+#[derive(Default)]
+struct AccountsComment {
+    lines: Vec<AccountLine>,
+    notes: Vec<String>,
+}
+
+// Reads the `// Accounts:` comment of `handler`. `// Accounts: those of <fn>` starts from that function's list;
+// lines that follow replace the entry with the same index or are appended.
+fn accounts_comment(handler: &str) -> AccountsComment {
+    let body = body_after(LIB_RS, &format!("fn {handler}("));
+    let comment: Vec<&str> = body
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| !line.starts_with("// Accounts:"))
+        .take_while(|line| line.starts_with("//"))
+        .map(|line| &line[2..])
+        .collect();
+    let (head, rest) = comment.split_first().unwrap_or_else(|| panic!("{handler} has no `// Accounts:` comment"));
+    let mut accounts = match head.split_once("those of ") {
+        Some((_, base)) => accounts_comment(base.split(|c: char| !c.is_alphanumeric() && c != '_').next().unwrap()),
+        None => AccountsComment::default(),
+    };
+    let mut notes = false;
+    for text in rest {
+        if notes || text.trim().is_empty() {
+            notes = true;
+            if !text.trim().is_empty() {
+                accounts.notes.push(text.trim().to_string());
+            }
+        } else if text.starts_with("  ") {
+            let last = accounts.lines.last_mut().expect("continuation line");
+            last.text.push(' ');
+            last.text.push_str(text.trim());
+        } else {
+            let (index, rest) = text.trim().split_once(" [").unwrap_or_else(|| panic!("bad account line `{text}`"));
+            let (flags, text) = rest.split_once(']').unwrap();
+            let line = AccountLine {
+                index: index.to_string(),
+                signer: flags.split(',').any(|f| f == "signer"),
+                writable: flags.split(',').any(|f| f == "writable"),
+                text: text.trim().to_string(),
+            };
+            match accounts.lines.iter_mut().find(|l| l.index == line.index) {
+                Some(existing) => *existing = line,
+                None => accounts.lines.push(line),
+            }
+        }
+    }
+    accounts
+}
+
+fn account_name(text: &str) -> String {
+    let end = text.find([';', '(']).unwrap_or(text.len());
+    text[..end]
+        .trim()
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+fn program_address(name: &str) -> Option<Pubkey> {
+    match name {
+        "system_program" => Some(solana_program::system_program::id()),
+        "token_program" => Some(spl_token::id()),
+        "associated_token_program" => Some(spl_associated_token_account::id()),
+        _ => None,
+    }
+}
+
+// The handler `dispatch` calls for `variant`.
+fn handler_of(variant: &str) -> String {
+    let dispatch = body_after(LIB_RS, "fn dispatch(");
+    let pattern = format!("EscrowInstruction::{variant}");
+    let at = dispatch
+        .match_indices(&pattern)
+        .map(|(i, _)| i + pattern.len())
+        .find(|&i| !dispatch[i..].starts_with(|c: char| c.is_alphanumeric()))
+        .unwrap_or_else(|| panic!("{variant} is not dispatched"));
+    let arm = &dispatch[at..];
+    let call = &arm[arm.find("=>").unwrap()..];
+    let call = &call[call.find("process_").unwrap()..];
+    call[..call.find('(').unwrap()].to_string()
+}
+
+fn idl_instruction(index: usize, variant: &Variant, defined: &mut Vec<String>) -> Value {
+    let handler = handler_of(&variant.name);
+    let comment = accounts_comment(&handler);
+    let mut docs = comment_above(LIB_RS, &format!("fn {handler}("));
+    let mut accounts = Vec::new();
+    for line in &comment.lines {
+        // Sysvars are read through syscalls in v2.
+        if line.text.contains("v1 only") {
+            continue;
+        }
+        let flags = match (line.signer, line.writable) {
+            (true, true) => "signer, writable",
+            (true, false) => "signer",
+            (false, true) => "writable",
+            (false, false) => "readonly",
+        };
+        if line.index.ends_with("..") {
+            docs.push(format!("Remaining accounts ({flags}): {}", line.text));
+            continue;
+        }
+        let name = account_name(&line.text);
+        let mut account = json!({ "name": name });
+        if line.writable {
+            account["writable"] = json!(true);
+        }
+        if line.signer {
+            account["signer"] = json!(true);
+        }
+        if ["only when", "required in", "required only"].iter().any(|o| line.text.contains(o)) {
+            account["optional"] = json!(true);
+        }
+        if let Some(address) = program_address(&name) {
+            account["address"] = json!(address.to_string());
+        }
+        let text_docs = if line.text.len() > name.len() { vec![line.text.clone()] } else { Vec::new() };
+        accounts.push(with_docs(account, text_docs));
+    }
+    docs.extend(comment.notes);
+    let args = match &variant.tuple {
+        Some(ty) => json!([{ "name": "args", "type": idl_type(ty, defined) }]),
+        None => idl_fields(&variant.fields, defined),
+    };
+    let instruction = json!({
+        "name": snake_case(&variant.name),
+        "discriminator": [ABI_V2_PREFIX, ABI_VERSION, index],
+        "accounts": accounts,
+        "args": args,
+    });
+    with_docs(instruction, docs)
+}
+
+fn idl_errors() -> Value {
+    let mut errors = Vec::new();
+    let mut msg = None;
+    for (code, _) in body_after(ERROR_RS, "pub enum EscrowError").lines().map(split_comment) {
+        if let Some(text) = code.strip_prefix("#[error(\"") {
+            msg = Some(text.trim_end_matches("\")]").to_string());
+        } else if let Some((name, value)) = code.trim_end_matches(',').split_once(" = ") {
+            let code: u32 = value.parse().unwrap();
+            errors.push(json!({ "code": code, "name": name, "msg": msg.take().expect("error message") }));
+        }
+    }
+    json!(errors)
+}
+
+// Every struct in lib.rs whose first field is the `v` version byte is an account layout.
+fn state_structs() -> Vec<String> {
+    LIB_RS
+        .lines()
+        .filter_map(|line| {
+            let name = line.trim_start_matches("pub ").strip_prefix("struct ")?.strip_suffix(" {")?;
+            let first = struct_fields(LIB_RS, name).into_iter().next()?;
+            (first.name == "v").then(|| name.to_string())
+        })
+        .collect()
+}
+
+fn generate() -> Value {
+    let mut defined = Vec::new();
+    let instructions: Vec<Value> = instruction_variants()
+        .iter()
+        .enumerate()
+        .map(|(i, v)| idl_instruction(i, v, &mut defined))
+        .collect();
+    defined.extend(state_structs());
+    let mut types = BTreeMap::new();
+    while let Some(name) = defined.pop() {
+        if types.contains_key(&name) {
+            continue;
+        }
+        let src = if INSTRUCTION_RS.contains(&format!("struct {name} {{")) { INSTRUCTION_RS } else { LIB_RS };
+        let fields = idl_fields(&struct_fields(src, &name), &mut defined);
+        let ty = json!({ "name": name, "type": { "kind": "struct", "fields": fields } });
+        types.insert(name.clone(), with_docs(ty, comment_above(src, &format!("struct {name} {{"))));
+    }
+    let program_ids: Value = serde_json::from_str(PROGRAM_IDS).unwrap();
+    json!({
+        "address": program_ids["mainnet"],
+        "metadata": {
+            "name": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+            "spec": "0.1.0",
+            "description": "Lightning-settled USDT escrow",
+        },
+        "docs": IDL_DOCS,
+        "instructions": instructions,
+        "errors": idl_errors(),
+        "types": types.into_values().collect::<Vec<_>>(),
+    })
+}
+
+fn checked_in_idl() -> Value {
+    let text = std::fs::read_to_string(IDL_PATH).expect("idl/ln_usdt_escrow.json missing; run with UPDATE_IDL=1");
+    serde_json::from_str(&text).unwrap()
+}
+
+fn find<'a>(list: &'a Value, name: &str) -> &'a Value {
+    list.as_array().unwrap().iter().find(|v| v["name"] == name).unwrap_or_else(|| panic!("{name} missing"))
+}
+
+#[test]
+fn idl_is_up_to_date() {
+    let generated = serde_json::to_string_pretty(&generate()).unwrap() + "\n";
+    if std::env::var_os("UPDATE_IDL").is_some() {
+        std::fs::create_dir_all(std::path::Path::new(IDL_PATH).parent().unwrap()).unwrap();
+        std::fs::write(IDL_PATH, generated).unwrap();
+        return;
+    }
+    let checked_in = std::fs::read_to_string(IDL_PATH).unwrap_or_default();
+    assert!(checked_in == generated, "idl/ln_usdt_escrow.json is stale; run `UPDATE_IDL=1 cargo test --test idl`");
+}
+
+// Borsh-encodes a sample value of `ty`, with no two primitives of a type sharing a value.
+fn sample(ty: &Value, types: &Value, out: &mut Vec<u8>) {
+    if let Some(ty) = ty.as_str() {
+        match ty {
+            "u8" | "bool" => out.push(1),
+            "u16" => out.extend(2u16.to_le_bytes()),
+            "u32" => out.extend(3u32.to_le_bytes()),
+            "u64" => out.extend(4u64.to_le_bytes()),
+            "i64" => out.extend(5i64.to_le_bytes()),
+            "pubkey" => out.extend([6u8; 32]),
+            other => panic!("unexpected type {other}"),
+        }
+    } else if let Some(array) = ty.get("array") {
+        for _ in 0..array[1].as_u64().unwrap() {
+            sample(&array[0], types, out);
+        }
+    } else if let Some(elem) = ty.get("vec") {
+        out.extend(1u32.to_le_bytes());
+        sample(elem, types, out);
+    } else {
+        let defined = find(types, ty["defined"]["name"].as_str().unwrap());
+        for field in defined["type"]["fields"].as_array().unwrap() {
+            sample(&field["type"], types, out);
+        }
+    }
+}
+
+fn size_of(ty: &Value, types: &Value) -> usize {
+    let mut out = Vec::new();
+    sample(ty, types, &mut out);
+    out.len()
+}
+
+fn pascal_case(name: &str) -> String {
+    name.split('_').map(|w| w[..1].to_uppercase() + &w[1..]).collect()
+}
+
+#[test]
+fn every_instruction_decodes_as_described() {
+    let idl = checked_in_idl();
+    let instructions = idl["instructions"].as_array().unwrap();
+    for (i, ix) in instructions.iter().enumerate() {
+        let name = ix["name"].as_str().unwrap();
+        let mut data: Vec<u8> = serde_json::from_value(ix["discriminator"].clone()).unwrap();
+        assert_eq!(data, [ABI_V2_PREFIX, ABI_VERSION, i as u8], "{name}");
+        for arg in ix["args"].as_array().unwrap() {
+            sample(&arg["type"], &idl["types"], &mut data);
+        }
+        let (decoded, abi) = EscrowInstruction::unpack(&data).unwrap_or_else(|e| panic!("{name}: {e:?}"));
+        assert_eq!(abi, Abi::V2);
+        assert!(format!("{decoded:?}").starts_with(&pascal_case(name)), "{name} decoded as {decoded:?}");
+        assert_eq!(decoded.pack(), data, "{name}");
+        // Neither shorter nor longer data decodes, so the args are described completely.
+        if data.len() > 3 {
+            assert!(EscrowInstruction::unpack(&data[..data.len() - 1]).is_err(), "{name}");
+        }
+        data.push(0);
+        assert!(EscrowInstruction::unpack(&data).is_err(), "{name}");
+    }
+    let past_the_end = [ABI_V2_PREFIX, ABI_VERSION, instructions.len() as u8];
+    assert!(EscrowInstruction::unpack(&past_the_end).is_err(), "instructions missing from the IDL");
+}
+
+#[test]
+fn errors_match_escrow_error() {
+    let idl = checked_in_idl();
+    let errors = idl["errors"].as_array().unwrap();
+    for error in errors {
+        let code = error["code"].as_u64().unwrap() as u32;
+        let e = EscrowError::from_u32(code).unwrap_or_else(|| panic!("no EscrowError with code {code}"));
+        assert_eq!(format!("{e:?}"), error["name"].as_str().unwrap());
+        assert_eq!(e.to_string(), error["msg"].as_str().unwrap());
+    }
+    let codes: Vec<u32> = errors.iter().map(|e| e["code"].as_u64().unwrap() as u32).collect();
+    let max = *codes.iter().max().unwrap();
+    assert!((1..=max).all(|code| codes.contains(&code)));
+    assert!(EscrowError::from_u32(max + 1).is_none(), "errors missing from the IDL");
+}
+
+fn offset_of(layout: &Value, field: &str, types: &Value) -> usize {
+    let fields = layout["type"]["fields"].as_array().unwrap();
+    let at = fields.iter().position(|f| f["name"] == field).unwrap_or_else(|| panic!("{field} missing"));
+    fields[..at].iter().map(|f| size_of(&f["type"], types)).sum()
+}
+
+#[test]
+fn state_layouts_match_the_decoders() {
+    let idl = checked_in_idl();
+    let types = &idl["types"];
+    let layout_size = |name: &str| size_of(&json!({ "defined": { "name": name } }), types);
+    assert_eq!(layout_size("EscrowState"), EscrowState::space(EscrowState::LATEST));
+    assert_eq!(layout_size("ConfigState"), ConfigState::space(ConfigState::LATEST));
+    assert_eq!(layout_size("TradeConfigState"), TradeConfigState::space(TradeConfigState::LATEST));
+
+    let escrow = find(types, "EscrowState");
+    for (field, offset) in [
+        ("status", EscrowState::STATUS_OFFSET),
+        ("payment_hash", EscrowState::PAYMENT_HASH_OFFSET),
+        ("recipient", EscrowState::RECIPIENT_OFFSET),
+        ("refund", EscrowState::REFUND_OFFSET),
+        ("mint", EscrowState::MINT_OFFSET),
+        ("platform_fee_collector", EscrowState::PLATFORM_FEE_COLLECTOR_OFFSET),
+        ("trade_fee_collector", EscrowState::TRADE_FEE_COLLECTOR_OFFSET),
+        ("referrer", EscrowState::REFERRER_OFFSET),
+        ("payer", EscrowState::PAYER_OFFSET),
+        ("tenant", EscrowState::TENANT_OFFSET),
+    ] {
+        assert_eq!(offset_of(escrow, field, types), offset, "EscrowState.{field}");
+    }
+    let config = find(types, "ConfigState");
+    assert_eq!(offset_of(config, "authority", types), ConfigState::AUTHORITY_OFFSET);
+    assert_eq!(offset_of(config, "fee_collector", types), ConfigState::FEE_COLLECTOR_OFFSET);
+    assert_eq!(offset_of(config, "tenant", types), ConfigState::TENANT_OFFSET);
+    let trade_config = find(types, "TradeConfigState");
+    assert_eq!(offset_of(trade_config, "authority", types), TradeConfigState::AUTHORITY_OFFSET);
+    assert_eq!(offset_of(trade_config, "fee_collector", types), TradeConfigState::FEE_COLLECTOR_OFFSET);
+}
+
+// The builders include exactly the accounts that are not optional when no optional feature is in use.
+fn assert_builder_matches(idl: &Value, name: &str, ix: &Instruction) {
+    let accounts: Vec<&Value> = find(&idl["instructions"], name)["accounts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|a| a["optional"] != true)
+        .collect();
+    assert_eq!(accounts.len(), ix.accounts.len(), "{name}");
+    for (account, meta) in accounts.iter().zip(&ix.accounts) {
+        let account_name = account["name"].as_str().unwrap();
+        assert_eq!(account["signer"] == true, meta.is_signer, "{name}.{account_name} signer");
+        assert_eq!(account["writable"] == true, meta.is_writable, "{name}.{account_name} writable");
+        if let Some(address) = account["address"].as_str() {
+            assert_eq!(address, meta.pubkey.to_string(), "{name}.{account_name}");
+        }
+    }
+}
+
+#[test]
+fn account_lists_match_the_builders() {
+    let idl = checked_in_idl();
+    let key = Pubkey::new_unique;
+    let (payer, mint) = (key(), key());
+    let mut config = vec![0u8; ConfigState::space(ConfigState::LATEST)];
+    config[0] = ConfigState::LATEST;
+    let config = ConfigState::unpack(&config).unwrap();
+    let mut trade_config = vec![0u8; TradeConfigState::space(TradeConfigState::LATEST)];
+    trade_config[0] = TradeConfigState::LATEST;
+    let trade_config = TradeConfigState::unpack(&trade_config).unwrap();
+    let mut escrow = vec![0u8; EscrowState::space(EscrowState::LATEST)];
+    escrow[0] = EscrowState::LATEST;
+    let escrow = EscrowState::unpack(&escrow).unwrap();
+    let args = InitArgs {
+        payment_hash: [1; 32],
+        recipient: key(),
+        refund: payer,
+        refund_after: 0,
+        amount: 1,
+        expected_platform_fee_bps: 0,
+        expected_trade_fee_bps: 0,
+        trade_fee_collector: key(),
+        referrer: Pubkey::default(),
+        referral_fee_bps: 0,
+        expected_platform_lamport_fee: 0,
+        expected_trade_lamport_fee: 0,
+    };
+
+    let ix = instruction::init(&payer, &key(), &mint, &config, &trade_config, args);
+    assert_builder_matches(&idl, "init", &ix);
+    let ix = instruction::validate_init(&payer, &key(), &mint, &config, &trade_config, args);
+    assert_builder_matches(&idl, "validate_init", &ix);
+    let ix = instruction::init_cpi(&payer, &key(), &key(), &mint, &config, &trade_config, args);
+    assert_builder_matches(&idl, "init_cpi", &ix);
+    assert_builder_matches(&idl, "claim", &instruction::claim(&escrow, &key(), [0; 32]));
+    assert_builder_matches(&idl, "refund", &instruction::refund(&escrow, &key()));
+    assert_builder_matches(&idl, "init_config", &instruction::init_config(&payer, key(), 0, key()));
+    assert_builder_matches(&idl, "set_config", &instruction::set_config(&payer, &key(), key(), 0));
+    assert_builder_matches(&idl, "withdraw_fees", &instruction::withdraw_fees(&payer, &key(), &mint, &key(), 0));
+    assert_builder_matches(&idl, "init_trade_config", &instruction::init_trade_config(&payer, key(), 0));
+    let ix = instruction::set_trade_config(&payer, &key(), key(), 0);
+    assert_builder_matches(&idl, "set_trade_config", &ix);
+    let ix = instruction::withdraw_trade_fees(&payer, &key(), &mint, &key(), 0);
+    assert_builder_matches(&idl, "withdraw_trade_fees", &ix);
+}
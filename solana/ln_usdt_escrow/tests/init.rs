@@ -0,0 +1,663 @@
+//! Init: the escrow it opens, how fees round, and every check it can reject on.
+
+mod common;
+
+use common::{to_v1, Account, Fixture};
+use ln_usdt_escrow::{
+    instruction::InitArgs,
+    pda_for_hash,
+    return_data::{EscrowOutcome, STATUS_ACTIVE},
+    EscrowError, EscrowState, VolumeTier,
+};
+use solana_program::{
+    instruction::Instruction, program_error::ProgramError, pubkey::Pubkey, rent::Rent, sysvar,
+};
+use spl_associated_token_account::get_associated_token_address;
+
+// Account indices of a v2 Init without referrer or payer stats.
+const PAYER: usize = 0;
+const PAYER_TOKEN: usize = 1;
+const ESCROW: usize = 2;
+const VAULT: usize = 3;
+const SYSTEM_PROGRAM: usize = 5;
+const TOKEN_PROGRAM: usize = 6;
+const ATA_PROGRAM: usize = 7;
+const CONFIG: usize = 8;
+const PLATFORM_FEE_VAULT: usize = 9;
+const TRADE_CONFIG: usize = 10;
+const TRADE_FEE_VAULT: usize = 11;
+const PLATFORM_STATS: usize = 12;
+
+// A payer holding 1_000_000 about to open a 100_000 escrow at 1% platform and 0.5% trade fee.
+struct Case {
+    fx: Fixture,
+    payer: Pubkey,
+    payer_token: Pubkey,
+    args: InitArgs,
+}
+
+fn case() -> Case {
+    let mut fx = Fixture::new(100, 50);
+    let (payer, payer_token) = fx.funded_payer(1_000_000);
+    let args = fx.init_args(&[1u8; 32], &Pubkey::new_unique(), &payer, 100_000);
+    Case {
+        fx,
+        payer,
+        payer_token,
+        args,
+    }
+}
+
+impl Case {
+    fn ix(&self) -> Instruction {
+        self.fx.init_ix(&self.payer, &self.payer_token, self.args)
+    }
+
+    fn process(&mut self, ix: &Instruction) -> Result<EscrowOutcome, ProgramError> {
+        let (_, data) = self.fx.bank.process(ix, &[&self.payer])?.expect("return data");
+        Ok(EscrowOutcome::from_return_data(&data).expect("escrow outcome"))
+    }
+
+    fn init(&mut self) -> Result<EscrowOutcome, ProgramError> {
+        let ix = self.ix();
+        self.process(&ix)
+    }
+
+    // Runs Init with the instruction changed by `edit`; it must fail without leaving an escrow behind.
+    fn init_with(&mut self, edit: impl FnOnce(&mut Instruction)) -> ProgramError {
+        let mut ix = self.ix();
+        edit(&mut ix);
+        let err = self.process(&ix).unwrap_err();
+        assert!(self.fx.bank.account(&self.escrow()).is_none());
+        err
+    }
+
+    // Builds Init from the fixture as it stands, then lets `corrupt` break the accounts before it runs.
+    fn init_after(&mut self, corrupt: impl FnOnce(&mut Fixture)) -> ProgramError {
+        let ix = self.ix();
+        corrupt(&mut self.fx);
+        let err = self.process(&ix).unwrap_err();
+        assert!(self.fx.bank.account(&self.escrow()).is_none());
+        err
+    }
+
+    fn escrow(&self) -> Pubkey {
+        pda_for_hash(&ln_usdt_escrow::id(), &self.args.payment_hash).0
+    }
+
+    fn set_owner(&mut self, key: &Pubkey, owner: Pubkey) {
+        let account = Account {
+            owner,
+            ..self.fx.bank.account(key).unwrap().clone()
+        };
+        self.fx.bank.set_account(*key, account);
+    }
+}
+
+#[test]
+fn opens_an_escrow_holding_amount_plus_fees() {
+    let mut c = case();
+    let outcome = c.init().unwrap();
+    let escrow = c.escrow();
+    let vault = get_associated_token_address(&escrow, &c.fx.mint);
+    assert_eq!(
+        outcome,
+        EscrowOutcome {
+            escrow,
+            vault,
+            status: STATUS_ACTIVE,
+            total_amount: 101_500,
+            net_amount: 100_000,
+            platform_fee_amount: 1_000,
+            trade_fee_amount: 500,
+            referral_fee_amount: 0,
+        }
+    );
+    assert_eq!(c.fx.bank.token_balance(&vault), 101_500);
+    assert_eq!(c.fx.bank.token_balance(&c.payer_token), 1_000_000 - 101_500);
+
+    let state = c.fx.escrow(&c.args.payment_hash);
+    assert_eq!((state.v, state.status), (EscrowState::LATEST, EscrowState::STATUS_ACTIVE));
+    assert_eq!(state.recipient, c.args.recipient.to_bytes());
+    assert_eq!((state.refund, state.payer), (c.payer.to_bytes(), c.payer.to_bytes()));
+    assert_eq!(state.refund_after, c.args.refund_after);
+    assert_eq!((state.platform_fee_bps, state.trade_fee_bps), (100, 50));
+    assert_eq!(state.platform_fee_collector, c.fx.platform_authority.to_bytes());
+    assert_eq!(state.trade_fee_collector, c.fx.trade_authority.to_bytes());
+    assert_eq!(state.vault, vault.to_bytes());
+    let account = c.fx.bank.account(&escrow).unwrap();
+    assert_eq!(account.data.len(), EscrowState::space(EscrowState::LATEST));
+    assert_eq!(account.lamports, Rent::default().minimum_balance(account.data.len()));
+
+    // Both fee vaults and both mint stats PDAs now exist.
+    let (config, trade_config) = (c.fx.config_key(), c.fx.trade_config_key());
+    for owner in [config, trade_config] {
+        let fee_vault = c.fx.bank.token_account(&get_associated_token_address(&owner, &c.fx.mint)).unwrap();
+        assert_eq!((fee_vault.owner, fee_vault.amount), (owner, 0));
+        let stats = c.fx.mint_stats(&owner).unwrap();
+        assert_eq!((stats.total_volume, stats.active_escrows, stats.fees_accrued), (101_500, 1, 0));
+    }
+}
+
+#[test]
+fn fees_round_down() {
+    let mut c = case();
+    let mut config = c.fx.config();
+    config.max_referral_bps = 25;
+    c.fx.write_config(&config);
+    let referrer = Pubkey::new_unique();
+    let mint = c.fx.mint;
+    c.fx.bank.create_token_account(&get_associated_token_address(&referrer, &mint), &mint, &referrer, 0);
+
+    // 1% of 10_099 is 100.99, 0.5% is 50.495 and 0.25% is 25.2475.
+    c.args.amount = 10_099;
+    c.args.referrer = referrer;
+    c.args.referral_fee_bps = 25;
+    let outcome = c.init().unwrap();
+    assert_eq!(
+        (outcome.platform_fee_amount, outcome.trade_fee_amount, outcome.referral_fee_amount),
+        (100, 50, 25)
+    );
+    assert_eq!(outcome.total_amount, 10_099 + 175);
+
+    // Below 1/bps of a unit a fee rounds to nothing.
+    c.args = c.fx.init_args(&[2u8; 32], &Pubkey::new_unique(), &c.payer, 199);
+    let outcome = c.init().unwrap();
+    assert_eq!((outcome.platform_fee_amount, outcome.trade_fee_amount, outcome.total_amount), (1, 0, 200));
+    c.args = c.fx.init_args(&[3u8; 32], &Pubkey::new_unique(), &c.payer, 99);
+    let outcome = c.init().unwrap();
+    assert_eq!((outcome.platform_fee_amount, outcome.trade_fee_amount, outcome.total_amount), (0, 0, 99));
+}
+
+#[test]
+fn v1_init_takes_the_rent_sysvar() {
+    let mut c = case();
+    let ix = to_v1(&c.ix(), Some((8, sysvar::clock::id())));
+    assert_eq!(c.process(&ix), Err(EscrowError::InvalidSysvar.into()));
+    let ix = to_v1(&c.ix(), Some((8, sysvar::rent::id())));
+    assert_eq!(c.process(&ix).unwrap().total_amount, 101_500);
+}
+
+#[test]
+fn clients_that_predate_mint_stats_open_uncounted_escrows() {
+    let mut c = case();
+    let mut ix = to_v1(&c.ix(), Some((8, sysvar::rent::id())));
+    ix.accounts.truncate(ix.accounts.len() - 2);
+    assert_eq!(c.process(&ix).unwrap().total_amount, 101_500);
+    let state = c.fx.escrow(&c.args.payment_hash);
+    assert_eq!(state.fee_flags & EscrowState::FLAG_TRACK_MINT_STATS, 0);
+    assert!(c.fx.mint_stats(&c.fx.config_key()).is_none());
+    assert!(c.fx.mint_stats(&c.fx.trade_config_key()).is_none());
+
+    // The stats come as a pair or not at all.
+    c.args = c.fx.init_args(&[2u8; 32], &Pubkey::new_unique(), &c.payer, 100_000);
+    assert_eq!(c.init_with(|ix| ix.accounts.truncate(PLATFORM_STATS + 1)), ProgramError::NotEnoughAccountKeys);
+}
+
+#[test]
+fn lamport_fee_mode_charges_lamports_instead_of_tokens() {
+    let mut c = case();
+    let mut config = c.fx.config();
+    config.lamport_fees.per_escrow = 5_000;
+    c.fx.write_config(&config);
+    let config_key = c.fx.config_key();
+    let config_lamports = c.fx.bank.lamports(&config_key);
+
+    c.args.expected_platform_fee_bps = 0;
+    c.args.expected_platform_lamport_fee = 5_000;
+    let outcome = c.init().unwrap();
+    assert_eq!((outcome.platform_fee_amount, outcome.total_amount), (0, 100_500));
+    assert_eq!(c.fx.bank.lamports(&config_key), config_lamports + 5_000);
+    assert_eq!(c.fx.config().lamport_fees.reserved, 5_000);
+    assert_eq!(c.fx.escrow(&c.args.payment_hash).platform_lamport_fee, 5_000);
+}
+
+#[test]
+fn needs_every_account() {
+    let mut c = case();
+    assert_eq!(c.init_with(|ix| ix.accounts.truncate(CONFIG)), ProgramError::NotEnoughAccountKeys);
+}
+
+#[test]
+fn payer_must_sign() {
+    let mut c = case();
+    assert_eq!(c.init_with(|ix| ix.accounts[PAYER].is_signer = false), EscrowError::InvalidSigner.into());
+}
+
+#[test]
+fn written_accounts_must_be_writable() {
+    for index in [PAYER, PAYER_TOKEN, ESCROW, VAULT, PLATFORM_FEE_VAULT, TRADE_FEE_VAULT] {
+        let mut c = case();
+        let err = c.init_with(|ix| ix.accounts[index].is_writable = false);
+        assert_eq!(err, EscrowError::AccountNotWritable.into(), "account {index}");
+    }
+}
+
+#[test]
+fn program_accounts_must_be_the_real_programs() {
+    for (index, expected) in [
+        (SYSTEM_PROGRAM, EscrowError::InvalidSystemProgram),
+        (TOKEN_PROGRAM, EscrowError::InvalidTokenProgram),
+        (ATA_PROGRAM, EscrowError::InvalidAtaProgram),
+    ] {
+        let mut c = case();
+        assert_eq!(c.init_with(|ix| ix.accounts[index].pubkey = Pubkey::new_unique()), expected.into());
+    }
+}
+
+#[test]
+fn escrow_must_be_the_payment_hash_pda() {
+    let mut c = case();
+    let other = pda_for_hash(&ln_usdt_escrow::id(), &[9u8; 32]).0;
+    assert_eq!(c.init_with(|ix| ix.accounts[ESCROW].pubkey = other), EscrowError::InvalidEscrowPda.into());
+}
+
+#[test]
+fn platform_config_must_be_a_valid_config_pda() {
+    // Not created yet.
+    let mut c = case();
+    let missing = Pubkey::new_unique();
+    let err = c.init_with(|ix| ix.accounts[CONFIG].pubkey = missing);
+    assert_eq!(err, EscrowError::InvalidConfigState.into());
+
+    // Written by another program.
+    let mut c = case();
+    let config = c.fx.config_key();
+    c.set_owner(&config, Pubkey::new_unique());
+    assert_eq!(c.init_with(|_| ()), EscrowError::InvalidAccountOwner.into());
+
+    // Not a config.
+    let mut c = case();
+    let config = c.fx.config_key();
+    let err = c.init_after(|fx| fx.bank.set_data(&config, vec![0xff; 16]));
+    assert_eq!(err, EscrowError::InvalidConfigState.into());
+
+    // A copy of the config at another address.
+    let mut c = case();
+    let config = c.fx.config_key();
+    let copy = Pubkey::new_unique();
+    c.fx.bank.set_account(copy, c.fx.bank.account(&config).unwrap().clone());
+    assert_eq!(c.init_with(|ix| ix.accounts[CONFIG].pubkey = copy), EscrowError::InvalidConfigPda.into());
+
+    // Wrong bump or unknown version.
+    let mut c = case();
+    let mut state = c.fx.config();
+    state.bump = state.bump.wrapping_add(1);
+    c.fx.write_config(&state);
+    assert_eq!(c.init_with(|_| ()), EscrowError::InvalidConfigState.into());
+    let mut c = case();
+    let mut state = c.fx.config();
+    state.v = 200;
+    assert_eq!(c.init_after(|fx| fx.write_config(&state)), EscrowError::InvalidConfigState.into());
+}
+
+#[test]
+fn platform_fee_above_the_cap_is_refused() {
+    let mut c = case();
+    let mut state = c.fx.config();
+    state.fee_bps = 501;
+    c.fx.write_config(&state);
+    c.args.expected_platform_fee_bps = 501;
+    assert_eq!(c.init_with(|_| ()), EscrowError::FeeTooHigh.into());
+}
+
+#[test]
+fn referrer_and_referral_fee_go_together() {
+    let mut c = case();
+    c.args.referrer = Pubkey::new_unique();
+    assert_eq!(c.init_with(|_| ()), EscrowError::ReferrerWithoutFee.into());
+
+    let mut c = case();
+    c.args.referral_fee_bps = 10;
+    assert_eq!(c.init_with(|_| ()), EscrowError::ReferrerWithoutFee.into());
+}
+
+#[test]
+fn referral_fee_is_capped() {
+    // By the config (no referrals until a cap is set) ...
+    let mut c = case();
+    c.args.referrer = Pubkey::new_unique();
+    c.args.referral_fee_bps = 1;
+    assert_eq!(c.init_with(|_| ()), EscrowError::ReferralFeeTooHigh.into());
+
+    // ... and by the program, whatever the config allows.
+    let mut c = case();
+    let mut state = c.fx.config();
+    state.max_referral_bps = 1_000;
+    c.fx.write_config(&state);
+    c.args.referrer = Pubkey::new_unique();
+    c.args.referral_fee_bps = 501;
+    assert_eq!(c.init_with(|_| ()), EscrowError::ReferralFeeTooHigh.into());
+}
+
+#[test]
+fn referrer_token_account_must_be_its_initialized_ata() {
+    let referral_case = || {
+        let mut c = case();
+        let mut state = c.fx.config();
+        state.max_referral_bps = 100;
+        c.fx.write_config(&state);
+        c.args.referrer = Pubkey::new_unique();
+        c.args.referral_fee_bps = 10;
+        let ata = get_associated_token_address(&c.args.referrer, &c.fx.mint);
+        (c, ata)
+    };
+    const REFERRER_TOKEN: usize = 12;
+
+    let (mut c, _) = referral_case();
+    let (mint, referrer) = (c.fx.mint, c.args.referrer);
+    let other = Pubkey::new_unique();
+    c.fx.bank.create_token_account(&other, &mint, &referrer, 0);
+    let err = c.init_with(|ix| ix.accounts[REFERRER_TOKEN].pubkey = other);
+    assert_eq!(err, EscrowError::InvalidReferrerAta.into());
+
+    let (mut c, _) = referral_case();
+    assert_eq!(c.init_with(|_| ()), EscrowError::InvalidReferrerAta.into());
+
+    let (mut c, ata) = referral_case();
+    let mint = c.fx.mint;
+    c.fx.bank.create_token_account(&ata, &mint, &Pubkey::new_unique(), 0);
+    assert_eq!(c.init_with(|_| ()), EscrowError::InvalidReferrerAta.into());
+
+    let (mut c, ata) = referral_case();
+    let other_mint = Pubkey::new_unique();
+    c.fx.bank.create_mint(&other_mint, 6);
+    let referrer = c.args.referrer;
+    c.fx.bank.create_token_account(&ata, &other_mint, &referrer, 0);
+    assert_eq!(c.init_with(|_| ()), EscrowError::InvalidReferrerAta.into());
+
+    let (mut c, ata) = referral_case();
+    let (mint, referrer) = (c.fx.mint, c.args.referrer);
+    c.fx.bank.create_token_account(&ata, &mint, &referrer, 0);
+    assert_eq!(c.init().unwrap().referral_fee_amount, 100);
+}
+
+#[test]
+fn payer_stats_must_be_the_payer_pda_when_volume_tiers_are_on() {
+    let tiered_case = || {
+        let mut c = case();
+        let mut state = c.fx.config();
+        state.volume_tiers.count = 1;
+        state.volume_tiers.tiers[0] = VolumeTier {
+            min_volume: 1_000_000,
+            discount_bps: 5_000,
+        };
+        c.fx.write_config(&state);
+        c
+    };
+    const PAYER_STATS: usize = 12;
+
+    let mut c = tiered_case();
+    let other = Pubkey::new_unique();
+    let err = c.init_with(|ix| ix.accounts[PAYER_STATS].pubkey = other);
+    assert_eq!(err, EscrowError::InvalidPayerStatsPda.into());
+
+    let mut c = tiered_case();
+    let err = c.init_with(|ix| ix.accounts[PAYER_STATS].is_writable = false);
+    assert_eq!(err, EscrowError::AccountNotWritable.into());
+
+    let mut c = tiered_case();
+    let ix = c.ix();
+    let payer_stats = ix.accounts[PAYER_STATS].pubkey;
+    c.process(&ix).unwrap();
+    assert_eq!(c.fx.bank.account(&payer_stats).unwrap().owner, ln_usdt_escrow::id());
+    assert_ne!(c.fx.escrow(&c.args.payment_hash).fee_flags & EscrowState::FLAG_TRACK_PAYER_VOLUME, 0);
+}
+
+#[test]
+fn expected_fees_must_match_the_configs() {
+    let mut c = case();
+    c.args.expected_platform_fee_bps = 99;
+    assert_eq!(c.init_with(|_| ()), EscrowError::FeeMismatch.into());
+
+    let mut c = case();
+    c.args.expected_platform_lamport_fee = 1;
+    assert_eq!(c.init_with(|_| ()), EscrowError::LamportFeeMismatch.into());
+
+    let mut c = case();
+    c.args.expected_trade_fee_bps = 49;
+    assert_eq!(c.init_with(|_| ()), EscrowError::FeeMismatch.into());
+
+    let mut c = case();
+    c.args.expected_trade_lamport_fee = 1;
+    assert_eq!(c.init_with(|_| ()), EscrowError::LamportFeeMismatch.into());
+}
+
+#[test]
+fn vault_must_be_the_escrow_ata() {
+    let mut c = case();
+    let other = get_associated_token_address(&c.payer, &c.fx.mint);
+    assert_eq!(c.init_with(|ix| ix.accounts[VAULT].pubkey = other), EscrowError::InvalidVaultAta.into());
+}
+
+#[test]
+fn trade_config_must_be_the_collector_pda() {
+    // Another collector's PDA than the one in the args.
+    let mut c = case();
+    let trade_config = c.fx.trade_config_key();
+    c.args.trade_fee_collector = Pubkey::new_unique();
+    let err = c.init_with(|ix| ix.accounts[TRADE_CONFIG].pubkey = trade_config);
+    assert_eq!(err, EscrowError::InvalidTradeConfigPda.into());
+
+    // A collector without a trade config.
+    let mut c = case();
+    c.args.trade_fee_collector = Pubkey::new_unique();
+    assert_eq!(c.init_with(|_| ()), EscrowError::TradeConfigNotInitialized.into());
+}
+
+#[test]
+fn trade_config_must_hold_a_valid_state() {
+    let mut c = case();
+    let trade_config = c.fx.trade_config_key();
+    c.set_owner(&trade_config, Pubkey::new_unique());
+    assert_eq!(c.init_with(|_| ()), EscrowError::InvalidAccountOwner.into());
+
+    let mut c = case();
+    let trade_config = c.fx.trade_config_key();
+    let err = c.init_after(|fx| fx.bank.set_data(&trade_config, vec![0xff; 16]));
+    assert_eq!(err, EscrowError::InvalidTradeConfigState.into());
+
+    // Wrong bump or unknown version.
+    let edits: [fn(&mut ln_usdt_escrow::TradeConfigState); 2] = [|s| s.bump = s.bump.wrapping_add(1), |s| s.v = 200];
+    for edit in edits {
+        let mut c = case();
+        let mut state = c.fx.trade_config();
+        edit(&mut state);
+        let err = c.init_after(|fx| fx.write_trade_config(&state));
+        assert_eq!(err, EscrowError::InvalidTradeConfigState.into());
+    }
+
+    // A collector/authority other than the one it is seeded by.
+    let edits: [fn(&mut ln_usdt_escrow::TradeConfigState); 2] =
+        [|s| s.fee_collector = [7u8; 32], |s| s.authority = [7u8; 32]];
+    for edit in edits {
+        let mut c = case();
+        let mut state = c.fx.trade_config();
+        edit(&mut state);
+        let err = c.init_after(|fx| fx.write_trade_config(&state));
+        assert_eq!(err, EscrowError::TradeConfigCollectorMismatch.into());
+    }
+}
+
+#[test]
+fn trade_fee_above_the_cap_is_refused() {
+    let mut c = case();
+    let mut state = c.fx.trade_config();
+    state.fee_bps = 1_001;
+    c.fx.write_trade_config(&state);
+    c.args.expected_trade_fee_bps = 1_001;
+    assert_eq!(c.init_with(|_| ()), EscrowError::FeeTooHigh.into());
+}
+
+#[test]
+fn lock_surcharge_is_capped_by_the_expected_trade_fee() {
+    let surcharged = || {
+        let mut c = case();
+        let mut state = c.fx.trade_config();
+        state.lock_surcharge_bps_per_hour = 10;
+        c.fx.write_trade_config(&state);
+        // Just under an hour: one started hour here, two for a client that quoted ten seconds earlier.
+        c.args.refund_after -= 5;
+        c
+    };
+    let mut c = surcharged();
+    c.args.expected_trade_fee_bps = 59;
+    assert_eq!(c.init_with(|_| ()), EscrowError::FeeMismatch.into());
+
+    let mut c = surcharged();
+    c.args.expected_trade_fee_bps = 70;
+    c.init().unwrap();
+    let state = c.fx.escrow(&c.args.payment_hash);
+    assert_eq!((state.trade_fee_bps, state.trade_fee_amount), (60, 600));
+}
+
+#[test]
+fn total_fee_is_capped() {
+    // 5% platform and 10% trade leave no room for a referral fee.
+    let mut c = Fixture::new(500, 1_000);
+    let mut state = c.config();
+    state.max_referral_bps = 500;
+    c.write_config(&state);
+    let (payer, payer_token) = c.funded_payer(1_000_000);
+    let referrer = Pubkey::new_unique();
+    let mint = c.mint;
+    c.bank.create_token_account(&get_associated_token_address(&referrer, &mint), &mint, &referrer, 0);
+    let mut args = c.init_args(&[1u8; 32], &Pubkey::new_unique(), &payer, 100_000);
+    args.referrer = referrer;
+    args.referral_fee_bps = 1;
+    let ix = c.init_ix(&payer, &payer_token, args);
+    assert_eq!(c.bank.process(&ix, &[&payer]), Err(EscrowError::TotalFeeTooHigh.into()));
+}
+
+#[test]
+fn fee_vaults_must_be_the_config_atas() {
+    let mut c = case();
+    let other = get_associated_token_address(&c.payer, &c.fx.mint);
+    let err = c.init_with(|ix| ix.accounts[PLATFORM_FEE_VAULT].pubkey = other);
+    assert_eq!(err, EscrowError::InvalidFeeVaultAta.into());
+
+    let mut c = case();
+    let err = c.init_with(|ix| ix.accounts[TRADE_FEE_VAULT].pubkey = other);
+    assert_eq!(err, EscrowError::InvalidTradeFeeVaultAta.into());
+}
+
+#[test]
+fn direct_payout_needs_the_collectors_own_ata() {
+    let direct_case = || {
+        let mut c = case();
+        let mut state = c.fx.config();
+        state.direct_payout = true;
+        c.fx.write_config(&state);
+        let ata = get_associated_token_address(&c.fx.platform_authority, &c.fx.mint);
+        (c, ata)
+    };
+
+    let (mut c, _) = direct_case();
+    let config_ata = get_associated_token_address(&c.fx.config_key(), &c.fx.mint);
+    let err = c.init_with(|ix| ix.accounts[PLATFORM_FEE_VAULT].pubkey = config_ata);
+    assert_eq!(err, EscrowError::InvalidFeeVaultAta.into());
+
+    let (mut c, _) = direct_case();
+    assert_eq!(c.init_with(|_| ()), EscrowError::InvalidTokenAccountData.into());
+
+    let (mut c, ata) = direct_case();
+    let mint = c.fx.mint;
+    c.fx.bank.create_token_account(&ata, &mint, &Pubkey::new_unique(), 0);
+    assert_eq!(c.init_with(|_| ()), EscrowError::TokenOwnerMismatch.into());
+
+    let (mut c, ata) = direct_case();
+    let other_mint = Pubkey::new_unique();
+    c.fx.bank.create_mint(&other_mint, 6);
+    let collector = c.fx.platform_authority;
+    c.fx.bank.create_token_account(&ata, &other_mint, &collector, 0);
+    assert_eq!(c.init_with(|_| ()), EscrowError::TokenMintMismatch.into());
+
+    let (mut c, ata) = direct_case();
+    let (mint, collector) = (c.fx.mint, c.fx.platform_authority);
+    c.fx.bank.create_token_account(&ata, &mint, &collector, 0);
+    c.init().unwrap();
+    let flags = c.fx.escrow(&c.args.payment_hash).fee_flags;
+    assert_ne!(flags & EscrowState::FLAG_PLATFORM_DIRECT_PAYOUT, 0);
+    // Nothing is created for the config itself.
+    let config_ata = get_associated_token_address(&c.fx.config_key(), &mint);
+    assert!(c.fx.bank.account(&config_ata).is_none());
+}
+
+#[test]
+fn trade_direct_payout_needs_the_collectors_own_ata() {
+    let mut c = case();
+    let mut state = c.fx.trade_config();
+    state.direct_payout = true;
+    c.fx.write_trade_config(&state);
+    let trade_config_ata = get_associated_token_address(&c.fx.trade_config_key(), &c.fx.mint);
+    let err = c.init_with(|ix| ix.accounts[TRADE_FEE_VAULT].pubkey = trade_config_ata);
+    assert_eq!(err, EscrowError::InvalidTradeFeeVaultAta.into());
+    assert_eq!(c.init_with(|_| ()), EscrowError::InvalidTokenAccountData.into());
+}
+
+#[test]
+fn mint_stats_must_be_the_config_pdas() {
+    for index in [PLATFORM_STATS, PLATFORM_STATS + 1] {
+        let mut c = case();
+        let other = Pubkey::new_unique();
+        let err = c.init_with(|ix| ix.accounts[index].pubkey = other);
+        assert_eq!(err, EscrowError::InvalidMintStatsPda.into(), "account {index}");
+    }
+}
+
+#[test]
+fn payer_token_account_must_be_the_payers_in_the_mint() {
+    let mut c = case();
+    let wallet = Pubkey::new_unique();
+    c.fx.bank.airdrop(&wallet, 1_000_000);
+    let err = c.init_with(|ix| ix.accounts[PAYER_TOKEN].pubkey = wallet);
+    assert_eq!(err, EscrowError::InvalidTokenAccountData.into());
+
+    let mut c = case();
+    let (other_owner, other_token) = c.fx.funded_payer(1_000_000);
+    assert_ne!(other_owner, c.payer);
+    let err = c.init_with(|ix| ix.accounts[PAYER_TOKEN].pubkey = other_token);
+    assert_eq!(err, EscrowError::TokenOwnerMismatch.into());
+
+    let mut c = case();
+    let (other_mint, other_token) = (Pubkey::new_unique(), Pubkey::new_unique());
+    c.fx.bank.create_mint(&other_mint, 6);
+    let payer = c.payer;
+    c.fx.bank.create_token_account(&other_token, &other_mint, &payer, 1_000_000);
+    let err = c.init_with(|ix| ix.accounts[PAYER_TOKEN].pubkey = other_token);
+    assert_eq!(err, EscrowError::TokenMintMismatch.into());
+}
+
+#[test]
+fn payer_must_cover_amount_and_fees() {
+    // 985_222 plus its rounded-down fees (9_852 + 4_926) is exactly the balance ...
+    let mut c = case();
+    c.args.amount = 985_222;
+    assert_eq!(c.init().unwrap().total_amount, 1_000_000);
+
+    // ... but 985_223 needs 1_000_001.
+    let mut c = case();
+    c.args.amount = 985_223;
+    assert_eq!(c.init_with(|_| ()), EscrowError::InsufficientFunds.into());
+}
+
+#[test]
+fn amounts_that_overflow_are_refused() {
+    let mut c = case();
+    c.args.amount = u64::MAX;
+    assert_eq!(c.init_with(|_| ()), EscrowError::ArithmeticOverflow.into());
+}
+
+#[test]
+fn payment_hash_opens_one_escrow_only() {
+    let mut c = case();
+    c.init().unwrap();
+    let (payer, payer_token) = c.fx.funded_payer(1_000_000);
+    let args = InitArgs {
+        refund: payer,
+        ..c.args
+    };
+    let ix = c.fx.init_ix(&payer, &payer_token, args);
+    assert_eq!(c.fx.bank.process(&ix, &[&payer]), Err(EscrowError::AlreadyInitialized.into()));
+    assert_eq!(c.fx.bank.token_balance(&payer_token), 1_000_000);
+}
@@ -0,0 +1,127 @@
+//! SetLamportFee and WithdrawLamportFees: lamports reserved for open escrows, freed by a claim and handed back by a
+//! refund.
+
+mod common;
+
+use common::{signer, Fixture, Opened};
+use ln_usdt_escrow::{instruction, EscrowError};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey, rent::Rent};
+
+const PLATFORM_FEE: u64 = 5_000;
+const TRADE_FEE: u64 = 3_000;
+
+// Both configs in lamport fee mode, set through the program, with two escrows open against them.
+struct Case {
+    fx: Fixture,
+    claimed: Opened,
+    refunded: Opened,
+}
+
+fn case() -> Case {
+    let mut fx = Fixture::new(100, 50);
+    let (authority, config) = (fx.platform_authority, fx.config_key());
+    let ix = instruction::set_lamport_fee(&authority, &config, PLATFORM_FEE);
+    fx.bank.process(&ix, &[&authority]).expect("set lamport fee");
+    let (collector, trade_config) = (fx.trade_authority, fx.trade_config_key());
+    let ix = instruction::set_lamport_fee(&collector, &trade_config, TRADE_FEE);
+    fx.bank.process(&ix, &[&collector]).expect("set trade lamport fee");
+
+    let claimed = fx.open(&fx.config(), &[1u8; 32], &signer(), 100_000);
+    let refunded = fx.open(&fx.config(), &[2u8; 32], &signer(), 100_000);
+    Case { fx, claimed, refunded }
+}
+
+impl Case {
+    fn claim(&mut self) {
+        self.fx.claim(&self.claimed, [1u8; 32]);
+    }
+
+    fn refund(&mut self) {
+        let state = self.fx.escrow(&self.refunded.args.payment_hash);
+        self.fx.bank.set_clock(state.refund_after);
+        let ix = instruction::refund(&state, &self.refunded.payer_token);
+        self.fx.bank.process(&ix, &[&self.refunded.payer]).expect("refund");
+    }
+
+    // The config's lamports above rent exemption.
+    fn excess(&self, config: &Pubkey) -> u64 {
+        let account = self.fx.bank.account(config).unwrap();
+        account.lamports - Rent::default().minimum_balance(account.data.len())
+    }
+}
+
+fn withdraw(fx: &mut Fixture, collector: &Pubkey, config: &Pubkey, amount: u64) -> Result<(), ProgramError> {
+    let ix = instruction::withdraw_lamport_fees(collector, config, amount);
+    fx.bank.process(&ix, &[collector]).map(|_| ())
+}
+
+#[test]
+fn open_escrows_reserve_their_lamport_fees() {
+    let mut c = case();
+    let (authority, config) = (c.fx.platform_authority, c.fx.config_key());
+    let (collector, trade_config) = (c.fx.trade_authority, c.fx.trade_config_key());
+    assert_eq!(c.fx.config().lamport_fees.reserved, 2 * PLATFORM_FEE);
+    assert_eq!(c.fx.trade_config().lamport_fees.reserved, 2 * TRADE_FEE);
+    assert_eq!((c.excess(&config), c.excess(&trade_config)), (2 * PLATFORM_FEE, 2 * TRADE_FEE));
+
+    // Everything above rent is reserved, so there is nothing to withdraw, not even with amount = 0.
+    for amount in [0, 1, PLATFORM_FEE] {
+        let attempt = withdraw(&mut c.fx, &authority, &config, amount);
+        assert_eq!(attempt, Err(EscrowError::WithdrawExceedsLamportFees.into()), "amount {amount}");
+    }
+    assert_eq!(withdraw(&mut c.fx, &collector, &trade_config, 0), Err(EscrowError::WithdrawExceedsLamportFees.into()));
+    assert_eq!(withdraw(&mut c.fx, &signer(), &config, 0), Err(EscrowError::AuthorityMismatch.into()));
+    assert_eq!(c.excess(&config), 2 * PLATFORM_FEE);
+}
+
+#[test]
+fn a_claim_frees_its_lamport_fees_for_withdrawal() {
+    let mut c = case();
+    let (authority, config) = (c.fx.platform_authority, c.fx.config_key());
+    let (collector, trade_config) = (c.fx.trade_authority, c.fx.trade_config_key());
+    c.claim();
+    assert_eq!(c.fx.config().lamport_fees.reserved, PLATFORM_FEE);
+    assert_eq!(c.fx.trade_config().lamport_fees.reserved, TRADE_FEE);
+
+    // Only the claimed escrow's fee is withdrawable; the open one's stays put.
+    let attempt = withdraw(&mut c.fx, &authority, &config, PLATFORM_FEE + 1);
+    assert_eq!(attempt, Err(EscrowError::WithdrawExceedsLamportFees.into()));
+    let before = c.fx.bank.lamports(&authority);
+    withdraw(&mut c.fx, &authority, &config, PLATFORM_FEE).unwrap();
+    assert_eq!(c.fx.bank.lamports(&authority), before + PLATFORM_FEE);
+    assert_eq!(c.excess(&config), PLATFORM_FEE);
+    assert_eq!(withdraw(&mut c.fx, &authority, &config, 0), Err(EscrowError::WithdrawExceedsLamportFees.into()));
+
+    let before = c.fx.bank.lamports(&collector);
+    withdraw(&mut c.fx, &collector, &trade_config, 0).unwrap();
+    assert_eq!(c.fx.bank.lamports(&collector), before + TRADE_FEE);
+    assert_eq!(c.excess(&trade_config), TRADE_FEE);
+}
+
+#[test]
+fn a_refund_hands_its_lamport_fees_back() {
+    let mut c = case();
+    let (config, trade_config, payer) = (c.fx.config_key(), c.fx.trade_config_key(), c.refunded.payer);
+    let before = c.fx.bank.lamports(&payer);
+    c.refund();
+    assert_eq!(c.fx.bank.lamports(&payer), before + PLATFORM_FEE + TRADE_FEE);
+    assert_eq!(c.fx.config().lamport_fees.reserved, PLATFORM_FEE);
+    assert_eq!(c.fx.trade_config().lamport_fees.reserved, TRADE_FEE);
+    assert_eq!((c.excess(&config), c.excess(&trade_config)), (PLATFORM_FEE, TRADE_FEE));
+
+    // What the config still holds is the other escrow's reservation.
+    let authority = c.fx.platform_authority;
+    assert_eq!(withdraw(&mut c.fx, &authority, &config, 0), Err(EscrowError::WithdrawExceedsLamportFees.into()));
+}
+
+#[test]
+fn set_lamport_fee_is_for_the_config_authority() {
+    let mut fx = Fixture::new(100, 50);
+    let (authority, config, stranger) = (fx.platform_authority, fx.config_key(), signer());
+    fx.bank.airdrop(&stranger, 1_000_000_000);
+    let ix = instruction::set_lamport_fee(&stranger, &config, PLATFORM_FEE);
+    assert_eq!(fx.bank.process(&ix, &[&stranger]), Err(EscrowError::AuthorityMismatch.into()));
+    let ix = instruction::set_lamport_fee(&authority, &config, 100_000_001);
+    assert_eq!(fx.bank.process(&ix, &[&authority]), Err(EscrowError::FeeTooHigh.into()));
+    assert_eq!(fx.config().lamport_fees.per_escrow, 0);
+}
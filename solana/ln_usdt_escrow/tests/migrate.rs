@@ -0,0 +1,168 @@
+//! Accounts written under older layouts stay usable, and MigrateAccount moves them to the latest one.
+
+mod common;
+
+use borsh::BorshSerialize;
+use common::{signer, Account, Fixture};
+use ln_usdt_escrow::{
+    instruction, pda_for_hash, ConfigState, EscrowError, EscrowState, TradeConfigState, RESERVED_LEN,
+};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey, rent::Rent};
+use spl_associated_token_account::get_associated_token_address;
+
+// Rewrites a program account as `state` at `space` bytes, rent exempt at that size, the way an older program
+// version left it.
+fn rewrite<T: BorshSerialize>(fx: &mut Fixture, key: &Pubkey, state: &T, space: usize) {
+    let data = borsh::to_vec(state).unwrap();
+    assert_eq!(data.len(), space);
+    let account = Account {
+        lamports: Rent::default().minimum_balance(space),
+        data,
+        owner: ln_usdt_escrow::id(),
+        executable: false,
+    };
+    fx.bank.set_account(*key, account);
+}
+
+// Opens an escrow for 100_000 and rewrites it at version `v`, folding the fees `v` has no fields for into the
+// amount.
+fn open_escrow_at(fx: &mut Fixture, v: u8, preimage: &[u8; 32], recipient: &Pubkey) -> EscrowState {
+    let opened = fx.open(&fx.config(), preimage, recipient, 100_000);
+    let mut state = fx.escrow(&opened.args.payment_hash);
+    if v < EscrowState::V2 {
+        state.net_amount += state.platform_fee_amount;
+    }
+    if v < EscrowState::V3 {
+        state.net_amount += state.trade_fee_amount;
+    }
+    state.v = v;
+    rewrite(fx, &opened.escrow, &state, EscrowState::space(v));
+    fx.escrow(&opened.args.payment_hash)
+}
+
+fn migrate(fx: &mut Fixture, account: &Pubkey) -> (Result<(), ProgramError>, u64) {
+    let payer = signer();
+    fx.bank.airdrop(&payer, 1_000_000_000);
+    let result = fx.bank.process(&instruction::migrate_account(&payer, account), &[&payer]).map(|_| ());
+    (result, 1_000_000_000 - fx.bank.lamports(&payer))
+}
+
+#[test]
+fn v1_and_v2_escrows_decode_and_claim() {
+    let mut fx = Fixture::new(100, 50);
+    let recipient = signer();
+    let recipient_token = Pubkey::new_unique();
+    let mint = fx.mint;
+    fx.bank.create_token_account(&recipient_token, &mint, &recipient, 0);
+
+    let v1 = open_escrow_at(&mut fx, EscrowState::V1, &[1u8; 32], &recipient);
+    assert_eq!((v1.v, v1.net_amount, v1.platform_fee_amount, v1.trade_fee_collector), (1, 101_500, 0, [0u8; 32]));
+    let ix = instruction::claim(&v1, &recipient_token, [1u8; 32]);
+    fx.bank.process(&ix, &[&recipient]).expect("claim v1");
+    assert_eq!(fx.bank.token_balance(&recipient_token), 101_500);
+
+    let v2 = open_escrow_at(&mut fx, EscrowState::V2, &[2u8; 32], &recipient);
+    assert_eq!((v2.v, v2.net_amount, v2.platform_fee_amount, v2.trade_fee_amount), (2, 100_500, 1_000, 0));
+    let ix = instruction::claim(&v2, &recipient_token, [2u8; 32]);
+    fx.bank.process(&ix, &[&recipient]).expect("claim v2");
+    assert_eq!(fx.bank.token_balance(&recipient_token), 202_000);
+    let platform_fee_vault = get_associated_token_address(&fx.config_key(), &mint);
+    assert_eq!(fx.bank.token_balance(&platform_fee_vault), 1_000);
+}
+
+#[test]
+fn escrow_migrates_to_the_latest_layout() {
+    let mut fx = Fixture::new(100, 50);
+    let recipient = signer();
+    let old = open_escrow_at(&mut fx, EscrowState::V1, &[3u8; 32], &recipient);
+    let key = pda_for_hash(&ln_usdt_escrow::id(), &old.payment_hash).0;
+    let old_lamports = fx.bank.lamports(&key);
+
+    let (result, paid) = migrate(&mut fx, &key);
+    result.unwrap();
+    let space = EscrowState::space(EscrowState::LATEST);
+    assert_eq!(fx.bank.account(&key).unwrap().data.len(), space);
+    assert_eq!(fx.bank.lamports(&key), Rent::default().minimum_balance(space));
+    assert_eq!(paid, Rent::default().minimum_balance(space) - old_lamports);
+
+    let migrated = fx.escrow(&old.payment_hash);
+    assert_eq!(migrated.v, EscrowState::LATEST);
+    assert_eq!(migrated.reserved, [0u8; RESERVED_LEN]);
+    assert_eq!(borsh::to_vec(&EscrowState { v: old.v, ..migrated }).unwrap(), borsh::to_vec(&old).unwrap());
+
+    // A second migration has nothing to do.
+    let (result, paid) = migrate(&mut fx, &key);
+    result.unwrap();
+    assert_eq!(paid, 0);
+    assert_eq!(fx.bank.account(&key).unwrap().data.len(), space);
+}
+
+#[test]
+fn configs_migrate_to_the_latest_layout() {
+    let mut fx = Fixture::new(100, 50);
+    let (config_key, trade_config_key) = (fx.config_key(), fx.trade_config_key());
+    let config = ConfigState {
+        v: ConfigState::V1,
+        ..fx.config()
+    };
+    rewrite(&mut fx, &config_key, &config, ConfigState::space(ConfigState::V1));
+    let trade_config = TradeConfigState {
+        v: TradeConfigState::V1,
+        ..fx.trade_config()
+    };
+    rewrite(&mut fx, &trade_config_key, &trade_config, TradeConfigState::space(TradeConfigState::V1));
+
+    let (result, paid) = migrate(&mut fx, &config_key);
+    result.unwrap();
+    let space = ConfigState::space(ConfigState::LATEST);
+    assert_eq!(fx.bank.account(&config_key).unwrap().data.len(), space);
+    let rent = Rent::default();
+    assert_eq!(paid, rent.minimum_balance(space) - rent.minimum_balance(ConfigState::space(ConfigState::V1)));
+    let migrated = fx.config();
+    assert_eq!((migrated.v, migrated.fee_bps, migrated.authority), (ConfigState::LATEST, 100, config.authority));
+
+    let (result, _) = migrate(&mut fx, &trade_config_key);
+    result.unwrap();
+    let space = TradeConfigState::space(TradeConfigState::LATEST);
+    assert_eq!(fx.bank.account(&trade_config_key).unwrap().data.len(), space);
+    let migrated = fx.trade_config();
+    assert_eq!((migrated.v, migrated.fee_bps), (TradeConfigState::LATEST, 50));
+    assert_eq!(migrated.fee_collector, trade_config.fee_collector);
+}
+
+#[test]
+fn reserved_lamport_fees_do_not_pay_for_the_realloc() {
+    let mut fx = Fixture::new(100, 50);
+    let config_key = fx.config_key();
+    let mut config = ConfigState {
+        v: ConfigState::V9,
+        ..fx.config()
+    };
+    config.lamport_fees.reserved = 5_000;
+    rewrite(&mut fx, &config_key, &config, ConfigState::space(ConfigState::V9));
+    let rent = Rent::default();
+    let old_rent = rent.minimum_balance(ConfigState::space(ConfigState::V9));
+    fx.bank.airdrop(&config_key, 5_000);
+
+    let (result, paid) = migrate(&mut fx, &config_key);
+    result.unwrap();
+    let new_rent = rent.minimum_balance(ConfigState::space(ConfigState::LATEST));
+    assert_eq!(paid, new_rent - old_rent);
+    assert_eq!(fx.bank.lamports(&config_key), new_rent + 5_000);
+    assert_eq!(fx.config().lamport_fees.reserved, 5_000);
+}
+
+#[test]
+fn only_program_state_accounts_migrate() {
+    let mut fx = Fixture::new(100, 50);
+    let stranger = signer();
+    fx.bank.airdrop(&stranger, 1_000_000);
+    assert_eq!(migrate(&mut fx, &stranger).0, Err(EscrowError::InvalidAccountOwner.into()));
+
+    // A state account copied to an address that is not its PDA is not recognised.
+    let config_key = fx.config_key();
+    let copy = Pubkey::new_unique();
+    let config = fx.bank.account(&config_key).unwrap().clone();
+    fx.bank.set_account(copy, config);
+    assert!(migrate(&mut fx, &copy).0.is_err());
+}
@@ -0,0 +1,340 @@
+//! Refund: the timelock, what goes back to the refund authority, and every check it can reject on.
+
+mod common;
+
+use common::{signer, to_v1, Account, Fixture, Opened};
+use ln_usdt_escrow::{
+    instruction,
+    return_data::{EscrowOutcome, STATUS_REFUNDED},
+    EscrowError, EscrowState,
+};
+use solana_program::{instruction::Instruction, program_error::ProgramError, pubkey::Pubkey, sysvar};
+use spl_associated_token_account::get_associated_token_address;
+
+// Account indices of a v2 Refund on an escrow without lamport fees.
+const REFUND: usize = 0;
+const ESCROW: usize = 1;
+const VAULT: usize = 2;
+const REFUND_TOKEN: usize = 3;
+const TOKEN_PROGRAM: usize = 4;
+const PLATFORM_STATS: usize = 5;
+const TRADE_STATS: usize = 6;
+
+const PREIMAGE: [u8; 32] = [1u8; 32];
+
+// An active 100_000 escrow at 1% platform and 0.5% trade fee whose refund time has just come.
+struct Case {
+    fx: Fixture,
+    opened: Opened,
+}
+
+fn case() -> Case {
+    case_with(|_| ())
+}
+
+// Opens the escrow after `configure` has changed the fixture, then moves the clock to its refund time.
+fn case_with(configure: impl FnOnce(&mut Fixture)) -> Case {
+    let mut fx = Fixture::new(100, 50);
+    configure(&mut fx);
+    let opened = fx.open(&fx.config(), &PREIMAGE, &signer(), 100_000);
+    fx.bank.set_clock(opened.args.refund_after);
+    Case { fx, opened }
+}
+
+impl Case {
+    fn state(&self) -> EscrowState {
+        self.fx.escrow(&self.opened.args.payment_hash)
+    }
+
+    fn ix(&self) -> Instruction {
+        instruction::refund(&self.state(), &self.opened.payer_token)
+    }
+
+    fn process(&mut self, ix: &Instruction, signer: &Pubkey) -> Result<EscrowOutcome, ProgramError> {
+        let (_, data) = self.fx.bank.process(ix, &[signer])?.expect("return data");
+        Ok(EscrowOutcome::from_return_data(&data).expect("escrow outcome"))
+    }
+
+    fn refund(&mut self) -> Result<EscrowOutcome, ProgramError> {
+        let (ix, payer) = (self.ix(), self.opened.payer);
+        self.process(&ix, &payer)
+    }
+
+    // Runs Refund with the instruction changed by `edit`; it must fail with the vault untouched.
+    fn refund_with(&mut self, edit: impl FnOnce(&mut Instruction)) -> ProgramError {
+        let mut ix = self.ix();
+        edit(&mut ix);
+        self.reject(&ix)
+    }
+
+    // Builds Refund from the escrow as it stands, then lets `corrupt` break the accounts before it runs.
+    fn refund_after(&mut self, corrupt: impl FnOnce(&mut Fixture)) -> ProgramError {
+        let ix = self.ix();
+        corrupt(&mut self.fx);
+        self.reject(&ix)
+    }
+
+    fn reject(&mut self, ix: &Instruction) -> ProgramError {
+        let (before, payer) = (self.fx.bank.token_balance(&self.opened.vault), self.opened.payer);
+        let err = self.process(ix, &payer).unwrap_err();
+        assert_eq!(self.fx.bank.token_balance(&self.opened.vault), before);
+        err
+    }
+
+    fn claim(&mut self) -> Result<EscrowOutcome, ProgramError> {
+        let recipient = self.opened.args.recipient;
+        let mint = self.fx.mint;
+        let recipient_token = get_associated_token_address(&recipient, &mint);
+        self.fx.bank.create_token_account(&recipient_token, &mint, &recipient, 0);
+        let ix = instruction::claim(&self.state(), &recipient_token, PREIMAGE);
+        self.process(&ix, &recipient)
+    }
+}
+
+#[test]
+fn returns_amount_and_fees_to_the_refund_authority() {
+    let mut c = case();
+    let outcome = c.refund().unwrap();
+    assert_eq!(
+        outcome,
+        EscrowOutcome {
+            escrow: c.opened.escrow,
+            vault: c.opened.vault,
+            status: STATUS_REFUNDED,
+            total_amount: 101_500,
+            net_amount: 101_500,
+            platform_fee_amount: 0,
+            trade_fee_amount: 0,
+            referral_fee_amount: 0,
+        }
+    );
+    assert_eq!(c.fx.bank.token_balance(&c.opened.payer_token), 10_000_000);
+    assert_eq!(c.fx.bank.token_balance(&c.opened.vault), 0);
+
+    let state = c.state();
+    assert_eq!(state.status, EscrowState::STATUS_REFUNDED);
+    assert_eq!((state.net_amount, state.platform_fee_amount, state.trade_fee_amount), (0, 0, 0));
+
+    for config in [c.fx.config_key(), c.fx.trade_config_key()] {
+        let stats = c.fx.mint_stats(&config).unwrap();
+        assert_eq!((stats.active_escrows, stats.refunded_escrows, stats.fees_accrued), (0, 1, 0));
+    }
+}
+
+#[test]
+fn refund_waits_for_the_refund_time() {
+    let mut c = case();
+    let refund_after = c.opened.args.refund_after;
+    c.fx.bank.set_clock(refund_after - 1);
+    assert_eq!(c.refund_with(|_| ()), EscrowError::TooEarly.into());
+    c.fx.bank.set_clock(refund_after);
+    assert_eq!(c.refund().unwrap().total_amount, 101_500);
+}
+
+#[test]
+fn an_escrow_settles_once() {
+    let mut c = case();
+    c.refund().unwrap();
+    assert_eq!(c.refund(), Err(EscrowError::NotActive.into()));
+    assert_eq!(c.claim(), Err(EscrowError::NotActive.into()));
+    assert_eq!(c.fx.bank.token_balance(&c.opened.payer_token), 10_000_000);
+}
+
+#[test]
+fn v1_refund_reads_the_clock_sysvar() {
+    const CLOCK: usize = 5;
+    let mut c = case();
+    let refund_after = c.opened.args.refund_after;
+    let (ix, payer) = (to_v1(&c.ix(), Some((CLOCK, sysvar::clock::id()))), c.opened.payer);
+
+    c.fx.bank.set_clock(refund_after - 1);
+    assert_eq!(c.process(&ix, &payer), Err(EscrowError::TooEarly.into()));
+    let mut wrong_sysvar = ix.clone();
+    wrong_sysvar.accounts[CLOCK].pubkey = sysvar::rent::id();
+    c.fx.bank.set_clock(refund_after);
+    assert_eq!(c.process(&wrong_sysvar, &payer), Err(EscrowError::InvalidSysvar.into()));
+    assert_eq!(c.process(&ix, &payer).unwrap().total_amount, 101_500);
+}
+
+#[test]
+fn refunds_without_mint_stats_leave_them_as_they_were() {
+    let mut c = case();
+    let mut ix = c.ix();
+    ix.accounts.truncate(PLATFORM_STATS);
+    let payer = c.opened.payer;
+    assert_eq!(c.process(&ix, &payer).unwrap().total_amount, 101_500);
+    for config in [c.fx.config_key(), c.fx.trade_config_key()] {
+        let stats = c.fx.mint_stats(&config).unwrap();
+        assert_eq!((stats.active_escrows, stats.refunded_escrows), (1, 0));
+    }
+}
+
+#[test]
+fn lamport_fees_go_back_to_the_refund_authority() {
+    const CONFIG: usize = 5;
+    const TRADE_CONFIG: usize = 6;
+    let lamport_case = || {
+        case_with(|fx| {
+            let mut config = fx.config();
+            config.lamport_fees.per_escrow = 5_000;
+            fx.write_config(&config);
+            let mut trade_config = fx.trade_config();
+            trade_config.lamport_fees.per_escrow = 3_000;
+            fx.write_trade_config(&trade_config);
+        })
+    };
+
+    let mut c = lamport_case();
+    let (config, trade_config, payer) = (c.fx.config_key(), c.fx.trade_config_key(), c.opened.payer);
+    let lamports = [config, trade_config, payer].map(|key| c.fx.bank.lamports(&key));
+    assert!(c.ix().accounts[REFUND].is_writable);
+    assert_eq!(c.refund().unwrap().total_amount, 100_000);
+    assert_eq!(
+        [config, trade_config, payer].map(|key| c.fx.bank.lamports(&key)),
+        [lamports[0] - 5_000, lamports[1] - 3_000, lamports[2] + 8_000]
+    );
+    assert_eq!((c.fx.config().lamport_fees.reserved, c.fx.trade_config().lamport_fees.reserved), (0, 0));
+
+    let mut c = lamport_case();
+    let err = c.refund_with(|ix| ix.accounts[REFUND].is_writable = false);
+    assert_eq!(err, EscrowError::AccountNotWritable.into());
+
+    for (index, wrong_key) in [
+        (CONFIG, EscrowError::InvalidConfigPda),
+        (TRADE_CONFIG, EscrowError::InvalidTradeConfigPda),
+    ] {
+        let mut c = lamport_case();
+        let err = c.refund_with(|ix| ix.accounts[index].pubkey = Pubkey::new_unique());
+        assert_eq!(err, wrong_key.into(), "account {index}");
+
+        let mut c = lamport_case();
+        let err = c.refund_with(|ix| ix.accounts[index].is_writable = false);
+        assert_eq!(err, EscrowError::AccountNotWritable.into(), "account {index}");
+    }
+}
+
+#[test]
+fn needs_every_account() {
+    let mut c = case();
+    assert_eq!(c.refund_with(|ix| ix.accounts.truncate(TOKEN_PROGRAM)), ProgramError::NotEnoughAccountKeys);
+    assert_eq!(c.refund_with(|ix| ix.accounts.truncate(TRADE_STATS)), ProgramError::NotEnoughAccountKeys);
+}
+
+#[test]
+fn only_the_refund_authority_can_refund() {
+    let mut c = case();
+    assert_eq!(c.refund_with(|ix| ix.accounts[REFUND].is_signer = false), EscrowError::InvalidSigner.into());
+
+    // The recipient, refunding into their own token account.
+    let recipient = c.opened.args.recipient;
+    let (recipient_token, mint) = (Pubkey::new_unique(), c.fx.mint);
+    c.fx.bank.create_token_account(&recipient_token, &mint, &recipient, 0);
+    let mut ix = c.ix();
+    ix.accounts[REFUND].pubkey = recipient;
+    ix.accounts[REFUND_TOKEN].pubkey = recipient_token;
+    assert_eq!(c.process(&ix, &recipient), Err(EscrowError::RefundMismatch.into()));
+}
+
+#[test]
+fn written_accounts_must_be_writable() {
+    for index in [ESCROW, VAULT, REFUND_TOKEN, PLATFORM_STATS, TRADE_STATS] {
+        let mut c = case();
+        let err = c.refund_with(|ix| ix.accounts[index].is_writable = false);
+        assert_eq!(err, EscrowError::AccountNotWritable.into(), "account {index}");
+    }
+}
+
+#[test]
+fn token_program_must_be_the_real_one() {
+    let mut c = case();
+    let err = c.refund_with(|ix| ix.accounts[TOKEN_PROGRAM].pubkey = Pubkey::new_unique());
+    assert_eq!(err, EscrowError::InvalidTokenProgram.into());
+}
+
+#[test]
+fn escrow_must_be_a_program_escrow() {
+    let mut c = case();
+    let escrow = c.opened.escrow;
+    let err = c.refund_after(|fx| {
+        let account = Account {
+            owner: Pubkey::new_unique(),
+            ..fx.bank.account(&escrow).unwrap().clone()
+        };
+        fx.bank.set_account(escrow, account);
+    });
+    assert_eq!(err, EscrowError::InvalidAccountOwner.into());
+
+    let mut c = case();
+    let escrow = c.opened.escrow;
+    let err = c.refund_after(|fx| fx.bank.set_data(&escrow, vec![0xff; 16]));
+    assert_eq!(err, EscrowError::InvalidEscrowState.into());
+}
+
+#[test]
+fn escrow_must_be_the_payment_hash_pda() {
+    let mut c = case();
+    let copy = Pubkey::new_unique();
+    let account = c.fx.bank.account(&c.opened.escrow).unwrap().clone();
+    c.fx.bank.set_account(copy, account);
+    assert_eq!(c.refund_with(|ix| ix.accounts[ESCROW].pubkey = copy), EscrowError::InvalidEscrowPda.into());
+
+    let mut c = case();
+    let mut state = c.state();
+    state.bump = state.bump.wrapping_add(1);
+    let escrow = c.opened.escrow;
+    c.fx.bank.set_data(&escrow, borsh::to_vec(&state).unwrap());
+    assert_eq!(c.refund_with(|_| ()), EscrowError::InvalidEscrowPda.into());
+}
+
+#[test]
+fn vault_must_be_the_escrows_own() {
+    let mut c = case();
+    let other = c.fx.open(&c.fx.config(), &[9u8; 32], &Pubkey::new_unique(), 1_000).vault;
+    assert_eq!(c.refund_with(|ix| ix.accounts[VAULT].pubkey = other), EscrowError::InvalidVaultAta.into());
+
+    let mut c = case();
+    let (vault, mint) = (c.opened.vault, c.fx.mint);
+    c.fx.bank.create_token_account(&vault, &mint, &Pubkey::new_unique(), 101_500);
+    assert_eq!(c.refund_with(|_| ()), EscrowError::VaultAuthorityMismatch.into());
+
+    let mut c = case();
+    let (vault, escrow, other_mint) = (c.opened.vault, c.opened.escrow, Pubkey::new_unique());
+    c.fx.bank.create_mint(&other_mint, 6);
+    c.fx.bank.create_token_account(&vault, &other_mint, &escrow, 101_500);
+    assert_eq!(c.refund_with(|_| ()), EscrowError::TokenMintMismatch.into());
+}
+
+#[test]
+fn refund_token_account_must_be_the_authoritys_in_the_mint() {
+    let mut c = case();
+    let wallet = c.opened.payer;
+    let err = c.refund_with(|ix| ix.accounts[REFUND_TOKEN].pubkey = wallet);
+    assert_eq!(err, EscrowError::InvalidTokenAccountData.into());
+
+    let mut c = case();
+    let (other, mint) = (Pubkey::new_unique(), c.fx.mint);
+    c.fx.bank.create_token_account(&other, &mint, &Pubkey::new_unique(), 0);
+    let err = c.refund_with(|ix| ix.accounts[REFUND_TOKEN].pubkey = other);
+    assert_eq!(err, EscrowError::TokenOwnerMismatch.into());
+
+    let mut c = case();
+    let (other, other_mint, payer) = (Pubkey::new_unique(), Pubkey::new_unique(), c.opened.payer);
+    c.fx.bank.create_mint(&other_mint, 6);
+    c.fx.bank.create_token_account(&other, &other_mint, &payer, 0);
+    let err = c.refund_with(|ix| ix.accounts[REFUND_TOKEN].pubkey = other);
+    assert_eq!(err, EscrowError::TokenMintMismatch.into());
+}
+
+#[test]
+fn mint_stats_must_be_the_config_pdas() {
+    for index in [PLATFORM_STATS, TRADE_STATS] {
+        let mut c = case();
+        let err = c.refund_with(|ix| ix.accounts[index].pubkey = Pubkey::new_unique());
+        assert_eq!(err, EscrowError::InvalidMintStatsPda.into(), "account {index}");
+
+        let mut c = case();
+        let other = c.ix().accounts[PLATFORM_STATS + TRADE_STATS - index].pubkey;
+        let err = c.refund_with(|ix| ix.accounts[index].pubkey = other);
+        assert_eq!(err, EscrowError::InvalidMintStatsPda.into(), "account {index}");
+    }
+}
@@ -0,0 +1,89 @@
+//! SetRevenueSplit and DistributeFees: pro rata shares, the rounding dust and the beneficiary checks.
+
+mod common;
+
+use common::Fixture;
+use ln_usdt_escrow::{instruction, EscrowError, RevenueBeneficiary};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+use spl_associated_token_account::get_associated_token_address;
+
+fn split(weights: &[(Pubkey, u16)]) -> Vec<RevenueBeneficiary> {
+    weights
+        .iter()
+        .map(|(owner, weight_bps)| RevenueBeneficiary {
+            owner: owner.to_bytes(),
+            weight_bps: *weight_bps,
+        })
+        .collect()
+}
+
+// The default config split three ways at 3333/3333/3334 bps, with 1_000 of fees in its vault and an ATA for each
+// beneficiary.
+fn case() -> (Fixture, [Pubkey; 3]) {
+    let mut fx = Fixture::new(100, 50);
+    fx.open_and_claim([1u8; 32], 100_000);
+    let owners = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+    let (authority, config, mint) = (fx.platform_authority, fx.config_key(), fx.mint);
+    let beneficiaries = split(&[(owners[0], 3_333), (owners[1], 3_333), (owners[2], 3_334)]);
+    let ix = instruction::set_revenue_split(&authority, &config, beneficiaries, None);
+    fx.bank.process(&ix, &[&authority]).expect("set revenue split");
+    let tokens = owners.map(|owner| get_associated_token_address(&owner, &mint));
+    for (owner, token) in owners.iter().zip(&tokens) {
+        fx.bank.create_token_account(token, &mint, owner, 0);
+    }
+    (fx, tokens)
+}
+
+fn fee_vault(fx: &Fixture) -> Pubkey {
+    get_associated_token_address(&fx.config_key(), &fx.mint)
+}
+
+#[test]
+fn distribution_is_pro_rata_with_the_dust_to_the_first_beneficiary() {
+    let (mut fx, tokens) = case();
+    // Nobody signs: the destinations are pinned by the split.
+    let ix = instruction::distribute_fees(&fx.config(), &fx.mint);
+    fx.bank.process(&ix, &[]).unwrap();
+    assert_eq!(tokens.map(|t| fx.bank.token_balance(&t)), [334, 333, 333]);
+    assert_eq!(fx.bank.token_balance(&fee_vault(&fx)), 0);
+    let stats = fx.mint_stats(&fx.config_key()).unwrap();
+    assert_eq!((stats.fees_accrued, stats.fees_withdrawn), (1_000, 1_000));
+
+    // Too little for anyone's share: it all goes to the first beneficiary.
+    fx.open_and_claim([2u8; 32], 200);
+    assert_eq!(fx.bank.token_balance(&fee_vault(&fx)), 2);
+    let ix = instruction::distribute_fees(&fx.config(), &fx.mint);
+    fx.bank.process(&ix, &[]).unwrap();
+    assert_eq!(tokens.map(|t| fx.bank.token_balance(&t)), [336, 333, 333]);
+}
+
+#[test]
+fn distribution_pays_the_beneficiaries_atas_in_split_order() {
+    let (mut fx, tokens) = case();
+    let ix = instruction::distribute_fees(&fx.config(), &fx.mint);
+
+    let mut swapped = ix.clone();
+    swapped.accounts.swap(4, 5);
+    assert_eq!(fx.bank.process(&swapped, &[]), Err(EscrowError::InvalidBeneficiaryAta.into()));
+    let mut missing = ix.clone();
+    missing.accounts.pop();
+    assert_eq!(fx.bank.process(&missing, &[]), Err(ProgramError::NotEnoughAccountKeys));
+    assert_eq!(fx.bank.token_balance(&fee_vault(&fx)), 1_000);
+    assert_eq!(tokens.map(|t| fx.bank.token_balance(&t)), [0, 0, 0]);
+}
+
+#[test]
+fn split_weights_must_add_up_to_the_whole() {
+    let mut fx = Fixture::new(100, 50);
+    let (authority, config) = (fx.platform_authority, fx.config_key());
+    let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+    for weights in [vec![(a, 5_000), (b, 4_999)], vec![(a, 5_000), (a, 5_000)], vec![(a, 10_000), (b, 0)]] {
+        let ix = instruction::set_revenue_split(&authority, &config, split(&weights), None);
+        assert_eq!(fx.bank.process(&ix, &[&authority]), Err(EscrowError::InvalidRevenueSplit.into()));
+    }
+    assert_eq!(fx.config().revenue_split.count, 0);
+
+    // Without a split there is nothing to distribute.
+    let ix = instruction::distribute_fees(&fx.config(), &fx.mint);
+    assert_eq!(fx.bank.process(&ix, &[]), Err(EscrowError::InvalidRevenueSplit.into()));
+}
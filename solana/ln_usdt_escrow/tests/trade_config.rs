@@ -0,0 +1,496 @@
+//! InitTradeConfig, SetTradeConfig, WithdrawTradeFees and WithdrawTradeFeesBatch, and every check they can reject on.
+
+mod common;
+
+use common::{signer, to_v1, Account, Fixture};
+use ln_usdt_escrow::{
+    audit_log_pda, instruction, mint_stats_pda, trade_config_pda, EscrowError, MintStatsState, TradeConfigState,
+    TreasuryState,
+};
+use solana_program::{
+    instruction::Instruction, program_error::ProgramError, pubkey::Pubkey, rent::Rent, sysvar,
+};
+use spl_associated_token_account::get_associated_token_address;
+
+// Account indices of InitTradeConfig.
+const PAYER: usize = 0;
+const TRADE_CONFIG: usize = 1;
+const SYSTEM_PROGRAM: usize = 2;
+
+// Account indices of SetTradeConfig.
+const AUDIT_LOG: usize = 2;
+const SET_SYSTEM_PROGRAM: usize = 3;
+
+// Account indices of WithdrawTradeFees.
+const FEE_VAULT: usize = 2;
+const DESTINATION: usize = 3;
+const TOKEN_PROGRAM: usize = 4;
+const MINT_STATS: usize = 5;
+
+fn process(fx: &mut Fixture, ix: &Instruction, signer: &Pubkey) -> Result<(), ProgramError> {
+    fx.bank.process(ix, &[signer]).map(|_| ())
+}
+
+// A fresh collector about to create its trade config at 2%.
+fn collector_case() -> (Fixture, Pubkey, Instruction) {
+    let mut fx = Fixture::new(100, 50);
+    let collector = signer();
+    fx.bank.airdrop(&collector, 1_000_000_000);
+    let ix = instruction::init_trade_config(&collector, collector, 200);
+    (fx, collector, ix)
+}
+
+fn init_trade_config_with(edit: impl FnOnce(&mut Instruction)) -> ProgramError {
+    let (mut fx, collector, mut ix) = collector_case();
+    edit(&mut ix);
+    let err = process(&mut fx, &ix, &collector).unwrap_err();
+    assert!(fx.bank.account(&trade_config_pda(&ln_usdt_escrow::id(), &collector).0).is_none());
+    err
+}
+
+#[test]
+fn init_trade_config_creates_the_collectors_config() {
+    let (mut fx, collector, ix) = collector_case();
+    process(&mut fx, &ix, &collector).unwrap();
+    let (key, bump) = trade_config_pda(&ln_usdt_escrow::id(), &collector);
+    let account = fx.bank.account(&key).unwrap();
+    assert_eq!(account.owner, ln_usdt_escrow::id());
+    assert_eq!(account.data.len(), TradeConfigState::space(TradeConfigState::LATEST));
+    assert_eq!(account.lamports, Rent::default().minimum_balance(account.data.len()));
+
+    let state = TradeConfigState::unpack(&account.data).unwrap();
+    assert_eq!((state.v, state.fee_bps, state.bump), (TradeConfigState::LATEST, 200, bump));
+    assert_eq!([state.authority, state.fee_collector], [collector.to_bytes(); 2]);
+    assert!(!state.treasury.enabled() && !state.direct_payout);
+    assert_eq!((state.lamport_fees.per_escrow, state.lock_surcharge_bps_per_hour), (0, 0));
+
+    assert_eq!(process(&mut fx, &ix, &collector), Err(EscrowError::AlreadyInitialized.into()));
+}
+
+#[test]
+fn v1_init_trade_config_takes_the_rent_sysvar() {
+    let err = init_trade_config_with(|ix| *ix = to_v1(ix, Some((3, sysvar::clock::id()))));
+    assert_eq!(err, EscrowError::InvalidSysvar.into());
+
+    let (mut fx, collector, ix) = collector_case();
+    process(&mut fx, &to_v1(&ix, Some((3, sysvar::rent::id()))), &collector).unwrap();
+    assert!(fx.bank.account(&trade_config_pda(&ln_usdt_escrow::id(), &collector).0).is_some());
+}
+
+#[test]
+fn init_trade_config_rejects_bad_accounts() {
+    let err = init_trade_config_with(|ix| ix.accounts.truncate(SYSTEM_PROGRAM));
+    assert_eq!(err, ProgramError::NotEnoughAccountKeys);
+    let err = init_trade_config_with(|ix| ix.accounts[PAYER].is_signer = false);
+    assert_eq!(err, EscrowError::InvalidSigner.into());
+    for index in [PAYER, TRADE_CONFIG] {
+        let err = init_trade_config_with(|ix| ix.accounts[index].is_writable = false);
+        assert_eq!(err, EscrowError::AccountNotWritable.into(), "account {index}");
+    }
+    let err = init_trade_config_with(|ix| ix.accounts[SYSTEM_PROGRAM].pubkey = Pubkey::new_unique());
+    assert_eq!(err, EscrowError::InvalidSystemProgram.into());
+    let err = init_trade_config_with(|ix| ix.accounts[TRADE_CONFIG].pubkey = Pubkey::new_unique());
+    assert_eq!(err, EscrowError::InvalidTradeConfigPda.into());
+}
+
+#[test]
+fn init_trade_config_rejects_bad_arguments() {
+    let rebuild = |fee_collector: Option<Pubkey>, fee_bps: u16| {
+        move |ix: &mut Instruction| {
+            let payer = ix.accounts[PAYER].pubkey;
+            let mut rebuilt = instruction::init_trade_config(&payer, fee_collector.unwrap_or(payer), fee_bps);
+            rebuilt.accounts[TRADE_CONFIG] = ix.accounts[TRADE_CONFIG].clone();
+            *ix = rebuilt;
+        }
+    };
+    assert_eq!(init_trade_config_with(rebuild(None, 1_001)), EscrowError::FeeTooHigh.into());
+    let err = init_trade_config_with(rebuild(Some(Pubkey::new_unique()), 200));
+    assert_eq!(err, EscrowError::FeeCollectorMismatch.into());
+
+    // The cap itself is allowed.
+    let (mut fx, collector, _) = collector_case();
+    process(&mut fx, &instruction::init_trade_config(&collector, collector, 1_000), &collector).unwrap();
+}
+
+// The fixture's trade collector about to change its fee from 0.5% to 2%.
+struct SetCase {
+    fx: Fixture,
+    authority: Pubkey,
+    trade_config: Pubkey,
+}
+
+fn set_case() -> SetCase {
+    let fx = Fixture::new(100, 50);
+    let (authority, trade_config) = (fx.trade_authority, fx.trade_config_key());
+    SetCase {
+        fx,
+        authority,
+        trade_config,
+    }
+}
+
+impl SetCase {
+    fn ix(&self, fee_bps: u16) -> Instruction {
+        instruction::set_trade_config(&self.authority, &self.trade_config, self.authority, fee_bps)
+    }
+
+    fn set(&mut self, fee_bps: u16) -> Result<(), ProgramError> {
+        let (ix, authority) = (self.ix(fee_bps), self.authority);
+        process(&mut self.fx, &ix, &authority)
+    }
+
+    // Runs SetTradeConfig(200) changed by `edit`; it must fail with the trade config untouched.
+    fn set_with(&mut self, edit: impl FnOnce(&mut Instruction)) -> ProgramError {
+        let mut ix = self.ix(200);
+        edit(&mut ix);
+        let authority = ix.accounts[PAYER].pubkey;
+        let err = process(&mut self.fx, &ix, &authority).unwrap_err();
+        assert_eq!(self.fx.trade_config().fee_bps, 50);
+        err
+    }
+}
+
+#[test]
+fn set_trade_config_changes_the_fee_and_logs_it() {
+    let mut c = set_case();
+    c.fx.bank.set_clock(1_700_000_000);
+    c.set(200).unwrap();
+    assert_eq!(c.fx.trade_config().fee_bps, 200);
+    let log = c.fx.audit_log(&c.trade_config).unwrap();
+    assert_eq!((log.config, log.total_changes), (c.trade_config.to_bytes(), 1));
+    let change = log.entries[0];
+    assert_eq!((change.unix_timestamp, change.signer), (1_700_000_000, c.authority.to_bytes()));
+    assert_eq!((change.old_fee_bps, change.new_fee_bps), (50, 200));
+
+    // Open escrows keep the fee they were opened with.
+    let opened = c.fx.open(&c.fx.config(), &[1u8; 32], &Pubkey::new_unique(), 100_000);
+    c.set(1_000).unwrap();
+    let state = c.fx.escrow(&opened.args.payment_hash);
+    assert_eq!((state.trade_fee_bps, state.trade_fee_amount), (200, 2_000));
+    assert_eq!(c.fx.audit_log(&c.trade_config).unwrap().total_changes, 2);
+}
+
+#[test]
+fn clients_that_predate_the_audit_log_change_the_trade_config_unlogged() {
+    let mut c = set_case();
+    let mut ix = c.ix(200);
+    ix.accounts.truncate(AUDIT_LOG);
+    ix.accounts[PAYER].is_writable = false;
+    process(&mut c.fx, &ix, &c.authority).unwrap();
+    assert_eq!(c.fx.trade_config().fee_bps, 200);
+    assert!(c.fx.audit_log(&c.trade_config).is_none());
+}
+
+#[test]
+fn set_trade_config_rejects_bad_accounts() {
+    let mut c = set_case();
+    assert_eq!(c.set_with(|ix| ix.accounts.truncate(SET_SYSTEM_PROGRAM)), ProgramError::NotEnoughAccountKeys);
+    assert_eq!(c.set_with(|ix| ix.accounts[PAYER].is_signer = false), EscrowError::InvalidSigner.into());
+    let err = c.set_with(|ix| ix.accounts[TRADE_CONFIG].is_writable = false);
+    assert_eq!(err, EscrowError::AccountNotWritable.into());
+    let err = c.set_with(|ix| ix.accounts[SET_SYSTEM_PROGRAM].pubkey = Pubkey::new_unique());
+    assert_eq!(err, EscrowError::InvalidSystemProgram.into());
+    // The platform config, which the program owns too.
+    let platform_config = c.fx.config_key();
+    let err = c.set_with(|ix| ix.accounts[TRADE_CONFIG].pubkey = platform_config);
+    assert_eq!(err, EscrowError::InvalidTradeConfigPda.into());
+
+    let err = c.set_with(|ix| ix.accounts[AUDIT_LOG].pubkey = Pubkey::new_unique());
+    assert_eq!(err, EscrowError::InvalidAuditLogPda.into());
+    // The platform config's log is not the trade config's.
+    let platform_log = audit_log_pda(&ln_usdt_escrow::id(), &c.fx.config_key()).0;
+    let err = c.set_with(|ix| ix.accounts[AUDIT_LOG].pubkey = platform_log);
+    assert_eq!(err, EscrowError::InvalidAuditLogPda.into());
+    assert_eq!(c.set_with(|ix| ix.accounts[AUDIT_LOG].is_writable = false), EscrowError::AccountNotWritable.into());
+    assert_eq!(c.set_with(|ix| ix.accounts[PAYER].is_writable = false), EscrowError::AccountNotWritable.into());
+}
+
+#[test]
+fn set_trade_config_rejects_bad_arguments() {
+    let mut c = set_case();
+    let (authority, trade_config) = (c.authority, c.trade_config);
+    let err = c.set_with(|ix| *ix = instruction::set_trade_config(&authority, &trade_config, authority, 1_001));
+    assert_eq!(err, EscrowError::FeeTooHigh.into());
+    let other = Pubkey::new_unique();
+    let err = c.set_with(|ix| *ix = instruction::set_trade_config(&authority, &trade_config, other, 200));
+    assert_eq!(err, EscrowError::FeeCollectorMismatch.into());
+    c.set(1_000).unwrap();
+}
+
+#[test]
+fn only_the_authority_sets_the_trade_config() {
+    // A stranger naming itself as collector is pointed at its own (missing) PDA, not this one.
+    let mut c = set_case();
+    let (stranger, trade_config) = (signer(), c.trade_config);
+    c.fx.bank.airdrop(&stranger, 1_000_000_000);
+    let err = c.set_with(|ix| *ix = instruction::set_trade_config(&stranger, &trade_config, stranger, 200));
+    assert_eq!(err, EscrowError::InvalidTradeConfigPda.into());
+
+    // A trade config whose authority is no longer its collector.
+    let mut c = set_case();
+    let mut state = c.fx.trade_config();
+    state.authority = Pubkey::new_unique().to_bytes();
+    c.fx.write_trade_config(&state);
+    assert_eq!(c.set_with(|_| ()), EscrowError::AuthorityMismatch.into());
+}
+
+#[test]
+fn set_trade_config_needs_a_valid_trade_config() {
+    let mut c = set_case();
+    let account = Account {
+        owner: Pubkey::new_unique(),
+        ..c.fx.bank.account(&c.trade_config).unwrap().clone()
+    };
+    c.fx.bank.set_account(c.trade_config, account);
+    assert_eq!(c.set(200), Err(EscrowError::InvalidAccountOwner.into()));
+
+    let mut c = set_case();
+    let trade_config = c.trade_config;
+    c.fx.bank.set_data(&trade_config, vec![0xff; 16]);
+    assert_eq!(c.set(200), Err(EscrowError::InvalidTradeConfigState.into()));
+
+    let edits: [fn(&mut TradeConfigState); 2] = [|s| s.bump = s.bump.wrapping_add(1), |s| s.v = 200];
+    for edit in edits {
+        let mut c = set_case();
+        let mut state = c.fx.trade_config();
+        edit(&mut state);
+        c.fx.write_trade_config(&state);
+        let trade_config = c.trade_config;
+        let data = c.fx.bank.account(&trade_config).unwrap().data.clone();
+        assert_eq!(c.set(200), Err(EscrowError::InvalidTradeConfigState.into()));
+        assert_eq!(c.fx.bank.account(&trade_config).unwrap().data, data);
+    }
+}
+
+// The fixture's trade collector, with 500 of fees accrued from one claimed escrow, withdrawing to its own token
+// account.
+struct WithdrawCase {
+    fx: Fixture,
+    collector: Pubkey,
+    destination: Pubkey,
+    fee_vault: Pubkey,
+}
+
+fn withdraw_case() -> WithdrawCase {
+    let mut fx = Fixture::new(100, 50);
+    fx.open_and_claim([1u8; 32], 100_000);
+    let mint = fx.mint;
+
+    let collector = fx.trade_authority;
+    let destination = get_associated_token_address(&collector, &mint);
+    fx.bank.create_token_account(&destination, &mint, &collector, 0);
+    let fee_vault = get_associated_token_address(&fx.trade_config_key(), &mint);
+    assert_eq!(fx.bank.token_balance(&fee_vault), 500);
+    WithdrawCase {
+        fx,
+        collector,
+        destination,
+        fee_vault,
+    }
+}
+
+impl WithdrawCase {
+    fn ix(&self, amount: u64) -> Instruction {
+        let trade_config = self.fx.trade_config_key();
+        instruction::withdraw_trade_fees(&self.collector, &trade_config, &self.fx.mint, &self.destination, amount)
+    }
+
+    fn withdraw(&mut self, amount: u64) -> Result<(), ProgramError> {
+        let (ix, collector) = (self.ix(amount), self.collector);
+        process(&mut self.fx, &ix, &collector)
+    }
+
+    // Runs a full withdrawal changed by `edit`; it must fail with the fee vault untouched.
+    fn withdraw_with(&mut self, edit: impl FnOnce(&mut Instruction)) -> ProgramError {
+        let mut ix = self.ix(0);
+        edit(&mut ix);
+        let signer = ix.accounts[PAYER].pubkey;
+        let err = process(&mut self.fx, &ix, &signer).unwrap_err();
+        assert_eq!(self.fx.bank.token_balance(&self.fee_vault), 500);
+        err
+    }
+}
+
+#[test]
+fn withdraws_part_or_all_of_the_trade_fee_vault() {
+    let mut c = withdraw_case();
+    c.withdraw(200).unwrap();
+    assert_eq!((c.fx.bank.token_balance(&c.fee_vault), c.fx.bank.token_balance(&c.destination)), (300, 200));
+    assert_eq!(c.withdraw(301), Err(EscrowError::WithdrawExceedsBalance.into()));
+    c.withdraw(0).unwrap();
+    assert_eq!((c.fx.bank.token_balance(&c.fee_vault), c.fx.bank.token_balance(&c.destination)), (0, 500));
+    let stats = c.fx.mint_stats(&c.fx.trade_config_key()).unwrap();
+    assert_eq!((stats.fees_accrued, stats.fees_withdrawn), (500, 500));
+}
+
+#[test]
+fn withdraw_trade_fees_rejects_bad_accounts() {
+    let mut c = withdraw_case();
+    assert_eq!(c.withdraw_with(|ix| ix.accounts.truncate(TOKEN_PROGRAM)), ProgramError::NotEnoughAccountKeys);
+    assert_eq!(c.withdraw_with(|ix| ix.accounts[PAYER].is_signer = false), EscrowError::InvalidSigner.into());
+    for index in [FEE_VAULT, DESTINATION, MINT_STATS] {
+        let err = c.withdraw_with(|ix| ix.accounts[index].is_writable = false);
+        assert_eq!(err, EscrowError::AccountNotWritable.into(), "account {index}");
+    }
+    let err = c.withdraw_with(|ix| ix.accounts[TOKEN_PROGRAM].pubkey = Pubkey::new_unique());
+    assert_eq!(err, EscrowError::InvalidTokenProgram.into());
+    let err = c.withdraw_with(|ix| ix.accounts[MINT_STATS].pubkey = Pubkey::new_unique());
+    assert_eq!(err, EscrowError::InvalidMintStatsPda.into());
+    let other_stats = mint_stats_pda(&ln_usdt_escrow::id(), &c.fx.config_key(), &c.fx.mint).0;
+    let err = c.withdraw_with(|ix| ix.accounts[MINT_STATS].pubkey = other_stats);
+    assert_eq!(err, EscrowError::InvalidMintStatsPda.into());
+}
+
+#[test]
+fn only_the_collector_withdraws_trade_fees() {
+    // The trade config is derived from the signer, so anyone else names a PDA that is not this one.
+    let mut c = withdraw_case();
+    let stranger = signer();
+    let (mint, trade_config) = (c.fx.mint, c.fx.trade_config_key());
+    let stranger_token = get_associated_token_address(&stranger, &mint);
+    c.fx.bank.create_token_account(&stranger_token, &mint, &stranger, 0);
+    let err = c.withdraw_with(|ix| {
+        *ix = instruction::withdraw_trade_fees(&stranger, &trade_config, &mint, &stranger_token, 0)
+    });
+    assert_eq!(err, EscrowError::InvalidTradeConfigPda.into());
+
+    // A trade config whose authority or collector is no longer the signer.
+    let mut c = withdraw_case();
+    let mut state = c.fx.trade_config();
+    state.authority = [7u8; 32];
+    c.fx.write_trade_config(&state);
+    assert_eq!(c.withdraw_with(|_| ()), EscrowError::AuthorityMismatch.into());
+
+    let mut c = withdraw_case();
+    let mut state = c.fx.trade_config();
+    state.fee_collector = [7u8; 32];
+    c.fx.write_trade_config(&state);
+    assert_eq!(c.withdraw_with(|_| ()), EscrowError::FeeCollectorMismatch.into());
+}
+
+#[test]
+fn treasury_locks_direct_trade_withdrawals() {
+    let mut c = withdraw_case();
+    let mut state = c.fx.trade_config();
+    state.treasury = TreasuryState {
+        owner: Pubkey::new_unique().to_bytes(),
+        guardian: Pubkey::new_unique().to_bytes(),
+        withdraw_delay_secs: 3600,
+    };
+    c.fx.write_trade_config(&state);
+    assert_eq!(c.withdraw_with(|_| ()), EscrowError::TreasuryLocked.into());
+}
+
+#[test]
+fn withdraw_trade_fees_needs_a_valid_trade_config() {
+    let mut c = withdraw_case();
+    let trade_config = c.fx.trade_config_key();
+    let account = Account {
+        owner: Pubkey::new_unique(),
+        ..c.fx.bank.account(&trade_config).unwrap().clone()
+    };
+    c.fx.bank.set_account(trade_config, account.clone());
+    assert_eq!(c.withdraw_with(|_| ()), EscrowError::InvalidAccountOwner.into());
+
+    c.fx.bank.set_account(trade_config, Account { owner: ln_usdt_escrow::id(), ..account });
+    let mut state = c.fx.trade_config();
+    state.v = 200;
+    c.fx.write_trade_config(&state);
+    assert_eq!(c.withdraw_with(|_| ()), EscrowError::InvalidTradeConfigState.into());
+}
+
+#[test]
+fn trade_fee_vault_must_be_the_trade_configs_ata() {
+    let mut c = withdraw_case();
+    let (mint, trade_config, other) = (c.fx.mint, c.fx.trade_config_key(), Pubkey::new_unique());
+    c.fx.bank.create_token_account(&other, &mint, &trade_config, 0);
+    let err = c.withdraw_with(|ix| ix.accounts[FEE_VAULT].pubkey = other);
+    assert_eq!(err, EscrowError::InvalidTradeFeeVaultAta.into());
+
+    let platform_fee_vault = get_associated_token_address(&c.fx.config_key(), &mint);
+    let err = c.withdraw_with(|ix| ix.accounts[FEE_VAULT].pubkey = platform_fee_vault);
+    assert_eq!(err, EscrowError::TokenOwnerMismatch.into());
+
+    let err = c.withdraw_with(|ix| ix.accounts[FEE_VAULT].pubkey = Pubkey::new_unique());
+    assert_eq!(err, EscrowError::InvalidTokenAccountData.into());
+}
+
+#[test]
+fn trade_destination_must_be_the_collectors_in_the_mint() {
+    let mut c = withdraw_case();
+    let collector = c.collector;
+    let err = c.withdraw_with(|ix| ix.accounts[DESTINATION].pubkey = collector);
+    assert_eq!(err, EscrowError::InvalidTokenAccountData.into());
+
+    let (mint, other) = (c.fx.mint, Pubkey::new_unique());
+    c.fx.bank.create_token_account(&other, &mint, &Pubkey::new_unique(), 0);
+    let err = c.withdraw_with(|ix| ix.accounts[DESTINATION].pubkey = other);
+    assert_eq!(err, EscrowError::TokenOwnerMismatch.into());
+
+    let (other_mint, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+    c.fx.bank.create_mint(&other_mint, 6);
+    c.fx.bank.create_token_account(&other, &other_mint, &collector, 0);
+    let err = c.withdraw_with(|ix| ix.accounts[DESTINATION].pubkey = other);
+    assert_eq!(err, EscrowError::TokenMintMismatch.into());
+}
+
+// The withdraw case plus a second mint whose fee vault also holds 500, and the collector's (mint, ATA) pairs.
+fn batch_case() -> (WithdrawCase, [(Pubkey, Pubkey); 2]) {
+    let mut c = withdraw_case();
+    let (first, second) = (c.fx.mint, Pubkey::new_unique());
+    c.fx.bank.create_mint(&second, 6);
+    c.fx.mint = second;
+    c.fx.open_and_claim([2u8; 32], 100_000);
+    c.fx.mint = first;
+    let destination = get_associated_token_address(&c.collector, &second);
+    c.fx.bank.create_token_account(&destination, &second, &c.collector, 0);
+    let pairs = [(first, c.destination), (second, destination)];
+    (c, pairs)
+}
+
+#[test]
+fn trade_batch_withdrawal_sweeps_every_fee_vault() {
+    let (mut c, pairs) = batch_case();
+    let (collector, config) = (c.collector, c.fx.trade_config_key());
+    process(&mut c.fx, &instruction::withdraw_trade_fees_batch(&collector, &config, &pairs), &collector).unwrap();
+    for (mint, destination) in pairs {
+        let fee_vault = get_associated_token_address(&config, &mint);
+        assert_eq!((c.fx.bank.token_balance(&fee_vault), c.fx.bank.token_balance(&destination)), (0, 500));
+        let stats = c.fx.bank.account(&mint_stats_pda(&ln_usdt_escrow::id(), &config, &mint).0).unwrap();
+        let stats = MintStatsState::unpack(&stats.data).unwrap();
+        assert_eq!((stats.fees_accrued, stats.fees_withdrawn), (500, 500));
+        let logged = format!("Program log: withdrew 500 from trade fee vault {fee_vault}");
+        assert!(c.fx.bank.logs().contains(&logged), "missing {logged:?}");
+    }
+}
+
+#[test]
+fn trade_batch_withdrawal_checks_every_pair() {
+    let (mut c, pairs) = batch_case();
+    let (collector, config, second) = (c.collector, c.fx.trade_config_key(), pairs[1].0);
+    let (stranger, other) = (signer(), Pubkey::new_unique());
+    c.fx.bank.create_token_account(&other, &second, &stranger, 0);
+    let mut odd = instruction::withdraw_trade_fees_batch(&collector, &config, &pairs);
+    odd.accounts.pop();
+    let cases: [(Instruction, Pubkey, ProgramError); 3] = [
+        // A destination of someone else's in the second pair undoes the first pair as well.
+        (
+            instruction::withdraw_trade_fees_batch(&collector, &config, &[pairs[0], (second, other)]),
+            collector,
+            EscrowError::TokenOwnerMismatch.into(),
+        ),
+        (odd, collector, ProgramError::NotEnoughAccountKeys),
+        // The trade config is derived from the signer.
+        (
+            instruction::withdraw_trade_fees_batch(&stranger, &config, &pairs),
+            stranger,
+            EscrowError::InvalidTradeConfigPda.into(),
+        ),
+    ];
+    for (ix, signer, expected) in cases {
+        assert_eq!(process(&mut c.fx, &ix, &signer), Err(expected));
+        for (mint, _) in pairs {
+            assert_eq!(c.fx.bank.token_balance(&get_associated_token_address(&config, &mint)), 500);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Treasury mode: RequestFeeWithdrawal, ExecuteFeeWithdrawal after the delay and VetoFeeWithdrawal by the guardian.
+
+mod common;
+
+use common::{signer, Fixture};
+use ln_usdt_escrow::{fee_withdrawal_pda, instruction, EscrowError, FeeWithdrawalState};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+use spl_associated_token_account::get_associated_token_address;
+
+const DELAY: u32 = 3600;
+
+// The default config in treasury mode with 1_000 of fees from one claimed escrow in its vault.
+struct Case {
+    fx: Fixture,
+    authority: Pubkey,
+    guardian: Pubkey,
+    config: Pubkey,
+    fee_vault: Pubkey,
+    withdrawal: Pubkey,
+    treasury_token: Pubkey,
+}
+
+fn case() -> Case {
+    let mut fx = Fixture::new(100, 50);
+    fx.open_and_claim([1u8; 32], 100_000);
+    let mint = fx.mint;
+
+    let (authority, config, guardian, treasury) = (fx.platform_authority, fx.config_key(), signer(), signer());
+    let ix = instruction::set_treasury(&authority, &config, treasury, guardian, DELAY, None);
+    fx.bank.process(&ix, &[&authority]).expect("set treasury");
+    let treasury_token = get_associated_token_address(&treasury, &mint);
+    fx.bank.create_token_account(&treasury_token, &mint, &treasury, 0);
+    let fee_vault = get_associated_token_address(&config, &mint);
+    assert_eq!(fx.bank.token_balance(&fee_vault), 1_000);
+    Case {
+        fx,
+        authority,
+        guardian,
+        config,
+        fee_vault,
+        withdrawal: fee_withdrawal_pda(&ln_usdt_escrow::id(), &config, &fee_vault).0,
+        treasury_token,
+    }
+}
+
+impl Case {
+    fn request(&mut self, amount: u64) -> Result<(), ProgramError> {
+        let ix = instruction::request_fee_withdrawal(&self.authority, &self.config, &self.fx.mint, amount);
+        self.fx.bank.process(&ix, &[&self.authority]).map(|_| ())
+    }
+
+    // Nobody signs: execution is open to anyone once the delay is over.
+    fn execute(&mut self) -> Result<(), ProgramError> {
+        let (mint, authority) = (self.fx.mint, self.authority);
+        let ix = instruction::execute_fee_withdrawal(&self.config, &mint, &self.treasury_token, &authority);
+        self.fx.bank.process(&ix, &[]).map(|_| ())
+    }
+
+    fn veto(&mut self, signer: &Pubkey) -> Result<(), ProgramError> {
+        let ix = instruction::veto_fee_withdrawal(signer, &self.config, &self.fx.mint, &self.authority);
+        self.fx.bank.process(&ix, &[signer]).map(|_| ())
+    }
+
+    fn pending(&self) -> Option<FeeWithdrawalState> {
+        let account = self.fx.bank.account(&self.withdrawal)?;
+        Some(FeeWithdrawalState::unpack(&account.data).unwrap())
+    }
+}
+
+#[test]
+fn execution_waits_for_the_delay() {
+    let mut c = case();
+    let now = c.fx.bank.now();
+    c.request(400).unwrap();
+    let pending = c.pending().unwrap();
+    assert_eq!((pending.amount, pending.requested_at), (400, now));
+    assert_eq!(pending.executable_at, now + DELAY as i64);
+    assert_eq!(pending.rent_payer, c.authority.to_bytes());
+    assert_eq!(c.request(400), Err(EscrowError::AlreadyInitialized.into()));
+
+    assert_eq!(c.execute(), Err(EscrowError::TooEarly.into()));
+    c.fx.bank.set_clock(pending.executable_at - 1);
+    assert_eq!(c.execute(), Err(EscrowError::TooEarly.into()));
+    assert_eq!(c.fx.bank.token_balance(&c.fee_vault), 1_000);
+
+    let (rent, before) = (c.fx.bank.lamports(&c.withdrawal), c.fx.bank.lamports(&c.authority));
+    c.fx.bank.set_clock(pending.executable_at);
+    c.execute().unwrap();
+    assert_eq!((c.fx.bank.token_balance(&c.fee_vault), c.fx.bank.token_balance(&c.treasury_token)), (600, 400));
+    assert!(c.pending().is_none());
+    assert_eq!(c.fx.bank.lamports(&c.authority), before + rent);
+    let stats = c.fx.mint_stats(&c.config).unwrap();
+    assert_eq!((stats.fees_accrued, stats.fees_withdrawn), (1_000, 400));
+
+    // Zero asks for whatever the vault holds when the request is executed.
+    c.request(0).unwrap();
+    c.fx.bank.set_clock(c.pending().unwrap().executable_at);
+    c.execute().unwrap();
+    assert_eq!((c.fx.bank.token_balance(&c.fee_vault), c.fx.bank.token_balance(&c.treasury_token)), (0, 1_000));
+}
+
+#[test]
+fn the_guardian_vetoes_within_the_window() {
+    let mut c = case();
+    c.request(1_000).unwrap();
+    let executable_at = c.pending().unwrap().executable_at;
+
+    // In treasury mode the authority cannot veto its own request, let alone a stranger.
+    let (authority, stranger, guardian) = (c.authority, signer(), c.guardian);
+    assert_eq!(c.veto(&authority), Err(EscrowError::GuardianMismatch.into()));
+    assert_eq!(c.veto(&stranger), Err(EscrowError::GuardianMismatch.into()));
+    assert!(c.pending().is_some());
+
+    c.fx.bank.set_clock(executable_at - 1);
+    let (rent, before) = (c.fx.bank.lamports(&c.withdrawal), c.fx.bank.lamports(&authority));
+    c.veto(&guardian).unwrap();
+    assert!(c.pending().is_none());
+    assert_eq!(c.fx.bank.lamports(&authority), before + rent);
+
+    // Nothing is left to execute once the delay is over, and the vault keeps its fees.
+    c.fx.bank.set_clock(executable_at);
+    assert_eq!(c.execute(), Err(EscrowError::InvalidAccountOwner.into()));
+    assert_eq!((c.fx.bank.token_balance(&c.fee_vault), c.fx.bank.token_balance(&c.treasury_token)), (1_000, 0));
+
+    // A new request starts a new window.
+    c.request(1_000).unwrap();
+    assert_eq!(c.pending().unwrap().executable_at, executable_at + DELAY as i64);
+}
+
+#[test]
+fn the_authority_alone_cannot_leave_treasury_mode() {
+    let mut c = case();
+    c.request(1_000).unwrap();
+    let (authority, config, guardian) = (c.authority, c.config, c.guardian);
+    let off = instruction::set_treasury(&authority, &config, Pubkey::default(), Pubkey::default(), 0, None);
+    assert_eq!(c.fx.bank.process(&off, &[&authority]), Err(ProgramError::NotEnoughAccountKeys));
+    let stranger = signer();
+    let ix = instruction::set_treasury(&authority, &config, Pubkey::default(), Pubkey::default(), 0, Some(&stranger));
+    assert_eq!(c.fx.bank.process(&ix, &[&authority, &stranger]), Err(EscrowError::GuardianMismatch.into()));
+    assert!(c.fx.config().treasury.enabled());
+
+    let ix = instruction::set_treasury(&authority, &config, Pubkey::default(), Pubkey::default(), 0, Some(&guardian));
+    c.fx.bank.process(&ix, &[&authority, &guardian]).unwrap();
+    assert!(!c.fx.config().treasury.enabled());
+    // A request still pending is now the authority's to veto, and no new one can be made.
+    assert_eq!(c.veto(&guardian), Err(EscrowError::AuthorityMismatch.into()));
+    c.veto(&authority).unwrap();
+    assert!(c.pending().is_none());
+    assert_eq!(c.request(1_000), Err(EscrowError::TreasuryNotConfigured.into()));
+}
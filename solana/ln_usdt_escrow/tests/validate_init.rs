@@ -0,0 +1,203 @@
+//! ValidateInit: the verdict in its return data, one `InitFailure` per kind of rejection, and that it leaves
+//! everything as it was.
+
+mod common;
+
+use common::{signer, Fixture};
+use ln_usdt_escrow::{
+    instruction::{self, InitArgs},
+    pda_for_hash,
+    return_data::{InitFailure, InitValidation},
+    EscrowError,
+};
+use solana_program::{instruction::Instruction, program_error::ProgramError, pubkey::Pubkey};
+
+// Account indices of a v2 Init (and so ValidateInit) without referrer or payer stats.
+const PAYER: usize = 0;
+const PAYER_TOKEN: usize = 1;
+const ESCROW: usize = 2;
+const VAULT: usize = 3;
+const TOKEN_PROGRAM: usize = 6;
+
+type Edit = dyn Fn(&mut Instruction);
+
+// A payer holding 1_000_000 about to open a 100_000 escrow at 1% platform and 0.5% trade fee.
+struct Case {
+    fx: Fixture,
+    payer: Pubkey,
+    payer_token: Pubkey,
+    args: InitArgs,
+}
+
+fn case() -> Case {
+    let mut fx = Fixture::new(100, 50);
+    let (payer, payer_token) = fx.funded_payer(1_000_000);
+    let args = fx.init_args(&[1u8; 32], &Pubkey::new_unique(), &payer, 100_000);
+    Case {
+        fx,
+        payer,
+        payer_token,
+        args,
+    }
+}
+
+impl Case {
+    fn validate_ix(&self) -> Instruction {
+        let (config, trade_config) = (self.fx.config(), self.fx.trade_config());
+        instruction::validate_init(&self.payer, &self.payer_token, &self.fx.mint, &config, &trade_config, self.args)
+    }
+
+    // Runs ValidateInit with the accounts changed by `edit`. It always succeeds and never moves a token.
+    fn validate_with(&mut self, edit: impl Fn(&mut Instruction)) -> InitValidation {
+        let mut ix = self.validate_ix();
+        edit(&mut ix);
+        let balance = self.fx.bank.token_balance(&self.payer_token);
+        let (_, data) = self.fx.bank.process(&ix, &[&self.payer]).unwrap().expect("return data");
+        assert_eq!(self.fx.bank.token_balance(&self.payer_token), balance);
+        InitValidation::from_return_data(&data).expect("init validation")
+    }
+
+    // The reason ValidateInit gives after `edit`, checked against the error Init itself fails with.
+    fn reject(&mut self, edit: impl Fn(&mut Instruction)) -> (InitFailure, ProgramError) {
+        let validation = self.validate_with(&edit);
+        let mut ix = self.fx.init_ix(&self.payer, &self.payer_token, self.args);
+        edit(&mut ix);
+        let err = self.fx.bank.process(&ix, &[&self.payer]).unwrap_err();
+        assert_eq!(validation.program_error, u64::from(err.clone()));
+        (validation.failure().expect("failure reason"), err)
+    }
+
+    fn escrow(&self) -> Pubkey {
+        pda_for_hash(&ln_usdt_escrow::id(), &self.args.payment_hash).0
+    }
+}
+
+#[test]
+fn a_valid_init_reports_its_fees_and_creates_nothing() {
+    let mut c = case();
+    let validation = c.validate_with(|_| ());
+    assert_eq!(
+        validation,
+        InitValidation {
+            reason: 0,
+            program_error: 0,
+            platform_fee_bps: 100,
+            trade_fee_bps: 50,
+            platform_lamport_fee: 0,
+            trade_lamport_fee: 0,
+            net_amount: 100_000,
+            platform_fee_amount: 1_000,
+            trade_fee_amount: 500,
+            referral_fee_amount: 0,
+            maker_rebate_amount: 0,
+            total_amount: 101_500,
+        }
+    );
+    assert_eq!(validation.failure(), None);
+    assert!(c.fx.bank.account(&c.escrow()).is_none());
+
+    // And Init agrees.
+    let ix = c.fx.init_ix(&c.payer, &c.payer_token, c.args);
+    c.fx.bank.process(&ix, &[&c.payer]).unwrap();
+    assert_eq!(c.fx.bank.token_balance(&c.payer_token), 1_000_000 - 101_500);
+}
+
+#[test]
+fn each_account_check_has_its_own_reason() {
+    let mut c = case();
+    let cases: [(InitFailure, ProgramError, &Edit); 5] = [
+        (InitFailure::NotEnoughAccounts, ProgramError::NotEnoughAccountKeys, &|ix| ix.accounts.truncate(3)),
+        (
+            InitFailure::PayerNotSigner,
+            EscrowError::InvalidSigner.into(),
+            &|ix| ix.accounts[PAYER].is_signer = false,
+        ),
+        (
+            InitFailure::AccountNotWritable,
+            EscrowError::AccountNotWritable.into(),
+            &|ix| ix.accounts[VAULT].is_writable = false,
+        ),
+        (
+            InitFailure::EscrowPdaMismatch,
+            EscrowError::InvalidEscrowPda.into(),
+            &|ix| ix.accounts[ESCROW].pubkey = Pubkey::new_unique(),
+        ),
+        (
+            InitFailure::InvalidProgramAccount,
+            EscrowError::InvalidTokenProgram.into(),
+            &|ix| ix.accounts[TOKEN_PROGRAM].pubkey = Pubkey::new_unique(),
+        ),
+    ];
+    for (reason, error, edit) in cases {
+        assert_eq!(c.reject(edit), (reason, error), "{reason:?}");
+    }
+    assert!(c.fx.bank.account(&c.escrow()).is_none());
+}
+
+#[test]
+fn fee_mismatches_report_the_fees_to_send() {
+    let mut c = case();
+    c.args.expected_platform_fee_bps = 99;
+    assert_eq!(c.reject(|_| ()), (InitFailure::PlatformFeeBpsMismatch, EscrowError::FeeMismatch.into()));
+    assert_eq!(c.validate_with(|_| ()).platform_fee_bps, 100);
+
+    c.args.expected_platform_fee_bps = 100;
+    c.args.expected_trade_fee_bps = 49;
+    assert_eq!(c.reject(|_| ()), (InitFailure::TradeFeeBpsMismatch, EscrowError::FeeMismatch.into()));
+    assert_eq!(c.validate_with(|_| ()).trade_fee_bps, 50);
+
+    c.args.expected_trade_fee_bps = 50;
+    c.args.expected_platform_lamport_fee = 1;
+    let expected = (InitFailure::PlatformLamportFeeMismatch, EscrowError::LamportFeeMismatch.into());
+    assert_eq!(c.reject(|_| ()), expected);
+}
+
+// Init reports a bad payer token account one way or another; ValidateInit says which.
+#[test]
+fn payer_token_problems_are_told_apart() {
+    let mut c = case();
+    let (mint, payer) = (c.fx.mint, c.payer);
+    let someone_elses = Pubkey::new_unique();
+    c.fx.bank.create_token_account(&someone_elses, &mint, &signer(), 1_000_000);
+    let other_mint = Pubkey::new_unique();
+    c.fx.bank.create_mint(&other_mint, 6);
+    let wrong_mint = Pubkey::new_unique();
+    c.fx.bank.create_token_account(&wrong_mint, &other_mint, &payer, 1_000_000);
+
+    let not_a_token_account = |ix: &mut Instruction| ix.accounts[PAYER_TOKEN].pubkey = Pubkey::new_unique();
+    let expected = (InitFailure::InvalidPayerToken, EscrowError::InvalidTokenAccountData.into());
+    assert_eq!(c.reject(not_a_token_account), expected);
+    let expected = (InitFailure::PayerTokenOwnerMismatch, EscrowError::TokenOwnerMismatch.into());
+    assert_eq!(c.reject(|ix| ix.accounts[PAYER_TOKEN].pubkey = someone_elses), expected);
+    let expected = (InitFailure::PayerTokenMintMismatch, EscrowError::TokenMintMismatch.into());
+    assert_eq!(c.reject(|ix| ix.accounts[PAYER_TOKEN].pubkey = wrong_mint), expected);
+
+    // Short on funds: the amounts are still worked out, so the shortfall can be read off.
+    c.args.amount = 1_000_000;
+    let expected = (InitFailure::InsufficientBalance, EscrowError::InsufficientFunds.into());
+    assert_eq!(c.reject(|_| ()), expected);
+    let validation = c.validate_with(|_| ());
+    assert_eq!((validation.platform_fee_amount, validation.trade_fee_amount), (10_000, 5_000));
+    assert_eq!(validation.total_amount, 1_015_000);
+}
+
+#[test]
+fn an_open_escrow_is_reported_as_such() {
+    let mut c = case();
+    let ix = c.fx.init_ix(&c.payer, &c.payer_token, c.args);
+    c.fx.bank.process(&ix, &[&c.payer]).unwrap();
+    let expected = (InitFailure::EscrowAlreadyInitialized, EscrowError::AlreadyInitialized.into());
+    assert_eq!(c.reject(|_| ()), expected);
+}
+
+#[test]
+fn failure_codes_round_trip() {
+    for code in 0..=u8::MAX {
+        if let Some(failure) = InitFailure::from_code(code) {
+            assert_eq!(failure as u8, code);
+        }
+    }
+    assert_eq!(InitFailure::from_code(0), None);
+    assert_eq!(InitFailure::from_code(InitFailure::InvalidSysvar as u8 + 1), None);
+    assert_eq!(InitFailure::from_code(255), Some(InitFailure::Other));
+}
@@ -62,6 +62,9 @@ import { SolanaRpcPool } from '../solana/rpcPool.js';
 import { solLocalStart, solLocalStatus, solLocalStop } from '../solana/localValidatorManager.js';
 import {
   LN_USDT_ESCROW_PROGRAM_ID,
+  ESCROW_STATE_SPACE,
+  MINT_STATS_SPACE,
+  PAYER_STATS_SPACE,
   deriveEscrowPda,
   deriveConfigPda,
   deriveTradeConfigPda,
@@ -71,6 +74,11 @@ import {
   getConfigState,
   getTradeConfigState,
   getEscrowState,
+  derivePayerStatsPda,
+  decodePayerStatsState,
+  payerRollingVolume,
+  expectedPlatformFeeBps,
+  expectedTradeFeeBps,
   initConfigTx,
   initTradeConfigTx,
   setConfigTx,
@@ -1136,7 +1144,58 @@ async function fetchOnchainFeeSnapshot({ pool, programId, commitment, tradeFeeCo
   if (!tradeCfg) throw new Error(`Trade fee config not initialized for ${tradeCollectorPk.toBase58()}`);
   const tradeFeeBps = Number(tradeCfg.feeBps || 0);
 
-  return { platformFeeBps, platformFeeCollector, tradeFeeBps, tradeFeeCollector: tradeCollectorPk };
+  return {
+    platformFeeBps,
+    platformFeeCollector,
+    tradeFeeBps,
+    tradeFeeCollector: tradeCollectorPk,
+    config: cfg,
+    tradeConfig: tradeCfg,
+  };
+}
+
+// Claim/refund accounts that depend on how the escrow was opened (see decodeEscrowState): its referrer, payout
+// mode, the configs holding its lamport fees and the payer whose volume it credits.
+function escrowSettleArgs(escrow) {
+  return {
+    referrer: escrow.referrer ?? null,
+    platformDirectPayout: Boolean(escrow.platformDirectPayout),
+    platformFeeCollector: escrow.platformFeeCollector ?? null,
+    tradeDirectPayout: Boolean(escrow.tradeDirectPayout),
+    platformLamportFee: escrow.platformLamportFee ?? 0n,
+    tradeLamportFee: escrow.tradeLamportFee ?? 0n,
+    payer: escrow.tracksPayerVolume ? escrow.payer : null,
+    tenant: escrow.tenant ?? null,
+  };
+}
+
+// The fee-related createEscrowTx args for one escrow: the bps Init will charge this payer for this lock (volume
+// tier discount, lock surcharge, 0 in lamport fee mode), the lamport fees, and the accounts the payout and
+// volume tracking modes need. `fees` is a fetchOnchainFeeSnapshot result.
+async function escrowInitFeeArgs({ connection, fees, payer, mint, refundAfterUnix, commitment, programId }) {
+  const { config, tradeConfig } = fees;
+  // Init reads the cluster clock; quoting from an earlier time only over-estimates the surcharge, which Init
+  // accepts (the expected trade bps is a cap).
+  const slot = await connection.getSlot(commitment);
+  const blockTime = await connection.getBlockTime(slot).catch(() => null);
+  const nowUnix = Math.min(blockTime ?? Number.MAX_SAFE_INTEGER, Math.floor(Date.now() / 1000));
+  const trackPayerVolume = config.volumeTiers.length > 0;
+  let rollingVolume = 0n;
+  if (trackPayerVolume) {
+    const { pda } = derivePayerStatsPda(payer, mint, programId);
+    const info = await connection.getAccountInfo(pda, commitment);
+    rollingVolume = payerRollingVolume(info ? decodePayerStatsState(info.data) : null, nowUnix);
+  }
+  return {
+    expectedPlatformFeeBps: expectedPlatformFeeBps(config, rollingVolume),
+    expectedTradeFeeBps: expectedTradeFeeBps(tradeConfig, { nowUnix, refundAfterUnix }),
+    expectedPlatformLamportFee: config.lamportFee,
+    expectedTradeLamportFee: tradeConfig.lamportFee,
+    platformDirectPayout: config.directPayout,
+    platformFeeCollector: config.feeCollector,
+    tradeDirectPayout: tradeConfig.directPayout,
+    trackPayerVolume,
+  };
 }
 
 function resolveRepoPath(p) {
@@ -5892,8 +5951,18 @@ export class ToolExecutor {
       const tradeFeeBps = Number(fees.tradeFeeBps || 0);
       if (platformFeeBps + tradeFeeBps > 1500) throw new Error(`${toolName}: on-chain total fee bps exceeds 1500 cap`);
 
+      let feeArgs = null;
       const build = await this._pool().call(async (connection) => {
         const payerAta = await getOrCreateAta(connection, signer, signer.publicKey, mint, commitment);
+        feeArgs = await escrowInitFeeArgs({
+          connection,
+          fees,
+          payer: signer.publicKey,
+          mint,
+          refundAfterUnix,
+          commitment,
+          programId,
+        });
         return createEscrowTx({
           connection,
           payer: signer,
@@ -5904,8 +5973,7 @@ export class ToolExecutor {
           refund,
           refundAfterUnix,
           amount,
-          expectedPlatformFeeBps: platformFeeBps,
-          expectedTradeFeeBps: tradeFeeBps,
+          ...feeArgs,
           tradeFeeCollector,
           computeUnitLimit,
           computeUnitPriceMicroLamports,
@@ -5914,15 +5982,26 @@ export class ToolExecutor {
       }, { label: 'swap_sol_escrow_build' });
 
       const solEscrowFunding = await this._pool().call(async (connection) => {
-        const [payerLamportsRaw, infos, feeResp, escrowRent, tokenRent] = await Promise.all([
+        const [platformStatsPda, tradeStatsPda] = build.mintStatsPdas;
+        const [payerLamportsRaw, infos, feeResp, escrowRent, tokenRent, mintStatsRent, payerStatsRent] = await Promise.all([
           connection.getBalance(signer.publicKey, commitment),
           connection.getMultipleAccountsInfo(
-            [build.escrowPda, build.vault, build.platformFeeVaultAta, build.tradeFeeVaultAta],
+            [
+              build.escrowPda,
+              build.vault,
+              build.platformFeeVaultAta,
+              build.tradeFeeVaultAta,
+              platformStatsPda,
+              tradeStatsPda,
+              ...(build.payerStatsPda ? [build.payerStatsPda] : []),
+            ],
             commitment
           ),
           connection.getFeeForMessage(build.tx.compileMessage(), commitment),
-          connection.getMinimumBalanceForRentExemption(SOL_ESCROW_GUARDRAIL_CONSTANTS.ESCROW_STATE_V3_SPACE, commitment),
+          connection.getMinimumBalanceForRentExemption(ESCROW_STATE_SPACE, commitment),
           connection.getMinimumBalanceForRentExemption(SOL_ESCROW_GUARDRAIL_CONSTANTS.SPL_TOKEN_ACCOUNT_SPACE, commitment),
+          connection.getMinimumBalanceForRentExemption(MINT_STATS_SPACE, commitment),
+          connection.getMinimumBalanceForRentExemption(PAYER_STATS_SPACE, commitment),
         ]);
         const feeLamports = parseFeeLamports(feeResp) ?? 5_000;
         return computeEscrowInitLamportsGuardrail({
@@ -5930,10 +6009,16 @@ export class ToolExecutor {
           feeLamports,
           escrowRentLamports: escrowRent,
           tokenAccountRentLamports: tokenRent,
+          mintStatsRentLamports: mintStatsRent,
+          payerStatsRentLamports: payerStatsRent,
+          lamportFeeLamports: Number(feeArgs.expectedPlatformLamportFee) + Number(feeArgs.expectedTradeLamportFee),
           hasEscrowAccount: Boolean(infos?.[0]),
           hasVaultAccount: Boolean(infos?.[1]),
           hasPlatformFeeVaultAccount: Boolean(infos?.[2]),
           hasTradeFeeVaultAccount: Boolean(infos?.[3]),
+          hasPlatformMintStatsAccount: Boolean(infos?.[4]),
+          hasTradeMintStatsAccount: Boolean(infos?.[5]),
+          hasPayerStatsAccount: build.payerStatsPda ? Boolean(infos?.[6]) : true,
         });
       }, { label: 'swap_sol_escrow_funding_guardrail' });
       if (!solEscrowFunding.ok) {
@@ -6517,6 +6602,7 @@ export class ToolExecutor {
           paymentHashHex,
           preimageHex,
           tradeFeeCollector,
+          ...escrowSettleArgs(escrow),
           computeUnitLimit,
           computeUnitPriceMicroLamports,
           programId,
@@ -7184,6 +7270,15 @@ export class ToolExecutor {
 
       return this._pool().call(async (connection) => {
         const payerAta = await getOrCreateAta(connection, signer, signer.publicKey, mint, commitment, { computeUnitLimit, computeUnitPriceMicroLamports });
+        const feeArgs = await escrowInitFeeArgs({
+          connection,
+          fees,
+          payer: signer.publicKey,
+          mint,
+          refundAfterUnix,
+          commitment,
+          programId,
+        });
         const build = await createEscrowTx({
           connection,
           payer: signer,
@@ -7194,8 +7289,7 @@ export class ToolExecutor {
           refund,
           refundAfterUnix,
           amount,
-          expectedPlatformFeeBps: platformFeeBps,
-          expectedTradeFeeBps: tradeFeeBps,
+          ...feeArgs,
           tradeFeeCollector,
           computeUnitLimit,
           computeUnitPriceMicroLamports,
@@ -7251,6 +7345,7 @@ export class ToolExecutor {
           paymentHashHex,
           preimageHex,
           tradeFeeCollector,
+          ...escrowSettleArgs(escrow),
           computeUnitLimit,
           computeUnitPriceMicroLamports,
           programId,
@@ -7287,6 +7382,10 @@ export class ToolExecutor {
           refundTokenAccount: refundAta,
           mint,
           paymentHashHex,
+          tradeFeeCollector: escrow.tradeFeeCollector ?? null,
+          platformLamportFee: escrow.platformLamportFee ?? 0n,
+          tradeLamportFee: escrow.tradeLamportFee ?? 0n,
+          tenant: escrow.tenant ?? null,
           computeUnitLimit,
           computeUnitPriceMicroLamports,
           programId,
@@ -7415,6 +7514,7 @@ export class ToolExecutor {
             paymentHashHex: hash,
             preimageHex,
             tradeFeeCollector,
+            ...escrowSettleArgs(onchain),
             computeUnitLimit,
             computeUnitPriceMicroLamports,
             programId,
@@ -7479,6 +7579,10 @@ export class ToolExecutor {
             refundTokenAccount: refundAta,
             mint,
             paymentHashHex: hash,
+            tradeFeeCollector: onchain.tradeFeeCollector ?? null,
+            platformLamportFee: onchain.platformLamportFee ?? 0n,
+            tradeLamportFee: onchain.tradeLamportFee ?? 0n,
+            tenant: onchain.tenant ?? null,
             computeUnitLimit,
             computeUnitPriceMicroLamports,
             programId,
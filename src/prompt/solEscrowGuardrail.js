@@ -1,4 +1,5 @@
-const ESCROW_STATE_V3_SPACE = 263;
+// Program account sizes (escrow, mint stats, payer stats) come from lnUsdtEscrowClient.js; only the SPL token
+// account size is fixed here.
 const SPL_TOKEN_ACCOUNT_SPACE = 165;
 const SOL_ESCROW_INIT_MARGIN_LAMPORTS = 25_000;
 
@@ -50,20 +51,31 @@ export function computeEscrowInitLamportsGuardrail({
   feeLamports = 0,
   escrowRentLamports = 0,
   tokenAccountRentLamports = 0,
+  mintStatsRentLamports = 0,
+  payerStatsRentLamports = 0,
+  // Platform + trade lamport fees Init charges the payer (configs in lamport fee mode).
+  lamportFeeLamports = 0,
   hasEscrowAccount = false,
   hasVaultAccount = false,
   hasPlatformFeeVaultAccount = false,
   hasTradeFeeVaultAccount = false,
+  hasPlatformMintStatsAccount = false,
+  hasTradeMintStatsAccount = false,
+  // Only escrows against a config with volume tiers create the payer stats PDA; pass true otherwise.
+  hasPayerStatsAccount = true,
   marginLamports = SOL_ESCROW_INIT_MARGIN_LAMPORTS,
 } = {}) {
   const have = toSafeLamports(payerLamports);
   const fee = toSafeLamports(feeLamports);
   const escrowRent = toSafeLamports(escrowRentLamports);
   const tokenRent = toSafeLamports(tokenAccountRentLamports);
+  const mintStatsRent = toSafeLamports(mintStatsRentLamports);
+  const payerStatsRent = toSafeLamports(payerStatsRentLamports);
+  const lamportFees = toSafeLamports(lamportFeeLamports);
   const margin = toSafeLamports(marginLamports);
   const missing_accounts = [];
 
-  let required = fee + margin;
+  let required = fee + lamportFees + margin;
   if (!hasEscrowAccount) {
     required += escrowRent;
     missing_accounts.push('escrow_pda');
@@ -80,6 +92,18 @@ export function computeEscrowInitLamportsGuardrail({
     required += tokenRent;
     missing_accounts.push('trade_fee_vault_ata');
   }
+  if (!hasPlatformMintStatsAccount) {
+    required += mintStatsRent;
+    missing_accounts.push('platform_mint_stats_pda');
+  }
+  if (!hasTradeMintStatsAccount) {
+    required += mintStatsRent;
+    missing_accounts.push('trade_mint_stats_pda');
+  }
+  if (!hasPayerStatsAccount) {
+    required += payerStatsRent;
+    missing_accounts.push('payer_stats_pda');
+  }
 
   return {
     ok: have >= required,
@@ -89,13 +113,15 @@ export function computeEscrowInitLamportsGuardrail({
     fee_lamports: fee,
     escrow_rent_lamports: escrowRent,
     token_account_rent_lamports: tokenRent,
+    mint_stats_rent_lamports: mintStatsRent,
+    payer_stats_rent_lamports: payerStatsRent,
+    lamport_fee_lamports: lamportFees,
     margin_lamports: margin,
     missing_accounts,
   };
 }
 
 export const SOL_ESCROW_GUARDRAIL_CONSTANTS = {
-  ESCROW_STATE_V3_SPACE,
   SPL_TOKEN_ACCOUNT_SPACE,
   SOL_ESCROW_INIT_MARGIN_LAMPORTS,
 };
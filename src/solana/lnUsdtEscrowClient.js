@@ -1,3 +1,5 @@
+import fs from 'node:fs';
+
 import {
   PublicKey,
   SystemProgram,
@@ -14,11 +16,19 @@ import {
 
 import { buildComputeBudgetIxs } from './computeBudget.js';
 
-export const LN_USDT_ESCROW_PROGRAM_ID = new PublicKey('4RS6xpspM1V2K7FKSqeSH6VVaZbtzHzhJqacwrz8gJrF');
+// Read from solana/ln_usdt_escrow/program-ids.json, the same file the Rust build takes `declare_id!` from, so the
+// two sides can not drift apart. The program is deployed on mainnet only; for any other cluster, build it with
+// LN_USDT_ESCROW_PROGRAM_ID set and pass that id as `programId`.
+export const LN_USDT_ESCROW_PROGRAM_ID = new PublicKey(
+  JSON.parse(fs.readFileSync(new URL('../../solana/ln_usdt_escrow/program-ids.json', import.meta.url), 'utf8')).mainnet
+);
 
 const ESCROW_SEED = Buffer.from('escrow');
 const CONFIG_SEED = Buffer.from('config');
 const TRADE_CONFIG_SEED = Buffer.from('trade_config');
+const PAYER_STATS_SEED = Buffer.from('payer_stats');
+const MINT_STATS_SEED = Buffer.from('stats');
+const AUDIT_LOG_SEED = Buffer.from('audit_log');
 
 function hexToBytes(hex) {
   const h = String(hex || '').trim().toLowerCase();
@@ -57,8 +67,10 @@ export function deriveEscrowPda(paymentHashHex, programId = LN_USDT_ESCROW_PROGR
   return { pda, bump };
 }
 
-export function deriveConfigPda(programId = LN_USDT_ESCROW_PROGRAM_ID) {
-  const [pda, bump] = PublicKey.findProgramAddressSync([CONFIG_SEED], programId);
+// Platform configs are namespaced by a tenant pubkey (the operator that created them); the default config has none.
+export function deriveConfigPda(programId = LN_USDT_ESCROW_PROGRAM_ID, tenant = null) {
+  const seeds = tenant ? [CONFIG_SEED, Buffer.from(tenant.toBytes())] : [CONFIG_SEED];
+  const [pda, bump] = PublicKey.findProgramAddressSync(seeds, programId);
   return { pda, bump };
 }
 
@@ -71,6 +83,36 @@ export function deriveTradeConfigPda(feeCollector, programId = LN_USDT_ESCROW_PR
   return { pda, bump };
 }
 
+export function derivePayerStatsPda(payer, mint, programId = LN_USDT_ESCROW_PROGRAM_ID) {
+  const [pda, bump] = PublicKey.findProgramAddressSync(
+    [PAYER_STATS_SEED, Buffer.from(payer.toBytes()), Buffer.from(mint.toBytes())],
+    programId
+  );
+  return { pda, bump };
+}
+
+// Accounting PDA of one config (platform or trade PDA) in one mint; see decodeMintStatsState.
+export function deriveMintStatsPda(configPda, mint, programId = LN_USDT_ESCROW_PROGRAM_ID) {
+  const [pda, bump] = PublicKey.findProgramAddressSync(
+    [MINT_STATS_SEED, Buffer.from(configPda.toBytes()), Buffer.from(mint.toBytes())],
+    programId
+  );
+  return { pda, bump };
+}
+
+// [platform, trade] mint stats PDAs an escrow's init/claim/refund must pass.
+export function mintStatsPdasFor({ mint, tradeFeeCollector, tenant = null, programId = LN_USDT_ESCROW_PROGRAM_ID }) {
+  const { pda: configPda } = deriveConfigPda(programId, tenant);
+  const { pda: tradeConfigPda } = deriveTradeConfigPda(tradeFeeCollector, programId);
+  return [deriveMintStatsPda(configPda, mint, programId).pda, deriveMintStatsPda(tradeConfigPda, mint, programId).pda];
+}
+
+// Ring buffer of the last SetConfig/SetTradeConfig changes of a config (platform or trade PDA).
+export function deriveAuditLogPda(configPda, programId = LN_USDT_ESCROW_PROGRAM_ID) {
+  const [pda, bump] = PublicKey.findProgramAddressSync([AUDIT_LOG_SEED, Buffer.from(configPda.toBytes())], programId);
+  return { pda, bump };
+}
+
 export async function deriveVaultAta(escrowPda, mint) {
   return getAssociatedTokenAddress(mint, escrowPda, true, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID);
 }
@@ -85,6 +127,33 @@ export async function deriveTradeFeeVaultAta(tradeConfigPda, mint) {
   return getAssociatedTokenAddress(mint, tradeConfigPda, true, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID);
 }
 
+// Fee destinations for Init/Claim: the config PDAs' fee vaults, or the collectors' own ATAs in direct payout mode.
+async function deriveFeeDestinations({
+  configPda,
+  tradeConfigPda,
+  mint,
+  platformDirectPayout,
+  platformFeeCollector,
+  tradeDirectPayout,
+  tradeFeeCollector,
+}) {
+  if (platformDirectPayout && !platformFeeCollector) {
+    throw new Error('platformFeeCollector is required for direct payout');
+  }
+  const platformFeeVaultAta = platformDirectPayout
+    ? await getAssociatedTokenAddress(mint, platformFeeCollector, true, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID)
+    : await deriveFeeVaultAta(configPda, mint);
+  const tradeFeeVaultAta = tradeDirectPayout
+    ? await getAssociatedTokenAddress(mint, tradeFeeCollector, true, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID)
+    : await deriveTradeFeeVaultAta(tradeConfigPda, mint);
+  return { platformFeeVaultAta, tradeFeeVaultAta };
+}
+
+// Referral fees are paid to the referrer's own ATA.
+export async function deriveReferrerAta(referrer, mint) {
+  return getAssociatedTokenAddress(mint, referrer, true, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID);
+}
+
 export function buildInitInstruction({
   paymentHashHex,
   recipient,
@@ -101,10 +170,19 @@ export function buildInitInstruction({
   platformFeeVaultAta,
   tradeConfigPda,
   tradeFeeVaultAta,
+  referrer = null,
+  referralFeeBps = 0,
+  referrerTokenAccount = null,
+  payerStatsPda = null, // required when the platform config has volume tiers (see derivePayerStatsPda)
+  // Lamport fee mode: must match the configs' per-escrow lamport fees (0 = token fee).
+  expectedPlatformLamportFee = 0n,
+  expectedTradeLamportFee = 0n,
+  tenant = null, // platform config namespace (see deriveConfigPda); null = the default config
   programId = LN_USDT_ESCROW_PROGRAM_ID,
 }) {
   const { pda: escrowPda } = deriveEscrowPda(paymentHashHex, programId);
-  const { pda: configPda } = deriveConfigPda(programId);
+  const { pda: configPda } = deriveConfigPda(programId, tenant);
+  if (referrer && !referrerTokenAccount) throw new Error('referrerTokenAccount is required with a referrer');
   const tradeCollectorPk = tradeFeeCollector;
   if (!(tradeCollectorPk instanceof PublicKey)) throw new Error('tradeFeeCollector must be a PublicKey');
   const wantTradeCfg = deriveTradeConfigPda(tradeCollectorPk, programId).pda;
@@ -121,26 +199,48 @@ export function buildInitInstruction({
     u16Le(expectedTradeFeeBps),
     Buffer.from(tradeCollectorPk.toBytes()),
   ]);
+  const platformLamportFee = BigInt(expectedPlatformLamportFee);
+  const tradeLamportFee = BigInt(expectedTradeLamportFee);
+  const withLamportFees = platformLamportFee > 0n || tradeLamportFee > 0n;
+  // Optional trailing arg groups: referral, then expected lamport fees (which need the referral group, zeroed
+  // when there is no referrer).
+  const optional = [];
+  if (referrer || withLamportFees) {
+    optional.push(Buffer.from((referrer ?? PublicKey.default).toBytes()), u16Le(referrer ? referralFeeBps : 0));
+  }
+  if (withLamportFees) optional.push(u64Le(platformLamportFee), u64Le(tradeLamportFee));
+  const fullData = Buffer.concat([data, ...optional]);
 
-  return new TransactionInstruction({
-    programId,
-    keys: [
-      { pubkey: payer, isSigner: true, isWritable: true },
-      { pubkey: payerTokenAccount, isSigner: false, isWritable: true },
-      { pubkey: escrowPda, isSigner: false, isWritable: true },
-      { pubkey: vault, isSigner: false, isWritable: true },
-      { pubkey: mint, isSigner: false, isWritable: false },
-      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
-      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
-      { pubkey: ASSOCIATED_TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
-      { pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false },
-      { pubkey: configPda, isSigner: false, isWritable: false },
-      { pubkey: platformFeeVaultAta, isSigner: false, isWritable: true },
-      { pubkey: tradeConfigPda, isSigner: false, isWritable: false },
-      { pubkey: tradeFeeVaultAta, isSigner: false, isWritable: true },
-    ],
-    data,
-  });
+  const keys = [
+    { pubkey: payer, isSigner: true, isWritable: true },
+    { pubkey: payerTokenAccount, isSigner: false, isWritable: true },
+    { pubkey: escrowPda, isSigner: false, isWritable: true },
+    { pubkey: vault, isSigner: false, isWritable: true },
+    { pubkey: mint, isSigner: false, isWritable: false },
+    { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+    { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+    { pubkey: ASSOCIATED_TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+    { pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false },
+    { pubkey: configPda, isSigner: false, isWritable: platformLamportFee > 0n },
+    { pubkey: platformFeeVaultAta, isSigner: false, isWritable: true },
+    { pubkey: tradeConfigPda, isSigner: false, isWritable: tradeLamportFee > 0n },
+    { pubkey: tradeFeeVaultAta, isSigner: false, isWritable: true },
+  ];
+  if (referrer) keys.push({ pubkey: referrerTokenAccount, isSigner: false, isWritable: false });
+  if (payerStatsPda) keys.push({ pubkey: payerStatsPda, isSigner: false, isWritable: true });
+  keys.push(
+    { pubkey: deriveMintStatsPda(configPda, mint, programId).pda, isSigner: false, isWritable: true },
+    { pubkey: deriveMintStatsPda(tradeConfigPda, mint, programId).pda, isSigner: false, isWritable: true }
+  );
+  return new TransactionInstruction({ programId, keys, data: fullData });
+}
+
+// ValidateInit: same args and accounts as Init; meant for simulation. It never fails on a check and reports the
+// outcome through return data instead (see decodeInitValidation).
+export function buildValidateInitInstruction(params) {
+  const ix = buildInitInstruction(params);
+  ix.data[0] = 26;
+  return ix;
 }
 
 export function buildClaimInstruction({
@@ -150,6 +250,10 @@ export function buildClaimInstruction({
   recipientTokenAccount,
   platformFeeVaultAta,
   tradeFeeVaultAta,
+  referrerTokenAccount = null, // required when the escrow has a referrer
+  lamportFeeConfigs = [], // config PDAs holding the escrow's lamport fees (platform first, then trade)
+  payerStatsPda = null, // required when the escrow tracks payer volume (see decodeEscrowState)
+  mintStatsPdas = [], // [platform, trade] mint stats PDAs (see mintStatsPdasFor); required for every escrow
   programId = LN_USDT_ESCROW_PROGRAM_ID,
 }) {
   const { pda: escrowPda } = deriveEscrowPda(paymentHashHex, programId);
@@ -157,45 +261,73 @@ export function buildClaimInstruction({
   if (preimage.length !== 32) throw new Error('preimage must be 32 bytes');
   const data = Buffer.concat([Buffer.from([1]), preimage]);
 
-  return (vault) =>
-    new TransactionInstruction({
-      programId,
-      keys: [
-        { pubkey: recipient, isSigner: true, isWritable: false },
-        { pubkey: escrowPda, isSigner: false, isWritable: true },
-        { pubkey: vault, isSigner: false, isWritable: true },
-        { pubkey: recipientTokenAccount, isSigner: false, isWritable: true },
-        { pubkey: platformFeeVaultAta, isSigner: false, isWritable: true },
-        { pubkey: tradeFeeVaultAta, isSigner: false, isWritable: true },
-        { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
-      ],
-      data,
-    });
+  return (vault) => {
+    const keys = [
+      { pubkey: recipient, isSigner: true, isWritable: false },
+      { pubkey: escrowPda, isSigner: false, isWritable: true },
+      { pubkey: vault, isSigner: false, isWritable: true },
+      { pubkey: recipientTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: platformFeeVaultAta, isSigner: false, isWritable: true },
+      { pubkey: tradeFeeVaultAta, isSigner: false, isWritable: true },
+      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+    ];
+    if (referrerTokenAccount) keys.push({ pubkey: referrerTokenAccount, isSigner: false, isWritable: true });
+    for (const pubkey of lamportFeeConfigs) keys.push({ pubkey, isSigner: false, isWritable: true });
+    if (payerStatsPda) keys.push({ pubkey: payerStatsPda, isSigner: false, isWritable: true });
+    for (const pubkey of mintStatsPdas) keys.push({ pubkey, isSigner: false, isWritable: true });
+    return new TransactionInstruction({ programId, keys, data });
+  };
 }
 
 export function buildRefundInstruction({
   paymentHashHex,
   refund,
   refundTokenAccount,
+  lamportFeeConfigs = [], // config PDAs holding the escrow's lamport fees (platform first, then trade)
+  mintStatsPdas = [], // [platform, trade] mint stats PDAs (see mintStatsPdasFor); ignored by escrows without stats
   programId = LN_USDT_ESCROW_PROGRAM_ID,
 }) {
   const { pda: escrowPda } = deriveEscrowPda(paymentHashHex, programId);
   const data = Buffer.from([2]);
-  return (vault) =>
-    new TransactionInstruction({
-      programId,
-      keys: [
-        { pubkey: refund, isSigner: true, isWritable: false },
-        { pubkey: escrowPda, isSigner: false, isWritable: true },
-        { pubkey: vault, isSigner: false, isWritable: true },
-        { pubkey: refundTokenAccount, isSigner: false, isWritable: true },
-        { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
-        { pubkey: SYSVAR_CLOCK_PUBKEY, isSigner: false, isWritable: false },
-      ],
-      data,
-    });
+  return (vault) => {
+    const keys = [
+      // Lamport fees are returned to the refund authority, so it must be writable then.
+      { pubkey: refund, isSigner: true, isWritable: lamportFeeConfigs.length > 0 },
+      { pubkey: escrowPda, isSigner: false, isWritable: true },
+      { pubkey: vault, isSigner: false, isWritable: true },
+      { pubkey: refundTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+      { pubkey: SYSVAR_CLOCK_PUBKEY, isSigner: false, isWritable: false },
+    ];
+    for (const pubkey of lamportFeeConfigs) keys.push({ pubkey, isSigner: false, isWritable: true });
+    for (const pubkey of mintStatsPdas) keys.push({ pubkey, isSigner: false, isWritable: true });
+    return new TransactionInstruction({ programId, keys, data });
+  };
 }
 
+// Config PDAs an escrow's claim/refund must pass because they hold its lamport fees (see decodeEscrowState).
+export function lamportFeeConfigsFor({
+  platformLamportFee = 0n,
+  tradeLamportFee = 0n,
+  tradeFeeCollector = null,
+  tenant = null,
+  programId = LN_USDT_ESCROW_PROGRAM_ID,
+}) {
+  const out = [];
+  if (BigInt(platformLamportFee) > 0n) out.push(deriveConfigPda(programId, tenant).pda);
+  if (BigInt(tradeLamportFee) > 0n) {
+    if (!tradeFeeCollector) throw new Error('tradeFeeCollector is required for a trade lamport fee');
+    out.push(deriveTradeConfigPda(tradeFeeCollector, programId).pda);
+  }
+  return out;
+}
+
+// Sizes of the program accounts Init creates at the latest layouts; the payer funds the rent of whichever are
+// still missing (see createEscrowTx).
+export const ESCROW_STATE_SPACE = 458;
+export const MINT_STATS_SPACE = 114;
+export const PAYER_STATS_SPACE = 426;
+
 export function decodeEscrowState(data) {
   const buf = Buffer.from(data);
   const v = buf.readUInt8(0);
@@ -260,8 +392,10 @@ export function decodeEscrowState(data) {
     };
   }
 
-  if (v === 3) {
-    if (buf.length < 263) throw new Error('Escrow account too small (v3)');
+  // v10 only appends zeroed reserved bytes.
+  if (v >= 3 && v <= 10) {
+    const minLen = { 3: 263, 4: 305, 5: 313, 6: 314, 7: 330, 8: 362, 9: 394, 10: ESCROW_STATE_SPACE }[v];
+    if (buf.length < minLen) throw new Error(`Escrow account too small (v${v})`);
     const status = buf.readUInt8(1);
     const paymentHash = buf.subarray(2, 34);
     const recipient = new PublicKey(buf.subarray(34, 66));
@@ -277,6 +411,28 @@ export function decodeEscrowState(data) {
     const tradeFeeCollector = new PublicKey(buf.subarray(198, 230));
     const vault = new PublicKey(buf.subarray(230, 262));
     const bump = buf.readUInt8(262);
+    // v4 appends the optional referrer (all-zero = none).
+    let referrer = null;
+    let referralFeeBps = 0;
+    let referralFeeAmount = 0n;
+    if (v >= 4) {
+      const referrerPk = new PublicKey(buf.subarray(263, 295));
+      if (!referrerPk.equals(PublicKey.default)) referrer = referrerPk;
+      referralFeeBps = buf.readUInt16LE(295);
+      referralFeeAmount = buf.readBigUInt64LE(297);
+    }
+    // v5: share of platformFeeAmount rebated to the recipient on claim.
+    const makerRebateAmount = v >= 5 ? buf.readBigUInt64LE(305) : 0n;
+    // v6: bit 0 = platform fee paid to the collector's ATA, bit 1 = same for the trade fee.
+    const feeFlags = v >= 6 ? buf.readUInt8(313) : 0;
+    // v7: lamport fees held by the config PDAs until the escrow settles.
+    const platformLamportFee = v >= 7 ? buf.readBigUInt64LE(314) : 0n;
+    const tradeLamportFee = v >= 7 ? buf.readBigUInt64LE(322) : 0n;
+    // v8: initial depositor, whose volume stats are credited on claim when bit 2 of the fee flags is set.
+    const payer = v >= 8 ? new PublicKey(buf.subarray(330, 362)) : null;
+    // v9: tenant of the platform config the escrow was opened against (null = the default config).
+    const tenantPk = v >= 9 ? new PublicKey(buf.subarray(362, 394)) : PublicKey.default;
+    const tenant = tenantPk.equals(PublicKey.default) ? null : tenantPk;
     return {
       v,
       status,
@@ -293,9 +449,21 @@ export function decodeEscrowState(data) {
       tradeFeeAmount,
       tradeFeeBps,
       tradeFeeCollector,
+      referrer,
+      referralFeeBps,
+      referralFeeAmount,
+      makerRebateAmount,
+      platformDirectPayout: (feeFlags & 1) !== 0,
+      tradeDirectPayout: (feeFlags & 2) !== 0,
+      platformLamportFee,
+      tradeLamportFee,
+      payer,
+      tracksPayerVolume: (feeFlags & 4) !== 0,
+      tracksMintStats: (feeFlags & 8) !== 0,
+      tenant,
       // Backwards-compatible fields expected by older code paths:
-      feeAmount: platformFeeAmount + tradeFeeAmount,
-      feeBps: platformFeeBps + tradeFeeBps,
+      feeAmount: platformFeeAmount + tradeFeeAmount + referralFeeAmount,
+      feeBps: platformFeeBps + tradeFeeBps + referralFeeBps,
       feeCollector: platformFeeCollector, // platform collector (legacy name)
       vault,
       bump,
@@ -309,28 +477,329 @@ export function decodeConfigState(data) {
   const buf = Buffer.from(data);
   if (buf.length < 68) throw new Error('Config account too small');
   const v = buf.readUInt8(0);
-  if (v !== 1) throw new Error(`Unsupported config version v=${v}`);
+  if (v < 1 || v > 10) throw new Error(`Unsupported config version v=${v}`);
   const authority = new PublicKey(buf.subarray(1, 33));
   const feeCollector = new PublicKey(buf.subarray(33, 65));
   const feeBps = buf.readUInt16LE(65);
   const bump = buf.readUInt8(67);
-  return { v, authority, feeCollector, feeBps, bump };
+  const out = {
+    v,
+    authority,
+    feeCollector,
+    feeBps,
+    bump,
+    treasury: null,
+    revenueSplit: [],
+    maxReferralBps: 0,
+    makerRebateBps: 0,
+    directPayout: false,
+    lamportFee: 0n,
+    lamportFeesReserved: 0n,
+    volumeTiers: [],
+    tenant: null,
+  };
+  if (v >= 2) {
+    if (buf.length < 136) throw new Error('Config account too small (v2)');
+    const treasuryOwner = new PublicKey(buf.subarray(68, 100));
+    // All-zero treasury owner = treasury mode off.
+    if (!treasuryOwner.equals(PublicKey.default)) {
+      out.treasury = {
+        owner: treasuryOwner,
+        guardian: new PublicKey(buf.subarray(100, 132)),
+        withdrawDelaySecs: buf.readUInt32LE(132),
+      };
+    }
+  }
+  if (v >= 3) {
+    if (buf.length < 409) throw new Error('Config account too small (v3)');
+    // Up to 8 fixed slots of (owner, weightBps); only the first `count` are active.
+    const count = buf.readUInt8(136);
+    for (let i = 0; i < count; i += 1) {
+      const off = 137 + i * 34;
+      out.revenueSplit.push({
+        owner: new PublicKey(buf.subarray(off, off + 32)),
+        weightBps: buf.readUInt16LE(off + 32),
+      });
+    }
+  }
+  if (v >= 4) {
+    if (buf.length < 411) throw new Error('Config account too small (v4)');
+    out.maxReferralBps = buf.readUInt16LE(409);
+  }
+  if (v >= 5) {
+    if (buf.length < 413) throw new Error('Config account too small (v5)');
+    out.makerRebateBps = buf.readUInt16LE(411);
+  }
+  if (v >= 6) {
+    if (buf.length < 414) throw new Error('Config account too small (v6)');
+    out.directPayout = buf.readUInt8(413) !== 0;
+  }
+  if (v >= 7) {
+    if (buf.length < 430) throw new Error('Config account too small (v7)');
+    out.lamportFee = buf.readBigUInt64LE(414);
+    out.lamportFeesReserved = buf.readBigUInt64LE(422);
+  }
+  if (v >= 8) {
+    if (buf.length < 471) throw new Error('Config account too small (v8)');
+    // Up to 4 fixed slots of (minVolume, discountBps); only the first `count` are active.
+    const count = buf.readUInt8(430);
+    for (let i = 0; i < count; i += 1) {
+      const off = 431 + i * 10;
+      out.volumeTiers.push({ minVolume: buf.readBigUInt64LE(off), discountBps: buf.readUInt16LE(off + 8) });
+    }
+  }
+  if (v >= 9) {
+    if (buf.length < 503) throw new Error('Config account too small (v9)');
+    const tenant = new PublicKey(buf.subarray(471, 503));
+    if (!tenant.equals(PublicKey.default)) out.tenant = tenant;
+  }
+  // v10 only appends zeroed reserved bytes.
+  if (v >= 10 && buf.length < 567) throw new Error('Config account too small (v10)');
+  return out;
+}
+
+// Running totals of one config in one mint. The config's fee vault balance equals feesAccrued - feesWithdrawn.
+export function decodeMintStatsState(data) {
+  const buf = Buffer.from(data);
+  if (buf.length < MINT_STATS_SPACE) throw new Error('MintStats account too small');
+  const v = buf.readUInt8(0);
+  if (v !== 1) throw new Error(`Unsupported mint stats version v=${v}`);
+  return {
+    v,
+    config: new PublicKey(buf.subarray(1, 33)),
+    mint: new PublicKey(buf.subarray(33, 65)),
+    bump: buf.readUInt8(65),
+    totalVolume: buf.readBigUInt64LE(66),
+    activeEscrows: buf.readBigUInt64LE(74),
+    claimedEscrows: buf.readBigUInt64LE(82),
+    refundedEscrows: buf.readBigUInt64LE(90),
+    feesAccrued: buf.readBigUInt64LE(98),
+    feesWithdrawn: buf.readBigUInt64LE(106),
+  };
+}
+
+// Return data of Init/Claim/Refund (simulation `returnData.data[0]`, base64). Layout: version byte, then the
+// Borsh-encoded EscrowOutcome.
+export function decodeEscrowOutcome(data) {
+  const buf = Buffer.isBuffer(data) ? data : Buffer.from(data, typeof data === 'string' ? 'base64' : undefined);
+  if (buf.length < 106) throw new Error('Escrow return data too small');
+  const version = buf.readUInt8(0);
+  if (version !== 1) throw new Error(`Unsupported escrow return data version=${version}`);
+  return {
+    escrow: new PublicKey(buf.subarray(1, 33)),
+    vault: new PublicKey(buf.subarray(33, 65)),
+    status: buf.readUInt8(65),
+    totalAmount: buf.readBigUInt64LE(66),
+    netAmount: buf.readBigUInt64LE(74),
+    platformFeeAmount: buf.readBigUInt64LE(82),
+    tradeFeeAmount: buf.readBigUInt64LE(90),
+    referralFeeAmount: buf.readBigUInt64LE(98),
+  };
+}
+
+export const INIT_FAILURE_REASONS = Object.freeze({
+  1: 'not_enough_accounts',
+  2: 'payer_not_signer',
+  3: 'account_not_writable',
+  4: 'escrow_pda_mismatch',
+  5: 'invalid_platform_config',
+  6: 'platform_fee_too_high',
+  7: 'referrer_without_fee',
+  8: 'referral_fee_above_cap',
+  9: 'referrer_ata_mismatch',
+  10: 'invalid_referrer_ata',
+  11: 'invalid_payer_stats',
+  12: 'platform_fee_bps_mismatch',
+  13: 'platform_lamport_fee_mismatch',
+  14: 'vault_ata_mismatch',
+  15: 'trade_config_pda_mismatch',
+  16: 'trade_config_not_initialized',
+  17: 'invalid_trade_config',
+  18: 'trade_fee_too_high',
+  19: 'trade_fee_bps_mismatch',
+  20: 'trade_lamport_fee_mismatch',
+  21: 'total_fee_too_high',
+  22: 'platform_fee_vault_mismatch',
+  23: 'trade_fee_vault_mismatch',
+  24: 'invalid_mint_stats',
+  25: 'invalid_payer_token',
+  26: 'payer_token_owner_mismatch',
+  27: 'payer_token_mint_mismatch',
+  28: 'amount_overflow',
+  29: 'insufficient_balance',
+  30: 'escrow_already_initialized',
+  31: 'invalid_program_account',
+  32: 'invalid_sysvar',
+  255: 'other',
+});
+
+// Return data of ValidateInit. `reason` is null when Init would succeed; `programError` is the error Init would
+// return, as Solana's u64 encoding (custom codes as-is, builtin errors as index << 32). Fee fields hold the
+// program's own values.
+export function decodeInitValidation(data) {
+  const buf = Buffer.isBuffer(data) ? data : Buffer.from(data, typeof data === 'string' ? 'base64' : undefined);
+  if (buf.length < 78) throw new Error('ValidateInit return data too small');
+  const version = buf.readUInt8(0);
+  if (version !== 1) throw new Error(`Unsupported ValidateInit return data version=${version}`);
+  const code = buf.readUInt8(1);
+  return {
+    ok: code === 0,
+    reasonCode: code,
+    reason: code === 0 ? null : (INIT_FAILURE_REASONS[code] ?? `unknown_${code}`),
+    programError: buf.readBigUInt64LE(2),
+    platformFeeBps: buf.readUInt16LE(10),
+    tradeFeeBps: buf.readUInt16LE(12),
+    platformLamportFee: buf.readBigUInt64LE(14),
+    tradeLamportFee: buf.readBigUInt64LE(22),
+    netAmount: buf.readBigUInt64LE(30),
+    platformFeeAmount: buf.readBigUInt64LE(38),
+    tradeFeeAmount: buf.readBigUInt64LE(46),
+    referralFeeAmount: buf.readBigUInt64LE(54),
+    makerRebateAmount: buf.readBigUInt64LE(62),
+    totalAmount: buf.readBigUInt64LE(70),
+  };
+}
+
+const AUDIT_LOG_ENTRIES = 16;
+
+// Config change history, oldest first (at most the last 16 changes; totalChanges counts all of them).
+export function decodeAuditLogState(data) {
+  const buf = Buffer.from(data);
+  if (buf.length < 42 + AUDIT_LOG_ENTRIES * 116) throw new Error('AuditLog account too small');
+  const v = buf.readUInt8(0);
+  if (v !== 1) throw new Error(`Unsupported audit log version v=${v}`);
+  const totalChanges = buf.readBigUInt64LE(34);
+  const kept = Number(totalChanges < BigInt(AUDIT_LOG_ENTRIES) ? totalChanges : BigInt(AUDIT_LOG_ENTRIES));
+  const first = Number((totalChanges - BigInt(kept)) % BigInt(AUDIT_LOG_ENTRIES));
+  const entries = [];
+  for (let i = 0; i < kept; i += 1) {
+    const off = 42 + ((first + i) % AUDIT_LOG_ENTRIES) * 116;
+    entries.push({
+      slot: buf.readBigUInt64LE(off),
+      unixTimestamp: buf.readBigInt64LE(off + 8),
+      signer: new PublicKey(buf.subarray(off + 16, off + 48)),
+      oldFeeBps: buf.readUInt16LE(off + 48),
+      newFeeBps: buf.readUInt16LE(off + 50),
+      oldFeeCollector: new PublicKey(buf.subarray(off + 52, off + 84)),
+      newFeeCollector: new PublicKey(buf.subarray(off + 84, off + 116)),
+    });
+  }
+  return { v, config: new PublicKey(buf.subarray(1, 33)), bump: buf.readUInt8(33), totalChanges, entries };
+}
+
+const PAYER_STATS_DAYS = 30;
+
+export function decodePayerStatsState(data) {
+  const buf = Buffer.from(data);
+  if (buf.length < PAYER_STATS_SPACE) throw new Error('PayerStats account too small');
+  const v = buf.readUInt8(0);
+  if (v !== 1) throw new Error(`Unsupported payer stats version v=${v}`);
+  // Ring of (unixDay, volume) buckets indexed by unixDay % 30; stale buckets are overwritten on reuse.
+  const days = [];
+  for (let i = 0; i < PAYER_STATS_DAYS; i += 1) {
+    const off = 66 + i * 12;
+    days.push({ day: buf.readUInt32LE(off), volume: buf.readBigUInt64LE(off + 4) });
+  }
+  return {
+    v,
+    payer: new PublicKey(buf.subarray(1, 33)),
+    mint: new PublicKey(buf.subarray(33, 65)),
+    bump: buf.readUInt8(65),
+    days,
+  };
+}
+
+// Mirrors the program: claimed volume over the 30 unix days ending today (missing stats account = 0).
+export function payerRollingVolume(payerStats, nowUnix) {
+  if (!payerStats) return 0n;
+  const today = Math.floor(Number(nowUnix) / 86400);
+  let total = 0n;
+  for (const { day, volume } of payerStats.days) {
+    if (day <= today && day + PAYER_STATS_DAYS > today) total += volume;
+  }
+  return total;
+}
+
+// The platform fee bps Init expects for a payer with `rollingVolume` (see payerRollingVolume): the highest
+// volume tier reached waives its share of the fee. 0 in lamport fee mode.
+export function expectedPlatformFeeBps(config, rollingVolume = 0n) {
+  if (BigInt(config.lamportFee ?? 0n) > 0n) return 0;
+  let discountBps = 0;
+  for (const tier of config.volumeTiers ?? []) {
+    if (BigInt(rollingVolume) >= tier.minVolume) discountBps = tier.discountBps;
+  }
+  return Math.floor((config.feeBps * (10000 - discountBps)) / 10000);
 }
 
 export function decodeTradeConfigState(data) {
   const buf = Buffer.from(data);
   if (buf.length < 68) throw new Error('TradeConfig account too small');
   const v = buf.readUInt8(0);
-  if (v !== 1) throw new Error(`Unsupported trade config version v=${v}`);
+  if (v < 1 || v > 6) throw new Error(`Unsupported trade config version v=${v}`);
   const authority = new PublicKey(buf.subarray(1, 33));
   const feeCollector = new PublicKey(buf.subarray(33, 65));
   const feeBps = buf.readUInt16LE(65);
   const bump = buf.readUInt8(67);
-  return { v, authority, feeCollector, feeBps, bump };
+  const out = {
+    v,
+    authority,
+    feeCollector,
+    feeBps,
+    bump,
+    treasury: null,
+    directPayout: false,
+    lamportFee: 0n,
+    lamportFeesReserved: 0n,
+    lockSurchargeBpsPerHour: 0,
+  };
+  if (v >= 2) {
+    if (buf.length < 136) throw new Error('TradeConfig account too small (v2)');
+    const treasuryOwner = new PublicKey(buf.subarray(68, 100));
+    // All-zero treasury owner = treasury mode off.
+    if (!treasuryOwner.equals(PublicKey.default)) {
+      out.treasury = {
+        owner: treasuryOwner,
+        guardian: new PublicKey(buf.subarray(100, 132)),
+        withdrawDelaySecs: buf.readUInt32LE(132),
+      };
+    }
+  }
+  if (v >= 3) {
+    if (buf.length < 137) throw new Error('TradeConfig account too small (v3)');
+    out.directPayout = buf.readUInt8(136) !== 0;
+  }
+  if (v >= 4) {
+    if (buf.length < 153) throw new Error('TradeConfig account too small (v4)');
+    out.lamportFee = buf.readBigUInt64LE(137);
+    out.lamportFeesReserved = buf.readBigUInt64LE(145);
+  }
+  if (v >= 5) {
+    if (buf.length < 155) throw new Error('TradeConfig account too small (v5)');
+    out.lockSurchargeBpsPerHour = buf.readUInt16LE(153);
+  }
+  // v6 only appends zeroed reserved bytes.
+  if (v >= 6 && buf.length < 219) throw new Error('TradeConfig account too small (v6)');
+  return out;
 }
 
-export async function getConfigState(connection, programId = LN_USDT_ESCROW_PROGRAM_ID, commitment = 'confirmed') {
-  const { pda } = deriveConfigPda(programId);
+// Mirrors the program's lock surcharge: the trade fee bps Init charges for an escrow locked from `nowUnix` until
+// `refundAfterUnix` (base fee + surcharge per started hour, capped at 10%; 0 in lamport fee mode). Init takes the
+// expected trade bps as a cap and charges what it computes from the cluster clock at execution, so a quote made
+// just before sending (with the cluster's time as `nowUnix`) is never too low.
+export function expectedTradeFeeBps(tradeConfig, { nowUnix, refundAfterUnix }) {
+  if (BigInt(tradeConfig.lamportFee ?? 0n) > 0n) return 0;
+  const lockSecs = Math.max(0, Number(refundAfterUnix) - Number(nowUnix));
+  const hours = Math.ceil(lockSecs / 3600);
+  return Math.min(tradeConfig.feeBps + hours * (tradeConfig.lockSurchargeBpsPerHour ?? 0), 1000);
+}
+
+export async function getConfigState(
+  connection,
+  programId = LN_USDT_ESCROW_PROGRAM_ID,
+  commitment = 'confirmed',
+  tenant = null
+) {
+  const { pda } = deriveConfigPda(programId, tenant);
   const info = await connection.getAccountInfo(pda, commitment);
   if (!info) return null;
   return decodeConfigState(info.data);
@@ -405,8 +874,10 @@ export async function setTradeConfigTx({
   const ix = new TransactionInstruction({
     programId,
     keys: [
-      { pubkey: authority.publicKey, isSigner: true, isWritable: false },
+      { pubkey: authority.publicKey, isSigner: true, isWritable: true },
       { pubkey: tradeConfigPda, isSigner: false, isWritable: true },
+      { pubkey: deriveAuditLogPda(tradeConfigPda, programId).pda, isSigner: false, isWritable: true },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
     ],
     data,
   });
@@ -441,6 +912,7 @@ export async function withdrawTradeFeesTx({
       { pubkey: feeVaultAta, isSigner: false, isWritable: true },
       { pubkey: feeCollectorTokenAccount, isSigner: false, isWritable: true },
       { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+      { pubkey: deriveMintStatsPda(tradeConfigPda, mint, programId).pda, isSigner: false, isWritable: true },
     ],
     data,
   });
@@ -454,6 +926,35 @@ export async function withdrawTradeFeesTx({
   return { tx, feeVaultAta, tradeConfigPda };
 }
 
+// Rewrites an escrow, platform config or trade config at the program's latest layout; `payer` covers the rent
+// of the larger account. Accounts already at the latest layout are left untouched.
+export async function migrateAccountTx({
+  connection,
+  payer,
+  account,
+  computeUnitLimit = null,
+  computeUnitPriceMicroLamports = null,
+  programId = LN_USDT_ESCROW_PROGRAM_ID,
+}) {
+  const ix = new TransactionInstruction({
+    programId,
+    keys: [
+      { pubkey: payer.publicKey, isSigner: true, isWritable: true },
+      { pubkey: account, isSigner: false, isWritable: true },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+    ],
+    data: Buffer.from([28]),
+  });
+  const tx = new Transaction();
+  for (const cbIx of buildComputeBudgetIxs({ computeUnitLimit, computeUnitPriceMicroLamports })) tx.add(cbIx);
+  tx.add(ix);
+  tx.feePayer = payer.publicKey;
+  const latest = await connection.getLatestBlockhash('confirmed');
+  tx.recentBlockhash = latest.blockhash;
+  tx.sign(payer);
+  return { tx };
+}
+
 export async function createEscrowTx({
   connection,
   payer,
@@ -467,16 +968,35 @@ export async function createEscrowTx({
   expectedPlatformFeeBps,
   expectedTradeFeeBps,
   tradeFeeCollector,
+  referrer = null,
+  referralFeeBps = 0,
+  // Direct payout mode (see decodeConfigState/decodeTradeConfigState): fees go to the collectors' own ATAs.
+  platformDirectPayout = false,
+  platformFeeCollector = null,
+  tradeDirectPayout = false,
+  expectedPlatformLamportFee = 0n,
+  expectedTradeLamportFee = 0n,
+  trackPayerVolume = false, // platform config has volume tiers (see decodeConfigState)
   computeUnitLimit = null,
   computeUnitPriceMicroLamports = null,
+  tenant = null, // platform config to open the escrow against (see deriveConfigPda)
   programId = LN_USDT_ESCROW_PROGRAM_ID,
 }) {
   const { pda: escrowPda } = deriveEscrowPda(paymentHashHex, programId);
-  const { pda: configPda } = deriveConfigPda(programId);
+  const { pda: configPda } = deriveConfigPda(programId, tenant);
   const vault = await deriveVaultAta(escrowPda, mint);
-  const platformFeeVaultAta = await deriveFeeVaultAta(configPda, mint);
   const { pda: tradeConfigPda } = deriveTradeConfigPda(tradeFeeCollector, programId);
-  const tradeFeeVaultAta = await deriveTradeFeeVaultAta(tradeConfigPda, mint);
+  const { platformFeeVaultAta, tradeFeeVaultAta } = await deriveFeeDestinations({
+    configPda,
+    tradeConfigPda,
+    mint,
+    platformDirectPayout,
+    platformFeeCollector,
+    tradeDirectPayout,
+    tradeFeeCollector,
+  });
+  const referrerTokenAccount = referrer ? await deriveReferrerAta(referrer, mint) : null;
+  const payerStatsPda = trackPayerVolume ? derivePayerStatsPda(payer.publicKey, mint, programId).pda : null;
 
   const initIx = buildInitInstruction({
     paymentHashHex,
@@ -494,6 +1014,13 @@ export async function createEscrowTx({
     platformFeeVaultAta,
     tradeConfigPda,
     tradeFeeVaultAta,
+    referrer,
+    referralFeeBps,
+    referrerTokenAccount,
+    payerStatsPda,
+    expectedPlatformLamportFee,
+    expectedTradeLamportFee,
+    tenant,
     programId,
   });
 
@@ -506,7 +1033,8 @@ export async function createEscrowTx({
   const latest = await connection.getLatestBlockhash('confirmed');
   tx.recentBlockhash = latest.blockhash;
   tx.sign(payer);
-  return { tx, escrowPda, vault, platformFeeVaultAta, tradeConfigPda, tradeFeeVaultAta };
+  const mintStatsPdas = mintStatsPdasFor({ mint, tradeFeeCollector, tenant, programId });
+  return { tx, escrowPda, vault, platformFeeVaultAta, tradeConfigPda, tradeFeeVaultAta, mintStatsPdas, payerStatsPda };
 }
 
 export async function claimEscrowTx({
@@ -517,16 +1045,33 @@ export async function claimEscrowTx({
   paymentHashHex,
   preimageHex,
   tradeFeeCollector,
+  referrer = null, // escrow's referrer (see decodeEscrowState), if any
+  // Payout mode the escrow was opened with (see decodeEscrowState).
+  platformDirectPayout = false,
+  platformFeeCollector = null,
+  tradeDirectPayout = false,
+  platformLamportFee = 0n,
+  tradeLamportFee = 0n,
+  payer = null, // escrow's payer when it tracks payer volume (see decodeEscrowState)
   computeUnitLimit = null,
   computeUnitPriceMicroLamports = null,
+  tenant = null, // escrow's tenant (see decodeEscrowState)
   programId = LN_USDT_ESCROW_PROGRAM_ID,
 }) {
   const { pda: escrowPda } = deriveEscrowPda(paymentHashHex, programId);
-  const { pda: configPda } = deriveConfigPda(programId);
+  const { pda: configPda } = deriveConfigPda(programId, tenant);
   const vault = await deriveVaultAta(escrowPda, mint);
-  const platformFeeVaultAta = await deriveFeeVaultAta(configPda, mint);
   const { pda: tradeConfigPda } = deriveTradeConfigPda(tradeFeeCollector, programId);
-  const tradeFeeVaultAta = await deriveTradeFeeVaultAta(tradeConfigPda, mint);
+  const { platformFeeVaultAta, tradeFeeVaultAta } = await deriveFeeDestinations({
+    configPda,
+    tradeConfigPda,
+    mint,
+    platformDirectPayout,
+    platformFeeCollector,
+    tradeDirectPayout,
+    tradeFeeCollector,
+  });
+  const referrerTokenAccount = referrer ? await deriveReferrerAta(referrer, mint) : null;
   const claimIxFactory = buildClaimInstruction({
     preimageHex,
     paymentHashHex,
@@ -534,6 +1079,16 @@ export async function claimEscrowTx({
     recipientTokenAccount,
     platformFeeVaultAta,
     tradeFeeVaultAta,
+    referrerTokenAccount,
+    lamportFeeConfigs: lamportFeeConfigsFor({
+      platformLamportFee,
+      tradeLamportFee,
+      tradeFeeCollector,
+      tenant,
+      programId,
+    }),
+    payerStatsPda: payer ? derivePayerStatsPda(payer, mint, programId).pda : null,
+    mintStatsPdas: mintStatsPdasFor({ mint, tradeFeeCollector, tenant, programId }),
     programId,
   });
   const tx = new Transaction();
@@ -552,6 +1107,12 @@ export async function refundEscrowTx({
   refundTokenAccount,
   mint,
   paymentHashHex,
+  // Lamport fees the escrow paid at init (see decodeEscrowState); tradeFeeCollector is needed for a trade one,
+  // and for escrows that track mint stats.
+  platformLamportFee = 0n,
+  tradeLamportFee = 0n,
+  tradeFeeCollector = null,
+  tenant = null, // escrow's tenant (see decodeEscrowState)
   computeUnitLimit = null,
   computeUnitPriceMicroLamports = null,
   programId = LN_USDT_ESCROW_PROGRAM_ID,
@@ -562,6 +1123,14 @@ export async function refundEscrowTx({
     paymentHashHex,
     refund: refund.publicKey,
     refundTokenAccount,
+    lamportFeeConfigs: lamportFeeConfigsFor({
+      platformLamportFee,
+      tradeLamportFee,
+      tradeFeeCollector,
+      tenant,
+      programId,
+    }),
+    mintStatsPdas: tradeFeeCollector ? mintStatsPdasFor({ mint, tradeFeeCollector, tenant, programId }) : [],
     programId,
   });
   const tx = new Transaction();
@@ -581,10 +1150,17 @@ export async function initConfigTx({
   feeBps,
   computeUnitLimit = null,
   computeUnitPriceMicroLamports = null,
+  tenant = null,
   programId = LN_USDT_ESCROW_PROGRAM_ID,
 }) {
-  const { pda: configPda } = deriveConfigPda(programId);
-  const data = Buffer.concat([Buffer.from([3]), Buffer.from(feeCollector.toBytes()), u16Le(feeBps)]);
+  const { pda: configPda } = deriveConfigPda(programId, tenant);
+  // A tenant config must be created by the tenant itself (payer == tenant).
+  const data = Buffer.concat([
+    Buffer.from([3]),
+    Buffer.from(feeCollector.toBytes()),
+    u16Le(feeBps),
+    ...(tenant ? [Buffer.from(tenant.toBytes())] : []),
+  ]);
   const ix = new TransactionInstruction({
     programId,
     keys: [
@@ -612,15 +1188,18 @@ export async function setConfigTx({
   feeBps,
   computeUnitLimit = null,
   computeUnitPriceMicroLamports = null,
+  tenant = null,
   programId = LN_USDT_ESCROW_PROGRAM_ID,
 }) {
-  const { pda: configPda } = deriveConfigPda(programId);
+  const { pda: configPda } = deriveConfigPda(programId, tenant);
   const data = Buffer.concat([Buffer.from([4]), Buffer.from(feeCollector.toBytes()), u16Le(feeBps)]);
   const ix = new TransactionInstruction({
     programId,
     keys: [
-      { pubkey: authority.publicKey, isSigner: true, isWritable: false },
+      { pubkey: authority.publicKey, isSigner: true, isWritable: true },
       { pubkey: configPda, isSigner: false, isWritable: true },
+      { pubkey: deriveAuditLogPda(configPda, programId).pda, isSigner: false, isWritable: true },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
     ],
     data,
   });
@@ -642,9 +1221,10 @@ export async function withdrawFeesTx({
   amount,
   computeUnitLimit = null,
   computeUnitPriceMicroLamports = null,
+  tenant = null,
   programId = LN_USDT_ESCROW_PROGRAM_ID,
 }) {
-  const { pda: configPda } = deriveConfigPda(programId);
+  const { pda: configPda } = deriveConfigPda(programId, tenant);
   const feeVaultAta = await deriveFeeVaultAta(configPda, mint);
   const data = Buffer.concat([Buffer.from([5]), u64Le(amount ?? 0)]);
   const ix = new TransactionInstruction({
@@ -655,6 +1235,7 @@ export async function withdrawFeesTx({
       { pubkey: feeVaultAta, isSigner: false, isWritable: true },
       { pubkey: feeCollectorTokenAccount, isSigner: false, isWritable: true },
       { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+      { pubkey: deriveMintStatsPda(configPda, mint, programId).pda, isSigner: false, isWritable: true },
     ],
     data,
   });
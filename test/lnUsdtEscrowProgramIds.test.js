@@ -0,0 +1,14 @@
+import test from 'node:test';
+import assert from 'node:assert/strict';
+import fs from 'node:fs';
+
+import { LN_USDT_ESCROW_PROGRAM_ID } from '../src/solana/lnUsdtEscrowClient.js';
+
+const rustIds = JSON.parse(
+  fs.readFileSync(new URL('../solana/ln_usdt_escrow/program-ids.json', import.meta.url), 'utf8')
+);
+
+test('ln-usdt-escrow program ids: JS client matches the Rust build', () => {
+  assert.deepEqual(Object.keys(rustIds), ['mainnet']);
+  assert.equal(LN_USDT_ESCROW_PROGRAM_ID.toBase58(), rustIds.mainnet);
+});
@@ -29,6 +29,8 @@ test('sol escrow guardrail: computes required lamports from missing accounts', (
     hasVaultAccount: true,
     hasPlatformFeeVaultAccount: true,
     hasTradeFeeVaultAccount: false,
+    hasPlatformMintStatsAccount: true,
+    hasTradeMintStatsAccount: true,
     marginLamports: 0,
   });
   assert.equal(out.ok, false);
@@ -38,6 +40,46 @@ test('sol escrow guardrail: computes required lamports from missing accounts', (
   assert.deepEqual(out.missing_accounts, ['escrow_pda', 'trade_fee_vault_ata']);
 });
 
+test('sol escrow guardrail: counts stats PDA rent and lamport fees', () => {
+  const out = computeEscrowInitLamportsGuardrail({
+    payerLamports: 3_000_000,
+    feeLamports: 5000,
+    escrowRentLamports: 4_078_560,
+    tokenAccountRentLamports: 2039280,
+    mintStatsRentLamports: 1_684_560,
+    payerStatsRentLamports: 3_855_840,
+    lamportFeeLamports: 250_000,
+    hasEscrowAccount: true,
+    hasVaultAccount: true,
+    hasPlatformFeeVaultAccount: true,
+    hasTradeFeeVaultAccount: true,
+    hasPlatformMintStatsAccount: true,
+    hasTradeMintStatsAccount: false,
+    hasPayerStatsAccount: false,
+    marginLamports: 0,
+  });
+  assert.equal(out.need_lamports, 5000 + 250_000 + 1_684_560 + 3_855_840);
+  assert.equal(out.ok, false);
+  assert.deepEqual(out.missing_accounts, ['trade_mint_stats_pda', 'payer_stats_pda']);
+
+  // Escrows without volume tiers never create the payer stats PDA.
+  const noTiers = computeEscrowInitLamportsGuardrail({
+    payerLamports: 3_000_000,
+    feeLamports: 5000,
+    mintStatsRentLamports: 1_684_560,
+    payerStatsRentLamports: 3_855_840,
+    hasEscrowAccount: true,
+    hasVaultAccount: true,
+    hasPlatformFeeVaultAccount: true,
+    hasTradeFeeVaultAccount: true,
+    hasPlatformMintStatsAccount: true,
+    hasTradeMintStatsAccount: true,
+    marginLamports: 0,
+  });
+  assert.equal(noTiers.ok, true);
+  assert.deepEqual(noTiers.missing_accounts, []);
+});
+
 test('sol escrow guardrail: parses fee lamports from rpc response shape', () => {
   assert.equal(parseFeeLamports(5000), 5000);
   assert.equal(parseFeeLamports({ value: 7000 }), 7000);